@@ -0,0 +1,173 @@
+//! Control socket protocol between `hi daemon` and other `hi` commands.
+//!
+//! Each connection carries one JSON request line and one JSON response line.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+const SOCKET_FILE: &str = "hi.sock";
+const PID_FILE: &str = "hi.pid";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Status,
+    /// Run a schedule now and return its response.
+    CronRun {
+        name: String,
+    },
+    /// Re-read schedules.json immediately.
+    CronReload,
+    /// Run a heartbeat tick now and return its result.
+    HeartbeatTick,
+    /// Shut the daemon down gracefully.
+    Shutdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<DaemonStatus>,
+}
+
+impl ControlResponse {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: message.into(),
+            status: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+            status: None,
+        }
+    }
+
+    /// Turn an error response into an `Err` carrying its message.
+    pub fn into_result(self) -> Result<Self> {
+        if self.ok {
+            Ok(self)
+        } else {
+            Err(anyhow::anyhow!(self.message))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started_at: String,
+    pub schedules: usize,
+    pub heartbeat: bool,
+    /// Schedule runs in progress.
+    pub in_flight: usize,
+}
+
+impl std::fmt::Display for DaemonStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {}, running since {}, {} active schedule(s), heartbeat {}, {} run(s) in progress",
+            self.pid,
+            self.started_at,
+            self.schedules,
+            if self.heartbeat { "on" } else { "off" },
+            self.in_flight
+        )
+    }
+}
+
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(shared::paths::data_dir()?.join(SOCKET_FILE))
+}
+
+pub fn pid_path() -> Result<PathBuf> {
+    Ok(shared::paths::data_dir()?.join(PID_FILE))
+}
+
+/// Send a request to the running daemon.
+///
+/// Returns `Ok(None)` when no daemon is listening, so callers can fall back to doing the
+/// work themselves.
+pub async fn request(request: &ControlRequest) -> Result<Option<ControlResponse>> {
+    request_at(&socket_path()?, request).await
+}
+
+pub(crate) async fn request_at(
+    socket: &Path,
+    request: &ControlRequest,
+) -> Result<Option<ControlResponse>> {
+    let stream = match UnixStream::connect(socket).await {
+        Ok(stream) => stream,
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to connect to {}", socket.display()));
+        }
+    };
+
+    let (reader, mut writer) = stream.into_split();
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .context("Failed to send control request")?;
+
+    let mut response = String::new();
+    BufReader::new(reader)
+        .read_line(&mut response)
+        .await
+        .context("Failed to read control response")?;
+    let response = serde_json::from_str(&response)
+        .with_context(|| format!("Invalid control response: {}", response.trim()))?;
+    Ok(Some(response))
+}
+
+/// Whether a daemon is accepting connections on `socket`.
+pub(crate) async fn is_listening(socket: &Path) -> bool {
+    UnixStream::connect(socket).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_wire_format() {
+        let json = serde_json::to_string(&ControlRequest::CronRun {
+            name: "daily".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"command":"cron_run","name":"daily"}"#);
+
+        let parsed: ControlRequest = serde_json::from_str(r#"{"command":"status"}"#).unwrap();
+        assert_eq!(parsed, ControlRequest::Status);
+    }
+
+    #[tokio::test]
+    async fn test_request_without_daemon_returns_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("hi.sock");
+        assert!(!is_listening(&socket).await);
+        let response = request_at(&socket, &ControlRequest::Status).await.unwrap();
+        assert!(response.is_none());
+    }
+}
@@ -0,0 +1,320 @@
+//! Headless mode: run only the background services (heartbeat and scheduler).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use shared::config::ModelConfig;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::{RwLock, mpsc};
+use tracing::{info, warn};
+
+use crate::control::{ControlRequest, ControlResponse, DaemonStatus};
+use crate::services::BackgroundServices;
+
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(60);
+
+/// PID file that is removed again when dropped.
+struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    fn create(path: &Path) -> Result<Self> {
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// State shared with control connections.
+struct DaemonState {
+    services: RwLock<BackgroundServices>,
+    started_at: String,
+    shutdown_tx: mpsc::Sender<()>,
+}
+
+/// Run the heartbeat and scheduler until SIGTERM, SIGINT, or a `shutdown` request.
+///
+/// On shutdown, new runs stop and in-progress runs get up to `grace` to finish.
+pub async fn run(config: ModelConfig, grace: Duration) -> Result<()> {
+    let socket_path = crate::control::socket_path()?;
+    if crate::control::is_listening(&socket_path).await {
+        bail!(
+            "A daemon is already running (control socket {})",
+            socket_path.display()
+        );
+    }
+    // Nothing answers on the socket, so any leftover file is stale.
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path)
+        .with_context(|| format!("Failed to bind {}", socket_path.display()))?;
+    let _pid_file = PidFile::create(&crate::control::pid_path()?)?;
+    crate::metrics::start(config.metrics.as_ref()).await?;
+
+    let data_dir = shared::paths::data_dir()?;
+    crate::session::refresh_runtime_index(&config, &data_dir);
+
+    let (background_tx, mut background_rx) = mpsc::unbounded_channel::<String>();
+    let services = BackgroundServices::start(&config, background_tx).await;
+    tokio::spawn(async move {
+        while let Some(msg) = background_rx.recv().await {
+            info!("[background] {}", msg);
+        }
+    });
+
+    let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
+    let state = Arc::new(DaemonState {
+        services: RwLock::new(services),
+        started_at: Utc::now().to_rfc3339(),
+        shutdown_tx,
+    });
+    let server = tokio::spawn(serve(listener, state.clone()));
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    sd_notify("READY=1\nSTATUS=Running background services");
+    info!(pid = std::process::id(), socket = %socket_path.display(), "Daemon ready");
+
+    tokio::select! {
+        _ = sigterm.recv() => info!("Received SIGTERM, shutting down..."),
+        _ = sigint.recv() => info!("Received SIGINT, shutting down..."),
+        _ = shutdown_rx.recv() => info!("Shutdown requested over control socket"),
+    }
+
+    sd_notify("STOPPING=1");
+    server.abort();
+    let _ = std::fs::remove_file(&socket_path);
+
+    // Control requests such as manual runs hold a read lock until they finish.
+    let finished = match tokio::time::timeout(grace, state.services.write()).await {
+        Ok(mut services) => services.shutdown(grace).await,
+        Err(_) => false,
+    };
+    if finished {
+        info!("Daemon stopped");
+    } else {
+        warn!(
+            grace_secs = grace.as_secs(),
+            "Daemon stopped with runs still in progress"
+        );
+    }
+    Ok(())
+}
+
+async fn serve(listener: UnixListener, state: Arc<DaemonState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &state).await {
+                        warn!(error = %e, "Control connection failed");
+                    }
+                });
+            }
+            Err(e) => warn!(error = %e, "Failed to accept control connection"),
+        }
+    }
+}
+
+async fn handle_connection(stream: UnixStream, state: &DaemonState) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => handle_request(request, state).await,
+        Err(e) => ControlResponse::error(format!("Invalid request: {}", e)),
+    };
+
+    let mut out = serde_json::to_string(&response)?;
+    out.push('\n');
+    writer.write_all(out.as_bytes()).await?;
+    Ok(())
+}
+
+async fn handle_request(request: ControlRequest, state: &DaemonState) -> ControlResponse {
+    match request {
+        ControlRequest::Status => {
+            let services = state.services.read().await;
+            let (schedules, in_flight) = match &services.scheduler {
+                Some(scheduler) => (scheduler.job_count().await, scheduler.in_flight()),
+                None => (0, 0),
+            };
+            ControlResponse {
+                status: Some(DaemonStatus {
+                    pid: std::process::id(),
+                    started_at: state.started_at.clone(),
+                    schedules,
+                    heartbeat: services
+                        .heartbeat
+                        .as_ref()
+                        .is_some_and(|heartbeat| heartbeat.is_running()),
+                    in_flight,
+                }),
+                ..ControlResponse::ok("running")
+            }
+        }
+        ControlRequest::CronRun { name } => {
+            let services = state.services.read().await;
+            match &services.scheduler {
+                Some(scheduler) => match scheduler.run(&name).await {
+                    Ok(response) => ControlResponse::ok(response),
+                    Err(e) => ControlResponse::error(e.to_string()),
+                },
+                None => ControlResponse::error("Scheduler is not running"),
+            }
+        }
+        ControlRequest::CronReload => {
+            let services = state.services.read().await;
+            match &services.scheduler {
+                Some(scheduler) => {
+                    scheduler.reload().await;
+                    ControlResponse::ok(format!(
+                        "Reloaded schedules ({} active)",
+                        scheduler.job_count().await
+                    ))
+                }
+                None => ControlResponse::error("Scheduler is not running"),
+            }
+        }
+        ControlRequest::HeartbeatTick => {
+            let services = state.services.read().await;
+            match &services.heartbeat {
+                Some(heartbeat) => match heartbeat.tick().await {
+                    Ok(report) => ControlResponse::ok(report),
+                    Err(e) => ControlResponse::error(e.to_string()),
+                },
+                None => ControlResponse::error("Heartbeat is not running"),
+            }
+        }
+        ControlRequest::Shutdown => {
+            let _ = state.shutdown_tx.try_send(());
+            ControlResponse::ok("Shutting down")
+        }
+    }
+}
+
+/// Send a state change to systemd when started as a `Type=notify` service.
+fn sd_notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = send_notify(&socket, state) {
+        warn!(error = %e, "Failed to notify systemd");
+    }
+}
+
+fn send_notify(socket: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    // A leading '@' names a socket in the Linux abstract namespace.
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::ffi::OsStrExt;
+        if let Some(name) = socket.as_bytes().strip_prefix(b"@") {
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &addr)?;
+            return Ok(());
+        }
+    }
+    datagram.send_to(state.as_bytes(), Path::new(socket))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file_removed_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hi.pid");
+        {
+            let _pid = PidFile::create(&path).unwrap();
+            let content = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(content.trim(), std::process::id().to_string());
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_send_notify_to_path_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+
+        send_notify(path.as_os_str(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+    }
+
+    #[tokio::test]
+    async fn test_control_connection_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("hi.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
+        let state = Arc::new(DaemonState {
+            services: RwLock::new(BackgroundServices {
+                heartbeat: None,
+                scheduler: None,
+            }),
+            started_at: "2026-03-05T09:00:00+00:00".to_string(),
+            shutdown_tx,
+        });
+        let server = tokio::spawn(serve(listener, state));
+
+        let status = crate::control::request_at(&socket, &ControlRequest::Status)
+            .await
+            .unwrap()
+            .unwrap();
+        let status = status.status.unwrap();
+        assert_eq!(status.pid, std::process::id());
+        assert_eq!(status.schedules, 0);
+        assert!(!status.heartbeat);
+
+        let run = crate::control::request_at(
+            &socket,
+            &ControlRequest::CronRun {
+                name: "daily".to_string(),
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(!run.ok);
+        assert!(run.message.contains("not running"));
+
+        let tick = crate::control::request_at(&socket, &ControlRequest::HeartbeatTick)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!tick.ok);
+        assert_eq!(tick.message, "Heartbeat is not running");
+
+        let stop = crate::control::request_at(&socket, &ControlRequest::Shutdown)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(stop.ok);
+        assert!(shutdown_rx.recv().await.is_some());
+        server.abort();
+    }
+}
@@ -1,35 +1,97 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use anyhow::Result;
-use rig::tool::ToolDyn;
-use tokio::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use rig::completion::ToolDefinition;
+use rig::tool::{ToolDyn, ToolError};
+use rig::wasm_compat::WasmBoxedFuture;
+use tokio::sync::{Mutex, mpsc, oneshot};
 use tokio::task::JoinHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::{info, warn};
 
-use shared::config::{HeartbeatConfig, ModelConfig};
+use shared::config::{ActiveHours, HeartbeatConfig, ModelConfig};
+use shared::heartbeat_scratchpad;
 use shared::heartbeat_store::{self, TaskStatus};
-use shared::runtime_index;
+use shared::heartbeat_transcript::{self, TickTranscript, ToolCallRecord};
+use shared::runtime_index::{self, HeartbeatSkip};
 
+use crate::metrics::metrics;
 use crate::provider::{ChatAgent, create_agent_from_parts};
+use crate::tool_audit::audit_tools;
+
+/// Tool calls made during the current tick, collected for its transcript.
+type ToolCallLog = Arc<std::sync::Mutex<Vec<ToolCallRecord>>>;
+
+/// Wraps a heartbeat tool so each call is added to the tick's [`ToolCallLog`].
+struct RecordedTool {
+    inner: Box<dyn ToolDyn>,
+    calls: ToolCallLog,
+}
+
+impl ToolDyn for RecordedTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(&'a self, prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
 
-fn build_heartbeat_tools(heartbeat_md_path: PathBuf) -> Vec<Box<dyn ToolDyn>> {
+    fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let result = self.inner.call(args.clone()).await;
+            let outcome = match &result {
+                Ok(output) => Ok(output.clone()),
+                Err(e) => Err(e.to_string()),
+            };
+            let record = ToolCallRecord::new(&self.inner.name(), &args, &outcome);
+            if let Ok(mut calls) = self.calls.lock() {
+                calls.push(record);
+            }
+            result
+        })
+    }
+}
+
+fn build_heartbeat_tools(
+    heartbeat_md_path: PathBuf,
+    scratchpad_path: PathBuf,
+    lease: chrono::Duration,
+) -> Vec<Box<dyn ToolDyn>> {
     vec![
         Box::new(hi_tools::ReadFileTool) as Box<dyn ToolDyn>,
         Box::new(hi_tools::WriteFileTool),
-        Box::new(hi_tools::HeartbeatWriteTool::new(heartbeat_md_path.clone())),
+        Box::new(hi_tools::HeartbeatWriteTool::new(
+            heartbeat_md_path.clone(),
+            lease,
+        )),
         Box::new(hi_tools::HeartbeatEditTool::new(heartbeat_md_path)),
+        Box::new(hi_tools::HeartbeatNoteTool::new(scratchpad_path)),
     ]
 }
 
+fn record_tool_calls(tools: Vec<Box<dyn ToolDyn>>, calls: &ToolCallLog) -> Vec<Box<dyn ToolDyn>> {
+    tools
+        .into_iter()
+        .map(|inner| {
+            Box::new(RecordedTool {
+                inner,
+                calls: calls.clone(),
+            }) as Box<dyn ToolDyn>
+        })
+        .collect()
+}
+
 fn create_heartbeat_agent(
     config: &ModelConfig,
     heartbeat_config: &HeartbeatConfig,
     preamble: Option<&str>,
-    heartbeat_md_path: PathBuf,
+    tools: Vec<Box<dyn ToolDyn>>,
 ) -> Result<ChatAgent> {
     let small_config = config.resolve_model_ref(&heartbeat_config.model);
-    let tools = build_heartbeat_tools(heartbeat_md_path);
     create_agent_from_parts(
         &small_config.provider,
         &small_config.model,
@@ -42,13 +104,161 @@ fn create_heartbeat_agent(
 }
 
 fn heartbeat_md_path() -> PathBuf {
-    shared::paths::data_dir()
-        .map(|d| d.join("HEARTBEAT.md"))
-        .unwrap_or_else(|_| PathBuf::from("HEARTBEAT.md"))
+    heartbeat_store::default_path().unwrap_or_else(|_| PathBuf::from("HEARTBEAT.md"))
+}
+
+fn scratchpad_path() -> PathBuf {
+    heartbeat_scratchpad::default_path()
+        .unwrap_or_else(|_| PathBuf::from("heartbeat_scratchpad.json"))
+}
+
+/// The heartbeat agent and tick settings, shared by the timer loop and manual ticks.
+struct Ticker {
+    agent: ChatAgent,
+    md_path: PathBuf,
+    scratchpad_path: PathBuf,
+    settings: TickSettings,
+    tool_calls: ToolCallLog,
+    /// Keeps a manual tick from overlapping a timer tick.
+    lock: Mutex<()>,
+}
+
+impl Ticker {
+    fn new(config: &HeartbeatConfig, model_config: &ModelConfig) -> Result<Self> {
+        let md_path = heartbeat_md_path();
+        let scratchpad_path = scratchpad_path();
+        let tool_calls = ToolCallLog::default();
+        let index = runtime_index::load();
+        let preamble = index.build_context_preamble();
+        let settings = TickSettings::from_config(config);
+        let tools = build_heartbeat_tools(md_path.clone(), scratchpad_path.clone(), settings.lease);
+        let tools = record_tool_calls(audit_tools(tools, "heartbeat"), &tool_calls);
+        let agent = create_heartbeat_agent(model_config, config, Some(&preamble), tools)?;
+        Ok(Self {
+            agent,
+            md_path,
+            scratchpad_path,
+            settings,
+            tool_calls,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Run one tick. Manual ticks ignore `active_hours`.
+    async fn tick(&self, manual: bool) -> TickReport {
+        let _guard = self.lock.lock().await;
+        if manual {
+            let settings = TickSettings {
+                active_hours: None,
+                ..self.settings.clone()
+            };
+            self.run(&settings).await
+        } else {
+            self.run(&self.settings).await
+        }
+    }
+
+    /// Send the tick's prompt to the agent, then record its transcript.
+    async fn run(&self, settings: &TickSettings) -> TickReport {
+        let started_at = Utc::now();
+        let epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (task_id, prompt) = match prepare_tick(&self.md_path, settings, started_at) {
+            Ok(tick) => tick,
+            Err(reason) => {
+                info!(%reason, "Skipping heartbeat tick");
+                metrics()
+                    .heartbeat_ticks
+                    .with_label_values(&["skipped"])
+                    .inc();
+                let _ = runtime_index::update(|idx| {
+                    idx.last_heartbeat_skip = Some(HeartbeatSkip {
+                        epoch,
+                        reason: reason.to_string(),
+                    });
+                });
+                return TickReport {
+                    outcome: TickOutcome::Skipped(reason),
+                    reply: None,
+                };
+            }
+        };
+        let prompt = match &task_id {
+            Some(id) => with_notes(prompt, task_notes(&self.scratchpad_path, id).as_deref()),
+            None => prompt,
+        };
+
+        self.take_tool_calls();
+        let history = vec![];
+        let reply = match self
+            .agent
+            .chat(rig::completion::message::Message::user(&prompt), history)
+            .await
+        {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                warn!(task = task_id.as_deref(), error = %e, "Heartbeat agent error");
+                if let Some(task_id) = &task_id {
+                    record_task_failure(&self.md_path, task_id, &e.to_string());
+                }
+                Err(e.to_string())
+            }
+        };
+
+        metrics()
+            .heartbeat_ticks
+            .with_label_values(&[crate::metrics::outcome(&reply)])
+            .inc();
+        let transcript = TickTranscript::new(
+            started_at,
+            task_id.as_deref(),
+            &prompt,
+            self.take_tool_calls(),
+            &reply,
+        );
+        if let Err(e) = heartbeat_transcript::record(&transcript) {
+            warn!(error = %e, "Failed to record heartbeat transcript");
+        }
+        if task_id.is_some() {
+            prune_notes(&self.md_path, &self.scratchpad_path);
+        }
+
+        let _ = runtime_index::update(|idx| {
+            idx.last_heartbeat_epoch = Some(epoch);
+            idx.last_heartbeat_skip = None;
+        });
+        TickReport {
+            outcome: task_id.map_or(TickOutcome::Fallback, TickOutcome::Task),
+            reply: Some(reply),
+        }
+    }
+
+    fn take_tool_calls(&self) -> Vec<ToolCallRecord> {
+        self.tool_calls
+            .lock()
+            .map(|mut calls| std::mem::take(&mut *calls))
+            .unwrap_or_default()
+    }
+}
+
+/// Run a single heartbeat tick now, outside any running heartbeat loop.
+///
+/// Works even when the heartbeat is disabled, as long as it is configured.
+pub async fn tick_once(model_config: &ModelConfig) -> Result<String> {
+    let config = model_config
+        .heartbeat
+        .as_ref()
+        .context("Heartbeat is not configured")?;
+    let ticker = Ticker::new(config, model_config)?;
+    Ok(ticker.tick(true).await.to_string())
 }
 
 pub struct HeartbeatSystem {
     handle: Option<JoinHandle<()>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    ticker: Option<Arc<Ticker>>,
 }
 
 impl HeartbeatSystem {
@@ -58,46 +268,85 @@ impl HeartbeatSystem {
         tx: mpsc::UnboundedSender<String>,
     ) -> Result<Self> {
         if !config.enabled {
-            return Ok(Self { handle: None });
+            return Ok(Self {
+                handle: None,
+                shutdown: None,
+                ticker: None,
+            });
         }
 
-        let md_path = heartbeat_md_path();
-        let index = runtime_index::load();
-        let preamble = index.build_context_preamble();
-        let agent = Arc::new(create_heartbeat_agent(
-            model_config,
-            config,
-            Some(&preamble),
-            md_path.clone(),
-        )?);
-
-        let interval_secs = config.interval_secs;
-        let fallback_prompt = config
-            .prompt
-            .clone()
-            .unwrap_or_else(|| "heartbeat check".to_string());
+        let ticker = Arc::new(Ticker::new(config, model_config)?);
+        let base = Duration::from_secs(config.interval_secs.max(1));
+        let max_backoff = config.max_backoff_secs.map(Duration::from_secs);
 
+        let (shutdown, mut shutdown_rx) = oneshot::channel();
+        let loop_ticker = ticker.clone();
         let handle = tokio::spawn(async move {
-            let mut interval =
-                tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
-            interval.tick().await;
-
+            let mut delay = base;
+            let mut ticks = tick_interval(delay);
             loop {
-                interval.tick().await;
-                run_heartbeat_tick(&agent, &tx, &md_path, &fallback_prompt).await;
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = &mut shutdown_rx => break,
+                }
+                let report = loop_ticker.tick(false).await;
+                if let Some(Ok(response)) = &report.reply {
+                    let _ = tx.send(format!("[heartbeat] {}", response));
+                }
+                let next = next_delay(delay, base, max_backoff, &report.outcome);
+                if next != delay {
+                    delay = next;
+                    ticks = tick_interval(delay);
+                }
             }
         });
 
         Ok(Self {
             handle: Some(handle),
+            shutdown: Some(shutdown),
+            ticker: Some(ticker),
         })
     }
 
+    /// Run a tick now, waiting for any tick already in progress.
+    pub async fn tick(&self) -> Result<String> {
+        match &self.ticker {
+            Some(ticker) => Ok(ticker.tick(true).await.to_string()),
+            None => bail!("Heartbeat is not running"),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+
     pub fn stop(&mut self) {
+        self.shutdown = None;
+        self.ticker = None;
         if let Some(handle) = self.handle.take() {
             handle.abort();
         }
     }
+
+    /// Stop scheduling ticks and wait up to `grace` for an in-progress tick to finish.
+    ///
+    /// Returns `false` if the tick had to be aborted.
+    pub async fn shutdown(&mut self, grace: Duration) -> bool {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.ticker = None;
+        let Some(mut handle) = self.handle.take() else {
+            return true;
+        };
+        match tokio::time::timeout(grace, &mut handle).await {
+            Ok(_) => true,
+            Err(_) => {
+                handle.abort();
+                false
+            }
+        }
+    }
 }
 
 impl Drop for HeartbeatSystem {
@@ -106,60 +355,173 @@ impl Drop for HeartbeatSystem {
     }
 }
 
-async fn run_heartbeat_tick(
-    agent: &ChatAgent,
-    tx: &mpsc::UnboundedSender<String>,
-    md_path: &PathBuf,
-    fallback_prompt: &str,
-) {
-    let prompt = match build_task_prompt(md_path) {
-        Some(p) => p,
-        None => fallback_prompt.to_string(),
-    };
+/// Tick settings taken from `HeartbeatConfig`.
+#[derive(Clone)]
+struct TickSettings {
+    fallback_prompt: String,
+    lease: chrono::Duration,
+    active_hours: Option<ActiveHours>,
+    skip_when_idle: bool,
+}
 
-    let history = vec![];
-    match agent
-        .chat(rig::completion::message::Message::user(&prompt), history)
-        .await
-    {
-        Ok(response) => {
-            let _ = tx.send(format!("[heartbeat] {}", response));
+impl TickSettings {
+    fn from_config(config: &HeartbeatConfig) -> Self {
+        Self {
+            fallback_prompt: config
+                .prompt
+                .clone()
+                .unwrap_or_else(|| "heartbeat check".to_string()),
+            lease: config.lease().unwrap_or_else(|| {
+                heartbeat_store::lease_from_secs(heartbeat_store::DEFAULT_LEASE_SECS)
+                    .expect("default lease is valid")
+            }),
+            active_hours: config.active_hours.clone(),
+            skip_when_idle: config.skip_when_idle,
         }
-        Err(e) => {
-            eprintln!("[heartbeat] Agent error: {}", e);
+    }
+}
+
+/// What a heartbeat tick did.
+#[derive(Debug, PartialEq)]
+enum TickOutcome {
+    /// A ledger task was sent to the agent.
+    Task(String),
+    /// No task was ready, so the fallback prompt was sent.
+    Fallback,
+    Skipped(SkipReason),
+}
+
+impl TickOutcome {
+    /// Whether the tick found nothing to do.
+    fn is_idle(&self) -> bool {
+        matches!(
+            self,
+            TickOutcome::Fallback | TickOutcome::Skipped(SkipReason::Idle)
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SkipReason {
+    OutsideActiveHours(String),
+    Idle,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::OutsideActiveHours(window) => {
+                write!(f, "outside active hours ({})", window)
+            }
+            SkipReason::Idle => write!(f, "no actionable tasks"),
         }
     }
+}
 
-    let epoch = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let mut idx = runtime_index::load();
-    idx.last_heartbeat_epoch = Some(epoch);
-    let _ = runtime_index::save(&idx);
+/// Ticks every `period`, starting one `period` from now. Ticks run at a fixed rate; one
+/// that is late because a tick ran long pushes the later ones back instead of bunching up.
+fn tick_interval(period: Duration) -> Interval {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
 }
 
-fn build_task_prompt(md_path: &PathBuf) -> Option<String> {
-    let mut ledger = match heartbeat_store::load(md_path) {
-        Ok(l) => l,
-        Err(e) => {
-            eprintln!("[heartbeat] Failed to load HEARTBEAT.md: {}", e);
-            return None;
+/// Wait before the next tick: doubled after an idle tick when back-off is enabled,
+/// otherwise the base interval.
+fn next_delay(
+    current: Duration,
+    base: Duration,
+    max_backoff: Option<Duration>,
+    outcome: &TickOutcome,
+) -> Duration {
+    match max_backoff {
+        Some(max) if outcome.is_idle() => current.saturating_mul(2).min(max.max(base)),
+        _ => base,
+    }
+}
+
+/// Decide what a tick should send: a task prompt (with its id), the fallback prompt, or
+/// nothing.
+fn prepare_tick(
+    md_path: &PathBuf,
+    settings: &TickSettings,
+    now: DateTime<Utc>,
+) -> Result<(Option<String>, String), SkipReason> {
+    if let Some(active_hours) = &settings.active_hours {
+        let inside = active_hours.contains(now).unwrap_or_else(|e| {
+            warn!(error = %e, "Ignoring invalid heartbeat active_hours");
+            true
+        });
+        if !inside {
+            return Err(SkipReason::OutsideActiveHours(active_hours.to_string()));
         }
-    };
+    }
+    match build_task_prompt(md_path, settings.lease) {
+        Some((id, prompt)) => Ok((Some(id), prompt)),
+        None if settings.skip_when_idle => Err(SkipReason::Idle),
+        None => Ok((None, settings.fallback_prompt.clone())),
+    }
+}
 
-    let task_idx = ledger
-        .tasks
-        .iter()
-        .position(|t| t.status == TaskStatus::Pending)?;
+/// A finished tick and the agent's reply, if the model was called.
+struct TickReport {
+    outcome: TickOutcome,
+    reply: Option<std::result::Result<String, String>>,
+}
 
-    ledger.tasks[task_idx].status = TaskStatus::InProgress;
-    if let Err(e) = heartbeat_store::save(md_path, &ledger) {
-        eprintln!("[heartbeat] Failed to persist in-progress status: {}", e);
+impl std::fmt::Display for TickReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.outcome, &self.reply) {
+            (TickOutcome::Skipped(reason), _) => write!(f, "Skipped: {}", reason),
+            (TickOutcome::Task(id), Some(Ok(response))) => write!(f, "Task '{}': {}", id, response),
+            (TickOutcome::Task(id), Some(Err(e))) => write!(f, "Task '{}' failed: {}", id, e),
+            (_, Some(Err(e))) => write!(f, "Heartbeat failed: {}", e),
+            (_, Some(Ok(response))) => write!(f, "{}", response),
+            (_, None) => Ok(()),
+        }
     }
+}
+
+/// Pick the next ready task, mark it in progress under `lease`, and build its prompt.
+///
+/// Expired leases and retryable failures are returned to `Pending` first. Returns the
+/// task id and prompt.
+fn build_task_prompt(md_path: &PathBuf, lease: chrono::Duration) -> Option<(String, String)> {
+    let claimed = heartbeat_store::update(md_path, |ledger| {
+        let now = Utc::now();
+        let recovery = heartbeat_store::recover(ledger, now);
+        for id in &recovery.released {
+            info!(task = id, "Heartbeat task lease expired; retrying");
+        }
+        for id in &recovery.exhausted {
+            warn!(task = id, "Heartbeat task gave up after its last attempt");
+        }
+
+        let task_idx = heartbeat_store::next_ready(ledger, now);
+        if let Some(task_idx) = task_idx {
+            ledger.tasks[task_idx].start(now, lease);
+        }
+        Ok(task_idx.map(|idx| ledger.tasks[idx].clone()))
+    });
+    let task = match claimed {
+        Ok(task) => task?,
+        Err(e) => {
+            warn!(error = %e, "Failed to claim the next heartbeat task");
+            return None;
+        }
+    };
 
-    let task = &ledger.tasks[task_idx];
     let mut prompt = format!("Execute heartbeat task '{}': {}", task.id, task.title);
+    if task.attempts > 1 {
+        prompt.push_str(&format!(
+            " (attempt {} of {})",
+            task.attempts,
+            task.max_attempts()
+        ));
+    }
+    if let Some(due) = task.due {
+        prompt.push_str(&format!("\nDue: {}", due.to_rfc3339()));
+    }
     if let Some(ref desc) = task.description {
         prompt.push_str(&format!("\n\nDetails:\n{}", desc));
     }
@@ -168,7 +530,59 @@ fn build_task_prompt(md_path: &PathBuf) -> Option<String> {
         task.id
     ));
 
-    Some(prompt)
+    Some((task.id.clone(), prompt))
+}
+
+/// Fail the in-progress task after the agent errored, so it is retried on a later tick.
+fn record_task_failure(md_path: &Path, task_id: &str, error: &str) {
+    let result = heartbeat_store::update(md_path, |ledger| {
+        if let Some(task) = ledger
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && t.status == TaskStatus::InProgress)
+        {
+            task.fail(Utc::now(), Some(error));
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!(task = task_id, error = %e, "Failed to persist failed heartbeat task");
+    }
+}
+
+fn task_notes(scratchpad_path: &Path, task_id: &str) -> Option<String> {
+    match heartbeat_scratchpad::load(scratchpad_path) {
+        Ok(mut scratchpad) => scratchpad.remove(task_id),
+        Err(e) => {
+            warn!(error = %e, "Failed to load heartbeat scratchpad");
+            None
+        }
+    }
+}
+
+/// Add the task's notes from earlier ticks to its prompt, and invite the agent to keep
+/// notes for the next one.
+fn with_notes(mut prompt: String, notes: Option<&str>) -> String {
+    if let Some(notes) = notes {
+        prompt.push_str(&format!(
+            "\n\nYour notes from earlier ticks on this task:\n{}",
+            notes
+        ));
+    }
+    prompt.push_str(
+        "\nIf the task needs more than one tick, use the heartbeat_note tool to record your \
+        progress before you stop.",
+    );
+    prompt
+}
+
+/// Drop scratchpad notes for tasks that finished or left the ledger.
+fn prune_notes(md_path: &Path, scratchpad_path: &Path) {
+    let result = heartbeat_store::load(md_path)
+        .and_then(|ledger| heartbeat_scratchpad::retain_active(scratchpad_path, &ledger));
+    if let Err(e) = result {
+        warn!(error = %e, "Failed to prune heartbeat scratchpad");
+    }
 }
 
 #[cfg(test)]
@@ -176,6 +590,10 @@ mod tests {
     use super::*;
     use shared::heartbeat_store::{HeartbeatLedger, HeartbeatTask};
 
+    fn lease() -> chrono::Duration {
+        chrono::Duration::hours(1)
+    }
+
     fn write_ledger(path: &std::path::Path, tasks: Vec<HeartbeatTask>) {
         let ledger = HeartbeatLedger {
             header: "# Heartbeat Tasks".to_string(),
@@ -196,23 +614,26 @@ mod tests {
                     status: TaskStatus::Done,
                     title: "Already done".to_string(),
                     description: None,
+                    ..Default::default()
                 },
                 HeartbeatTask {
                     id: "pending-task".to_string(),
                     status: TaskStatus::Pending,
                     title: "Check logs".to_string(),
                     description: Some("Look at system logs".to_string()),
+                    ..Default::default()
                 },
                 HeartbeatTask {
                     id: "pending-task-2".to_string(),
                     status: TaskStatus::Pending,
                     title: "Run backup".to_string(),
                     description: None,
+                    ..Default::default()
                 },
             ],
         );
 
-        let prompt = build_task_prompt(&path.to_path_buf()).unwrap();
+        let (_, prompt) = build_task_prompt(&path.to_path_buf(), lease()).unwrap();
         assert!(prompt.contains("pending-task"));
         assert!(prompt.contains("Check logs"));
         assert!(prompt.contains("Look at system logs"));
@@ -224,6 +645,216 @@ mod tests {
         assert_eq!(ledger.tasks[2].status, TaskStatus::Pending);
     }
 
+    #[test]
+    fn test_build_task_prompt_sets_lease_and_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(&path, "- [pending] backup: Run backup\n").unwrap();
+
+        // The ledger stores whole seconds.
+        let before = Utc::now() - chrono::Duration::seconds(1);
+        let (id, _) = build_task_prompt(&path.to_path_buf(), lease()).unwrap();
+        assert_eq!(id, "backup");
+
+        let task = &heartbeat_store::load(&path).unwrap().tasks[0];
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert_eq!(task.attempts, 1);
+        assert!(task.lease_until.unwrap() >= before + lease());
+    }
+
+    #[test]
+    fn test_build_task_prompt_retries_expired_lease() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(
+            &path,
+            "- [in-progress] backup: Run backup {lease=2020-01-01T00:00:00Z attempts=1}\n",
+        )
+        .unwrap();
+
+        let (id, prompt) = build_task_prompt(&path.to_path_buf(), lease()).unwrap();
+        assert_eq!(id, "backup");
+        assert!(prompt.contains("(attempt 2 of 3)"));
+        assert_eq!(heartbeat_store::load(&path).unwrap().tasks[0].attempts, 2);
+    }
+
+    #[test]
+    fn test_agent_failure_is_retried_then_final() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(&path, "- [pending] flaky: Flaky task {max_attempts=2}\n").unwrap();
+        let path = path.to_path_buf();
+
+        build_task_prompt(&path, lease()).unwrap();
+        record_task_failure(&path, "flaky", "provider timeout");
+        assert_eq!(
+            heartbeat_store::load(&path).unwrap().tasks[0].status,
+            TaskStatus::Failed
+        );
+
+        build_task_prompt(&path, lease()).unwrap();
+        record_task_failure(&path, "flaky", "provider timeout");
+        assert!(build_task_prompt(&path, lease()).is_none());
+
+        let task = &heartbeat_store::load(&path).unwrap().tasks[0];
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(
+            task.outcome.as_deref(),
+            Some("failed after 2 attempts: provider timeout")
+        );
+    }
+
+    #[test]
+    fn test_build_task_prompt_picks_highest_priority_ready_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(
+            &path,
+            "- [pending] low: Low priority\n\
+             - [pending] later: Not yet {priority=9 not_before=2999-01-01T00:00:00Z}\n\
+             - [pending] blocked: Waiting on low {priority=9 depends_on=low}\n\
+             - [pending] high: High priority {priority=5 due=2026-03-05T12:00:00Z}\n",
+        )
+        .unwrap();
+        let path = path.to_path_buf();
+
+        let (id, prompt) = build_task_prompt(&path, lease()).unwrap();
+        assert_eq!(id, "high");
+        assert!(prompt.contains("Due: 2026-03-05T12:00:00+00:00"));
+
+        let mut ledger = heartbeat_store::load(&path).unwrap();
+        ledger.tasks[3].complete(Utc::now());
+        heartbeat_store::save(&path, &ledger).unwrap();
+        assert_eq!(build_task_prompt(&path, lease()).unwrap().0, "low");
+
+        let mut ledger = heartbeat_store::load(&path).unwrap();
+        ledger.tasks[0].complete(Utc::now());
+        heartbeat_store::save(&path, &ledger).unwrap();
+        assert_eq!(build_task_prompt(&path, lease()).unwrap().0, "blocked");
+        assert!(build_task_prompt(&path, lease()).is_none());
+    }
+
+    fn settings() -> TickSettings {
+        TickSettings {
+            fallback_prompt: "heartbeat check".to_string(),
+            lease: lease(),
+            active_hours: None,
+            skip_when_idle: false,
+        }
+    }
+
+    #[test]
+    fn test_prepare_tick_skips_outside_active_hours() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(&path, "- [pending] backup: Run backup\n").unwrap();
+        let settings = TickSettings {
+            active_hours: Some(ActiveHours {
+                start: "08:00".to_string(),
+                end: "22:00".to_string(),
+                timezone: Some("Asia/Taipei".to_string()),
+            }),
+            ..settings()
+        };
+
+        let night = "2026-03-05T15:00:00Z".parse().unwrap();
+        let reason = prepare_tick(&path, &settings, night).unwrap_err();
+        assert_eq!(
+            reason.to_string(),
+            "outside active hours (08:00-22:00 Asia/Taipei)"
+        );
+        assert_eq!(
+            heartbeat_store::load(&path).unwrap().tasks[0].status,
+            TaskStatus::Pending
+        );
+
+        let day = "2026-03-05T02:00:00Z".parse().unwrap();
+        let (id, _) = prepare_tick(&path, &settings, day).unwrap();
+        assert_eq!(id.as_deref(), Some("backup"));
+    }
+
+    #[test]
+    fn test_prepare_tick_idle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(&path, "- [done] backup: Run backup\n").unwrap();
+
+        let (id, prompt) = prepare_tick(&path, &settings(), Utc::now()).unwrap();
+        assert!(id.is_none());
+        assert_eq!(prompt, "heartbeat check");
+
+        let skip_idle = TickSettings {
+            skip_when_idle: true,
+            ..settings()
+        };
+        let reason = prepare_tick(&path, &skip_idle, Utc::now()).unwrap_err();
+        assert_eq!(reason, SkipReason::Idle);
+        assert_eq!(reason.to_string(), "no actionable tasks");
+    }
+
+    #[test]
+    fn test_tick_report_display() {
+        let report = TickReport {
+            outcome: TickOutcome::Task("backup".to_string()),
+            reply: Some(Ok("Backup finished".to_string())),
+        };
+        assert_eq!(report.to_string(), "Task 'backup': Backup finished");
+        let report = TickReport {
+            outcome: TickOutcome::Fallback,
+            reply: Some(Err("timeout".to_string())),
+        };
+        assert_eq!(report.to_string(), "Heartbeat failed: timeout");
+        let report = TickReport {
+            outcome: TickOutcome::Skipped(SkipReason::Idle),
+            reply: None,
+        };
+        assert_eq!(report.to_string(), "Skipped: no actionable tasks");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tick_interval_keeps_a_fixed_rate() {
+        let start = tokio::time::Instant::now();
+        let mut ticks = tick_interval(Duration::from_secs(10));
+        ticks.tick().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+        // A 4s tick does not push the next one back.
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        ticks.tick().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(20));
+        // A tick that overruns fires the missed one at once, then waits a full period.
+        tokio::time::sleep(Duration::from_secs(25)).await;
+        ticks.tick().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(45));
+        ticks.tick().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(55));
+    }
+
+    #[test]
+    fn test_next_delay_backs_off_while_idle() {
+        let base = Duration::from_secs(60);
+        let max = Some(Duration::from_secs(300));
+        let idle = TickOutcome::Skipped(SkipReason::Idle);
+
+        assert_eq!(next_delay(base, base, None, &idle), base);
+        let mut delay = base;
+        let mut waits = Vec::new();
+        for _ in 0..4 {
+            delay = next_delay(delay, base, max, &idle);
+            waits.push(delay.as_secs());
+        }
+        assert_eq!(waits, vec![120, 240, 300, 300]);
+        assert_eq!(
+            next_delay(delay, base, max, &TickOutcome::Fallback).as_secs(),
+            300
+        );
+        assert_eq!(
+            next_delay(delay, base, max, &TickOutcome::Task("t1".to_string())),
+            base
+        );
+        let outside = TickOutcome::Skipped(SkipReason::OutsideActiveHours(String::new()));
+        assert_eq!(next_delay(delay, base, max, &outside), base);
+    }
+
     #[test]
     fn test_build_task_prompt_returns_none_when_no_pending() {
         let dir = tempfile::tempdir().unwrap();
@@ -235,10 +866,11 @@ mod tests {
                 status: TaskStatus::Done,
                 title: "Done task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let result = build_task_prompt(&path.to_path_buf());
+        let result = build_task_prompt(&path.to_path_buf(), lease());
         assert!(result.is_none());
     }
 
@@ -248,7 +880,7 @@ mod tests {
         let path = dir.path().join("HEARTBEAT.md");
         write_ledger(&path, vec![]);
 
-        let result = build_task_prompt(&path.to_path_buf());
+        let result = build_task_prompt(&path.to_path_buf(), lease());
         assert!(result.is_none());
     }
 
@@ -257,7 +889,7 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join("HEARTBEAT.md");
 
-        let result = build_task_prompt(&path.to_path_buf());
+        let result = build_task_prompt(&path.to_path_buf(), lease());
         assert!(result.is_none());
     }
 
@@ -272,12 +904,85 @@ mod tests {
                 status: TaskStatus::Pending,
                 title: "Simple check".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let prompt = build_task_prompt(&path.to_path_buf()).unwrap();
+        let (_, prompt) = build_task_prompt(&path.to_path_buf(), lease()).unwrap();
         assert!(prompt.contains("simple"));
         assert!(prompt.contains("Simple check"));
         assert!(!prompt.contains("Details:"));
     }
+
+    #[test]
+    fn test_with_notes() {
+        let prompt = with_notes("Execute task".to_string(), None);
+        assert!(prompt.starts_with("Execute task\nIf the task needs more than one tick"));
+        assert!(!prompt.contains("Your notes"));
+
+        let prompt = with_notes("Execute task".to_string(), Some("checked 2 of 5 hosts"));
+        assert!(
+            prompt.contains("Your notes from earlier ticks on this task:\nchecked 2 of 5 hosts")
+        );
+        assert!(prompt.contains("heartbeat_note"));
+    }
+
+    #[test]
+    fn test_prune_notes_drops_finished_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let md_path = dir.path().join("HEARTBEAT.md");
+        let scratchpad_path = dir.path().join("heartbeat_scratchpad.json");
+        write_ledger(
+            &md_path,
+            vec![
+                HeartbeatTask {
+                    id: "open".to_string(),
+                    status: TaskStatus::InProgress,
+                    title: "Open".to_string(),
+                    ..Default::default()
+                },
+                HeartbeatTask {
+                    id: "closed".to_string(),
+                    status: TaskStatus::Done,
+                    title: "Closed".to_string(),
+                    ..Default::default()
+                },
+            ],
+        );
+        heartbeat_scratchpad::write(&scratchpad_path, "open", "half way", false).unwrap();
+        heartbeat_scratchpad::write(&scratchpad_path, "closed", "finished", false).unwrap();
+
+        prune_notes(&md_path, &scratchpad_path);
+        assert_eq!(
+            task_notes(&scratchpad_path, "open").as_deref(),
+            Some("half way")
+        );
+        assert_eq!(task_notes(&scratchpad_path, "closed"), None);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_tool_logs_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let calls = ToolCallLog::default();
+        let tools = record_tool_calls(
+            build_heartbeat_tools(
+                dir.path().join("HEARTBEAT.md"),
+                dir.path().join("heartbeat_scratchpad.json"),
+                lease(),
+            ),
+            &calls,
+        );
+        let note = tools.iter().find(|t| t.name() == "heartbeat_note").unwrap();
+        note.call(r#"{"task_id":"a","note":"step 1"}"#.to_string())
+            .await
+            .unwrap();
+        assert!(note.call("not json".to_string()).await.is_err());
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].name, "heartbeat_note");
+        assert!(calls[0].success);
+        assert!(calls[0].args.contains("step 1"));
+        assert!(!calls[1].success);
+    }
 }
@@ -1,9 +1,16 @@
 pub mod context;
+pub mod control;
+pub mod daemon;
 pub mod heartbeat;
 pub mod mcp;
+pub mod metrics;
 pub mod model_pool;
 pub mod provider;
 pub mod scheduler;
+pub mod services;
 pub mod session;
 pub mod skills;
+pub mod summarize;
+pub mod telemetry;
+pub mod tool_audit;
 pub mod validate;
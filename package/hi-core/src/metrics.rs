@@ -0,0 +1,280 @@
+//! Prometheus metrics for long-running modes (`hi remote`, `hi daemon`).
+//!
+//! Metrics are always collected in the process-wide registry. They are only exposed
+//! when `metrics.enabled` is set, on a plain HTTP `/metrics` endpoint.
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use shared::config::MetricsConfig;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+/// Latency buckets for model requests, in seconds. Model calls range from under a second
+/// to several minutes for long tool loops.
+const MODEL_LATENCY_BUCKETS: &[f64] = &[0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// How long a client may take to send its request before the connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most bytes of a request read, request line and headers together. A scrape needs far fewer.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+pub struct Metrics {
+    registry: Registry,
+    /// Chat sessions held by the remote session manager.
+    pub sessions: IntGauge,
+    /// Sessions dropped by the session manager, by `reason` (`idle`, `capacity`, `memory`).
+    pub session_evictions: IntCounterVec,
+    /// Resident set size at the last memory budget check.
+    pub resident_memory_bytes: IntGauge,
+    /// Model requests by `provider` and `outcome` (`ok`, `error`).
+    pub model_requests: IntCounterVec,
+    pub model_request_duration: HistogramVec,
+    /// Switches from the primary model to `small_model` after a failure.
+    pub model_fallbacks: IntCounter,
+    /// Tool calls by `tool` and `outcome`.
+    pub tool_calls: IntCounterVec,
+    /// Scheduled runs by `schedule` and `outcome`
+    /// (`ok`, `suppressed`, `error`, `skipped`, `cancelled`).
+    pub schedule_runs: IntCounterVec,
+    /// Heartbeat ticks by `outcome` (`ok`, `error`, `skipped`).
+    pub heartbeat_ticks: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("hi".to_string()), None).expect("metrics prefix is valid");
+
+        let sessions = IntGauge::new("sessions", "Active chat sessions").unwrap();
+        let session_evictions = IntCounterVec::new(
+            Opts::new("session_evictions_total", "Chat sessions evicted"),
+            &["reason"],
+        )
+        .unwrap();
+        let resident_memory_bytes = IntGauge::new(
+            "resident_memory_bytes",
+            "Resident set size at the last memory budget check",
+        )
+        .unwrap();
+        let model_requests = IntCounterVec::new(
+            Opts::new("model_requests_total", "Model requests"),
+            &["provider", "outcome"],
+        )
+        .unwrap();
+        let model_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "model_request_duration_seconds",
+                "Model request latency in seconds",
+            )
+            .buckets(MODEL_LATENCY_BUCKETS.to_vec()),
+            &["provider"],
+        )
+        .unwrap();
+        let model_fallbacks = IntCounter::new(
+            "model_fallbacks_total",
+            "Fallbacks from the primary model to small_model",
+        )
+        .unwrap();
+        let tool_calls = IntCounterVec::new(
+            Opts::new("tool_calls_total", "Tool calls"),
+            &["tool", "outcome"],
+        )
+        .unwrap();
+        let schedule_runs = IntCounterVec::new(
+            Opts::new("schedule_runs_total", "Scheduled job runs"),
+            &["schedule", "outcome"],
+        )
+        .unwrap();
+        let heartbeat_ticks = IntCounterVec::new(
+            Opts::new("heartbeat_ticks_total", "Heartbeat ticks"),
+            &["outcome"],
+        )
+        .unwrap();
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 9] = [
+            Box::new(sessions.clone()),
+            Box::new(session_evictions.clone()),
+            Box::new(resident_memory_bytes.clone()),
+            Box::new(model_requests.clone()),
+            Box::new(model_request_duration.clone()),
+            Box::new(model_fallbacks.clone()),
+            Box::new(tool_calls.clone()),
+            Box::new(schedule_runs.clone()),
+            Box::new(heartbeat_ticks.clone()),
+        ];
+        for collector in collectors {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            sessions,
+            session_evictions,
+            resident_memory_bytes,
+            model_requests,
+            model_request_duration,
+            model_fallbacks,
+            tool_calls,
+            schedule_runs,
+            heartbeat_ticks,
+        }
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buf) {
+            warn!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// `ok` or `error`, for outcome labels.
+pub fn outcome<T, E>(result: &std::result::Result<T, E>) -> &'static str {
+    if result.is_ok() { "ok" } else { "error" }
+}
+
+/// Start the `/metrics` endpoint if it is enabled. Runs until the process exits.
+pub async fn start(config: Option<&MetricsConfig>) -> Result<()> {
+    let Some(config) = config.filter(|c| c.enabled) else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind(&config.listen)
+        .await
+        .with_context(|| format!("Failed to bind metrics endpoint on {}", config.listen))?;
+    info!(listen = %config.listen, "Serving metrics at /metrics");
+    tokio::spawn(serve(listener));
+    Ok(())
+}
+
+async fn serve(listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream).await {
+                        warn!(error = %e, "Metrics connection failed");
+                    }
+                });
+            }
+            Err(e) => warn!(error = %e, "Failed to accept metrics connection"),
+        }
+    }
+}
+
+/// Answer a single HTTP request: `GET /metrics` gets the metrics, anything else a 404.
+async fn handle_connection(stream: TcpStream) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader.take(MAX_REQUEST_BYTES));
+    let request_line = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut reader))
+        .await
+        .context("Timed out reading the request")??;
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics().render(),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Read the request line and drain the headers; the request body, if any, is ignored.
+async fn read_request(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<String> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut header = String::new();
+    while reader.read_line(&mut header).await? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+    Ok(request_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nhost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint_serves_registry() {
+        metrics().model_fallbacks.inc();
+        metrics()
+            .schedule_runs
+            .with_label_values(&["daily", "ok"])
+            .inc();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve(listener));
+
+        let response = get(addr, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("# TYPE hi_sessions gauge"));
+        assert!(response.contains("hi_model_fallbacks_total "));
+        assert!(response.contains(r#"hi_schedule_runs_total{outcome="ok",schedule="daily"}"#));
+
+        let response = get(addr, "/").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        server.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_silent_client_is_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve(listener));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.is_empty());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_start_is_noop_when_disabled() {
+        let config = MetricsConfig {
+            enabled: false,
+            listen: "not an address".to_string(),
+        };
+        assert!(start(Some(&config)).await.is_ok());
+        assert!(start(None).await.is_ok());
+    }
+}
@@ -3,22 +3,34 @@ use futures::StreamExt;
 use rig::agent::{Agent, MultiTurnStreamItem};
 use rig::completion::Chat;
 use rig::completion::PromptError;
+use rig::completion::Usage;
 use rig::completion::message::Message;
 use rig::message::Text;
 use rig::prelude::CompletionClient;
 use rig::providers::{anthropic, gemini, ollama, openai};
 use rig::streaming::{StreamedAssistantContent, StreamingChat};
 use rig::tool::ToolDyn;
-use shared::config::{ModelConfig, Provider, SmallModelConfig, ThinkingConfig};
+use shared::config::{ModelConfig, Provider, ScheduleTaskConfig, SmallModelConfig, ThinkingConfig};
+use shared::storage::{FileStorage, Storage};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
+use crate::tool_audit::audit_tools;
 use hi_tools::{
-    BashTool, HeartbeatEditTool, ListFilesTool, MemoryTool, ReadFileTool, ReadSkillsTool,
-    ScheduleAddTool, ScheduleRemoveTool, ScheduleViewTool, SkillSummary, WriteFileTool,
+    BashTool, HeartbeatEditTool, ListFilesTool, MemoryTool, PinTool, ReadFileTool, ReadSkillsTool,
+    RecallArchiveTool, RemindTool, ScheduleAddTool, ScheduleControlTool, ScheduleRemoveTool,
+    ScheduleRunFn, ScheduleStorage, ScheduleViewTool, SearchHistoryTool, SkillSummary,
+    WriteFileTool,
 };
 
 pub const STREAM_CHANNEL_CAPACITY: usize = 256;
 
+/// A streamed reply, with the token usage from its final response if the provider sent one.
+pub struct StreamReply {
+    pub text: String,
+    pub usage: Option<Usage>,
+}
+
 pub enum ChatAgent {
     OpenAI(Agent<openai::completion::CompletionModel>),
     OpenAICompatible(Agent<openai::completion::CompletionModel>),
@@ -30,6 +42,7 @@ pub enum ChatAgent {
 macro_rules! consume_stream {
     ($agent:expr, $prompt:expr, $history:expr, $chunk_tx:expr, $acc:expr) => {{
         let mut stream = $agent.stream_chat($prompt, $history).await;
+        let mut usage = None;
         while let Some(chunk) = stream.next().await {
             match chunk {
                 Ok(MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(
@@ -40,12 +53,22 @@ macro_rules! consume_stream {
                         tracing::warn!("Channel send failed: {e}");
                     }
                 }
-                Ok(MultiTurnStreamItem::FinalResponse(_)) => {}
+                Ok(MultiTurnStreamItem::FinalResponse(final_response)) => {
+                    let final_usage = final_response.usage();
+                    crate::telemetry::record_usage(
+                        final_usage.input_tokens,
+                        final_usage.output_tokens,
+                    );
+                    usage = Some(final_usage);
+                }
                 Err(e) => return Err(anyhow::anyhow!("{e}")),
                 _ => continue,
             }
         }
-        Ok(std::mem::take($acc))
+        Ok(StreamReply {
+            text: std::mem::take($acc),
+            usage,
+        })
     }};
 }
 
@@ -69,7 +92,7 @@ impl ChatAgent {
         prompt: impl Into<Message> + Send + Sync,
         history: Vec<Message>,
         chunk_tx: mpsc::Sender<String>,
-    ) -> Result<String> {
+    ) -> Result<StreamReply> {
         let msg = prompt.into();
         let mut acc = String::new();
         match self {
@@ -82,13 +105,20 @@ impl ChatAgent {
     }
 }
 
-fn build_tools(skill_summaries: Vec<SkillSummary>) -> Vec<Box<dyn ToolDyn>> {
+fn build_tools(
+    skill_summaries: Vec<SkillSummary>,
+    schedule_runner: Option<ScheduleRunFn>,
+    config_schedules: Option<&[ScheduleTaskConfig]>,
+    recall_scope: Option<&str>,
+) -> Vec<Box<dyn ToolDyn>> {
     let memory_path = shared::paths::data_dir()
         .map(|d| d.join("memory.md"))
         .unwrap_or_else(|_| std::path::PathBuf::from("memory.md"));
-    let schedules_path = shared::paths::data_dir()
-        .map(|d| d.join("schedules.json"))
-        .unwrap_or_else(|_| std::path::PathBuf::from("schedules.json"));
+    let storage: Arc<dyn Storage> = match shared::storage::handle() {
+        Ok(storage) => storage,
+        Err(_) => Arc::new(FileStorage::new(".".into())),
+    };
+    let schedules = ScheduleStorage::new(storage.clone(), config_schedules);
     let heartbeat_path = shared::paths::data_dir()
         .map(|d| d.join("HEARTBEAT.md"))
         .unwrap_or_else(|_| std::path::PathBuf::from("HEARTBEAT.md"));
@@ -99,21 +129,35 @@ fn build_tools(skill_summaries: Vec<SkillSummary>) -> Vec<Box<dyn ToolDyn>> {
         Box::new(WriteFileTool),
         Box::new(ReadSkillsTool::new(skill_summaries)),
         Box::new(MemoryTool::new(memory_path)),
-        Box::new(ScheduleViewTool::new(schedules_path.clone())),
-        Box::new(ScheduleAddTool::new(schedules_path.clone())),
-        Box::new(ScheduleRemoveTool::new(schedules_path)),
+        Box::new(ScheduleViewTool::new(schedules.clone())),
+        Box::new(ScheduleAddTool::new(schedules.clone())),
+        Box::new(ScheduleRemoveTool::new(schedules.clone())),
+        Box::new(RemindTool::new(schedules.clone())),
+        Box::new(ScheduleControlTool::new(schedules, schedule_runner)),
         Box::new(HeartbeatEditTool::new(heartbeat_path)),
+        Box::new(SearchHistoryTool::new(storage.clone(), recall_scope)),
+        Box::new(RecallArchiveTool::new(storage, recall_scope)),
+        Box::new(PinTool),
     ]
 }
 
+/// Create the main chat agent. Tool calls are audited under `caller`.
 pub fn create_agent(
     config: &ModelConfig,
     preamble: Option<&str>,
     skill_summaries: Vec<SkillSummary>,
     extra_tools: Vec<Box<dyn ToolDyn>>,
+    caller: &str,
 ) -> Result<ChatAgent> {
-    let mut tools = build_tools(skill_summaries);
+    let runner = crate::scheduler::Scheduler::runner(config);
+    let mut tools = build_tools(
+        skill_summaries,
+        Some(runner),
+        config.schedules.as_deref(),
+        config.recall_scope(caller),
+    );
     tools.extend(extra_tools);
+    let tools = audit_tools(tools, caller);
     create_agent_from_parts(
         &config.provider,
         &config.model,
@@ -144,8 +188,18 @@ pub fn create_agent_from_small_with_tools(
     config: &SmallModelConfig,
     preamble: Option<&str>,
     skill_summaries: Vec<SkillSummary>,
+    schedule_runner: Option<ScheduleRunFn>,
+    config_schedules: Option<&[ScheduleTaskConfig]>,
+    recall_scope: Option<&str>,
+    caller: &str,
 ) -> Result<ChatAgent> {
-    let tools = build_tools(skill_summaries);
+    let tools = build_tools(
+        skill_summaries,
+        schedule_runner,
+        config_schedules,
+        recall_scope,
+    );
+    let tools = audit_tools(tools, caller);
     create_agent_from_parts(
         &config.provider,
         &config.model,
@@ -1,23 +1,229 @@
+use crate::control::ControlRequest;
 use crate::model_pool::ModelPool;
-use anyhow::Result;
-use shared::config::{ModelConfig, ScheduleTaskConfig};
+use crate::services::BackgroundServices;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use hi_tools::ScheduleRunFn;
+use shared::config::{
+    CatchUpPolicy, DEFAULT_CATCH_UP_LIMIT, ModelConfig, OverlapPolicy, ScheduleTaskConfig,
+};
 use shared::runtime_index;
+use shared::schedule_store::{RunTrigger, ScheduleRun};
+use shared::storage::Storage;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify, Semaphore, mpsc};
+use tokio::task::AbortHandle;
 use tokio_cron_scheduler::{Job, JobScheduler};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How often schedules.json is checked for changes made by tools or commands.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Everything needed to run a schedule prompt.
+#[derive(Clone)]
+struct RunContext {
+    model_config: ModelConfig,
+    pool: Arc<ModelPool>,
+    preamble: String,
+}
+
+impl RunContext {
+    fn new(model_config: &ModelConfig, pool: Arc<ModelPool>) -> Self {
+        Self {
+            model_config: model_config.clone(),
+            pool,
+            preamble: runtime_index::load().build_context_preamble(),
+        }
+    }
+}
+
+/// A run context plus the channel cron-fired results are delivered to.
+#[derive(Clone)]
+struct JobContext {
+    run: RunContext,
+    tx: mpsc::UnboundedSender<String>,
+    in_flight: Arc<InFlight>,
+}
+
+impl JobContext {
+    fn new(
+        model_config: &ModelConfig,
+        pool: Arc<ModelPool>,
+        tx: mpsc::UnboundedSender<String>,
+    ) -> Self {
+        Self {
+            run: RunContext::new(model_config, pool),
+            tx,
+            in_flight: Arc::new(InFlight::default()),
+        }
+    }
+}
+
+/// Counts runs in progress so shutdown can wait for them.
+#[derive(Default)]
+struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+struct InFlightGuard(Arc<InFlight>);
+
+impl InFlight {
+    fn enter(self: &Arc<Self>) -> InFlightGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.clone())
+    }
+
+    fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.count() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+struct RegisteredJob {
+    id: Uuid,
+    /// Serialized task; a changed fingerprint means the job must be rebuilt.
+    fingerprint: String,
+    slots: Arc<RunSlots>,
+}
+
+/// How a run gated by [`RunSlots`] ended.
+#[derive(Debug)]
+enum SlotOutcome<T> {
+    Finished(T),
+    /// Dropped because the schedule was at `max_concurrency` with overlap `skip`.
+    Skipped,
+    /// Aborted by a newer run with overlap `cancel_previous`.
+    Cancelled,
+}
+
+/// Enforces a schedule's `max_concurrency` and `overlap` policy across its firings.
+struct RunSlots {
+    overlap: OverlapPolicy,
+    permits: Arc<Semaphore>,
+    /// Runs in flight, oldest first.
+    running: std::sync::Mutex<VecDeque<(u64, AbortHandle)>>,
+    next_id: AtomicU64,
+}
+
+impl RunSlots {
+    fn new(task: &ScheduleTaskConfig) -> Self {
+        Self {
+            overlap: task.overlap,
+            permits: Arc::new(Semaphore::new(task.effective_max_concurrency())),
+            running: std::sync::Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    async fn run<F, T>(&self, fut: F) -> SlotOutcome<T>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                match self.overlap {
+                    OverlapPolicy::Skip => return SlotOutcome::Skipped,
+                    OverlapPolicy::Queue => {}
+                    OverlapPolicy::CancelPrevious => {
+                        let oldest = self.running.lock().unwrap().pop_front();
+                        if let Some((_, handle)) = oldest {
+                            handle.abort();
+                        }
+                    }
+                }
+                // The semaphore is never closed.
+                let Ok(permit) = self.permits.clone().acquire_owned().await else {
+                    return SlotOutcome::Skipped;
+                };
+                permit
+            }
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            fut.await
+        });
+        self.running
+            .lock()
+            .unwrap()
+            .push_back((id, handle.abort_handle()));
+        let result = handle.await;
+        self.running.lock().unwrap().retain(|(run, _)| *run != id);
+
+        match result {
+            Ok(value) => SlotOutcome::Finished(value),
+            Err(e) if e.is_cancelled() => SlotOutcome::Cancelled,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+type JobTable = Arc<Mutex<HashMap<String, RegisteredJob>>>;
 
 pub struct Scheduler {
     job_scheduler: JobScheduler,
+    jobs: JobTable,
+    ctx: JobContext,
+    reload: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl Scheduler {
+    /// Start from schedules.json (falling back to config) and keep following file changes.
     pub async fn start_with_store(
         model_config: &ModelConfig,
         pool: Arc<ModelPool>,
         tx: mpsc::UnboundedSender<String>,
     ) -> Result<Self> {
         let tasks = shared::schedule_store::load(model_config.schedules.as_deref());
-        Self::start(&tasks, model_config, pool, tx).await
+        let mut scheduler = Self::start(&tasks, model_config, pool, tx).await?;
+
+        let ctx = scheduler.ctx.clone();
+        let job_scheduler = scheduler.job_scheduler.clone();
+        let jobs = scheduler.jobs.clone();
+        scheduler.reload = Some(tokio::spawn(async move {
+            let mut last_revision = shared::schedule_store::revision();
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let revision = shared::schedule_store::revision();
+                if revision == last_revision {
+                    continue;
+                }
+                last_revision = revision;
+                let tasks = shared::schedule_store::load(ctx.run.model_config.schedules.as_deref());
+                sync_jobs(&job_scheduler, &jobs, &ctx, &tasks).await;
+            }
+        }));
+
+        Ok(scheduler)
     }
 
     pub async fn start(
@@ -30,49 +236,15 @@ impl Scheduler {
             .await
             .map_err(|e| anyhow::anyhow!("{:?}", e))?;
 
-        let index = runtime_index::load();
-        let context_preamble = index.build_context_preamble();
-
-        for task in tasks.iter().filter(|t| t.enabled) {
-            let small_config = model_config.resolve_model_ref(&task.model);
-
-            let pool = pool.clone();
-            let tx = tx.clone();
-            let task_name = task.name.clone();
-            let task_prompt = task.prompt.clone();
-            let preamble = context_preamble.clone();
-
-            let job = Job::new_async(task.cron.as_str(), move |_uuid, _lock| {
-                let pool = pool.clone();
-                let tx = tx.clone();
-                let name = task_name.clone();
-                let prompt = task_prompt.clone();
-                let cfg = small_config.clone();
-                let preamble = preamble.clone();
-                Box::pin(async move {
-                    let agent = match pool.get_or_create(&cfg, Some(&preamble)) {
-                        Ok(a) => a,
-                        Err(_) => return,
-                    };
-
-                    let history = vec![];
-                    match agent
-                        .chat(rig::completion::message::Message::user(&prompt), history)
-                        .await
-                    {
-                        Ok(response) => {
-                            let _ = tx.send(format!("[schedule:{}] {}", name, response));
-                        }
-                        Err(_) => {}
-                    }
-                })
-            })
-            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let ctx = JobContext::new(model_config, pool, tx);
 
-            job_scheduler
-                .add(job)
-                .await
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let jobs: JobTable = Arc::new(Mutex::new(HashMap::new()));
+        sync_jobs(&job_scheduler, &jobs, &ctx, tasks).await;
+        match shared::storage::storage() {
+            Ok(storage) => {
+                catch_up(&ctx, &jobs, tasks, storage).await;
+            }
+            Err(e) => warn!(error = %e, "Failed to open storage; skipping catch-up"),
         }
 
         job_scheduler
@@ -80,10 +252,112 @@ impl Scheduler {
             .await
             .map_err(|e| anyhow::anyhow!("{:?}", e))?;
 
-        Ok(Self { job_scheduler })
+        Ok(Self {
+            job_scheduler,
+            jobs,
+            ctx,
+            reload: None,
+        })
+    }
+
+    /// Run the named schedule now using this scheduler's model pool.
+    ///
+    /// The run shares the job's run slots with its cron firings, so `max_concurrency` and
+    /// the `overlap` policy apply to it too.
+    pub async fn run(&self, name: &str) -> Result<String> {
+        let tasks = shared::schedule_store::load(self.ctx.run.model_config.schedules.as_deref());
+        let task = shared::schedule_store::find(&tasks, name)
+            .ok_or_else(|| anyhow::anyhow!("Schedule '{}' not found", name))?;
+        let slots = self
+            .jobs
+            .lock()
+            .await
+            .get(&task.name)
+            .map(|job| job.slots.clone());
+        let Some(slots) = slots else {
+            // Paused schedules have no job, so no cron firing can overlap this run.
+            let _guard = self.ctx.in_flight.enter();
+            return run_task(&self.ctx.run, task, RunTrigger::Manual)
+                .await
+                .map(|output| output.response);
+        };
+        match run_in_slots(&self.ctx, &slots, task, RunTrigger::Manual).await {
+            SlotOutcome::Finished(result) => result.map(|output| output.response),
+            SlotOutcome::Skipped => Err(anyhow!("Schedule '{}' is already running", task.name)),
+            SlotOutcome::Cancelled => Err(anyhow!(
+                "Schedule '{}' was cancelled by a newer run",
+                task.name
+            )),
+        }
+    }
+
+    /// Re-read schedules.json now instead of waiting for the next reload poll.
+    pub async fn reload(&self) {
+        let tasks = shared::schedule_store::load(self.ctx.run.model_config.schedules.as_deref());
+        sync_jobs(&self.job_scheduler, &self.jobs, &self.ctx, &tasks).await;
+    }
+
+    /// Run the named schedule immediately, whether or not it is enabled.
+    ///
+    /// A running daemon executes the run, so `hi cron run`, `/cron run` and the
+    /// `cron_control` tool all go through its scheduler. Without one, the scheduler of this
+    /// process's chat sessions runs it, as [`Scheduler::run`] does. Only when neither is
+    /// running does it use a one-off model pool. Either way it is recorded as manual.
+    pub async fn run_now(model_config: &ModelConfig, name: &str) -> Result<String> {
+        let request = ControlRequest::CronRun {
+            name: name.to_string(),
+        };
+        if let Some(response) = crate::control::request(&request).await? {
+            return Ok(response.into_result()?.message);
+        }
+        if let Some(services) = BackgroundServices::running().await
+            && let Some(scheduler) = &services.scheduler
+        {
+            return scheduler.run(name).await;
+        }
+
+        let tasks = shared::schedule_store::load(model_config.schedules.as_deref());
+        let task = shared::schedule_store::find(&tasks, name)
+            .ok_or_else(|| anyhow::anyhow!("Schedule '{}' not found", name))?;
+        let ctx = RunContext::new(model_config, Arc::new(ModelPool::new()));
+        run_task(&ctx, task, RunTrigger::Manual)
+            .await
+            .map(|output| output.response)
+    }
+
+    /// Build a runner for the `cron_control` tool's `run` action.
+    pub fn runner(model_config: &ModelConfig) -> ScheduleRunFn {
+        let model_config = model_config.clone();
+        Arc::new(move |name: String| {
+            let model_config = model_config.clone();
+            Box::pin(async move { Self::run_now(&model_config, &name).await })
+        })
+    }
+
+    /// Number of schedules currently registered with the job scheduler.
+    pub async fn job_count(&self) -> usize {
+        self.jobs.lock().await.len()
+    }
+
+    /// Number of schedule runs currently in progress.
+    pub fn in_flight(&self) -> usize {
+        self.ctx.in_flight.count()
+    }
+
+    /// Stop firing new runs, then wait up to `grace` for in-progress runs to finish.
+    ///
+    /// Returns `false` if runs were still in progress when the grace period ran out.
+    pub async fn shutdown(&mut self, grace: Duration) -> Result<bool> {
+        self.stop().await?;
+        Ok(tokio::time::timeout(grace, self.ctx.in_flight.wait_idle())
+            .await
+            .is_ok())
     }
 
     pub async fn stop(&mut self) -> Result<()> {
+        if let Some(reload) = self.reload.take() {
+            reload.abort();
+        }
         self.job_scheduler
             .shutdown()
             .await
@@ -94,10 +368,368 @@ impl Scheduler {
 
 impl Drop for Scheduler {
     fn drop(&mut self) {
+        if let Some(reload) = self.reload.take() {
+            reload.abort();
+        }
         let _ = self.job_scheduler.shutdown();
     }
 }
 
+/// Tasks that should currently have a job: enabled, and not an already-fired one-shot.
+fn active_tasks<'a>(
+    tasks: &'a [ScheduleTaskConfig],
+    archive: &[shared::schedule_store::ArchivedSchedule],
+) -> Vec<&'a ScheduleTaskConfig> {
+    tasks
+        .iter()
+        .filter(|t| t.enabled)
+        .filter(|t| !(t.is_one_shot() && shared::schedule_store::is_archived(archive, t)))
+        .collect()
+}
+
+/// Reconcile registered jobs with `tasks`: drop removed or changed ones, add new ones.
+async fn sync_jobs(
+    job_scheduler: &JobScheduler,
+    jobs: &JobTable,
+    ctx: &JobContext,
+    tasks: &[ScheduleTaskConfig],
+) {
+    let archive = shared::schedule_store::load_archive();
+    let desired: HashMap<&str, (&ScheduleTaskConfig, String)> = active_tasks(tasks, &archive)
+        .into_iter()
+        .map(|t| {
+            let fingerprint = serde_json::to_string(t).unwrap_or_default();
+            (t.name.as_str(), (t, fingerprint))
+        })
+        .collect();
+
+    let mut jobs = jobs.lock().await;
+
+    let stale: Vec<String> = jobs
+        .iter()
+        .filter(|(name, job)| {
+            desired
+                .get(name.as_str())
+                .is_none_or(|(_, fingerprint)| *fingerprint != job.fingerprint)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in stale {
+        if let Some(job) = jobs.remove(&name) {
+            // One-shot jobs remove themselves after firing, so a failure here is expected.
+            let _ = job_scheduler.remove(&job.id).await;
+            info!(schedule = name, "Unregistered schedule");
+        }
+    }
+
+    for (name, (task, fingerprint)) in desired {
+        if jobs.contains_key(name) {
+            continue;
+        }
+        let slots = Arc::new(RunSlots::new(task));
+        let job = match build_job(task, ctx, slots.clone()) {
+            Ok(job) => job,
+            Err(e) => {
+                warn!(schedule = name, error = %e, "Skipping invalid schedule");
+                continue;
+            }
+        };
+        match job_scheduler.add(job).await {
+            Ok(id) => {
+                info!(
+                    schedule = name,
+                    one_shot = task.is_one_shot(),
+                    "Registered schedule"
+                );
+                jobs.insert(
+                    name.to_string(),
+                    RegisteredJob {
+                        id,
+                        fingerprint,
+                        slots,
+                    },
+                );
+            }
+            Err(e) => warn!(schedule = name, error = ?e, "Failed to register schedule"),
+        }
+    }
+}
+
+/// Replay cron firings missed while hi was not running, per each task's `catch_up` policy.
+///
+/// Missed firings are worked out from the persisted last-run times. Tasks that have never
+/// fired get a baseline so that downtime from now on can be detected. Returns the number of
+/// runs replayed.
+async fn catch_up(
+    ctx: &JobContext,
+    jobs: &JobTable,
+    tasks: &[ScheduleTaskConfig],
+    storage: &dyn Storage,
+) -> usize {
+    let now = Utc::now();
+    let last_runs = storage.load_last_runs().unwrap_or_else(|e| {
+        warn!(error = %e, "Failed to load last schedule runs");
+        HashMap::new()
+    });
+    let jobs = jobs.lock().await;
+    let mut replayed = 0;
+
+    for task in tasks {
+        if task.catch_up == CatchUpPolicy::Skip || task.is_one_shot() {
+            continue;
+        }
+        let Some(job) = jobs.get(&task.name) else {
+            continue;
+        };
+        let Some(last_run) = last_runs.get(&task.name) else {
+            if let Err(e) = storage.record_last_run(&task.name, now) {
+                warn!(schedule = task.name, error = %e, "Failed to record schedule state");
+            }
+            continue;
+        };
+
+        let missed = match missed_firings(task, *last_run, now) {
+            Ok(missed) if !missed.is_empty() => missed,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!(schedule = task.name, error = %e, "Failed to compute missed runs");
+                continue;
+            }
+        };
+        let latest = missed[missed.len() - 1];
+        if let Err(e) = storage.record_last_run(&task.name, latest) {
+            warn!(schedule = task.name, error = %e, "Failed to record schedule state");
+        }
+
+        info!(
+            schedule = task.name,
+            missed = missed.len(),
+            policy = ?task.catch_up,
+            "Catching up missed schedule runs"
+        );
+        let ctx = ctx.clone();
+        let task = task.clone();
+        let slots = job.slots.clone();
+        let runs = missed.len();
+        replayed += runs;
+        tokio::spawn(async move {
+            for _ in 0..runs {
+                deliver(&ctx, &slots, &task, RunTrigger::CatchUp).await;
+            }
+        });
+    }
+    replayed
+}
+
+/// Firings of a cron task after `last_run` and up to `now` that its catch-up policy wants
+/// replayed, oldest first.
+fn missed_firings(
+    task: &ScheduleTaskConfig,
+    last_run: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>> {
+    let limit = match task.catch_up {
+        CatchUpPolicy::Skip => return Ok(Vec::new()),
+        CatchUpPolicy::RunOnce => 1,
+        CatchUpPolicy::RunAll => task.catch_up_limit.unwrap_or(DEFAULT_CATCH_UP_LIMIT),
+    };
+    let cron = croner::Cron::from_str(task.cron.trim())
+        .map_err(|e| anyhow!("Invalid cron expression '{}': {}", task.cron, e))?;
+    let tz = shared::schedule_time::parse_timezone(task.timezone.as_deref())?;
+
+    // Walk backwards from now so long outages stay cheap and the most recent runs are kept.
+    let mut missed: Vec<DateTime<Utc>> = cron
+        .iter_before(now.with_timezone(&tz))
+        .map(|t| t.with_timezone(&Utc))
+        .take_while(|t| *t > last_run)
+        .take(limit)
+        .collect();
+    missed.reverse();
+    Ok(missed)
+}
+
+fn build_job(task: &ScheduleTaskConfig, ctx: &JobContext, slots: Arc<RunSlots>) -> Result<Job> {
+    let ctx = ctx.clone();
+    let task = task.clone();
+
+    if task.is_one_shot() {
+        let fire_at = shared::schedule_time::parse_at(task.at.as_deref().unwrap_or_default())?;
+        // Reminders missed while hi was not running fire as soon as possible.
+        let delay = (fire_at - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+        return Job::new_one_shot_async(delay, move |_uuid, _lock| {
+            let ctx = ctx.clone();
+            let task = task.clone();
+            let slots = slots.clone();
+            Box::pin(async move {
+                if !deliver(&ctx, &slots, &task, RunTrigger::OneShot).await {
+                    // Failed runs are in the run history; the next start fires it again.
+                    warn!(
+                        schedule = task.name,
+                        "One-shot schedule did not complete; keeping it"
+                    );
+                    return;
+                }
+                if let Err(e) = shared::schedule_store::archive_one_shot(&task, Utc::now()) {
+                    warn!(schedule = task.name, error = %e, "Failed to archive one-shot schedule");
+                }
+            })
+        })
+        .map_err(|e| anyhow::anyhow!("{:?}", e));
+    }
+
+    let tz = shared::schedule_time::parse_timezone(task.timezone.as_deref())?;
+    let cron = with_seconds_field(&task.cron);
+    Job::new_async_tz(cron.as_str(), tz, move |_uuid, _lock| {
+        let ctx = ctx.clone();
+        let task = task.clone();
+        let slots = slots.clone();
+        Box::pin(async move {
+            deliver(&ctx, &slots, &task, RunTrigger::Cron).await;
+        })
+    })
+    .map_err(|e| anyhow::anyhow!("{:?}", e))
+}
+
+/// Schedules use five-field cron; the job scheduler expects a leading seconds field.
+fn with_seconds_field(cron: &str) -> String {
+    let cron = cron.trim();
+    if cron.split_whitespace().count() == 5 {
+        format!("0 {cron}")
+    } else {
+        cron.to_string()
+    }
+}
+
+/// Run a scheduled task through its run slots and send a successful result to the
+/// background channel. Returns whether the run finished without an error.
+async fn deliver(
+    ctx: &JobContext,
+    slots: &RunSlots,
+    task: &ScheduleTaskConfig,
+    trigger: RunTrigger,
+) -> bool {
+    match run_in_slots(ctx, slots, task, trigger).await {
+        SlotOutcome::Finished(Ok(output)) if output.deliver => {
+            let _ = ctx
+                .tx
+                .send(format!("[schedule:{}] {}", task.name, output.response));
+            true
+        }
+        SlotOutcome::Finished(Ok(_)) => {
+            info!(schedule = task.name, %trigger, "Output contract withheld delivery");
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Run a scheduled task through its run slots, count it in the metrics, and record a run
+/// that was cancelled by a newer one.
+async fn run_in_slots(
+    ctx: &JobContext,
+    slots: &RunSlots,
+    task: &ScheduleTaskConfig,
+    trigger: RunTrigger,
+) -> SlotOutcome<Result<RunOutput>> {
+    let _guard = ctx.in_flight.enter();
+    let started_at = Utc::now();
+    if trigger == RunTrigger::Cron
+        && let Err(e) = shared::schedule_store::record_last_run(&task.name, started_at)
+    {
+        warn!(schedule = task.name, error = %e, "Failed to record schedule state");
+    }
+
+    let run_ctx = ctx.run.clone();
+    let run = task.clone();
+    let outcome = slots
+        .run(async move { run_task(&run_ctx, &run, trigger).await })
+        .await;
+
+    let label = match &outcome {
+        SlotOutcome::Finished(Ok(output)) if output.deliver => "ok",
+        SlotOutcome::Finished(Ok(_)) => "suppressed",
+        SlotOutcome::Finished(Err(_)) => "error",
+        SlotOutcome::Skipped => "skipped",
+        SlotOutcome::Cancelled => "cancelled",
+    };
+    crate::metrics::metrics()
+        .schedule_runs
+        .with_label_values(&[task.name.as_str(), label])
+        .inc();
+
+    match &outcome {
+        SlotOutcome::Finished(_) => {}
+        SlotOutcome::Skipped => {
+            info!(schedule = task.name, %trigger, "Skipping run; previous run still in progress");
+        }
+        SlotOutcome::Cancelled => {
+            warn!(schedule = task.name, %trigger, "Run cancelled by a newer run");
+            let run = ScheduleRun::new(
+                &task.name,
+                trigger,
+                started_at,
+                &Err(anyhow!("cancelled by a newer run")),
+            );
+            if let Err(e) = shared::schedule_store::record_run(&run) {
+                warn!(schedule = task.name, error = %e, "Failed to record schedule run");
+            }
+        }
+    }
+    outcome
+}
+
+/// A successful run's response and whether its output contract allows delivery.
+struct RunOutput {
+    response: String,
+    deliver: bool,
+}
+
+/// Execute a schedule prompt, check it against the output contract, and record the outcome
+/// in the run history.
+async fn run_task(
+    ctx: &RunContext,
+    task: &ScheduleTaskConfig,
+    trigger: RunTrigger,
+) -> Result<RunOutput> {
+    let started_at = Utc::now();
+    let mut deliver = true;
+    let result = execute_prompt(ctx, task).await.and_then(|response| {
+        if let Some(contract) = &task.output {
+            deliver = shared::schedule_output::evaluate(contract, &response)?;
+        }
+        Ok(response)
+    });
+    if let Err(e) = &result {
+        warn!(schedule = task.name, %trigger, error = %e, "Schedule run failed");
+    }
+
+    let mut run = ScheduleRun::new(&task.name, trigger, started_at, &result);
+    run.suppressed = result.is_ok() && !deliver;
+    if let Err(e) = shared::schedule_store::record_run(&run) {
+        warn!(schedule = task.name, error = %e, "Failed to record schedule run");
+    }
+    result.map(|response| RunOutput { response, deliver })
+}
+
+async fn execute_prompt(ctx: &RunContext, task: &ScheduleTaskConfig) -> Result<String> {
+    let cfg = ctx.model_config.resolve_model_ref(&task.model);
+    let agent = ctx.pool.get_or_create(&cfg, Some(&ctx.preamble))?;
+
+    let prompt = match task
+        .output
+        .as_ref()
+        .and_then(shared::schedule_output::instructions)
+    {
+        Some(instructions) => format!("{}\n\n{}", task.prompt, instructions),
+        None => task.prompt.clone(),
+    };
+    let history = vec![];
+    let response = agent
+        .chat(rig::completion::message::Message::user(&prompt), history)
+        .await?;
+    Ok(response)
+}
+
 impl Scheduler {
     /// Start the scheduler, enabling the first schedule if none are enabled.
     /// Returns a tuple of (Scheduler, bool) where the bool indicates if a schedule was auto-enabled.
@@ -141,6 +773,9 @@ mod tests {
             remote: None,
             memory: None,
             thinking: None,
+            metrics: None,
+            storage: None,
+            recall: None,
         }
     }
 
@@ -153,6 +788,7 @@ mod tests {
                 model: None,
                 prompt: "task 1".to_string(),
                 enabled: false,
+                ..Default::default()
             },
             ScheduleTaskConfig {
                 name: "second".to_string(),
@@ -160,6 +796,7 @@ mod tests {
                 model: None,
                 prompt: "task 2".to_string(),
                 enabled: false,
+                ..Default::default()
             },
         ];
 
@@ -184,6 +821,7 @@ mod tests {
                 model: None,
                 prompt: "task 1".to_string(),
                 enabled: false,
+                ..Default::default()
             },
             ScheduleTaskConfig {
                 name: "second".to_string(),
@@ -191,6 +829,7 @@ mod tests {
                 model: None,
                 prompt: "task 2".to_string(),
                 enabled: true,
+                ..Default::default()
             },
         ];
 
@@ -230,6 +869,7 @@ mod tests {
                 model: None,
                 prompt: "disabled task".to_string(),
                 enabled: false,
+                ..Default::default()
             },
             ScheduleTaskConfig {
                 name: "enabled".to_string(),
@@ -237,6 +877,7 @@ mod tests {
                 model: None,
                 prompt: "enabled task".to_string(),
                 enabled: true,
+                ..Default::default()
             },
         ];
 
@@ -254,6 +895,7 @@ mod tests {
                 model: None,
                 prompt: "task 1".to_string(),
                 enabled: false,
+                ..Default::default()
             },
             ScheduleTaskConfig {
                 name: "second".to_string(),
@@ -261,10 +903,326 @@ mod tests {
                 model: None,
                 prompt: "task 2".to_string(),
                 enabled: false,
+                ..Default::default()
             },
         ];
 
         let enabled_tasks: Vec<_> = tasks.iter().filter(|t| t.enabled).collect();
         assert!(enabled_tasks.is_empty());
     }
+
+    #[test]
+    fn test_active_tasks_skips_fired_one_shots() {
+        let reminder = ScheduleTaskConfig {
+            name: "reminder".to_string(),
+            prompt: "call Alice".to_string(),
+            enabled: true,
+            at: Some("2026-03-05T09:00:00+00:00".to_string()),
+            ..Default::default()
+        };
+        let cron = ScheduleTaskConfig {
+            name: "daily".to_string(),
+            cron: "0 0 * * *".to_string(),
+            prompt: "summarize".to_string(),
+            enabled: true,
+            ..Default::default()
+        };
+        let tasks = vec![reminder.clone(), cron];
+
+        assert_eq!(active_tasks(&tasks, &[]).len(), 2);
+
+        let archive = vec![shared::schedule_store::ArchivedSchedule {
+            task: reminder,
+            fired_at: "2026-03-05T09:00:01+00:00".to_string(),
+        }];
+        let active = active_tasks(&tasks, &archive);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].name, "daily");
+    }
+
+    #[test]
+    fn test_with_seconds_field() {
+        assert_eq!(with_seconds_field("0 9 * * 1-5"), "0 0 9 * * 1-5");
+        assert_eq!(with_seconds_field(" 30 0 9 * * * "), "30 0 9 * * *");
+    }
+
+    #[tokio::test]
+    async fn test_sync_jobs_registers_and_unregisters() {
+        let job_scheduler = JobScheduler::new().await.unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let ctx = JobContext {
+            run: RunContext {
+                model_config: make_test_config(),
+                pool: Arc::new(ModelPool::new()),
+                preamble: String::new(),
+            },
+            tx,
+            in_flight: Arc::new(InFlight::default()),
+        };
+        let jobs: JobTable = Arc::new(Mutex::new(HashMap::new()));
+
+        let mut tasks = vec![
+            ScheduleTaskConfig {
+                name: "taipei-morning".to_string(),
+                cron: "0 9 * * *".to_string(),
+                prompt: "morning".to_string(),
+                enabled: true,
+                timezone: Some("Asia/Taipei".to_string()),
+                ..Default::default()
+            },
+            ScheduleTaskConfig {
+                name: "later".to_string(),
+                prompt: "remind".to_string(),
+                enabled: true,
+                at: Some((Utc::now() + chrono::Duration::days(1)).to_rfc3339()),
+                ..Default::default()
+            },
+            ScheduleTaskConfig {
+                name: "bad-tz".to_string(),
+                cron: "0 9 * * *".to_string(),
+                prompt: "never".to_string(),
+                enabled: true,
+                timezone: Some("Nowhere/Special".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        sync_jobs(&job_scheduler, &jobs, &ctx, &tasks).await;
+        {
+            let registered = jobs.lock().await;
+            assert_eq!(registered.len(), 2);
+            assert!(registered.contains_key("taipei-morning"));
+            assert!(registered.contains_key("later"));
+        }
+
+        tasks[0].enabled = false;
+        sync_jobs(&job_scheduler, &jobs, &ctx, &tasks).await;
+        let registered = jobs.lock().await;
+        assert_eq!(registered.len(), 1);
+        assert!(registered.contains_key("later"));
+    }
+
+    fn catch_up_task(policy: CatchUpPolicy) -> ScheduleTaskConfig {
+        ScheduleTaskConfig {
+            name: "hourly".to_string(),
+            cron: "0 * * * *".to_string(),
+            prompt: "check".to_string(),
+            enabled: true,
+            catch_up: policy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_missed_firings_by_policy() {
+        let last_run = "2026-03-05T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let now = "2026-03-05T13:30:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let skip = catch_up_task(CatchUpPolicy::Skip);
+        assert!(missed_firings(&skip, last_run, now).unwrap().is_empty());
+
+        let once = catch_up_task(CatchUpPolicy::RunOnce);
+        let missed = missed_firings(&once, last_run, now).unwrap();
+        assert_eq!(
+            missed,
+            vec!["2026-03-05T13:00:00Z".parse::<DateTime<Utc>>().unwrap()]
+        );
+
+        let mut all = catch_up_task(CatchUpPolicy::RunAll);
+        let missed = missed_firings(&all, last_run, now).unwrap();
+        assert_eq!(missed.len(), 4);
+        assert_eq!(missed[0].to_rfc3339(), "2026-03-05T10:00:00+00:00");
+        assert_eq!(missed[3].to_rfc3339(), "2026-03-05T13:00:00+00:00");
+
+        all.catch_up_limit = Some(2);
+        let missed = missed_firings(&all, last_run, now).unwrap();
+        assert_eq!(missed.len(), 2);
+        assert_eq!(missed[0].to_rfc3339(), "2026-03-05T12:00:00+00:00");
+
+        let recent = "2026-03-05T13:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(missed_firings(&all, recent, now).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_skips_firings_while_paused() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = shared::storage::FileStorage::new(dir.path().to_path_buf());
+        let tasks = vec![catch_up_task(CatchUpPolicy::RunAll)];
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let ctx = JobContext::new(&make_test_config(), Arc::new(ModelPool::new()), tx);
+        let jobs: JobTable = Arc::default();
+        jobs.lock().await.insert(
+            "hourly".to_string(),
+            RegisteredJob {
+                id: Uuid::new_v4(),
+                fingerprint: String::new(),
+                slots: slots(1, OverlapPolicy::Skip),
+            },
+        );
+
+        shared::schedule_store::set_enabled(&storage, Some(&tasks), "hourly", false).unwrap();
+        let paused_at = Utc::now() - chrono::Duration::days(4);
+        storage.record_last_run("hourly", paused_at).unwrap();
+        shared::schedule_store::set_enabled(&storage, None, "hourly", true).unwrap();
+
+        let tasks = storage.load_schedules().unwrap().unwrap();
+        assert_eq!(catch_up(&ctx, &jobs, &tasks, &storage).await, 0);
+    }
+
+    #[test]
+    fn test_missed_firings_uses_timezone() {
+        let mut task = catch_up_task(CatchUpPolicy::RunAll);
+        task.cron = "0 9 * * *".to_string();
+        task.timezone = Some("Asia/Taipei".to_string());
+        let last_run = "2026-03-04T01:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let now = "2026-03-06T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let missed = missed_firings(&task, last_run, now).unwrap();
+        assert_eq!(missed.len(), 1);
+        assert_eq!(missed[0].to_rfc3339(), "2026-03-05T01:00:00+00:00");
+    }
+
+    fn slots(max_concurrency: usize, overlap: OverlapPolicy) -> Arc<RunSlots> {
+        Arc::new(RunSlots::new(&ScheduleTaskConfig {
+            max_concurrency: Some(max_concurrency),
+            overlap,
+            ..Default::default()
+        }))
+    }
+
+    /// Start a run that stays in flight until the returned sender fires.
+    async fn start_blocking_run(
+        slots: &Arc<RunSlots>,
+    ) -> (
+        tokio::sync::oneshot::Sender<()>,
+        tokio::task::JoinHandle<SlotOutcome<&'static str>>,
+    ) {
+        let (release, wait) = tokio::sync::oneshot::channel::<()>();
+        let available = slots.permits.available_permits();
+        let runner = slots.clone();
+        let handle = tokio::spawn(async move {
+            runner
+                .run(async move {
+                    let _ = wait.await;
+                    "first"
+                })
+                .await
+        });
+        while slots.permits.available_permits() == available {
+            tokio::task::yield_now().await;
+        }
+        (release, handle)
+    }
+
+    #[tokio::test]
+    async fn test_overlap_skip() {
+        let slots = slots(1, OverlapPolicy::Skip);
+        let (release, first) = start_blocking_run(&slots).await;
+
+        assert!(matches!(
+            slots.run(async { "second" }).await,
+            SlotOutcome::Skipped
+        ));
+
+        release.send(()).unwrap();
+        assert!(matches!(
+            first.await.unwrap(),
+            SlotOutcome::Finished("first")
+        ));
+        assert!(matches!(
+            slots.run(async { "third" }).await,
+            SlotOutcome::Finished("third")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_manual_run_shares_slots() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let ctx = JobContext::new(&make_test_config(), Arc::new(ModelPool::new()), tx);
+        let slots = slots(1, OverlapPolicy::Skip);
+        let (release, first) = start_blocking_run(&slots).await;
+
+        let task = catch_up_task(CatchUpPolicy::Skip);
+        assert!(matches!(
+            run_in_slots(&ctx, &slots, &task, RunTrigger::Manual).await,
+            SlotOutcome::Skipped
+        ));
+        assert_eq!(ctx.in_flight.count(), 0);
+
+        release.send(()).unwrap();
+        first.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_overlap_queue() {
+        let slots = slots(1, OverlapPolicy::Queue);
+        let (release, first) = start_blocking_run(&slots).await;
+
+        let queued = {
+            let slots = slots.clone();
+            tokio::spawn(async move { slots.run(async { "second" }).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!queued.is_finished());
+
+        release.send(()).unwrap();
+        assert!(matches!(
+            first.await.unwrap(),
+            SlotOutcome::Finished("first")
+        ));
+        assert!(matches!(
+            queued.await.unwrap(),
+            SlotOutcome::Finished("second")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_overlap_cancel_previous() {
+        let slots = slots(1, OverlapPolicy::CancelPrevious);
+        let (_release, first) = start_blocking_run(&slots).await;
+
+        assert!(matches!(
+            slots.run(async { "second" }).await,
+            SlotOutcome::Finished("second")
+        ));
+        assert!(matches!(first.await.unwrap(), SlotOutcome::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency_allows_parallel_runs() {
+        let slots = slots(2, OverlapPolicy::Skip);
+        let (release, first) = start_blocking_run(&slots).await;
+
+        assert!(matches!(
+            slots.run(async { "second" }).await,
+            SlotOutcome::Finished("second")
+        ));
+        release.send(()).unwrap();
+        assert!(matches!(
+            first.await.unwrap(),
+            SlotOutcome::Finished("first")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_wait_idle() {
+        let in_flight = Arc::new(InFlight::default());
+        in_flight.wait_idle().await;
+
+        let guard = in_flight.enter();
+        let second = in_flight.enter();
+        assert_eq!(in_flight.count(), 2);
+        let waiter = {
+            let in_flight = in_flight.clone();
+            tokio::spawn(async move { in_flight.wait_idle().await })
+        };
+        drop(guard);
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+        drop(second);
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
 }
@@ -0,0 +1,107 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use shared::config::ModelConfig;
+use tokio::sync::{Mutex, mpsc};
+
+use crate::heartbeat::HeartbeatSystem;
+use crate::model_pool::ModelPool;
+use crate::scheduler::Scheduler;
+
+/// The services shared by this process's chat sessions, while any session holds them.
+static SESSION_SERVICES: Mutex<Weak<BackgroundServices>> = Mutex::const_new(Weak::new());
+
+/// Heartbeat and scheduler, started together by chat sessions and by `hi daemon`.
+pub struct BackgroundServices {
+    pub heartbeat: Option<HeartbeatSystem>,
+    pub scheduler: Option<Scheduler>,
+}
+
+impl BackgroundServices {
+    /// The services for a chat session. Every session of a process shares one instance,
+    /// which stops when the last session is dropped. Returns `None` while a daemon answers
+    /// on the control socket, since the daemon runs the services itself.
+    pub async fn for_session(config: &ModelConfig) -> Option<Arc<Self>> {
+        if let Ok(socket) = crate::control::socket_path()
+            && crate::control::is_listening(&socket).await
+        {
+            tracing::info!("Daemon is running; leaving heartbeat and scheduler to it");
+            return None;
+        }
+
+        let mut shared = SESSION_SERVICES.lock().await;
+        if let Some(services) = shared.upgrade() {
+            return Some(services);
+        }
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let services = Arc::new(Self::start(config, tx).await);
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                tracing::info!("[background] {}", msg);
+            }
+        });
+        *shared = Arc::downgrade(&services);
+        Some(services)
+    }
+
+    /// The services this process's chat sessions share, while any session holds them.
+    pub async fn running() -> Option<Arc<Self>> {
+        SESSION_SERVICES.lock().await.upgrade()
+    }
+
+    /// Start whichever services are configured. Failures are logged and leave that
+    /// service off rather than failing the caller.
+    pub async fn start(config: &ModelConfig, tx: mpsc::UnboundedSender<String>) -> Self {
+        let heartbeat = config.heartbeat.as_ref().and_then(|hb_config| {
+            if hb_config.enabled {
+                HeartbeatSystem::start(hb_config, config, tx.clone())
+                    .map_err(|e| tracing::warn!("Failed to start heartbeat: {}", e))
+                    .ok()
+            } else {
+                None
+            }
+        });
+
+        let pool = Arc::new(ModelPool::new());
+        let scheduler = match Scheduler::start_with_store(config, pool, tx).await {
+            Ok(sch) => {
+                tracing::info!(
+                    "Scheduler started with {} active schedule(s)",
+                    sch.job_count().await
+                );
+                Some(sch)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to start scheduler: {}", e);
+                None
+            }
+        };
+
+        Self {
+            heartbeat,
+            scheduler,
+        }
+    }
+
+    /// Stop both services, giving in-progress heartbeat ticks and schedule runs up to
+    /// `grace` to finish. Returns `false` if anything had to be abandoned.
+    pub async fn shutdown(&mut self, grace: Duration) -> bool {
+        let heartbeat = async {
+            match self.heartbeat.as_mut() {
+                Some(heartbeat) => heartbeat.shutdown(grace).await,
+                None => true,
+            }
+        };
+        let scheduler = async {
+            match self.scheduler.as_mut() {
+                Some(scheduler) => scheduler.shutdown(grace).await.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to stop scheduler: {}", e);
+                    false
+                }),
+                None => true,
+            }
+        };
+        let (heartbeat_done, scheduler_done) = tokio::join!(heartbeat, scheduler);
+        heartbeat_done && scheduler_done
+    }
+}
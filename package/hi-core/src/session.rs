@@ -1,25 +1,43 @@
+use std::sync::Arc;
+use std::time::Instant;
+
 use anyhow::Result;
 use rig::completion::message::Message;
-use std::sync::Arc;
+use rig::tool::Tool;
 use tokio::sync::mpsc;
+use tracing::field::Empty;
+use tracing::info_span;
 
-use hi_history::{ChatHistory, ChatMessage};
-use shared::config::{CompactStrategy, ModelConfig};
+use hi_history::{ChatHistory, ChatMessage, HistorySnapshot, Retention, TokenUsage};
+use hi_tools::{PinArgs, PinTool};
+use shared::config::{CompactStrategy, ModelConfig, SmallModelConfig};
 
 use crate::context::ContextManager;
-use crate::heartbeat::HeartbeatSystem;
 use crate::mcp::{McpManager, load_and_connect};
-use crate::model_pool::ModelPool;
 use crate::scheduler::Scheduler;
 use crate::provider::{
     ChatAgent, create_agent, create_agent_from_small, create_agent_from_small_with_tools,
 };
+use crate::services::BackgroundServices;
 use crate::skills::{Skill, build_preamble, load_skills};
+use crate::summarize::{Stage, chunk_bytes, summarize_chunked};
+use crate::telemetry::ModelRequest;
+use crate::tool_audit::{ToolCall, collect_calls};
 
 const DEFAULT_COMPACT_PROMPT: &str = "Summarize the following conversation concisely. \
 Preserve key topics, decisions, tool results, and any context needed to continue naturally. \
 Output only the summary, no preamble.";
 
+const REDUCE_PROMPT: &str = "The following are summaries of consecutive parts of one \
+conversation. Combine them into a single concise summary, in order. Preserve key topics, \
+decisions, tool results, and any context needed to continue naturally. \
+Output only the summary, no preamble.";
+
+const GIST_PROMPT: &str = "The following are the long-term gist of a conversation, if it has \
+one, and summaries of later parts of it. Merge them into a brief long-term gist: lasting facts, \
+goals, decisions and preferences. Drop details that no longer matter. \
+Output only the gist, no preamble.";
+
 pub const DEFAULT_PREAMBLE: &str = "You are a helpful assistant with access to tools. \
 Use them when appropriate to fulfill user requests.";
 
@@ -46,15 +64,33 @@ fn limited_rig_messages(
     selected.iter().map(ChatMessage::to_rig_message).collect()
 }
 
-fn refresh_runtime_index(config: &ModelConfig, data_dir: &std::path::Path) {
+/// Ask the compaction model to apply `prompt` to `text`.
+async fn summarize_with(
+    agent: &ChatAgent,
+    model: &SmallModelConfig,
+    prompt: &str,
+    language: Option<&str>,
+    text: String,
+) -> Result<String> {
+    let prompt = match language {
+        Some(lang) => format!("{prompt}\n\n[Current user language: {lang}]\n\n{text}"),
+        None => format!("{prompt}\n\n{text}"),
+    };
+    let model_request = ModelRequest::new(&model.provider.to_string(), &model.model);
+    Ok(model_request
+        .run(agent.chat(Message::user(&prompt), vec![]))
+        .await?)
+}
+
+pub(crate) fn refresh_runtime_index(config: &ModelConfig, data_dir: &std::path::Path) {
     let memory_path = data_dir.join("memory.md");
     let memory_sections = shared::runtime_index::refresh_memory_sections(&memory_path);
     let schedules = shared::schedule_store::load(config.schedules.as_deref());
     let schedule_names = shared::runtime_index::refresh_schedule_names(&schedules);
-    let mut index = shared::runtime_index::load();
-    index.memory_sections = memory_sections;
-    index.schedule_names = schedule_names;
-    let _ = shared::runtime_index::save(&index);
+    let _ = shared::runtime_index::update(|index| {
+        index.memory_sections = memory_sections;
+        index.schedule_names = schedule_names;
+    });
 }
 
 pub struct ChatSession {
@@ -66,14 +102,15 @@ pub struct ChatSession {
     using_small_model: bool,
     _mcp_manager: McpManager,
     mcp_tool_names: Vec<String>,
+    /// Identifies this session in the tool audit log, e.g. `tui` or `telegram:42`.
+    label: String,
+    /// Held so that the process's shared services run while this session is open.
     #[allow(dead_code)]
-    heartbeat: Option<HeartbeatSystem>,
-    #[allow(dead_code)]
-    scheduler: Option<Scheduler>,
+    services: Option<Arc<BackgroundServices>>,
 }
 
 impl ChatSession {
-    pub async fn new(config: ModelConfig) -> Result<Self> {
+    pub async fn new(config: ModelConfig, label: &str) -> Result<Self> {
         let config_dir = shared::paths::config_dir()?;
         let data_dir = shared::paths::data_dir()?;
 
@@ -85,48 +122,20 @@ impl ChatSession {
         let (mcp_manager, mcp_tools) = load_and_connect().await;
         let mcp_tool_names: Vec<String> = mcp_tools.iter().map(|t| t.name().to_string()).collect();
 
-        let agent = create_agent(&config, Some(&preamble), skill_summaries, mcp_tools)?;
-        let history = ChatHistory::load(&data_dir)?;
+        let agent = create_agent(&config, Some(&preamble), skill_summaries, mcp_tools, label)?;
+        let mut history = ChatHistory::open(shared::storage::handle()?)?;
+        history.set_session(label);
+        if let Some(memory) = &config.memory {
+            history.set_memory_config(memory.clone());
+        }
+        if let Some(compact) = &config.compact {
+            history.set_retention(Retention::from(compact));
+        }
         let context_manager = ContextManager::new();
 
         refresh_runtime_index(&config, &data_dir);
 
-        let (background_tx, mut background_rx) = mpsc::unbounded_channel::<String>();
-
-        let heartbeat = config.heartbeat.as_ref().and_then(|hb_config| {
-            if hb_config.enabled {
-                HeartbeatSystem::start(hb_config, &config, background_tx.clone())
-                    .map_err(|e| tracing::warn!("Failed to start heartbeat: {}", e))
-                    .ok()
-            } else {
-                None
-            }
-        });
-
-        let schedules = shared::schedule_store::load(config.schedules.as_deref());
-        let has_enabled_schedule = schedules.iter().any(|s| s.enabled);
-        let scheduler = if has_enabled_schedule {
-            let pool = Arc::new(ModelPool::new());
-            match Scheduler::start(&schedules, &config, pool, background_tx.clone()).await {
-                Ok(sch) => {
-                    tracing::info!("Scheduler started with {} enabled schedule(s)", 
-                        schedules.iter().filter(|s| s.enabled).count());
-                    Some(sch)
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to start scheduler: {}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
-
-        tokio::spawn(async move {
-            while let Some(msg) = background_rx.recv().await {
-                tracing::info!("[background] {}", msg);
-            }
-        });
+        let services = BackgroundServices::for_session(&config).await;
 
         Ok(Self {
             agent,
@@ -137,8 +146,8 @@ impl ChatSession {
             using_small_model: false,
             _mcp_manager: mcp_manager,
             mcp_tool_names,
-            heartbeat,
-            scheduler,
+            label: label.to_string(),
+            services,
         })
     }
 
@@ -146,6 +155,7 @@ impl ChatSession {
         self.config.preamble.as_deref().unwrap_or(DEFAULT_PREAMBLE)
     }
 
+    #[tracing::instrument(name = "agent_turn", skip_all, fields(session = %self.label, streaming = false))]
     pub async fn send_message(&mut self, text: &str) -> Result<String> {
         self.run_compact_if_needed().await;
 
@@ -157,9 +167,14 @@ impl ChatSession {
             "read_skills: List available skills".to_string(),
             "memory: Read/write persistent hierarchical markdown memory".to_string(),
             "view_schedules: View configured cron schedules".to_string(),
-            "cron_add: Add a cron schedule (name, cron expression, prompt, optional model)".to_string(),
+            "cron_add: Add a cron schedule (name, cron expression, prompt, optional model and timezone)".to_string(),
             "cron_remove: Remove a cron schedule by name".to_string(),
+            "remind: Schedule a one-shot reminder (when: 'in 2 hours', 'tomorrow at 9', RFC 3339; prompt; optional timezone)".to_string(),
+            "cron_control: Run a schedule now to test it, or pause/resume it (action, name)".to_string(),
             "heartbeat_edit: Replace HEARTBEAT.md content with validated markdown".to_string(),
+            "search_history: Search past conversations, including compacted messages".to_string(),
+            "recall_archive: Read the original messages behind a conversation summary (session, from, to)".to_string(),
+            "pin: Keep the current exchange, or a note, through history compaction".to_string(),
         ];
         for name in &self.mcp_tool_names {
             tool_descriptions.push(format!("{name}: MCP tool"));
@@ -173,35 +188,73 @@ impl ChatSession {
         );
 
         if let Some(ctx) = context_msg {
-            self.history.push(ChatMessage::system(ctx));
+            self.history.push(ChatMessage::context(ctx));
         }
 
         self.history.push(ChatMessage::user(text));
 
         let rig_messages = limited_rig_messages(&self.history, self.config.history_limit, text);
         let prompt = Message::user(text);
-        let response = match self.agent.chat(prompt, rig_messages).await {
+        let model_request = self.model_request();
+        let mut started = Instant::now();
+        let request = self.agent.chat(prompt, rig_messages);
+        let (result, mut calls) = collect_calls(model_request.run(request)).await;
+        let response = match result {
             Ok(r) => r,
             Err(e) => {
                 if !self.using_small_model && self.config.small_model.is_some() {
                     tracing::warn!("Primary model failed ({e}), falling back to small model");
-                    self.switch_to_small_model()?;
+                    self.fall_back_to_small_model(&e.to_string())?;
                     let rig_messages =
                         limited_rig_messages(&self.history, self.config.history_limit, text);
                     let retry_prompt = Message::user(text);
-                    self.agent.chat(retry_prompt, rig_messages).await?
+                    let model_request = self.model_request();
+                    started = Instant::now();
+                    let request = self.agent.chat(retry_prompt, rig_messages);
+                    let (result, retry_calls) = collect_calls(model_request.run(request)).await;
+                    calls.extend(retry_calls);
+                    result?
                 } else {
                     return Err(e.into());
                 }
             }
         };
 
-        self.history.push(ChatMessage::assistant(&response));
-        self.history.save()?;
+        // `chat` only returns the reply text, so usage is recorded on streamed replies only.
+        let reply = ChatMessage::assistant(&response).with_reply_details(
+            self.current_model_name(),
+            None,
+            started.elapsed(),
+        );
+        self.finish_turn(calls, reply)?;
 
         Ok(response)
     }
 
+    /// Add the turn's tool results and `reply` to the history, apply the pins the agent
+    /// asked for, and save.
+    fn finish_turn(&mut self, calls: Vec<ToolCall>, reply: ChatMessage) -> Result<()> {
+        let mut pins = Vec::new();
+        for call in calls {
+            if call.tool == PinTool::NAME {
+                pins.extend(serde_json::from_str::<PinArgs>(&call.args).map(|args| args.note));
+            } else {
+                self.history
+                    .push(ChatMessage::tool(&call.tool, &call.args, &call.output));
+            }
+        }
+        self.history.push(reply);
+        for note in pins {
+            match note {
+                Some(note) => self.history.push(ChatMessage::pinned_note(note.trim())),
+                None => {
+                    self.history.pin_last_turn();
+                }
+            }
+        }
+        self.history.save()
+    }
+
     async fn run_compact_if_needed(&mut self) {
         let (trigger_ratio, compact_enabled, strategy) = match &self.config.compact {
             Some(c) if c.enabled => (c.trigger_ratio, true, c.strategy.clone()),
@@ -215,6 +268,21 @@ impl ChatSession {
             return;
         }
 
+        self.compact(compact_enabled, strategy).await;
+    }
+
+    /// Compact history with the small model when configured, falling back to truncation.
+    #[tracing::instrument(
+        name = "compaction",
+        skip_all,
+        fields(
+            session = %self.label,
+            strategy = ?strategy,
+            messages_before = self.history.messages().len(),
+            summarized = Empty,
+        )
+    )]
+    async fn compact(&mut self, compact_enabled: bool, strategy: CompactStrategy) {
         let compacted = if compact_enabled && strategy == CompactStrategy::SmallModel {
             self.try_small_model_compact().await
         } else {
@@ -224,8 +292,12 @@ impl ChatSession {
         if !compacted {
             self.history.compact(self.config.context_window);
         }
+        tracing::Span::current().record("summarized", compacted);
 
-        self.context_manager.mark_dirty();
+        // Compaction keeps the latest context message; inject it again only if there was none.
+        if !self.history.messages().iter().any(|m| m.context) {
+            self.context_manager.mark_dirty();
+        }
     }
 
     async fn try_small_model_compact(&mut self) -> bool {
@@ -242,35 +314,82 @@ impl ChatSession {
 
         let language = self.history.detect_user_language();
 
-        let conversation_text: String = self
+        let lines: Vec<String> = self
             .history
-            .messages()
+            .compactable()
             .iter()
             .map(|m| format!("[{}]: {}", m.role, m.content))
-            .collect::<Vec<_>>()
-            .join("\n");
+            .collect();
+        if lines.is_empty() {
+            return false;
+        }
 
         let base_prompt = compact_config
             .prompt
             .as_deref()
             .unwrap_or(DEFAULT_COMPACT_PROMPT);
-
-        let prompt = if let Some(ref lang) = language {
-            format!(
-                "{}\n\n[Current user language: {}]\n\n{}",
-                base_prompt, lang, conversation_text
-            )
+        let longest_prompt = if base_prompt.len() > REDUCE_PROMPT.len() {
+            base_prompt
         } else {
-            format!("{}\n\n{}", base_prompt, conversation_text)
+            REDUCE_PROMPT
         };
-
-        match agent.chat(Message::user(&prompt), vec![]).await {
+        let max_bytes = chunk_bytes(resolved.context_window, longest_prompt);
+        let summary = summarize_chunked(lines, max_bytes, |stage, text| {
+            let prompt = match stage {
+                Stage::Map => base_prompt,
+                Stage::Reduce => REDUCE_PROMPT,
+            };
+            summarize_with(&agent, &resolved, prompt, language.as_deref(), text)
+        })
+        .await;
+        match summary {
             Ok(summary) => {
                 self.history
                     .compact_with_summary(&summary, language.as_deref());
-                true
             }
-            Err(_) => false,
+            Err(e) => {
+                tracing::warn!(error = %e, "Summarizing history failed, truncating instead");
+                return false;
+            }
+        }
+
+        let max_summaries = compact_config.max_summaries;
+        self.roll_up_summaries(&agent, &resolved, max_summaries, language.as_deref())
+            .await;
+        true
+    }
+
+    /// Once there are more than `max` summaries, merge the oldest into the long-term gist,
+    /// keeping the newest `max / 2`. Summaries are merged as they are, not re-summarized
+    /// from the messages they replaced.
+    async fn roll_up_summaries(
+        &mut self,
+        agent: &ChatAgent,
+        model: &SmallModelConfig,
+        max: usize,
+        language: Option<&str>,
+    ) {
+        let summaries = self.history.summaries();
+        if summaries.len() <= max {
+            return;
+        }
+        let merge = summaries.len() - max / 2;
+        let mut lines: Vec<String> = self
+            .history
+            .gist()
+            .map(|g| g.content.clone())
+            .into_iter()
+            .collect();
+        lines.extend(summaries[..merge].iter().map(|m| m.content.clone()));
+
+        let max_bytes = chunk_bytes(model.context_window, GIST_PROMPT);
+        let gist = summarize_chunked(lines, max_bytes, |_, text| {
+            summarize_with(agent, model, GIST_PROMPT, language, text)
+        })
+        .await;
+        match gist {
+            Ok(gist) => self.history.merge_into_gist(merge, &gist, language),
+            Err(e) => tracing::warn!(error = %e, "Merging summaries into the gist failed"),
         }
     }
 
@@ -292,24 +411,122 @@ impl ChatSession {
             _ => (false, CompactStrategy::Truncate),
         };
 
-        let compacted = if compact_enabled && strategy == CompactStrategy::SmallModel {
-            self.try_small_model_compact().await
-        } else {
-            false
+        self.compact(compact_enabled, strategy).await;
+        true
+    }
+
+    /// Remove this session's last user message and the reply to it, and save. Returns the
+    /// removed message's text, or `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let Some(removed) = self.history.undo_turn() else {
+            return Ok(None);
         };
+        self.history.save()?;
+        Ok(removed.into_iter().next().map(|m| m.content))
+    }
 
-        if !compacted {
-            self.history.compact(self.config.context_window);
+    /// Pin `note`, or this session's last exchange when `None`, so that compaction keeps it,
+    /// and save. Returns `false` if there is no exchange to pin.
+    pub fn pin(&mut self, note: Option<&str>) -> Result<bool> {
+        match note {
+            Some(note) => self.history.push(ChatMessage::pinned_note(note)),
+            None if self.history.pin_last_turn() => {}
+            None => return Ok(false),
         }
+        self.history.save()?;
+        Ok(true)
+    }
 
+    /// Unpin this session's messages, save, and return how many were pinned.
+    pub fn unpin(&mut self) -> Result<usize> {
+        let unpinned = self.history.unpin();
+        self.history.save()?;
+        Ok(unpinned)
+    }
+
+    /// Replace the reply to this session's last user message with a new one. If no new
+    /// reply arrives, the old one stays.
+    pub async fn retry_streaming(&mut self, chunk_tx: mpsc::Sender<String>) -> Result<String> {
+        let before = self.history.snapshot();
+        let removed = self
+            .history
+            .undo_turn()
+            .ok_or_else(|| anyhow::anyhow!("Nothing to retry"))?;
+        let result = self
+            .send_message_streaming(&removed[0].content, chunk_tx)
+            .await;
+        self.restore_on_error(before, result)
+    }
+
+    /// Replace this session's last user message with `text` and reply to it. If no reply
+    /// arrives, the old message and its reply stay.
+    pub async fn edit_last_streaming(
+        &mut self,
+        text: &str,
+        chunk_tx: mpsc::Sender<String>,
+    ) -> Result<String> {
+        let before = self.history.snapshot();
+        self.history
+            .undo_turn()
+            .ok_or_else(|| anyhow::anyhow!("Nothing to edit"))?;
+        let result = self.send_message_streaming(text, chunk_tx).await;
+        self.restore_on_error(before, result)
+    }
+
+    /// Put the history back to `before` if `result` is an error.
+    fn restore_on_error(
+        &mut self,
+        before: HistorySnapshot,
+        result: Result<String>,
+    ) -> Result<String> {
+        if result.is_err() {
+            self.history.restore(before);
+            self.context_manager.mark_dirty();
+        }
+        result
+    }
+
+    /// Fork the conversation into a new branch `name`, keeping it up to the reply to the
+    /// `turns`-th user message (all of it when `None`), and continue on the new branch.
+    /// The fork point is always a turn boundary; a turn is never split.
+    pub fn fork_branch(&mut self, name: &str, turns: Option<usize>) -> Result<()> {
+        let at = match turns {
+            Some(turns) => self.history.turn_boundary(turns),
+            None => self.history.messages().len(),
+        };
+        self.history.fork(name, at)?;
         self.context_manager.mark_dirty();
-        true
+        Ok(())
+    }
+
+    /// Continue the conversation on branch `name`.
+    pub fn switch_branch(&mut self, name: &str) -> Result<()> {
+        self.history.switch_branch(name)?;
+        self.context_manager.mark_dirty();
+        Ok(())
+    }
+
+    /// The history's branches, one per line, with the current one marked.
+    pub fn describe_branches(&self) -> Result<String> {
+        Ok(hi_history::branch::format_branches(
+            &self.history.branches()?,
+            self.history.branch(),
+        ))
     }
 
     pub fn history(&self) -> &ChatHistory {
         &self.history
     }
 
+    /// Save history changes that no turn has written yet, such as a compaction.
+    pub fn flush_history(&mut self) -> Result<()> {
+        if self.history.has_unsaved_changes() {
+            self.history.save()?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "agent_turn", skip_all, fields(session = %self.label, streaming = true))]
     pub async fn send_message_streaming(
         &mut self,
         text: &str,
@@ -325,9 +542,14 @@ impl ChatSession {
             "read_skills: List available skills".to_string(),
             "memory: Read/write persistent hierarchical markdown memory".to_string(),
             "view_schedules: View configured cron schedules".to_string(),
-            "cron_add: Add a cron schedule (name, cron expression, prompt, optional model)".to_string(),
+            "cron_add: Add a cron schedule (name, cron expression, prompt, optional model and timezone)".to_string(),
             "cron_remove: Remove a cron schedule by name".to_string(),
+            "remind: Schedule a one-shot reminder (when: 'in 2 hours', 'tomorrow at 9', RFC 3339; prompt; optional timezone)".to_string(),
+            "cron_control: Run a schedule now to test it, or pause/resume it (action, name)".to_string(),
             "heartbeat_edit: Replace HEARTBEAT.md content with validated markdown".to_string(),
+            "search_history: Search past conversations, including compacted messages".to_string(),
+            "recall_archive: Read the original messages behind a conversation summary (session, from, to)".to_string(),
+            "pin: Keep the current exchange, or a note, through history compaction".to_string(),
         ];
         for name in &self.mcp_tool_names {
             tool_descriptions.push(format!("{name}: MCP tool"));
@@ -341,7 +563,7 @@ impl ChatSession {
         );
 
         if let Some(ctx) = context_msg {
-            self.history.push(ChatMessage::system(ctx));
+            self.history.push(ChatMessage::context(ctx));
         }
 
         self.history.push(ChatMessage::user(text));
@@ -349,28 +571,45 @@ impl ChatSession {
         let rig_messages = limited_rig_messages(&self.history, self.config.history_limit, text);
         let prompt = Message::user(text);
         let fallback_tx = chunk_tx.clone();
-        let response = match self.agent.stream_chat(prompt, rig_messages, chunk_tx).await {
+        let model_request = self.model_request();
+        let mut started = Instant::now();
+        let request = self.agent.stream_chat(prompt, rig_messages, chunk_tx);
+        let (result, mut calls) = collect_calls(model_request.run(request)).await;
+        let reply = match result {
             Ok(r) => r,
             Err(e) => {
                 if !self.using_small_model && self.config.small_model.is_some() {
                     tracing::warn!("Primary model failed ({e}), falling back to small model");
-                    self.switch_to_small_model()?;
+                    self.fall_back_to_small_model(&e.to_string())?;
                     let rig_messages =
                         limited_rig_messages(&self.history, self.config.history_limit, text);
                     let retry_prompt = Message::user(text);
-                    self.agent
-                        .stream_chat(retry_prompt, rig_messages, fallback_tx)
-                        .await?
+                    let model_request = self.model_request();
+                    started = Instant::now();
+                    let request = self
+                        .agent
+                        .stream_chat(retry_prompt, rig_messages, fallback_tx);
+                    let (result, retry_calls) = collect_calls(model_request.run(request)).await;
+                    calls.extend(retry_calls);
+                    result?
                 } else {
                     return Err(e);
                 }
             }
         };
 
-        self.history.push(ChatMessage::assistant(&response));
-        self.history.save()?;
+        let usage = reply.usage.map(|usage| TokenUsage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        });
+        let message = ChatMessage::assistant(&reply.text).with_reply_details(
+            self.current_model_name(),
+            usage,
+            started.elapsed(),
+        );
+        self.finish_turn(calls, message)?;
 
-        Ok(response)
+        Ok(reply.text)
     }
 
     pub fn config(&self) -> &ModelConfig {
@@ -397,6 +636,32 @@ impl ChatSession {
         self.using_small_model
     }
 
+    fn current_provider(&self) -> String {
+        match &self.config.small_model {
+            Some(small) if self.using_small_model => small.provider.to_string(),
+            _ => self.config.provider.to_string(),
+        }
+    }
+
+    fn model_request(&self) -> ModelRequest {
+        ModelRequest::new(&self.current_provider(), self.current_model_name())
+    }
+
+    /// Switch to the small model after the primary failed, inside a `model_fallback` span.
+    fn fall_back_to_small_model(&mut self, error: &str) -> Result<String> {
+        let span = info_span!(
+            "model_fallback",
+            session = %self.label,
+            from = %self.current_model_name(),
+            to = Empty,
+            error = %error,
+        );
+        let model = span.in_scope(|| self.switch_to_small_model())?;
+        span.record("to", model.as_str());
+        crate::metrics::metrics().model_fallbacks.inc();
+        Ok(model)
+    }
+
     /// Fails if no `small_model` is configured.
     pub fn switch_to_small_model(&mut self) -> Result<String> {
         let small_config = self
@@ -410,8 +675,15 @@ impl ChatSession {
             &self.skills,
         );
         let skill_summaries = ContextManager::skill_summaries(&self.skills);
-        let agent =
-            create_agent_from_small_with_tools(small_config, Some(&preamble), skill_summaries)?;
+        let agent = create_agent_from_small_with_tools(
+            small_config,
+            Some(&preamble),
+            skill_summaries,
+            Some(Scheduler::runner(&self.config)),
+            self.config.schedules.as_deref(),
+            self.config.recall_scope(&self.label),
+            &self.label,
+        )?;
 
         self.agent = agent;
         self.using_small_model = true;
@@ -426,7 +698,13 @@ impl ChatSession {
             &self.skills,
         );
         let skill_summaries = ContextManager::skill_summaries(&self.skills);
-        let agent = create_agent(&self.config, Some(&preamble), skill_summaries, vec![])?;
+        let agent = create_agent(
+            &self.config,
+            Some(&preamble),
+            skill_summaries,
+            vec![],
+            &self.label,
+        )?;
 
         self.agent = agent;
         self.using_small_model = false;
@@ -0,0 +1,155 @@
+//! Summaries of text longer than the summarizing model's context window.
+//!
+//! The lines are split into chunks that fit, each chunk is summarized, and the partial
+//! summaries are combined the same way until one is left.
+
+use std::future::Future;
+
+use anyhow::Result;
+
+/// Smallest chunk, so that a tiny `context_window` does not make one request per word.
+const MIN_CHUNK_BYTES: usize = 1024;
+
+/// What a summarizing request is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Consecutive lines of the original text.
+    Map,
+    /// Summaries of consecutive parts of it.
+    Reduce,
+}
+
+/// Bytes of text per request to a model with `context_window` tokens, at about four bytes
+/// a token. Half of the window is left for the prompt and the reply.
+pub fn chunk_bytes(context_window: usize, prompt: &str) -> usize {
+    context_window
+        .saturating_mul(2)
+        .saturating_sub(prompt.len())
+        .max(MIN_CHUNK_BYTES)
+}
+
+/// Summarize `lines` with `summarize`, giving it at most `max_bytes` of text at a time.
+pub async fn summarize_chunked<F, Fut>(
+    lines: Vec<String>,
+    max_bytes: usize,
+    mut summarize: F,
+) -> Result<String>
+where
+    F: FnMut(Stage, String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let mut stage = Stage::Map;
+    let mut chunks = chunk(lines, max_bytes);
+    loop {
+        let mut partials = Vec::with_capacity(chunks.len());
+        for text in chunks {
+            partials.push(summarize(stage, text).await?);
+        }
+        if partials.len() <= 1 {
+            return Ok(partials.pop().unwrap_or_default());
+        }
+        let count = partials.len();
+        stage = Stage::Reduce;
+        chunks = chunk(partials.clone(), max_bytes);
+        if chunks.len() >= count {
+            // Partial summaries too long to share a chunk: shorten them to combine in pairs.
+            let half = max_bytes.saturating_sub(1) / 2;
+            chunks = chunk(
+                partials.into_iter().map(|s| truncate(s, half)).collect(),
+                max_bytes,
+            );
+        }
+    }
+}
+
+/// Join consecutive `lines` into chunks of at most `max_bytes`, splitting longer lines.
+fn chunk(lines: Vec<String>, max_bytes: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        for piece in split(&line, max_bytes) {
+            if !current.is_empty() && current.len() + 1 + piece.len() > max_bytes {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(piece);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// `text` in pieces of at most `max_bytes`, cut at character boundaries.
+fn split(text: &str, max_bytes: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        let mut end = max_bytes;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (piece, tail) = rest.split_at(end.max(1));
+        pieces.push(piece);
+        rest = tail;
+    }
+    pieces.push(rest);
+    pieces
+}
+
+fn truncate(mut text: String, max_bytes: usize) -> String {
+    if let Some(piece) = split(&text, max_bytes).first() {
+        text.truncate(piece.len());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_chunk_keeps_lines_together_and_splits_long_ones() {
+        let lines = vec!["aaaa".to_string(), "bbbb".to_string(), "c".repeat(10)];
+        assert_eq!(chunk(lines, 9), ["aaaa\nbbbb", "ccccccccc", "c"]);
+        assert_eq!(split("héllo", 2), ["h", "é", "ll", "o"]);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_chunked_reduces_until_one_summary() {
+        let calls = RefCell::new(Vec::new());
+        let lines: Vec<String> = (0..6).map(|i| format!("line {i}")).collect();
+        let summary = summarize_chunked(lines, 13, |stage, text| {
+            calls.borrow_mut().push((stage, text.clone()));
+            async move { Ok(format!("S({})", text.replace('\n', "+"))) }
+        })
+        .await
+        .unwrap();
+
+        let calls = calls.into_inner();
+        let count = |stage| calls.iter().filter(|c| c.0 == stage).count();
+        assert_eq!(count(Stage::Map), 3);
+        assert_eq!(count(Stage::Reduce), 3);
+        assert!(calls.iter().all(|c| c.1.len() <= 13));
+        assert_eq!(calls[0].1, "line 0\nline 1");
+        assert!(summary.starts_with("S("));
+    }
+
+    #[tokio::test]
+    async fn test_summarize_chunked_single_chunk_is_one_request() {
+        let mut calls = 0;
+        let summary = summarize_chunked(vec!["hello".to_string()], 100, |stage, text| {
+            calls += 1;
+            assert_eq!(stage, Stage::Map);
+            async move { Ok(text.to_uppercase()) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(summary, "HELLO");
+        assert_eq!(calls, 1);
+    }
+}
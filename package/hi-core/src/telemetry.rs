@@ -0,0 +1,62 @@
+//! Tracing spans shared by agent turns. With the `otlp` feature they are exported as
+//! OpenTelemetry traces; otherwise they only reach the fmt log layers. Model requests
+//! also feed the Prometheus metrics.
+
+use std::future::Future;
+use std::time::Instant;
+
+use tracing::field::Empty;
+use tracing::{Instrument, Span, info_span};
+
+use crate::metrics::{metrics, outcome};
+
+/// One request to a model: a `model_request` span plus the request metrics.
+pub struct ModelRequest {
+    span: Span,
+    provider: String,
+}
+
+impl ModelRequest {
+    /// Token counts on the span are filled in by the streaming path.
+    pub fn new(provider: &str, model: &str) -> Self {
+        let span = info_span!(
+            "model_request",
+            provider = %provider,
+            model = %model,
+            input_tokens = Empty,
+            output_tokens = Empty,
+            latency_ms = Empty,
+        );
+        Self {
+            span,
+            provider: provider.to_string(),
+        }
+    }
+
+    /// Run `request` inside the span, recording its latency and outcome.
+    pub async fn run<F, T, E>(self, request: F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let result = request.instrument(self.span.clone()).await;
+        let elapsed = started.elapsed();
+        self.span.record("latency_ms", elapsed.as_millis() as u64);
+
+        let m = metrics();
+        m.model_requests
+            .with_label_values(&[self.provider.as_str(), outcome(&result)])
+            .inc();
+        m.model_request_duration
+            .with_label_values(&[self.provider.as_str()])
+            .observe(elapsed.as_secs_f64());
+        result
+    }
+}
+
+/// Record token usage on the current model request span.
+pub fn record_usage(input_tokens: u64, output_tokens: u64) {
+    let span = Span::current();
+    span.record("input_tokens", input_tokens);
+    span.record("output_tokens", output_tokens);
+}
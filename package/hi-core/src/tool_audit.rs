@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rig::completion::ToolDefinition;
+use rig::tool::{ToolDyn, ToolError};
+use rig::wasm_compat::WasmBoxedFuture;
+use shared::tool_audit::{self, ToolAuditEntry};
+use tracing::{Instrument, info, info_span, warn};
+
+/// Characters of tool arguments attached to the tracing span.
+const SPAN_ARGS_CHARS: usize = 500;
+
+tokio::task_local! {
+    static TURN_CALLS: RefCell<Vec<ToolCall>>;
+}
+
+/// A successful tool call made while a chat turn ran.
+pub(crate) struct ToolCall {
+    pub tool: String,
+    pub args: String,
+    pub output: String,
+}
+
+/// Run `future` and return the successful tool calls it made, so the chat session can add
+/// them to its history. Tools called outside of it, e.g. by schedules, are not collected.
+pub(crate) async fn collect_calls<F: Future>(future: F) -> (F::Output, Vec<ToolCall>) {
+    TURN_CALLS
+        .scope(RefCell::new(Vec::new()), async {
+            let output = future.await;
+            (output, TURN_CALLS.with(RefCell::take))
+        })
+        .await
+}
+
+/// Wraps a tool so every call gets a tracing span and an audit log entry.
+pub struct AuditedTool {
+    inner: Box<dyn ToolDyn>,
+    caller: Arc<str>,
+    /// Where audit entries go; `None` if the data directory is unavailable.
+    audit_dir: Option<Arc<PathBuf>>,
+}
+
+impl ToolDyn for AuditedTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(&'a self, prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
+
+    fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, ToolError>> {
+        let tool = self.inner.name();
+        let span = info_span!(
+            "tool_call",
+            tool = %tool,
+            caller = %self.caller,
+            args = %args.chars().take(SPAN_ARGS_CHARS).collect::<String>(),
+        );
+        Box::pin(
+            async move {
+                let started = Instant::now();
+                let result = self.inner.call(args.clone()).await;
+                let duration = started.elapsed();
+                let duration_ms = duration.as_millis() as u64;
+                crate::metrics::metrics()
+                    .tool_calls
+                    .with_label_values(&[tool.as_str(), crate::metrics::outcome(&result)])
+                    .inc();
+
+                let entry = match &result {
+                    Ok(output) => {
+                        info!(duration_ms, "Tool call succeeded");
+                        let _ = TURN_CALLS.try_with(|calls| {
+                            calls.borrow_mut().push(ToolCall {
+                                tool: tool.clone(),
+                                args: args.clone(),
+                                output: output.clone(),
+                            })
+                        });
+                        ToolAuditEntry::new(&self.caller, &tool, &args, duration, Ok(output))
+                    }
+                    Err(e) => {
+                        let e = e.to_string();
+                        warn!(duration_ms, error = %e, "Tool call failed");
+                        ToolAuditEntry::new(&self.caller, &tool, &args, duration, Err(&e))
+                    }
+                };
+                if let Some(dir) = &self.audit_dir
+                    && let Err(e) = tool_audit::record(dir, &entry)
+                {
+                    warn!(error = %e, "Failed to write tool audit entry");
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Wrap each tool in an [`AuditedTool`] attributed to `caller`.
+pub fn audit_tools(tools: Vec<Box<dyn ToolDyn>>, caller: &str) -> Vec<Box<dyn ToolDyn>> {
+    let audit_dir = match tool_audit::audit_dir() {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            warn!(error = %e, "Tool audit log unavailable");
+            None
+        }
+    };
+    audit_tools_to(tools, caller, audit_dir)
+}
+
+fn audit_tools_to(
+    tools: Vec<Box<dyn ToolDyn>>,
+    caller: &str,
+    audit_dir: Option<PathBuf>,
+) -> Vec<Box<dyn ToolDyn>> {
+    let caller: Arc<str> = Arc::from(caller);
+    let audit_dir = audit_dir.map(Arc::new);
+    tools
+        .into_iter()
+        .map(|inner| {
+            Box::new(AuditedTool {
+                inner,
+                caller: caller.clone(),
+                audit_dir: audit_dir.clone(),
+            }) as Box<dyn ToolDyn>
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audited_tool_records_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory_path = dir.path().join("memory.md");
+        let tools = audit_tools_to(
+            vec![Box::new(hi_tools::MemoryTool::new(memory_path)) as Box<dyn ToolDyn>],
+            "telegram:42",
+            Some(dir.path().to_path_buf()),
+        );
+        assert_eq!(tools[0].name(), "memory");
+
+        assert!(tools[0].call("not json".to_string()).await.is_err());
+
+        let log = std::fs::read_to_string(dir.path().join("tool_audit.jsonl")).unwrap();
+        let entries: Vec<ToolAuditEntry> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].caller, "telegram:42");
+        assert_eq!(entries[0].tool, "memory");
+        assert_eq!(entries[0].args, "not json");
+        assert!(!entries[0].success);
+        assert!(entries[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_turn_collects_successful_calls() {
+        let tools = audit_tools_to(
+            vec![Box::new(hi_tools::PinTool) as Box<dyn ToolDyn>],
+            "tui",
+            None,
+        );
+
+        let (_, calls) = collect_calls(async {
+            tools[0].call("{}".to_string()).await.unwrap();
+            tools[0].call("not json".to_string()).await.unwrap_err();
+        })
+        .await;
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].tool, "pin");
+        assert_eq!(calls[0].args, "{}");
+
+        // Outside a turn, calls still work and are not collected.
+        tools[0].call("{}".to_string()).await.unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+//! Per-turn save cost as history grows.
+//!
+//! Run with `cargo bench -p hi-history`. Each row pre-fills a history with `messages`
+//! entries, then times saving one user/assistant turn at a time. With the append-only
+//! log the per-turn cost should stay flat across rows.
+
+use std::time::{Duration, Instant};
+
+use hi_history::{ChatHistory, ChatMessage};
+
+const TURNS: u32 = 50;
+
+fn main() {
+    println!("{:>10}  {:>14}", "messages", "per-turn save");
+    for size in [100, 1_000, 10_000, 50_000] {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let mut history = ChatHistory::load(dir.path()).expect("load history");
+        for i in 0..size / 2 {
+            history.push(ChatMessage::user(format!(
+                "question {i}: {}",
+                "lorem ipsum ".repeat(8)
+            )));
+            history.push(ChatMessage::assistant(format!(
+                "answer {i}: {}",
+                "dolor sit amet ".repeat(16)
+            )));
+        }
+        history.save().expect("save history");
+
+        let mut elapsed = Duration::ZERO;
+        for i in 0..TURNS {
+            history.push(ChatMessage::user(format!("follow-up {i}")));
+            history.push(ChatMessage::assistant(format!("reply {i}")));
+            let started = Instant::now();
+            history.save().expect("save history");
+            elapsed += started.elapsed();
+        }
+        println!("{size:>10}  {:>14.2?}", elapsed / TURNS);
+    }
+}
@@ -0,0 +1,203 @@
+//! Named branches of the chat history.
+//!
+//! A branch is forked from another at a message and stores only the messages after its
+//! fork point; the shared messages are read from the parent. When a parent is about to
+//! rewrite messages a branch shares with it, for example by compaction, the branch first
+//! stores them itself and stops depending on the parent.
+
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use tracing::info;
+
+use shared::storage::{HistoryBranch, Storage};
+
+/// The history every session starts on. It has no parent and is not listed in storage.
+pub const MAIN_BRANCH: &str = "main";
+
+const MAX_NAME_LEN: usize = 64;
+
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        bail!("Branch names must be 1-{MAX_NAME_LEN} characters");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        bail!("Branch names may only contain letters, digits, '-', '_' and '.'");
+    }
+    Ok(())
+}
+
+/// Every message of `name`: the ones it shares with its parent, then its own.
+pub(crate) fn resolve(
+    storage: &dyn Storage,
+    branches: &[HistoryBranch],
+    name: &str,
+) -> Result<Vec<Value>> {
+    let mut chain = Vec::new();
+    let mut current = name;
+    while current != MAIN_BRANCH {
+        let branch = find(branches, current)?;
+        if chain.len() > branches.len() {
+            bail!("History branch '{name}' has a cycle in its parents");
+        }
+        chain.push(branch);
+        match &branch.parent {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+
+    let mut messages = if current == MAIN_BRANCH {
+        storage.open_history()?.1
+    } else {
+        Vec::new()
+    };
+    for branch in chain.iter().rev() {
+        messages.truncate(branch.fork_at);
+        messages.extend(storage.open_branch_history(&branch.name)?.1);
+    }
+    Ok(messages)
+}
+
+pub(crate) fn find<'a>(branches: &'a [HistoryBranch], name: &str) -> Result<&'a HistoryBranch> {
+    branches
+        .iter()
+        .find(|b| b.name == name)
+        .with_context(|| format!("No history branch named '{name}'"))
+}
+
+/// Record a new branch of `parent` sharing its first `fork_at` messages.
+pub(crate) fn create(
+    storage: &dyn Storage,
+    name: &str,
+    parent: &str,
+    fork_at: usize,
+) -> Result<()> {
+    validate_name(name)?;
+    let mut exists = name == MAIN_BRANCH;
+    storage.update_branches(&mut |branches| {
+        exists |= branches.iter().any(|b| b.name == name);
+        if !exists {
+            branches.push(HistoryBranch {
+                name: name.to_string(),
+                parent: Some(parent.to_string()),
+                fork_at,
+                created_at: chrono::Utc::now(),
+            });
+        }
+        !exists
+    })?;
+    if exists {
+        bail!("A history branch named '{name}' already exists");
+    }
+    Ok(())
+}
+
+/// Before `parent` is rewritten with `messages`, make each branch forked from it store the
+/// messages it shares with `parent` if those are about to change.
+pub(crate) fn detach_children(
+    storage: &dyn Storage,
+    branches: &[HistoryBranch],
+    parent: &str,
+    messages: &[Value],
+) -> Result<()> {
+    let children: Vec<&HistoryBranch> = branches
+        .iter()
+        .filter(|b| b.parent.as_deref() == Some(parent) && b.fork_at > 0)
+        .collect();
+    if children.is_empty() {
+        return Ok(());
+    }
+    let stored = resolve(storage, branches, parent)?;
+    for child in children {
+        let shared = &stored[..child.fork_at.min(stored.len())];
+        if messages.get(..child.fork_at) == Some(shared) {
+            continue;
+        }
+        let (mut log, own) = storage.open_branch_history(&child.name)?;
+        let mut all = shared.to_vec();
+        all.extend(own);
+        log.rewrite(&all)?;
+        detach(storage, &child.name)?;
+        info!(branch = %child.name, parent, "Detached history branch from its parent");
+    }
+    Ok(())
+}
+
+/// Mark `name` as storing all of its messages.
+pub(crate) fn detach(storage: &dyn Storage, name: &str) -> Result<()> {
+    storage.update_branches(
+        &mut |branches| match branches.iter_mut().find(|b| b.name == name) {
+            Some(branch) if branch.parent.is_some() => {
+                branch.parent = None;
+                branch.fork_at = 0;
+                true
+            }
+            _ => false,
+        },
+    )
+}
+
+/// Split `/fork` arguments into the branch name and the number of user messages to keep.
+pub fn parse_fork_args(args: &str) -> Option<(&str, Option<usize>)> {
+    let mut parts = args.split_whitespace();
+    let name = parts.next()?;
+    let turns = match parts.next() {
+        Some(turns) => Some(turns.parse().ok()?),
+        None => None,
+    };
+    parts.next().is_none().then_some((name, turns))
+}
+
+/// One line per branch, marking `current`.
+pub fn format_branches(branches: &[HistoryBranch], current: &str) -> String {
+    let marker = |name: &str| if name == current { " (current)" } else { "" };
+    let mut lines = vec![
+        "History branches:".to_string(),
+        format!("• {MAIN_BRANCH}{}", marker(MAIN_BRANCH)),
+    ];
+    for branch in branches {
+        let origin = match &branch.parent {
+            Some(parent) => format!("forked from {parent} after {} messages", branch.fork_at),
+            None => "stored on its own".to_string(),
+        };
+        lines.push(format!(
+            "• {}{} — {origin}, {}",
+            branch.name,
+            marker(&branch.name),
+            branch.created_at.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fork_args() {
+        assert_eq!(parse_fork_args("draft"), Some(("draft", None)));
+        assert_eq!(parse_fork_args(" draft  3 "), Some(("draft", Some(3))));
+        assert_eq!(parse_fork_args(""), None);
+        assert_eq!(parse_fork_args("draft three"), None);
+        assert_eq!(parse_fork_args("draft 3 4"), None);
+    }
+
+    #[test]
+    fn test_format_branches() {
+        let created_at = "2026-10-19T14:03:00Z".parse().unwrap();
+        let branches = [HistoryBranch {
+            name: "draft".to_string(),
+            parent: Some(MAIN_BRANCH.to_string()),
+            fork_at: 4,
+            created_at,
+        }];
+        assert_eq!(
+            format_branches(&branches, "draft"),
+            "History branches:\n• main\n• draft (current) — forked from main after 4 messages, 2026-10-19 14:03 UTC"
+        );
+    }
+}
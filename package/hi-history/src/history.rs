@@ -1,38 +1,165 @@
-use anyhow::{Context, Result};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 use rig::completion::message::Message;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use serde_json::Value;
+use tracing::warn;
 
-use shared::config::MemoryConfig;
+use shared::config::{CompactConfig, DEFAULT_KEEP_TOOL_RESULTS, MemoryConfig};
+use shared::history_search::SearchEntry;
 use shared::memory::evaluate_reclamation;
+use shared::storage::{FileStorage, HistoryBranch, HistoryLog, Storage};
+
+use crate::branch::{self, MAIN_BRANCH};
+
+/// Session label for histories that were not given one.
+const DEFAULT_SESSION: &str = "default";
+
+/// Characters of a tool call, arguments and output, kept in the history.
+const MAX_TOOL_RESULT_CHARS: usize = 2000;
+
+/// Start of a stored tool result, followed by the tool's name.
+pub(crate) const TOOL_RESULT_PREFIX: &str = "[Tool result: ";
+
+/// Tokens reported by the provider for one reply, summed over its tool-call turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
 
+/// A history message. Everything after `content` is optional, so histories saved before
+/// these fields existed still load.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Label of the session that added the message, e.g. `tui` or `telegram:42`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Model that wrote an assistant reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tokens the reply used, as reported at the end of a streamed reply. Replies from
+    /// the non-streaming `send_message` path have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// Time from sending the request to the end of the reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Kept verbatim by compaction, set by `/pin` or the agent's `pin` tool.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Injected by the session's context manager: system prompt, tools and skills.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub context: bool,
+    /// Set on the summaries compaction wrote, which later compactions keep.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SummaryTier>,
+}
+
+/// The tiers of summaries at the start of a compacted history, before the recent messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryTier {
+    /// One compaction's removed messages.
+    Summary,
+    /// The older summaries, merged.
+    Gist,
 }
 
 impl ChatMessage {
-    pub fn user(content: impl Into<String>) -> Self {
+    pub(crate) fn new(role: &str, content: String) -> Self {
         Self {
-            role: "user".to_string(),
-            content: content.into(),
+            role: role.to_string(),
+            content,
+            timestamp: Some(Utc::now()),
+            session: None,
+            model: None,
+            usage: None,
+            latency_ms: None,
+            pinned: false,
+            context: false,
+            summary: None,
         }
     }
 
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content.into())
+    }
+
     pub fn assistant(content: impl Into<String>) -> Self {
-        Self {
-            role: "assistant".to_string(),
-            content: content.into(),
-        }
+        Self::new("assistant", content.into())
     }
 
     pub fn system(content: impl Into<String>) -> Self {
-        Self {
-            role: "system".to_string(),
-            content: content.into(),
+        Self::new("system", content.into())
+    }
+
+    /// A context message from the session's context manager.
+    pub fn context(content: impl Into<String>) -> Self {
+        let mut message = Self::system(content);
+        message.context = true;
+        message
+    }
+
+    /// A note pinned so that compaction keeps it.
+    pub fn pinned_note(note: &str) -> Self {
+        let mut message = Self::system(format!("[Pinned note]\n{note}"));
+        message.pinned = true;
+        message
+    }
+
+    /// The result of a tool call made while a reply was written, cut to
+    /// [`MAX_TOOL_RESULT_CHARS`]. The model reads it as a user message on later turns.
+    pub fn tool(name: &str, args: &str, output: &str) -> Self {
+        let mut content = format!("{TOOL_RESULT_PREFIX}{name}] {args}\n{output}");
+        if let Some((end, _)) = content.char_indices().nth(MAX_TOOL_RESULT_CHARS) {
+            content.truncate(end);
+            content.push('…');
         }
+        Self::new("tool", content)
+    }
+
+    /// Record which model wrote this reply, what it cost and how long it took.
+    pub fn with_reply_details(
+        mut self,
+        model: &str,
+        usage: Option<TokenUsage>,
+        latency: Duration,
+    ) -> Self {
+        self.model = Some(model.to_string());
+        self.usage = usage;
+        self.latency_ms = Some(latency.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// One line with the metadata this message has, e.g.
+    /// `2026-10-19 14:03:12 UTC · gpt-4o · 1200 in / 85 out tokens · 2.4s`.
+    pub fn details(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(timestamp) = self.timestamp {
+            parts.push(timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+        }
+        if let Some(model) = &self.model {
+            parts.push(model.clone());
+        }
+        if let Some(usage) = self.usage {
+            parts.push(format!(
+                "{} in / {} out tokens",
+                usage.input_tokens, usage.output_tokens
+            ));
+        }
+        if let Some(latency_ms) = self.latency_ms {
+            parts.push(format!("{:.1}s", latency_ms as f64 / 1000.0));
+        }
+        (!parts.is_empty()).then(|| parts.join(" · "))
     }
 
     pub fn to_rig_message(&self) -> Message {
@@ -44,61 +171,387 @@ impl ChatMessage {
     }
 }
 
+/// What compaction keeps besides the newest messages. Pinned messages and the latest
+/// context message are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// Most recent tool results kept.
+    pub tool_results: usize,
+    /// Estimated tokens of the newest messages to keep; `None` keeps the newer half.
+    pub target_tokens: Option<usize>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            tool_results: DEFAULT_KEEP_TOOL_RESULTS,
+            target_tokens: None,
+        }
+    }
+}
+
+impl From<&CompactConfig> for Retention {
+    fn from(config: &CompactConfig) -> Self {
+        Self {
+            tool_results: config.keep_tool_results,
+            target_tokens: config.target_tokens,
+        }
+    }
+}
+
+/// The messages of a [`ChatHistory`] at one point, to go back to with
+/// [`ChatHistory::restore`].
+pub struct HistorySnapshot {
+    messages: Vec<ChatMessage>,
+    unindexed: Vec<SearchEntry>,
+    unwanted: Vec<SearchEntry>,
+    saved: Option<usize>,
+    inherited: usize,
+}
+
 pub struct ChatHistory {
     messages: Vec<ChatMessage>,
-    history_path: PathBuf,
+    log: Box<dyn HistoryLog>,
+    storage: Arc<dyn Storage>,
+    /// Labels this history's messages in the search index.
+    session: String,
+    /// User and assistant messages not yet added to the search index.
+    unindexed: Vec<SearchEntry>,
+    /// Indexed messages undone since the last save, to remove from the search index.
+    unwanted: Vec<SearchEntry>,
     memory_config: MemoryConfig,
+    retention: Retention,
+    /// How many leading messages are stored, or `None` once earlier messages were
+    /// rewritten (by compaction) and the next save must rewrite the whole history.
+    saved: Option<usize>,
+    /// Branch the messages belong to; [`MAIN_BRANCH`] unless switched or forked.
+    branch: String,
+    /// How many leading messages the branch shares with its parent instead of storing them.
+    /// Reset to 0 when any of them change, so the next save stores them in the branch.
+    inherited: usize,
 }
 
 impl ChatHistory {
+    /// Open the history kept as files in `data_dir`.
     pub fn load(data_dir: &std::path::Path) -> Result<Self> {
-        let history_path = data_dir.join("history.json.lz4");
-        let messages = if history_path.exists() {
-            let compressed =
-                std::fs::read(&history_path).with_context(|| "Failed to read history file")?;
-            let mut decoder = lz4_flex::frame::FrameDecoder::new(&compressed[..]);
-            let mut json_bytes = Vec::new();
-            decoder
-                .read_to_end(&mut json_bytes)
-                .with_context(|| "Failed to decompress history")?;
-            serde_json::from_slice(&json_bytes).with_context(|| "Failed to parse history JSON")?
+        Self::open(Arc::new(FileStorage::new(data_dir.to_path_buf())))
+    }
+
+    pub fn open(storage: Arc<dyn Storage>) -> Result<Self> {
+        Self::open_branch(storage, MAIN_BRANCH)
+    }
+
+    /// Open a branch of the history: the messages it shares with its parent, then its own.
+    pub fn open_branch(storage: Arc<dyn Storage>, name: &str) -> Result<Self> {
+        let (log, stored, inherited) = if name == MAIN_BRANCH {
+            let (log, stored) = storage.open_history()?;
+            (log, stored, 0)
         } else {
-            Vec::new()
+            let branches = storage.load_branches()?;
+            let branch = branch::find(&branches, name)?;
+            let mut inherited = Vec::new();
+            if let Some(parent) = &branch.parent {
+                inherited = branch::resolve(storage.as_ref(), &branches, parent)?;
+                inherited.truncate(branch.fork_at);
+            }
+            let (log, own) = storage.open_branch_history(name)?;
+            let len = inherited.len();
+            inherited.extend(own);
+            (log, inherited, len)
         };
+        let messages = stored
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<serde_json::Result<Vec<ChatMessage>>>()?;
 
+        // Index what was saved before the index existed or while indexing failed.
+        let entries: Vec<SearchEntry> = messages.iter().filter_map(stored_entry).collect();
+        if let Err(e) = storage.index_missing_messages(&entries) {
+            warn!(error = %e, "Failed to index stored messages for search");
+        }
         Ok(Self {
+            saved: Some(messages.len()),
             messages,
-            history_path,
+            log,
+            storage,
+            session: DEFAULT_SESSION.to_string(),
+            unindexed: Vec::new(),
+            unwanted: Vec::new(),
             memory_config: MemoryConfig::default(),
+            retention: Retention::default(),
+            branch: name.to_string(),
+            inherited,
         })
     }
 
+    /// The branch these messages belong to.
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Branches of the history other than [`MAIN_BRANCH`], oldest first.
+    pub fn branches(&self) -> Result<Vec<HistoryBranch>> {
+        self.storage.load_branches()
+    }
+
+    /// Save, then continue on a new branch `name` that shares the first `at` messages with
+    /// the current one. Later messages of the current branch stay there.
+    pub fn fork(&mut self, name: &str, at: usize) -> Result<()> {
+        if at > self.messages.len() {
+            anyhow::bail!(
+                "Cannot fork after message {at}; the branch has {} messages",
+                self.messages.len()
+            );
+        }
+        self.save()?;
+        branch::create(self.storage.as_ref(), name, &self.branch, at)?;
+        let (log, _) = self.storage.open_branch_history(name)?;
+        let released = byte_size(&self.messages[at..]);
+        self.messages.truncate(at);
+        self.log = log;
+        self.saved = Some(at);
+        self.branch = name.to_string();
+        self.inherited = at;
+        if released > 0 {
+            evaluate_reclamation(&self.memory_config, released);
+        }
+        Ok(())
+    }
+
+    /// Save, then continue on branch `name`, keeping this history's session label.
+    pub fn switch_branch(&mut self, name: &str) -> Result<()> {
+        self.save()?;
+        let mut other = Self::open_branch(Arc::clone(&self.storage), name)?;
+        other.session = std::mem::take(&mut self.session);
+        other.memory_config = self.memory_config.clone();
+        other.retention = self.retention;
+        other.unindexed = std::mem::take(&mut self.unindexed);
+        other.unwanted = std::mem::take(&mut self.unwanted);
+        *self = other;
+        Ok(())
+    }
+
+    /// Messages kept by a fork that ends with the reply to the `turns`-th user message.
+    ///
+    /// Forks from the commands happen only at these boundaries, so that a branch keeps
+    /// whole turns, each user message with the tool results and reply after it, and goes on
+    /// with a user message of its own. [`ChatHistory::fork`] itself takes any position.
+    pub fn turn_boundary(&self, turns: usize) -> usize {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == "user")
+            .nth(turns)
+            .map_or(self.messages.len(), |(i, _)| i)
+    }
+
+    /// Label this history's messages in the search index, e.g. `tui` or `telegram:42`.
+    pub fn set_session(&mut self, session: &str) {
+        self.session = session.to_string();
+    }
+
     pub fn set_memory_config(&mut self, config: MemoryConfig) {
         self.memory_config = config;
     }
 
-    pub fn save(&self) -> Result<()> {
-        if let Some(parent) = self.history_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        let json =
-            serde_json::to_vec(&self.messages).with_context(|| "Failed to serialize history")?;
-        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
-        encoder
-            .write_all(&json)
-            .with_context(|| "Failed to compress history")?;
-        let compressed = encoder
-            .finish()
-            .with_context(|| "Failed to finish compression")?;
-        std::fs::write(&self.history_path, compressed)
-            .with_context(|| "Failed to write history file")?;
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+
+    /// Persist changes since the last save. New messages are appended after whatever is
+    /// stored, and messages other sessions saved meanwhile are loaded in before them. A
+    /// history compacted by this handle is rewritten.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.has_unsaved_changes() {
+            return Ok(());
+        }
+        match self.saved {
+            Some(saved) if saved <= self.messages.len() => {
+                let unsaved = to_values(&self.messages[saved..])?;
+                if let Some(stored) = self.log.append(&unsaved)? {
+                    self.catch_up(saved, stored)?;
+                }
+            }
+            _ => self.rewrite()?,
+        }
+        self.saved = Some(self.messages.len());
+
+        // Search is secondary to keeping the conversation: retry on the next save.
+        if !self.unindexed.is_empty() {
+            match self.storage.index_messages(&self.unindexed) {
+                Ok(()) => self.unindexed.clear(),
+                Err(e) => warn!(error = %e, "Failed to index messages for search"),
+            }
+        }
+        if !self.unwanted.is_empty() {
+            match self.storage.unindex_messages(&self.unwanted) {
+                Ok(()) => self.unwanted.clear(),
+                Err(e) => warn!(error = %e, "Failed to remove undone messages from search"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the first `saved` messages with `stored`, what the branch stores after other
+    /// handles changed it.
+    fn catch_up(&mut self, saved: usize, stored: Vec<Value>) -> Result<()> {
+        let stored = stored
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<serde_json::Result<Vec<ChatMessage>>>()?;
+        if self.inherited > 0 && !self.attached(&self.storage.load_branches()?) {
+            self.inherited = 0;
+        }
+        let unsaved = self.messages.split_off(saved);
+        self.messages.truncate(self.inherited);
+        self.messages.extend(stored);
+        self.messages.extend(unsaved);
         Ok(())
     }
 
-    pub fn push(&mut self, msg: ChatMessage) {
+    /// Whether the branch still shares its first `inherited` messages with its parent.
+    fn attached(&self, branches: &[HistoryBranch]) -> bool {
+        branches
+            .iter()
+            .any(|b| b.name == self.branch && b.parent.is_some() && b.fork_at == self.inherited)
+    }
+
+    /// Replace what the branch stores with its messages. Branches forked from it first store
+    /// the messages they share with it if those change. If this branch's inherited messages
+    /// changed, or its parent detached it meanwhile, it stores all of its messages.
+    fn rewrite(&mut self) -> Result<()> {
+        let messages = to_values(&self.messages)?;
+        let branches = self.storage.load_branches()?;
+        branch::detach_children(self.storage.as_ref(), &branches, &self.branch, &messages)?;
+        if self.branch == MAIN_BRANCH {
+            return self.log.rewrite(&messages);
+        }
+        let attached = self.inherited > 0 && self.attached(&branches);
+        if !attached {
+            self.inherited = 0;
+        }
+        self.log.rewrite(&messages[self.inherited..])?;
+        if !attached {
+            branch::detach(self.storage.as_ref(), &self.branch)?;
+        }
+        Ok(())
+    }
+
+    /// Whether messages have changed since the history was last saved or loaded.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.saved != Some(self.messages.len())
+    }
+
+    /// Add a message, labelling it with this history's session unless it already has one.
+    pub fn push(&mut self, mut msg: ChatMessage) {
+        msg.session.get_or_insert_with(|| self.session.clone());
+        self.unindexed.extend(search_entry(&msg));
         self.messages.push(msg);
     }
 
+    /// Remove this session's last user message and this session's messages after it, such
+    /// as the reply. Returns the removed messages, starting with the user message, or `None`
+    /// if this session has no user message.
+    ///
+    /// Context messages are pushed before the user message of their turn, so they stay, and
+    /// what the `ContextManager` recorded as injected is still in the history. Messages of
+    /// other sessions stay too.
+    pub fn undo_turn(&mut self) -> Option<Vec<ChatMessage>> {
+        let session = self.session.clone();
+        let own = |m: &ChatMessage| m.session.as_deref() == Some(session.as_str());
+        let start = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == "user" && own(m))?;
+        let (removed, kept): (Vec<_>, Vec<_>) = self.messages.drain(start..).partition(own);
+        self.messages.extend(kept);
+        if start < self.inherited {
+            self.inherited = 0;
+        }
+
+        // Removed messages leave the index, or stay out of it if they were never indexed.
+        for entry in removed.iter().filter_map(search_entry) {
+            match self.unindexed.iter().rposition(|e| *e == entry) {
+                Some(i) => {
+                    self.unindexed.remove(i);
+                }
+                None => self.unwanted.push(entry),
+            }
+        }
+        if self.saved.is_some_and(|saved| saved > start) {
+            self.saved = None;
+        }
+        Some(removed)
+    }
+
+    /// The messages as they are now, e.g. to put an undone turn back if replacing it fails.
+    pub fn snapshot(&self) -> HistorySnapshot {
+        HistorySnapshot {
+            messages: self.messages.clone(),
+            unindexed: self.unindexed.clone(),
+            unwanted: self.unwanted.clone(),
+            saved: self.saved,
+            inherited: self.inherited,
+        }
+    }
+
+    /// Go back to the messages of `snapshot`, taken from this history since it last saved.
+    pub fn restore(&mut self, snapshot: HistorySnapshot) {
+        self.messages = snapshot.messages;
+        self.unindexed = snapshot.unindexed;
+        self.unwanted = snapshot.unwanted;
+        self.saved = snapshot.saved;
+        self.inherited = snapshot.inherited;
+    }
+
+    /// Pin this session's last user message and this session's messages after it, such as
+    /// the reply. Returns `false` if this session has no user message.
+    pub fn pin_last_turn(&mut self) -> bool {
+        let session = self.session.clone();
+        let own = |m: &ChatMessage| m.session.as_deref() == Some(session.as_str());
+        let Some(start) = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == "user" && own(m))
+        else {
+            return false;
+        };
+        for index in start..self.messages.len() {
+            if own(&self.messages[index]) {
+                self.set_pinned(index, true);
+            }
+        }
+        true
+    }
+
+    /// Unpin this session's messages and return how many were pinned.
+    pub fn unpin(&mut self) -> usize {
+        let pinned: Vec<usize> = (0..self.messages.len())
+            .filter(|&i| {
+                let m = &self.messages[i];
+                m.pinned && m.session.as_deref() == Some(self.session.as_str())
+            })
+            .collect();
+        for &index in &pinned {
+            self.set_pinned(index, false);
+        }
+        pinned.len()
+    }
+
+    /// Changing a stored message means rewriting the history, like undo.
+    fn set_pinned(&mut self, index: usize, pinned: bool) {
+        if self.messages[index].pinned == pinned {
+            return;
+        }
+        self.messages[index].pinned = pinned;
+        if index < self.inherited {
+            self.inherited = 0;
+        }
+        if self.saved.is_some_and(|saved| saved > index) {
+            self.saved = None;
+        }
+    }
+
     pub fn messages(&self) -> &[ChatMessage] {
         &self.messages
     }
@@ -111,29 +564,37 @@ impl ChatHistory {
         self.messages.iter().map(|m| m.content.len()).sum::<usize>() / 4
     }
 
+    /// Bytes of message text held in memory.
+    pub fn byte_size(&self) -> usize {
+        byte_size(&self.messages)
+    }
+
+    /// Drop the messages [`Self::compactable`] returns, if the history is above 80% of
+    /// `context_window`.
     pub fn compact(&mut self, context_window: usize) {
         let estimate = self.token_estimate();
         let threshold = (context_window as f64 * 0.8) as usize;
         if estimate > threshold {
-            let len = self.messages.len();
-            let retain = len / 2;
-            let released_bytes: usize = self.messages[..len - retain]
-                .iter()
-                .map(|m| m.content.len() + m.role.len())
-                .sum();
-            self.messages = self.messages.split_off(len - retain);
-            evaluate_reclamation(&self.memory_config, released_bytes);
+            let removed = self.drain_compactable();
+            if removed.is_empty() {
+                return;
+            }
+            self.archive(&removed);
+            self.saved = None;
+            self.inherited = 0;
+            evaluate_reclamation(&self.memory_config, byte_size(&removed));
         }
     }
 
+    /// Replace the messages [`Self::compactable`] returns with `summary`, placed after the
+    /// earlier summaries. The removed messages go to the session's archive, and the summary
+    /// names their range for the `recall_archive` tool.
     pub fn compact_with_summary(&mut self, summary: &str, language_marker: Option<&str>) {
-        let len = self.messages.len();
-        let retain = len / 2;
-        let released_bytes: usize = self.messages[..len - retain]
-            .iter()
-            .map(|m| m.content.len() + m.role.len())
-            .sum();
-        let recent = self.messages.split_off(len - retain);
+        let removed = self.drain_compactable();
+        if removed.is_empty() {
+            return;
+        }
+        let archived = self.archive(&removed);
 
         let mut summary_text = String::new();
         if let Some(lang) = language_marker {
@@ -141,10 +602,155 @@ impl ChatHistory {
         }
         summary_text.push_str("[Conversation Summary]\n");
         summary_text.push_str(summary);
+        if let Some(range) = archived.filter(|r| !r.is_empty()) {
+            summary_text.push('\n');
+            summary_text.push_str(&archive_note(&self.session, range.start, range.end - 1));
+        }
 
-        self.messages = vec![ChatMessage::system(summary_text)];
-        self.messages.extend(recent);
-        evaluate_reclamation(&self.memory_config, released_bytes);
+        let mut message = ChatMessage::system(summary_text);
+        message.summary = Some(SummaryTier::Summary);
+        let at = self
+            .messages
+            .iter()
+            .rposition(|m| m.summary.is_some())
+            .map_or(0, |i| i + 1);
+        self.messages.insert(at, message);
+        self.saved = None;
+        self.inherited = 0;
+        evaluate_reclamation(&self.memory_config, byte_size(&removed));
+    }
+
+    /// Summaries written by compaction, oldest first.
+    pub fn summaries(&self) -> Vec<&ChatMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.summary == Some(SummaryTier::Summary))
+            .collect()
+    }
+
+    /// The long-term gist the oldest summaries were merged into.
+    pub fn gist(&self) -> Option<&ChatMessage> {
+        self.messages
+            .iter()
+            .find(|m| m.summary == Some(SummaryTier::Gist))
+    }
+
+    /// Replace the gist and the `count` oldest summaries with `gist`, which merges them. The
+    /// new gist keeps one archive note per session, covering the ranges theirs named.
+    pub fn merge_into_gist(&mut self, count: usize, gist: &str, language_marker: Option<&str>) {
+        let mut merged = 0;
+        let mut notes: Vec<(String, u64, u64)> = Vec::new();
+        let mut released = 0;
+        self.messages.retain(|m| {
+            let remove = match m.summary {
+                Some(SummaryTier::Gist) => true,
+                Some(SummaryTier::Summary) if merged < count => {
+                    merged += 1;
+                    true
+                }
+                _ => false,
+            };
+            if remove {
+                released += m.content.len();
+                for (session, first, last) in m.content.lines().filter_map(parse_archive_note) {
+                    match notes.iter_mut().find(|n| n.0 == session) {
+                        Some(note) => {
+                            note.1 = note.1.min(first);
+                            note.2 = note.2.max(last);
+                        }
+                        None => notes.push((session, first, last)),
+                    }
+                }
+            }
+            !remove
+        });
+
+        let mut text = String::new();
+        if let Some(lang) = language_marker {
+            text.push_str(&format!("[User Language: {}]\n", lang));
+        }
+        text.push_str("[Long-term Gist]\n");
+        text.push_str(gist);
+        for (session, first, last) in &notes {
+            text.push('\n');
+            text.push_str(&archive_note(session, *first, *last));
+        }
+        let mut message = ChatMessage::system(text);
+        message.summary = Some(SummaryTier::Gist);
+        self.messages.insert(0, message);
+        self.saved = None;
+        self.inherited = 0;
+        evaluate_reclamation(&self.memory_config, released);
+    }
+
+    /// Messages compaction removes: those older than the newest ones the retention target
+    /// keeps, other than earlier summaries, pinned messages, the latest context message and
+    /// the latest tool results.
+    pub fn compactable(&self) -> Vec<&ChatMessage> {
+        self.messages
+            .iter()
+            .zip(self.retained())
+            .filter(|(_, keep)| !keep)
+            .map(|(m, _)| m)
+            .collect()
+    }
+
+    fn retained(&self) -> Vec<bool> {
+        let len = self.messages.len();
+        let recent = match self.retention.target_tokens {
+            Some(target) => {
+                let mut start = len;
+                let mut bytes = 0;
+                while start > 0 {
+                    bytes += self.messages[start - 1].content.len();
+                    if bytes / 4 > target {
+                        break;
+                    }
+                    start -= 1;
+                }
+                start
+            }
+            None => len - len / 2,
+        };
+        let latest_context = self.messages.iter().rposition(|m| m.context);
+        let tool_results: Vec<usize> = (0..len)
+            .rev()
+            .filter(|&i| self.messages[i].role == "tool")
+            .take(self.retention.tool_results)
+            .collect();
+        (0..len)
+            .map(|i| {
+                i >= recent
+                    || self.messages[i].summary.is_some()
+                    || self.messages[i].pinned
+                    || latest_context == Some(i)
+                    || tool_results.contains(&i)
+            })
+            .collect()
+    }
+
+    fn drain_compactable(&mut self) -> Vec<ChatMessage> {
+        let retained = self.retained();
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.messages)
+            .into_iter()
+            .zip(retained)
+            .partition(|(_, keep)| *keep);
+        self.messages = kept.into_iter().map(|(m, _)| m).collect();
+        removed.into_iter().map(|(m, _)| m).collect()
+    }
+
+    /// Move messages removed by compaction to the session's archive. Compaction goes ahead
+    /// without the archive if it cannot be written, as it did before there was one.
+    fn archive(&self, removed: &[ChatMessage]) -> Option<Range<u64>> {
+        let result = to_values(removed)
+            .and_then(|values| self.storage.archive_history(&self.session, &values));
+        match result {
+            Ok(range) => Some(range),
+            Err(e) => {
+                warn!(error = %e, session = %self.session, "Failed to archive compacted messages");
+                None
+            }
+        }
     }
 
     pub fn needs_compact(&self, context_window: usize) -> bool {
@@ -200,16 +806,12 @@ impl ChatHistory {
     }
 
     pub fn reset(&mut self) -> Result<()> {
-        let released_bytes: usize = self
-            .messages
-            .iter()
-            .map(|m| m.content.len() + m.role.len())
-            .sum();
+        let released_bytes = self.byte_size();
         self.messages.clear();
-        if self.history_path.exists() {
-            std::fs::remove_file(&self.history_path)
-                .with_context(|| "Failed to delete history file")?;
-        }
+        self.unindexed.clear();
+        self.inherited = 0;
+        self.rewrite()?;
+        self.saved = Some(0);
         if released_bytes > 0 {
             evaluate_reclamation(&self.memory_config, released_bytes);
         }
@@ -217,6 +819,53 @@ impl ChatHistory {
     }
 }
 
+/// The index entry of a stored message, if it is searchable. Messages saved without a
+/// timestamp have no stable entry to compare with the index, so they are left out.
+fn stored_entry(msg: &ChatMessage) -> Option<SearchEntry> {
+    msg.timestamp.and_then(|_| search_entry(msg))
+}
+
+/// The search index entry for a user or assistant message.
+fn search_entry(msg: &ChatMessage) -> Option<SearchEntry> {
+    (msg.role == "user" || msg.role == "assistant").then(|| {
+        SearchEntry::new(
+            msg.session.as_deref().unwrap_or(DEFAULT_SESSION),
+            msg.timestamp.unwrap_or_else(Utc::now),
+            &msg.role,
+            &msg.content,
+        )
+    })
+}
+
+/// Where the agent finds the original text of summarized messages.
+fn archive_note(session: &str, first: u64, last: u64) -> String {
+    format!(
+        "[Archived: messages {first}-{last} of session \"{session}\"; recall_archive returns their original text]"
+    )
+}
+
+fn parse_archive_note(line: &str) -> Option<(String, u64, u64)> {
+    let rest = line.strip_prefix("[Archived: messages ")?;
+    let (range, rest) = rest.split_once(" of session \"")?;
+    let (session, _) = rest.split_once("\";")?;
+    let (first, last) = range.split_once('-')?;
+    Some((session.to_string(), first.parse().ok()?, last.parse().ok()?))
+}
+
+fn byte_size(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| m.content.len() + m.role.len())
+        .sum()
+}
+
+fn to_values(messages: &[ChatMessage]) -> Result<Vec<Value>> {
+    Ok(messages
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<serde_json::Result<_>>()?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,6 +886,365 @@ mod tests {
         assert_eq!(loaded.messages()[1].content, "Hi there!");
     }
 
+    #[test]
+    fn test_reply_details_roundtrip_and_old_messages_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let (mut log, _) = storage.open_history().unwrap();
+        log.rewrite(&[serde_json::json!({ "role": "user", "content": "saved long ago" })])
+            .unwrap();
+
+        let mut history = ChatHistory::open(storage.clone()).unwrap();
+        let old = &history.messages()[0];
+        assert!(old.timestamp.is_none());
+        assert_eq!(old.details(), None);
+
+        let usage = TokenUsage {
+            input_tokens: 1200,
+            output_tokens: 85,
+        };
+        history.push(ChatMessage::assistant("Hi").with_reply_details(
+            "gpt-4o",
+            Some(usage),
+            Duration::from_millis(2400),
+        ));
+        history.save().unwrap();
+
+        let loaded = ChatHistory::open(storage).unwrap();
+        let reply = &loaded.messages()[1];
+        assert_eq!(reply.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(reply.usage, Some(usage));
+        assert_eq!(reply.latency_ms, Some(2400));
+        let details = reply.details().unwrap();
+        assert!(details.ends_with(" UTC · gpt-4o · 1200 in / 85 out tokens · 2.4s"));
+    }
+
+    #[test]
+    fn test_unsaved_changes_tracking() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        assert!(!history.has_unsaved_changes());
+
+        history.push(ChatMessage::user("Hello"));
+        assert!(history.has_unsaved_changes());
+        assert_eq!(history.byte_size(), "Hello".len() + "user".len());
+        history.save().unwrap();
+        assert!(!history.has_unsaved_changes());
+
+        history.compact_with_summary("greeting", None);
+        assert!(history.has_unsaved_changes());
+    }
+
+    #[test]
+    fn test_appends_and_compaction_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        for i in 0..20 {
+            history.push(ChatMessage::user(format!("message {i}")));
+            history.save().unwrap();
+        }
+        history.compact_with_summary("Earlier messages", None);
+        history.save().unwrap();
+        history.push(ChatMessage::assistant("after compaction"));
+        history.save().unwrap();
+
+        let loaded = ChatHistory::load(dir.path()).unwrap();
+        assert_eq!(loaded.messages().len(), 12);
+        assert!(loaded.messages()[0].content.contains("Earlier messages"));
+        assert_eq!(loaded.messages()[1].content, "message 10");
+        assert_eq!(loaded.messages()[11].content, "after compaction");
+    }
+
+    #[test]
+    fn test_saved_messages_stay_searchable_after_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        history.set_session("telegram:7");
+        history.push(ChatMessage::system("context"));
+        history.push(ChatMessage::user("Where is the staging database?"));
+        history.push(ChatMessage::assistant("It runs on db-staging-2."));
+        for i in 0..10 {
+            history.push(ChatMessage::user(format!("message {i}")));
+        }
+        history.save().unwrap();
+        history.compact_with_summary("Earlier messages", None);
+        history.save().unwrap();
+
+        let storage = FileStorage::new(dir.path().to_path_buf());
+        let hits = storage.search_history("staging", None, 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].session, "telegram:7");
+        assert_eq!(hits[0].role, "assistant");
+        assert!(
+            storage
+                .search_history("context", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            storage
+                .search_history("earlier", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_compaction_archives_removed_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let mut history = ChatHistory::open(storage.clone()).unwrap();
+        history.set_session("tui");
+        for i in 0..8 {
+            history.push(ChatMessage::user(format!("message {i}")));
+        }
+        history.compact(1);
+        history.compact_with_summary("Earlier messages", None);
+
+        let summary = &history.messages()[0].content;
+        assert!(summary.contains("[Archived: messages 4-5 of session \"tui\""));
+        let archived = storage.load_history_archive("tui", 0..10).unwrap();
+        assert_eq!(archived.len(), 6);
+        assert_eq!(archived[5]["content"], "message 5");
+    }
+
+    #[test]
+    fn test_undo_turn() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let mut other = ChatHistory::open(storage.clone()).unwrap();
+        other.set_session("tui");
+        other.push(ChatMessage::user("from the tui"));
+        other.save().unwrap();
+
+        let mut history = ChatHistory::open(storage.clone()).unwrap();
+        history.set_session("telegram:7");
+        assert!(history.undo_turn().is_none());
+        history.push(ChatMessage::system("context"));
+        history.push(ChatMessage::user("first"));
+        history.push(ChatMessage::assistant("first reply"));
+        history.save().unwrap();
+        history.push(ChatMessage::user("second"));
+
+        let removed = history.undo_turn().unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].content, "second");
+        assert!(!history.has_unsaved_changes());
+
+        let removed = history.undo_turn().unwrap();
+        let removed: Vec<_> = removed.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(removed, ["first", "first reply"]);
+        history.save().unwrap();
+
+        let loaded = ChatHistory::open(storage.clone()).unwrap();
+        let contents: Vec<_> = loaded.messages().iter().map(|m| &m.content).collect();
+        assert_eq!(contents, ["from the tui", "context"]);
+        assert!(
+            storage
+                .search_history("second", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            storage
+                .search_history("first", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(storage.search_history("tui", None, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_open_indexes_stored_messages_missing_from_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let (mut log, _) = storage.open_history().unwrap();
+        let saved = [
+            ChatMessage::user("legacy question"),
+            ChatMessage::user("legacy"),
+        ];
+        log.rewrite(&to_values(&saved).unwrap()).unwrap();
+        assert!(
+            storage
+                .search_history("legacy", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+
+        ChatHistory::open(storage.clone()).unwrap();
+        ChatHistory::open(storage.clone()).unwrap();
+        assert_eq!(storage.search_history("legacy", None, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restore_puts_an_undone_turn_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        history.push(ChatMessage::user("question"));
+        history.push(ChatMessage::assistant("answer"));
+        history.save().unwrap();
+
+        let snapshot = history.snapshot();
+        history.undo_turn().unwrap();
+        history.push(ChatMessage::user("question"));
+        history.restore(snapshot);
+        assert_eq!(contents(&history), ["question", "answer"]);
+        assert!(!history.has_unsaved_changes());
+
+        history.push(ChatMessage::user("next"));
+        history.save().unwrap();
+        let loaded = ChatHistory::load(dir.path()).unwrap();
+        assert_eq!(contents(&loaded), ["question", "answer", "next"]);
+    }
+
+    fn contents(history: &ChatHistory) -> Vec<&str> {
+        history
+            .messages()
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_turn_boundary_keeps_whole_turns() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        history.push(ChatMessage::context("prompt"));
+        history.push(ChatMessage::user("q1"));
+        history.push(ChatMessage::tool("bash", "{}", "ok"));
+        history.push(ChatMessage::assistant("a1"));
+        history.push(ChatMessage::user("q2"));
+        history.push(ChatMessage::assistant("a2"));
+
+        let boundaries: Vec<usize> = (0..4).map(|n| history.turn_boundary(n)).collect();
+        assert_eq!(boundaries, [1, 4, 6, 6]);
+        history.fork("first", history.turn_boundary(1)).unwrap();
+        let roles: Vec<&str> = history.messages().iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "tool", "assistant"]);
+    }
+
+    #[test]
+    fn test_fork_shares_messages_with_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let mut history = ChatHistory::open(storage.clone()).unwrap();
+        for text in ["q1", "a1", "q2", "a2"] {
+            history.push(ChatMessage::user(text));
+        }
+        assert_eq!(history.turn_boundary(1), 1);
+        assert_eq!(history.turn_boundary(9), 4);
+        assert!(history.fork("main", 2).is_err());
+        assert!(history.fork("bad name", 2).is_err());
+        assert!(history.fork("draft", 5).is_err());
+
+        history.fork("draft", 2).unwrap();
+        assert_eq!(history.branch(), "draft");
+        history.push(ChatMessage::user("q2 again"));
+        history.save().unwrap();
+        assert!(history.fork("draft", 0).is_err());
+        assert_eq!(
+            storage.open_branch_history("draft").unwrap().1.len(),
+            1,
+            "shared messages are not stored again"
+        );
+
+        let draft = ChatHistory::open_branch(storage.clone(), "draft").unwrap();
+        assert_eq!(contents(&draft), ["q1", "a1", "q2 again"]);
+        history.switch_branch(MAIN_BRANCH).unwrap();
+        assert_eq!(contents(&history), ["q1", "a1", "q2", "a2"]);
+
+        // A branch of a branch reads through both parents.
+        history.switch_branch("draft").unwrap();
+        history.fork("draft.2", 3).unwrap();
+        history.push(ChatMessage::assistant("a2 again"));
+        history.save().unwrap();
+        let nested = ChatHistory::open_branch(storage.clone(), "draft.2").unwrap();
+        assert_eq!(contents(&nested), ["q1", "a1", "q2 again", "a2 again"]);
+        let names: Vec<_> = history
+            .branches()
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, ["draft", "draft.2"]);
+        assert!(ChatHistory::open_branch(storage, "missing").is_err());
+    }
+
+    #[test]
+    fn test_two_handles_keep_each_others_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let mut tui = ChatHistory::open(storage.clone()).unwrap();
+        tui.push(ChatMessage::user("tui 1"));
+        tui.save().unwrap();
+        let mut telegram = ChatHistory::open(storage.clone()).unwrap();
+
+        tui.push(ChatMessage::user("tui 2"));
+        tui.save().unwrap();
+        telegram.push(ChatMessage::user("telegram 1"));
+        telegram.save().unwrap();
+        assert_eq!(contents(&telegram), ["tui 1", "tui 2", "telegram 1"]);
+        tui.push(ChatMessage::user("tui 3"));
+        tui.save().unwrap();
+        assert_eq!(contents(&tui), ["tui 1", "tui 2", "telegram 1", "tui 3"]);
+        let loaded = ChatHistory::open(storage.clone()).unwrap();
+        assert_eq!(contents(&loaded), contents(&tui));
+
+        // A handle on a branch that another handle detached stops reading through the parent.
+        tui.fork("draft", 2).unwrap();
+        let mut other = ChatHistory::open_branch(storage.clone(), "draft").unwrap();
+        tui.compact_with_summary("Earlier messages", None);
+        tui.save().unwrap();
+        other.push(ChatMessage::user("draft 1"));
+        other.save().unwrap();
+        let mut expected = contents(&tui);
+        expected.push("draft 1");
+        assert_eq!(contents(&other), expected);
+        let draft = ChatHistory::open_branch(storage, "draft").unwrap();
+        assert_eq!(contents(&draft), expected);
+    }
+
+    #[test]
+    fn test_rewriting_parent_detaches_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let mut main = ChatHistory::open(storage.clone()).unwrap();
+        for i in 0..8 {
+            main.push(ChatMessage::user(format!("message {i}")));
+        }
+        main.fork("early", 2).unwrap();
+        main.switch_branch(MAIN_BRANCH).unwrap();
+        main.fork("late", 8).unwrap();
+        main.push(ChatMessage::user("late 8"));
+        main.save().unwrap();
+        main.switch_branch(MAIN_BRANCH).unwrap();
+
+        // Undoing the last message leaves the first two alone.
+        main.undo_turn().unwrap();
+        main.save().unwrap();
+        let branches = main.branches().unwrap();
+        assert_eq!(branches[0].parent.as_deref(), Some(MAIN_BRANCH));
+        assert_eq!(branches[1].parent, None);
+        let late = ChatHistory::open_branch(storage.clone(), "late").unwrap();
+        assert_eq!(late.messages().len(), 9);
+        assert_eq!(late.messages()[7].content, "message 7");
+
+        main.compact(1);
+        main.save().unwrap();
+        assert_eq!(main.branches().unwrap()[0].parent, None);
+        let early = ChatHistory::open_branch(storage.clone(), "early").unwrap();
+        assert_eq!(contents(&early), ["message 0", "message 1"]);
+
+        // Compacting a branch stores its inherited messages in the branch.
+        let mut history = ChatHistory::open(storage.clone()).unwrap();
+        history.fork("compacted", 3).unwrap();
+        history.compact_with_summary("Earlier messages", None);
+        history.save().unwrap();
+        let compacted = ChatHistory::open_branch(storage.clone(), "compacted").unwrap();
+        assert_eq!(compacted.messages().len(), 2);
+        assert_eq!(storage.open_branch_history("compacted").unwrap().1.len(), 2);
+    }
+
     #[test]
     fn test_load_nonexistent() {
         let dir = tempfile::tempdir().unwrap();
@@ -268,17 +1276,146 @@ mod tests {
         assert_eq!(history.messages()[0].content, "message 10");
     }
 
+    #[test]
+    fn test_compaction_keeps_pinned_context_and_tool_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        history.set_retention(Retention {
+            tool_results: 1,
+            target_tokens: None,
+        });
+        history.push(ChatMessage::context("old context"));
+        history.push(ChatMessage::pinned_note("Always answer in French"));
+        history.push(ChatMessage::context("new context"));
+        history.push(ChatMessage::tool("bash", "ls", "a.txt"));
+        history.push(ChatMessage::tool("bash", "pwd", "/tmp"));
+        for i in 0..7 {
+            history.push(ChatMessage::user(format!("message {i}")));
+        }
+
+        assert_eq!(
+            history
+                .compactable()
+                .iter()
+                .map(|m| m.content.as_str())
+                .collect::<Vec<_>>(),
+            ["old context", "[Tool result: bash] ls\na.txt", "message 0"]
+        );
+        history.compact_with_summary("Earlier messages", None);
+        let contents = contents(&history);
+        assert!(contents[0].contains("Earlier messages"));
+        assert_eq!(
+            contents[1..5],
+            [
+                "[Pinned note]\nAlways answer in French",
+                "new context",
+                "[Tool result: bash] pwd\n/tmp",
+                "message 1"
+            ]
+        );
+        assert_eq!(contents.len(), 10);
+    }
+
+    #[test]
+    fn test_compaction_keeps_newest_messages_within_target_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        history.set_retention(Retention {
+            tool_results: 0,
+            target_tokens: Some(5),
+        });
+        for i in 0..10 {
+            history.push(ChatMessage::user(format!("message {i}")));
+        }
+
+        // Each message is 9 bytes, about 2 tokens: two fit in 5 tokens, three do not.
+        history.compact(1);
+        assert_eq!(contents(&history), ["message 8", "message 9"]);
+    }
+
+    #[test]
+    fn test_summaries_are_kept_and_merged_into_gist() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        for i in 0..20 {
+            history.push(ChatMessage::user(format!("message {i}")));
+        }
+        history.compact_with_summary("first", None);
+        for i in 20..30 {
+            history.push(ChatMessage::user(format!("message {i}")));
+        }
+        history.compact_with_summary("second", None);
+
+        let summaries = history.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert!(
+            summaries[0]
+                .content
+                .contains("first\n[Archived: messages 0-9 ")
+        );
+        assert!(
+            summaries[1]
+                .content
+                .contains("second\n[Archived: messages 10-19 ")
+        );
+        assert_eq!(history.messages()[2].content, "message 20");
+
+        history.merge_into_gist(2, "gist", Some("English"));
+        assert!(history.summaries().is_empty());
+        assert_eq!(
+            history.gist().unwrap().content,
+            "[User Language: English]\n[Long-term Gist]\ngist\n[Archived: messages 0-19 of session \"default\"; recall_archive returns their original text]"
+        );
+
+        history.compact_with_summary("third", None);
+        assert_eq!(history.messages()[0].summary, Some(SummaryTier::Gist));
+        assert_eq!(history.messages()[1].summary, Some(SummaryTier::Summary));
+        history.save().unwrap();
+        let loaded = ChatHistory::load(dir.path()).unwrap();
+        assert!(loaded.gist().is_some());
+        assert_eq!(loaded.summaries().len(), 1);
+    }
+
+    #[test]
+    fn test_pin_last_turn_and_unpin() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = ChatHistory::load(dir.path()).unwrap();
+        history.set_session("tui");
+        history.push(ChatMessage::user("Use tabs"));
+        history.push(ChatMessage::assistant("Noted."));
+        history.save().unwrap();
+        assert!(history.pin_last_turn());
+        assert!(history.has_unsaved_changes());
+        history.save().unwrap();
+
+        let mut loaded = ChatHistory::load(dir.path()).unwrap();
+        assert!(loaded.messages().iter().all(|m| m.pinned));
+        loaded.set_session("telegram:7");
+        assert!(!loaded.pin_last_turn());
+        assert_eq!(loaded.unpin(), 0);
+        loaded.set_session("tui");
+        assert_eq!(loaded.unpin(), 2);
+        loaded.save().unwrap();
+        assert!(
+            ChatHistory::load(dir.path())
+                .unwrap()
+                .messages()
+                .iter()
+                .all(|m| !m.pinned)
+        );
+    }
+
     #[test]
     fn test_reset() {
         let dir = tempfile::tempdir().unwrap();
         let mut history = ChatHistory::load(dir.path()).unwrap();
         history.push(ChatMessage::user("Hello"));
         history.save().unwrap();
-        assert!(dir.path().join("history.json.lz4").exists());
 
         history.reset().unwrap();
         assert!(history.messages().is_empty());
-        assert!(!dir.path().join("history.json.lz4").exists());
+        assert!(!history.has_unsaved_changes());
+        assert!(ChatHistory::load(dir.path()).unwrap().messages().is_empty());
     }
 
     #[test]
@@ -1,3 +1,6 @@
+pub mod branch;
 pub mod history;
+pub mod transcript;
 
-pub use history::{ChatHistory, ChatMessage};
+pub use branch::MAIN_BRANCH;
+pub use history::{ChatHistory, ChatMessage, HistorySnapshot, Retention, SummaryTier, TokenUsage};
@@ -0,0 +1,649 @@
+//! Export chat history as Markdown, JSON, JSON Lines, HTML or provider message formats, and
+//! import conversations saved by hi or by other chat tools.
+//!
+//! Imports accept hi's own JSON and JSON Lines exports, OpenAI chat messages (a bare array or
+//! `{"messages": [...]}`), Anthropic requests (`{"system": ..., "messages": [...]}`), Gemini
+//! `contents`, and ChatGPT's `conversations.json` data export. Only text is kept: tool results
+//! become hi's tool messages, and tool calls and images are skipped.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use chrono::DateTime;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use shared::storage::Storage;
+
+use crate::ChatMessage;
+use crate::history::TOOL_RESULT_PREFIX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    /// hi's messages with their metadata, as one JSON array.
+    Json,
+    /// hi's messages with their metadata, one per line.
+    Jsonl,
+    Html,
+    /// OpenAI chat messages: `[{"role", "content"}]`.
+    OpenAi,
+    /// An Anthropic request body: `{"system", "messages"}`.
+    Anthropic,
+}
+
+impl ExportFormat {
+    pub const NAMES: &'static str = "md, json, jsonl, html, openai, anthropic";
+
+    /// File extension for exports in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json | Self::OpenAi | Self::Anthropic => "json",
+            Self::Jsonl => "jsonl",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            "html" => Ok(Self::Html),
+            "openai" => Ok(Self::OpenAi),
+            "anthropic" => Ok(Self::Anthropic),
+            _ => bail!(
+                "Unknown export format '{s}' (expected one of {})",
+                Self::NAMES
+            ),
+        }
+    }
+}
+
+/// The stored history, or with `session` only the messages that session wrote, preceded by
+/// those compaction moved to its archive.
+pub fn load(storage: &dyn Storage, session: Option<&str>) -> Result<Vec<ChatMessage>> {
+    let (_, stored) = storage.open_history()?;
+    let stored = parse_stored(stored)?;
+    let Some(session) = session else {
+        return Ok(stored);
+    };
+    let from_session = |m: &ChatMessage| m.session.as_deref().is_none_or(|s| s == session);
+    let mut messages: Vec<ChatMessage> =
+        parse_stored(storage.load_history_archive(session, 0..u64::MAX)?)?
+            .into_iter()
+            .filter(from_session)
+            .collect();
+    messages.extend(
+        stored
+            .into_iter()
+            .filter(|m| m.session.as_deref() == Some(session)),
+    );
+    Ok(messages)
+}
+
+fn parse_stored(values: Vec<Value>) -> Result<Vec<ChatMessage>> {
+    values
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<serde_json::Result<_>>()
+        .context("Failed to parse stored messages")
+}
+
+pub fn export(messages: &[ChatMessage], format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => to_markdown(messages),
+        ExportFormat::Json => serde_json::to_string_pretty(messages)?,
+        ExportFormat::Jsonl => {
+            let mut out = String::new();
+            for message in messages {
+                out.push_str(&serde_json::to_string(message)?);
+                out.push('\n');
+            }
+            out
+        }
+        ExportFormat::Html => to_html(messages),
+        ExportFormat::OpenAi => {
+            let messages: Vec<Value> = messages
+                .iter()
+                .map(|m| json!({ "role": provider_role(&m.role), "content": m.content }))
+                .collect();
+            serde_json::to_string_pretty(&messages)?
+        }
+        ExportFormat::Anthropic => serde_json::to_string_pretty(&to_anthropic(messages))?,
+    })
+}
+
+fn title(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        "tool" => "Tool",
+        other => other,
+    }
+}
+
+/// Tool results are sent to the model as user messages, as provider formats expect tool
+/// messages to answer a tool call.
+fn provider_role(role: &str) -> &str {
+    match role {
+        "tool" => "user",
+        other => other,
+    }
+}
+
+fn to_markdown(messages: &[ChatMessage]) -> String {
+    let mut out = String::from("# Conversation\n");
+    for message in messages {
+        let _ = write!(out, "\n## {}\n\n", title(&message.role));
+        if let Some(details) = message.details() {
+            let _ = write!(out, "*{details}*\n\n");
+        }
+        out.push_str(message.content.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn to_html(messages: &[ChatMessage]) -> String {
+    let mut out = String::from(concat!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>Conversation</title>\n<style>\n",
+        "body { font-family: sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; }\n",
+        ".message { margin: 1rem 0; padding: 0.75rem 1rem; border-radius: 0.5rem; }\n",
+        ".user { background: #e8f0fe; }\n.assistant { background: #f1f3f4; }\n",
+        ".system { background: #fff8e1; }\n.role { font-weight: bold; }\n",
+        ".details { color: #5f6368; font-size: 0.85rem; }\n",
+        ".content { white-space: pre-wrap; margin-top: 0.5rem; }\n",
+        "</style>\n</head>\n<body>\n<h1>Conversation</h1>\n",
+    ));
+    for message in messages {
+        let _ = writeln!(
+            out,
+            "<div class=\"message {}\">\n<div class=\"role\">{}</div>",
+            escape_html(&message.role),
+            escape_html(title(&message.role))
+        );
+        if let Some(details) = message.details() {
+            let _ = writeln!(
+                out,
+                "<div class=\"details\">{}</div>",
+                escape_html(&details)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "<div class=\"content\">{}</div>\n</div>",
+            escape_html(&message.content)
+        );
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Anthropic takes system text separately and expects user and assistant turns to
+/// alternate, so system messages are joined into `system` and consecutive turns merged.
+fn to_anthropic(messages: &[ChatMessage]) -> Value {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, String)> = Vec::new();
+    for message in messages {
+        match provider_role(&message.role) {
+            "system" => system.push(message.content.as_str()),
+            role => match turns.last_mut() {
+                Some((last, content)) if *last == role => {
+                    content.push_str("\n\n");
+                    content.push_str(&message.content);
+                }
+                _ => turns.push((role, message.content.clone())),
+            },
+        }
+    }
+    let turns: Vec<Value> = turns
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    if system.is_empty() {
+        json!({ "messages": turns })
+    } else {
+        json!({ "system": system.join("\n\n"), "messages": turns })
+    }
+}
+
+/// Parse a conversation in any of the supported formats.
+pub fn import(text: &str) -> Result<Vec<ChatMessage>> {
+    let messages = match serde_json::from_str::<Value>(text) {
+        Ok(value) => from_value(&value),
+        Err(_) => {
+            let values = text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str::<Value>)
+                .collect::<serde_json::Result<Vec<_>>>()
+                .context("Input is neither JSON nor JSON Lines")?;
+            parse_messages(&values)
+        }
+    };
+    if messages.is_empty() {
+        bail!("No user, assistant or system messages found");
+    }
+    Ok(messages)
+}
+
+fn from_value(value: &Value) -> Vec<ChatMessage> {
+    match value {
+        Value::Array(items) => {
+            let calls = tool_calls(items);
+            items
+                .iter()
+                .flat_map(|item| {
+                    if item.get("mapping").is_some() {
+                        chatgpt_conversation(item)
+                    } else {
+                        parse_message(item, &calls).into_iter().collect()
+                    }
+                })
+                .collect()
+        }
+        Value::Object(_) if value.get("mapping").is_some() => chatgpt_conversation(value),
+        Value::Object(_) => {
+            let mut messages = Vec::new();
+            let system = value
+                .get("system")
+                .or_else(|| value.get("system_instruction"))
+                .or_else(|| value.get("systemInstruction"));
+            if let Some(system) = system {
+                let text = content_text(system.get("parts").unwrap_or(system));
+                if !text.trim().is_empty() {
+                    messages.push(imported("system", text));
+                }
+            }
+            match value.get("messages").or_else(|| value.get("contents")) {
+                Some(Value::Array(items)) => messages.extend(parse_messages(items)),
+                _ => messages.extend(parse_message(value, &HashMap::new())),
+            }
+            messages
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// A message from another tool; it gets metadata only from the source.
+fn imported(role: &str, content: String) -> ChatMessage {
+    let mut message = ChatMessage::new(role, content);
+    message.timestamp = None;
+    message
+}
+
+fn field<T: DeserializeOwned>(value: &Value, key: &str) -> Option<T> {
+    value
+        .get(key)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+/// A tool call's name and arguments, as OpenAI lists them in an assistant message.
+struct ToolCall<'a> {
+    name: &'a str,
+    arguments: &'a str,
+}
+
+/// The tool calls made in `items`, by id, to label the results that answer them.
+fn tool_calls(items: &[Value]) -> HashMap<&str, ToolCall<'_>> {
+    items
+        .iter()
+        .filter_map(|item| item.get("tool_calls")?.as_array())
+        .flatten()
+        .filter_map(|call| {
+            let id = call.get("id")?.as_str()?;
+            let name = call.pointer("/function/name")?.as_str()?;
+            let arguments = call
+                .pointer("/function/arguments")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            Some((id, ToolCall { name, arguments }))
+        })
+        .collect()
+}
+
+fn parse_messages(items: &[Value]) -> Vec<ChatMessage> {
+    let calls = tool_calls(items);
+    items
+        .iter()
+        .filter_map(|item| parse_message(item, &calls))
+        .collect()
+}
+
+/// One message in hi, OpenAI, Anthropic or Gemini form, keeping hi's metadata if present.
+/// Tool results are labelled with the call in `calls` they answer, if any.
+fn parse_message(value: &Value, calls: &HashMap<&str, ToolCall>) -> Option<ChatMessage> {
+    let role = normalize_role(value.get("role")?.as_str()?)?;
+    let content = content_text(value.get("content").or_else(|| value.get("parts"))?);
+    if content.trim().is_empty() {
+        return None;
+    }
+    let mut message = if role == "tool" {
+        let call = value
+            .get("tool_call_id")
+            .and_then(Value::as_str)
+            .and_then(|id| calls.get(id));
+        let name = value.get("name").and_then(Value::as_str);
+        tool_result(
+            call.map(|c| c.name).or(name),
+            call.map_or("", |c| c.arguments),
+            content,
+        )
+    } else {
+        imported(role, content)
+    };
+    message.timestamp = field(value, "timestamp");
+    message.session = field(value, "session");
+    message.model = field(value, "model");
+    message.usage = field(value, "usage");
+    message.latency_ms = field(value, "latency_ms");
+    message.pinned = field(value, "pinned").unwrap_or(false);
+    Some(message)
+}
+
+fn normalize_role(role: &str) -> Option<&'static str> {
+    match role {
+        "user" | "human" => Some("user"),
+        "assistant" | "model" => Some("assistant"),
+        "system" | "developer" => Some("system"),
+        "tool" | "function" => Some("tool"),
+        _ => None,
+    }
+}
+
+/// A tool result in the shape hi stores them, unless `content` already has it, as in hi's
+/// own exports.
+fn tool_result(name: Option<&str>, args: &str, content: String) -> ChatMessage {
+    if content.starts_with(TOOL_RESULT_PREFIX) {
+        return imported("tool", content);
+    }
+    let mut message = ChatMessage::tool(name.unwrap_or("unknown"), args, &content);
+    message.timestamp = None;
+    message
+}
+
+/// Text of a message's content: a string, or the text parts of a list of content blocks.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(text) => Some(text.as_str()),
+                Value::Object(_) => part.get("text").and_then(Value::as_str),
+                _ => None,
+            })
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
+    }
+}
+
+/// A conversation from ChatGPT's data export: a tree of nodes in `mapping`, read from
+/// `current_node` back to the root so that only the branch last shown is kept.
+fn chatgpt_conversation(conversation: &Value) -> Vec<ChatMessage> {
+    let Some(mapping) = conversation.get("mapping").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let mut messages = Vec::new();
+    let mut node_id = conversation
+        .get("current_node")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    // A well-formed tree has no cycles; the bound keeps a malformed one from looping.
+    for _ in 0..mapping.len() {
+        let Some(node) = node_id.as_deref().and_then(|id| mapping.get(id)) else {
+            break;
+        };
+        if let Some(message) = node.get("message").and_then(chatgpt_message) {
+            messages.push(message);
+        }
+        node_id = node
+            .get("parent")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+    }
+    messages.reverse();
+    messages
+}
+
+fn chatgpt_message(message: &Value) -> Option<ChatMessage> {
+    let role = normalize_role(message.pointer("/author/role")?.as_str()?)?;
+    let content = content_text(message.pointer("/content/parts")?);
+    if content.trim().is_empty() {
+        return None;
+    }
+    let mut imported = if role == "tool" {
+        let name = message.pointer("/author/name").and_then(Value::as_str);
+        tool_result(name, "", content)
+    } else {
+        imported(role, content)
+    };
+    imported.timestamp = message
+        .get("create_time")
+        .and_then(Value::as_f64)
+        .and_then(|secs| DateTime::from_timestamp_millis((secs * 1000.0) as i64));
+    imported.model = message
+        .pointer("/metadata/model_slug")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Some(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatHistory, TokenUsage};
+    use shared::storage::FileStorage;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system("Be brief."),
+            ChatMessage::user("Is <b> & \"c\" safe?"),
+            ChatMessage::assistant("Yes.").with_reply_details(
+                "gpt-4o",
+                Some(TokenUsage {
+                    input_tokens: 12,
+                    output_tokens: 3,
+                }),
+                Duration::from_millis(800),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let messages = conversation();
+
+        let md = export(&messages, ExportFormat::Markdown).unwrap();
+        assert!(md.starts_with("# Conversation\n\n## System\n\n*"));
+        assert!(md.contains("## Assistant\n\n*"));
+        assert!(md.contains(" · gpt-4o · 12 in / 3 out tokens · 0.8s*\n\nYes.\n"));
+
+        let html = export(&messages, ExportFormat::Html).unwrap();
+        assert!(html.contains("Is &lt;b&gt; &amp; &quot;c&quot; safe?"));
+        assert!(html.ends_with("</html>\n"));
+
+        let jsonl = export(&messages, ExportFormat::Jsonl).unwrap();
+        assert_eq!(jsonl.lines().count(), 3);
+        assert!(jsonl.contains("\"latency_ms\":800"));
+
+        let openai: Value =
+            serde_json::from_str(&export(&messages, ExportFormat::OpenAi).unwrap()).unwrap();
+        assert_eq!(openai[2], json!({ "role": "assistant", "content": "Yes." }));
+
+        let anthropic: Value =
+            serde_json::from_str(&export(&messages, ExportFormat::Anthropic).unwrap()).unwrap();
+        assert_eq!(anthropic["system"], "Be brief.");
+        assert_eq!(anthropic["messages"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_provider_exports_send_tool_results_as_user_messages() {
+        let mut messages = conversation();
+        messages.insert(2, ChatMessage::tool("bash", "ls", "a.txt"));
+
+        let openai: Value =
+            serde_json::from_str(&export(&messages, ExportFormat::OpenAi).unwrap()).unwrap();
+        assert_eq!(openai[2]["role"], "user");
+
+        let anthropic: Value =
+            serde_json::from_str(&export(&messages, ExportFormat::Anthropic).unwrap()).unwrap();
+        let turns = anthropic["messages"].as_array().unwrap();
+        assert_eq!(turns.len(), 2);
+        assert!(turns[0]["content"].as_str().unwrap().ends_with("a.txt"));
+    }
+
+    #[test]
+    fn test_exports_import_back() {
+        let messages = conversation();
+        for format in [
+            ExportFormat::Json,
+            ExportFormat::Jsonl,
+            ExportFormat::OpenAi,
+            ExportFormat::Anthropic,
+        ] {
+            let imported = import(&export(&messages, format).unwrap()).unwrap();
+            let roles: Vec<&str> = imported.iter().map(|m| m.role.as_str()).collect();
+            assert_eq!(roles, ["system", "user", "assistant"], "{format:?}");
+            assert_eq!(imported[2].content, "Yes.");
+        }
+        let imported = import(&export(&messages, ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(imported[2].latency_ms, Some(800));
+        assert_eq!(imported[2].model.as_deref(), Some("gpt-4o"));
+    }
+
+    #[test]
+    fn test_tool_results_round_trip() {
+        let mut messages = conversation();
+        messages.insert(2, ChatMessage::tool("bash", "ls", "a.txt"));
+        for format in [ExportFormat::Json, ExportFormat::Jsonl] {
+            let imported = import(&export(&messages, format).unwrap()).unwrap();
+            let roles: Vec<&str> = imported.iter().map(|m| m.role.as_str()).collect();
+            assert_eq!(roles, ["system", "user", "tool", "assistant"], "{format:?}");
+            assert_eq!(imported[2].content, messages[2].content);
+            assert_eq!(imported[2].timestamp, messages[2].timestamp);
+        }
+    }
+
+    #[test]
+    fn test_imports_content_blocks_and_tool_results() {
+        let openai = json!({ "messages": [
+            { "role": "developer", "content": "Rules" },
+            { "role": "user", "content": [{ "type": "text", "text": "Weather?" }] },
+            { "role": "assistant", "content": null, "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "weather", "arguments": "{\"city\":\"Oslo\"}" }
+            }]},
+            { "role": "tool", "tool_call_id": "call_1", "content": "sunny" },
+            { "role": "assistant", "content": "Sunny." }
+        ]});
+        let messages = import(&openai.to_string()).unwrap();
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "tool", "assistant"]);
+        assert_eq!(messages[1].content, "Weather?");
+        assert!(messages[1].timestamp.is_none());
+        assert_eq!(
+            messages[2].content,
+            "[Tool result: weather] {\"city\":\"Oslo\"}\nsunny"
+        );
+        assert!(messages[2].timestamp.is_none());
+
+        let gemini = json!({ "contents": [
+            { "role": "user", "parts": [{ "text": "Hi" }] },
+            { "role": "model", "parts": [{ "text": "Hello" }, { "inline_data": {} }] }
+        ]});
+        let messages = import(&gemini.to_string()).unwrap();
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].content, "Hello");
+
+        assert!(import("{\"messages\": []}").is_err());
+        assert!(import("not json").is_err());
+    }
+
+    #[test]
+    fn test_imports_chatgpt_export_current_branch() {
+        let export = json!([{
+            "title": "Trip",
+            "current_node": "c",
+            "mapping": {
+                "root": { "message": null, "parent": null },
+                "a": { "parent": "root", "message": {
+                    "author": { "role": "user" },
+                    "create_time": 1700000000.5,
+                    "content": { "content_type": "text", "parts": ["Plan a trip"] }
+                }},
+                "b": { "parent": "a", "message": {
+                    "author": { "role": "assistant" },
+                    "content": { "content_type": "text", "parts": ["Old answer"] }
+                }},
+                "c": { "parent": "a", "message": {
+                    "author": { "role": "assistant" },
+                    "metadata": { "model_slug": "gpt-4o" },
+                    "content": { "content_type": "text", "parts": ["New answer"] }
+                }}
+            }
+        }]);
+        let messages = import(&export.to_string()).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "Plan a trip");
+        assert_eq!(
+            messages[0].timestamp.unwrap().timestamp_millis(),
+            1_700_000_000_500
+        );
+        assert_eq!(messages[1].content, "New answer");
+        assert_eq!(messages[1].model.as_deref(), Some("gpt-4o"));
+    }
+
+    #[test]
+    fn test_load_session_includes_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let mut history = ChatHistory::open(storage.clone()).unwrap();
+        history.set_session("telegram:1");
+        for i in 0..4 {
+            history.push(ChatMessage::user(format!("one {i}")));
+        }
+        history.compact(1);
+        history.set_session("telegram:2");
+        history.push(ChatMessage::user("two"));
+        history.save().unwrap();
+
+        assert_eq!(load(storage.as_ref(), None).unwrap().len(), 3);
+        let one: Vec<String> = load(storage.as_ref(), Some("telegram:1"))
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(one, ["one 0", "one 1", "one 2", "one 3"]);
+        assert_eq!(load(storage.as_ref(), Some("telegram:2")).unwrap().len(), 1);
+    }
+}
@@ -11,6 +11,7 @@ pub async fn run_remote(config_path: Option<PathBuf>) -> Result<()> {
         Some(ref p) => ModelConfig::load_from_path(p)?,
         None => ModelConfig::load()?,
     };
+    shared::storage::init(config.storage.as_ref())?;
 
     let telegram_config = config
         .remote
@@ -25,5 +26,6 @@ pub async fn run_remote(config_path: Option<PathBuf>) -> Result<()> {
         ),
     };
 
+    hi_core::metrics::start(config.metrics.as_ref()).await?;
     telegram::run_polling_loop(&config, &telegram_config).await
 }
@@ -1,12 +1,19 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
+use hi_core::metrics::metrics;
 use hi_core::session::ChatSession;
 use shared::config::ModelConfig;
 use tokio::sync::Mutex;
 use tokio::time::Instant;
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// Labels a chat's session in the tool audit log, search index and history archive.
+pub(crate) fn session_label(chat_id: i64) -> String {
+    format!("telegram:{chat_id}")
+}
 
 struct SessionEntry {
     session: Arc<Mutex<ChatSession>>,
@@ -47,9 +54,15 @@ impl SessionManager {
 
         for id in &expired {
             sessions.remove(id);
+            metrics()
+                .session_evictions
+                .with_label_values(&["idle"])
+                .inc();
             debug!(chat_id = id, ttl_secs, "Evicted idle session");
         }
 
+        metrics().sessions.set(sessions.len() as i64);
+
         // Reuse existing session
         if let Some(entry) = sessions.get_mut(&chat_id) {
             entry.last_activity = now;
@@ -63,6 +76,10 @@ impl SessionManager {
                 sessions.iter().min_by_key(|(_, entry)| entry.last_activity)
             {
                 sessions.remove(&oldest_id);
+                metrics()
+                    .session_evictions
+                    .with_label_values(&["capacity"])
+                    .inc();
                 debug!(
                     chat_id = oldest_id,
                     max_sessions, "Evicted oldest session (capacity)"
@@ -71,7 +88,7 @@ impl SessionManager {
         }
 
         // Create new session
-        let session = ChatSession::new(self.config.clone()).await?;
+        let session = ChatSession::new(self.config.clone(), &session_label(chat_id)).await?;
         let session = Arc::new(Mutex::new(session));
         sessions.insert(
             chat_id,
@@ -80,6 +97,7 @@ impl SessionManager {
                 last_activity: now,
             },
         );
+        metrics().sessions.set(sessions.len() as i64);
         debug!(chat_id, "Created session");
 
         Ok(session)
@@ -89,11 +107,119 @@ impl SessionManager {
         &self.config
     }
 
-    #[allow(dead_code)]
     pub async fn session_count(&self) -> usize {
         self.sessions.lock().await.len()
     }
 
+    /// Check resident memory against `memory.rss_budget_bytes` every `check_interval_secs`.
+    /// Returns at once when no budget is configured.
+    pub async fn watch_memory(self: Arc<Self>) {
+        let Some(memory) = self.config.memory.clone() else {
+            return;
+        };
+        let Some(budget) = memory.rss_budget_bytes else {
+            return;
+        };
+        info!(budget, "Watching resident memory");
+        let mut interval = tokio::time::interval(Duration::from_secs(memory.check_interval_secs));
+        loop {
+            interval.tick().await;
+            self.enforce_memory_budget(
+                budget,
+                memory.large_session_bytes,
+                shared::memory::resident_bytes,
+            )
+            .await;
+        }
+    }
+
+    /// Bring resident memory, as reported by `sample`, back under `budget`.
+    ///
+    /// Sessions with more than `large_session_bytes` of history are compacted first. If that
+    /// is not enough, least-recently-used sessions are flushed to disk and evicted one at a
+    /// time until the budget is met. Sessions busy with a message are left alone.
+    async fn enforce_memory_budget(
+        &self,
+        budget: u64,
+        large_session_bytes: usize,
+        mut sample: impl FnMut() -> Option<u64>,
+    ) {
+        let over_budget = |rss: Option<u64>| {
+            if let Some(rss) = rss {
+                metrics().resident_memory_bytes.set(rss as i64);
+            }
+            rss.is_some_and(|rss| rss > budget)
+        };
+        if !over_budget(sample()) {
+            return;
+        }
+        warn!(budget, "Resident memory over budget");
+
+        // Compaction keeps the session, so try it before evicting anything. The map lock
+        // is released first because compaction may call a model.
+        let sessions: Vec<(i64, Arc<Mutex<ChatSession>>)> = {
+            let sessions = self.sessions.lock().await;
+            sessions
+                .iter()
+                .map(|(id, entry)| (*id, Arc::clone(&entry.session)))
+                .collect()
+        };
+        for (chat_id, session) in sessions {
+            let Ok(mut session) = session.try_lock() else {
+                continue;
+            };
+            let before = session.history().byte_size();
+            if before <= large_session_bytes || !session.run_compact().await {
+                continue;
+            }
+            if let Err(e) = session.flush_history() {
+                warn!(chat_id, error = %e, "Failed to save compacted history");
+            }
+            info!(
+                chat_id,
+                before,
+                after = session.history().byte_size(),
+                "Compacted session (memory pressure)"
+            );
+        }
+        shared::memory::trim_heap();
+        if !over_budget(sample()) {
+            return;
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        let mut by_age: Vec<(i64, Instant)> = sessions
+            .iter()
+            .map(|(id, entry)| (*id, entry.last_activity))
+            .collect();
+        by_age.sort_by_key(|(_, last_activity)| *last_activity);
+        for (chat_id, _) in by_age {
+            let Some(entry) = sessions.get(&chat_id) else {
+                continue;
+            };
+            {
+                let Ok(mut session) = entry.session.try_lock() else {
+                    continue;
+                };
+                if let Err(e) = session.flush_history() {
+                    warn!(chat_id, error = %e, "Failed to save history; keeping session");
+                    continue;
+                }
+            }
+            sessions.remove(&chat_id);
+            metrics()
+                .session_evictions
+                .with_label_values(&["memory"])
+                .inc();
+            info!(chat_id, "Evicted session (memory pressure)");
+            shared::memory::trim_heap();
+            if !over_budget(sample()) {
+                break;
+            }
+        }
+        metrics().sessions.set(sessions.len() as i64);
+    }
+
     pub async fn reset_session(&self, chat_id: i64) -> Result<bool> {
         let sessions = self.sessions.lock().await;
         match sessions.get(&chat_id) {
@@ -106,6 +232,57 @@ impl SessionManager {
         }
     }
 
+    /// Undo the chat's last exchange, loading its session if it was evicted. Returns the
+    /// removed message's text, or `None` if there is nothing to undo.
+    pub async fn undo_turn(&self, chat_id: i64) -> Result<Option<String>> {
+        let session = self.get_or_create(chat_id).await?;
+        let mut session = session.lock().await;
+        session.undo()
+    }
+
+    /// Pin `note`, or the chat's last exchange when `None`. Returns `false` if there is no
+    /// exchange to pin.
+    pub async fn pin(&self, chat_id: i64, note: Option<&str>) -> Result<bool> {
+        let session = self.get_or_create(chat_id).await?;
+        let mut session = session.lock().await;
+        session.pin(note)
+    }
+
+    /// Unpin the chat's messages and return how many were pinned.
+    pub async fn unpin(&self, chat_id: i64) -> Result<usize> {
+        let session = self.get_or_create(chat_id).await?;
+        let mut session = session.lock().await;
+        session.unpin()
+    }
+
+    /// Fork the chat's conversation into branch `name` and continue there. Returns how
+    /// many messages the branch starts with.
+    pub async fn fork_branch(
+        &self,
+        chat_id: i64,
+        name: &str,
+        turns: Option<usize>,
+    ) -> Result<usize> {
+        let session = self.get_or_create(chat_id).await?;
+        let mut session = session.lock().await;
+        session.fork_branch(name, turns)?;
+        Ok(session.history().messages().len())
+    }
+
+    /// Continue the chat's conversation on branch `name`. Returns how many messages it has.
+    pub async fn switch_branch(&self, chat_id: i64, name: &str) -> Result<usize> {
+        let session = self.get_or_create(chat_id).await?;
+        let mut session = session.lock().await;
+        session.switch_branch(name)?;
+        Ok(session.history().messages().len())
+    }
+
+    pub async fn describe_branches(&self, chat_id: i64) -> Result<String> {
+        let session = self.get_or_create(chat_id).await?;
+        let session = session.lock().await;
+        session.describe_branches()
+    }
+
     pub async fn compact_session(&self, chat_id: i64) -> Result<bool> {
         let sessions = self.sessions.lock().await;
         match sessions.get(&chat_id) {
@@ -257,6 +434,60 @@ mod tests {
         assert_eq!(manager.session_count().await, 2);
     }
 
+    /// Resident memory readings handed out in order, then `None`.
+    fn readings(values: Vec<u64>) -> impl FnMut() -> Option<u64> {
+        let mut values = values.into_iter();
+        move || values.next()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memory_budget_evicts_least_recently_used() {
+        let manager = SessionManager::new(test_config());
+        for chat_id in [100, 200, 300] {
+            manager.get_or_create(chat_id).await.unwrap();
+            tokio::time::advance(Duration::from_secs(1)).await;
+        }
+        manager.get_or_create(100).await.unwrap();
+
+        // Over budget until two sessions are gone.
+        manager
+            .enforce_memory_budget(500, usize::MAX, readings(vec![900, 900, 700, 400]))
+            .await;
+
+        let sessions = manager.sessions.lock().await;
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions.contains_key(&100));
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget_skips_busy_sessions() {
+        let manager = SessionManager::new(test_config());
+        let busy = manager.get_or_create(100).await.unwrap();
+        manager.get_or_create(200).await.unwrap();
+
+        let _guard = busy.lock().await;
+        manager
+            .enforce_memory_budget(500, usize::MAX, readings(vec![900, 900, 900]))
+            .await;
+
+        let sessions = manager.sessions.lock().await;
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions.contains_key(&100));
+    }
+
+    #[tokio::test]
+    async fn test_memory_under_budget_keeps_sessions() {
+        let manager = SessionManager::new(test_config());
+        manager.get_or_create(100).await.unwrap();
+        manager
+            .enforce_memory_budget(500, usize::MAX, readings(vec![400]))
+            .await;
+        manager
+            .enforce_memory_budget(500, usize::MAX, readings(vec![]))
+            .await;
+        assert_eq!(manager.session_count().await, 1);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn test_ttl_eviction_does_not_evict_fresh_sessions() {
         let manager = SessionManager::new(test_config_with_session(60, 100));
@@ -1,24 +1,27 @@
 use std::sync::Arc;
 
 use anyhow::Result;
+use hi_history::transcript::{self, ExportFormat};
 use shared::config::{ModelConfig, TelegramConfig};
+use shared::storage::Storage;
 use teloxide::Bot;
 use teloxide::RequestError;
 use teloxide::payloads::{GetUpdatesSetters, SendMessageSetters};
 use teloxide::requests::Requester;
 use teloxide::types::{
-    AllowedUpdate, ChatAction, ChatId, MediaKind, MessageKind, ParseMode, UpdateKind,
+    AllowedUpdate, ChatAction, ChatId, InputFile, MediaKind, MessageKind, ParseMode, UpdateKind,
 };
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
-use crate::session_manager::SessionManager;
+use crate::session_manager::{SessionManager, session_label};
 
 // Telegram API: 4096 UTF-8 chars per message
 const MAX_MESSAGE_LENGTH: usize = 4096;
 const MAX_RETRY_ATTEMPTS: u32 = 3;
 const TYPING_INTERVAL_SECS: u64 = 5;
+const CRON_HISTORY_LIMIT: usize = 10;
 
 pub async fn run_polling_loop(
     config: &ModelConfig,
@@ -26,6 +29,7 @@ pub async fn run_polling_loop(
 ) -> Result<()> {
     let bot = Bot::new(&telegram_config.bot_token);
     let session_manager = Arc::new(SessionManager::new(config.clone()));
+    tokio::spawn(Arc::clone(&session_manager).watch_memory());
     let allowed_user_ids = telegram_config.allowed_user_ids.clone();
 
     let timeout = telegram_config.poll_timeout_secs.unwrap_or(30);
@@ -103,7 +107,24 @@ async fn handle_message(
     if let Some(command) = text.strip_prefix('/') {
         return handle_command(chat_id, command.trim(), bot, session_manager).await;
     }
+    reply_to_turn(chat_id, Turn::Send(text), bot, session_manager).await
+}
+
+/// A turn that asks the chat's session for a reply.
+enum Turn<'a> {
+    Send(&'a str),
+    /// `/retry`: replace the reply to the last message.
+    Retry,
+    /// `/edit`: replace the last message and its reply.
+    Edit(&'a str),
+}
 
+async fn reply_to_turn(
+    chat_id: i64,
+    turn: Turn<'_>,
+    bot: &Bot,
+    session_manager: &SessionManager,
+) -> Result<()> {
     // Send initial typing indicator and spawn periodic re-send
     let typing_handle = spawn_typing_indicator(bot.clone(), chat_id);
 
@@ -121,7 +142,11 @@ async fn handle_message(
     let session = session_manager.get_or_create(chat_id).await?;
     let result = {
         let mut session = session.lock().await;
-        session.send_message_streaming(text, stream_tx).await
+        match turn {
+            Turn::Send(text) => session.send_message_streaming(text, stream_tx).await,
+            Turn::Retry => session.retry_streaming(stream_tx).await,
+            Turn::Edit(text) => session.edit_last_streaming(text, stream_tx).await,
+        }
     };
 
     let aggregated = aggregator.await?;
@@ -189,18 +214,93 @@ async fn handle_command(
             Ok(false) => "No active conversation to reset.".to_string(),
             Err(e) => format!("Failed to reset: {e}"),
         },
-        "cron" => handle_cron_command(args, session_manager.config()),
-        "heartbeat" => format_heartbeat(session_manager.config().heartbeat.as_ref()),
+        "retry" => return reply_to_turn(chat_id, Turn::Retry, bot, session_manager).await,
+        "edit" if args.is_empty() => "Usage: /edit <new message>".to_string(),
+        "edit" => return reply_to_turn(chat_id, Turn::Edit(args), bot, session_manager).await,
+        "undo" => match session_manager.undo_turn(chat_id).await {
+            Ok(Some(_)) => "✓ Removed your last message and its reply.".to_string(),
+            Ok(None) => "Nothing to undo.".to_string(),
+            Err(e) => format!("Failed to undo: {e}"),
+        },
+        "pin" => {
+            let note = (!args.is_empty()).then_some(args);
+            match session_manager.pin(chat_id, note).await {
+                Ok(true) if note.is_some() => "✓ Pinned the note.".to_string(),
+                Ok(true) => "✓ Pinned your last message and its reply.".to_string(),
+                Ok(false) => "Nothing to pin.".to_string(),
+                Err(e) => format!("Failed to pin: {e}"),
+            }
+        }
+        "unpin" => match session_manager.unpin(chat_id).await {
+            Ok(0) => "Nothing is pinned.".to_string(),
+            Ok(n) => format!("✓ Unpinned this chat's messages ({n})."),
+            Err(e) => format!("Failed to unpin: {e}"),
+        },
+        "branches" => match session_manager.describe_branches(chat_id).await {
+            Ok(text) => text,
+            Err(e) => format!("Failed to list branches: {e}"),
+        },
+        "fork" => match hi_history::branch::parse_fork_args(args) {
+            Some((name, turns)) => match session_manager.fork_branch(chat_id, name, turns).await {
+                Ok(len) => format!("✓ Forked into branch '{name}' with {len} messages."),
+                Err(e) => format!("Failed to fork: {e}"),
+            },
+            None => "Usage: /fork <name> [n]".to_string(),
+        },
+        "switch" if args.is_empty() => "Usage: /switch <branch>".to_string(),
+        "switch" => match session_manager.switch_branch(chat_id, args).await {
+            Ok(len) => format!("✓ Switched to branch '{args}' ({len} messages)."),
+            Err(e) => format!("Failed to switch: {e}"),
+        },
+        "cron" => match args.split_once(char::is_whitespace) {
+            Some(("run", name)) => handle_cron_run(name.trim(), session_manager.config()).await,
+            _ => handle_cron_command(args, session_manager.config()),
+        },
+        "heartbeat" => handle_heartbeat_command(args, session_manager.config()).await,
+        "search" => {
+            let session = session_label(chat_id);
+            handle_search(args, session_manager.config().recall_scope(&session))
+        }
+        "export" => match shared::storage::storage()
+            .and_then(|storage| export_transcript(storage, chat_id, args))
+        {
+            Ok(Some((file_name, contents))) => {
+                let document = InputFile::memory(contents.into_bytes()).file_name(file_name);
+                match bot.send_document(ChatId(chat_id), document).await {
+                    Ok(_) => return Ok(()),
+                    Err(e) => format!("Failed to send transcript: {e}"),
+                }
+            }
+            Ok(None) => "Nothing to export yet.".to_string(),
+            Err(e) => format!("Failed to export: {e}"),
+        },
         "mcp" => format_mcp_servers(&shared::mcp_store::load()),
         "skills" => format_skills(),
         "help" => concat!(
             "Available commands:\n",
             "/compact - Compact chat history\n",
             "/new - Start a new conversation\n",
+            "/retry - Regenerate the last reply\n",
+            "/undo - Remove your last message and its reply\n",
+            "/edit <message> - Replace your last message and regenerate the reply\n",
+            "/pin [note] - Keep your last exchange, or a note, through compaction\n",
+            "/unpin - Unpin this chat's messages\n",
+            "/fork <name> [n] - Continue on a new branch, keeping your first n messages\n",
+            "/switch <branch> - Continue on another branch (main is the original)\n",
+            "/branches - List conversation branches\n",
             "/cron - List scheduled tasks\n",
             "/cron add <name> <cron> <prompt> - Add a schedule\n",
             "/cron remove <name> - Remove a schedule\n",
+            "/cron run <name> - Run a schedule now\n",
+            "/cron pause <name> - Pause a schedule\n",
+            "/cron resume <name> - Resume a schedule\n",
+            "/cron history [name] - Show recent runs\n",
             "/heartbeat - Show heartbeat status\n",
+            "/heartbeat tasks - List heartbeat tasks\n",
+            "/heartbeat add <title> - Queue a heartbeat task\n",
+            "/heartbeat tick - Run a heartbeat tick now\n",
+            "/search <query> - Search past conversations\n",
+            "/export [md|json|jsonl|html|openai|anthropic] - Send this chat's transcript as a file\n",
             "/mcp - List MCP servers\n",
             "/skills - List loaded skills\n",
             "/help - Show this help message",
@@ -226,13 +326,66 @@ fn handle_cron_command(args: &str, config: &ModelConfig) -> String {
     match sub {
         "add" => handle_cron_add(sub_args, config),
         "remove" => handle_cron_remove(sub_args, config),
-        _ => {
-            "Usage:\n/cron - List schedules\n/cron add <name> <cron> <prompt>\n/cron remove <name>"
-                .to_string()
+        "pause" => handle_cron_set_enabled(sub_args, config, false),
+        "resume" => handle_cron_set_enabled(sub_args, config, true),
+        "history" => handle_cron_history(sub_args),
+        _ => concat!(
+            "Usage:\n/cron - List schedules\n/cron add <name> <cron> <prompt>\n",
+            "/cron remove <name>\n/cron run <name>\n/cron pause <name>\n",
+            "/cron resume <name>\n/cron history [name]"
+        )
+        .to_string(),
+    }
+}
+
+async fn handle_cron_run(name: &str, config: &ModelConfig) -> String {
+    if name.is_empty() {
+        return "Usage: /cron run <name>".to_string();
+    }
+
+    match hi_core::scheduler::Scheduler::run_now(config, name).await {
+        Ok(response) => format!("[schedule:{name}] {response}"),
+        Err(e) => format!("Failed to run schedule '{name}': {e}"),
+    }
+}
+
+fn handle_cron_set_enabled(args: &str, config: &ModelConfig, enabled: bool) -> String {
+    let name = args.split_whitespace().next().unwrap_or("");
+    let verb = if enabled { "resume" } else { "pause" };
+    if name.is_empty() {
+        return format!("Usage: /cron {verb} <name>");
+    }
+
+    let updated = shared::storage::storage().and_then(|storage| {
+        shared::schedule_store::set_enabled(storage, config.schedules.as_deref(), name, enabled)
+    });
+    match updated {
+        Ok(None) => format!("Not found: {name}"),
+        Ok(Some(_)) => {
+            let state = if enabled { "Resumed" } else { "Paused" };
+            format!(
+                "✓ {state} schedule '{name}'.\nNote: the running scheduler picks up changes within a minute."
+            )
         }
+        Err(e) => format!("Failed to save: {e}"),
     }
 }
 
+fn handle_cron_history(args: &str) -> String {
+    let name = args.split_whitespace().next();
+    format_runs(&shared::schedule_store::load_runs(name, CRON_HISTORY_LIMIT))
+}
+
+fn format_runs(runs: &[shared::schedule_store::ScheduleRun]) -> String {
+    if runs.is_empty() {
+        return "No schedule runs recorded.".to_string();
+    }
+
+    let mut lines = vec!["Recent runs:".to_string()];
+    lines.extend(runs.iter().rev().map(|run| format!("• {run}")));
+    lines.join("\n")
+}
+
 fn handle_cron_add(args: &str, config: &ModelConfig) -> String {
     let parts: Vec<&str> = args.splitn(7, char::is_whitespace).collect();
     if parts.len() < 7 {
@@ -250,32 +403,35 @@ fn handle_cron_add(args: &str, config: &ModelConfig) -> String {
         return "Name and prompt must not be empty.".to_string();
     }
 
-    let mut schedules = shared::schedule_store::load(config.schedules.as_deref());
-
-    if schedules.iter().any(|s| s.name == name) {
-        return format!("Schedule '{name}' already exists. Remove it first to replace.");
-    }
+    let added = shared::schedule_store::update(config.schedules.as_deref(), |schedules| {
+        if schedules.iter().any(|s| s.name == name) {
+            return None;
+        }
 
-    let is_first_schedule = schedules.is_empty();
-    let auto_enable = !schedules.iter().any(|s| s.enabled);
+        let is_first_schedule = schedules.is_empty();
+        let auto_enable = !schedules.iter().any(|s| s.enabled);
 
-    schedules.push(shared::config::ScheduleTaskConfig {
-        name: name.to_string(),
-        cron: cron_expr.clone(),
-        model: None,
-        prompt: prompt.to_string(),
-        enabled: auto_enable,
+        schedules.push(shared::config::ScheduleTaskConfig {
+            name: name.to_string(),
+            cron: cron_expr.clone(),
+            model: None,
+            prompt: prompt.to_string(),
+            enabled: auto_enable,
+            ..Default::default()
+        });
+        Some((is_first_schedule, auto_enable))
     });
 
-    match shared::schedule_store::save(&schedules) {
-        Ok(()) => {
+    match added {
+        Ok(None) => format!("Schedule '{name}' already exists. Remove it first to replace."),
+        Ok(Some((is_first_schedule, auto_enable))) => {
             let msg = if auto_enable && is_first_schedule {
                 format!(
-                    "✓ Added schedule '{name}' ({cron_expr}).\nSchedule auto-enabled. Restart to activate."
+                    "✓ Added schedule '{name}' ({cron_expr}).\nSchedule auto-enabled; it takes effect within a minute."
                 )
             } else {
                 format!(
-                    "✓ Added schedule '{name}' ({cron_expr}).\nNote: restart required for schedule to take effect."
+                    "✓ Added schedule '{name}' ({cron_expr}).\nNote: the running scheduler picks up changes within a minute."
                 )
             };
             msg
@@ -290,17 +446,16 @@ fn handle_cron_remove(args: &str, config: &ModelConfig) -> String {
         return "Usage: /cron remove <name>".to_string();
     }
 
-    let mut schedules = shared::schedule_store::load(config.schedules.as_deref());
-    let before = schedules.len();
-    schedules.retain(|s| s.name != name);
-
-    if schedules.len() == before {
-        return format!("Not found: {name}");
-    }
+    let removed = shared::schedule_store::update(config.schedules.as_deref(), |schedules| {
+        let before = schedules.len();
+        schedules.retain(|s| s.name != name);
+        (schedules.len() < before).then_some(())
+    });
 
-    match shared::schedule_store::save(&schedules) {
-        Ok(()) => format!(
-            "✓ Removed schedule '{name}'.\nNote: restart required for change to take effect."
+    match removed {
+        Ok(None) => format!("Not found: {name}"),
+        Ok(Some(())) => format!(
+            "✓ Removed schedule '{name}'.\nNote: the running scheduler picks up changes within a minute."
         ),
         Err(e) => format!("Failed to save: {e}"),
     }
@@ -323,14 +478,76 @@ fn format_schedules(schedules: &[shared::config::ScheduleTaskConfig]) -> String
         } else {
             s.prompt.clone()
         };
+        let trigger = match (&s.at, &s.timezone) {
+            (Some(at), _) => format!("at {at}"),
+            (None, Some(tz)) => format!("{} ({tz})", s.cron),
+            (None, None) => s.cron.clone(),
+        };
         lines.push(format!(
             "• {} | {} | model={} | {}",
-            s.name, s.cron, model, prompt_preview
+            s.name, trigger, model, prompt_preview
         ));
     }
     lines.join("\n")
 }
 
+async fn handle_heartbeat_command(args: &str, config: &ModelConfig) -> String {
+    let (sub, sub_args) = match args.split_once(char::is_whitespace) {
+        Some((s, a)) => (s, a.trim()),
+        None => (args, ""),
+    };
+    let path = match shared::heartbeat_store::default_path() {
+        Ok(path) => path,
+        Err(e) => return format!("Failed to locate HEARTBEAT.md: {e}"),
+    };
+
+    match sub {
+        "" => format_heartbeat(config.heartbeat.as_ref()),
+        "tasks" => format_heartbeat_tasks(&path),
+        "add" => handle_heartbeat_add(&path, sub_args),
+        "tick" => match hi_core::heartbeat::tick_once(config).await {
+            Ok(report) => format!("[heartbeat] {report}"),
+            Err(e) => format!("Heartbeat tick failed: {e}"),
+        },
+        _ => concat!(
+            "Usage:\n/heartbeat - Show settings\n/heartbeat tasks\n",
+            "/heartbeat add <title>\n/heartbeat tick"
+        )
+        .to_string(),
+    }
+}
+
+fn format_heartbeat_tasks(path: &std::path::Path) -> String {
+    let ledger = match shared::heartbeat_store::load(path) {
+        Ok(ledger) => ledger,
+        Err(e) => return format!("Failed to load HEARTBEAT.md: {e}"),
+    };
+    if ledger.tasks.is_empty() {
+        return "No heartbeat tasks.".to_string();
+    }
+    let mut lines = vec!["Heartbeat tasks:".to_string()];
+    lines.extend(
+        ledger
+            .tasks
+            .iter()
+            .map(|task| format!("• {}", task.summary())),
+    );
+    lines.join("\n")
+}
+
+fn handle_heartbeat_add(path: &std::path::Path, title: &str) -> String {
+    if title.is_empty() {
+        return "Usage: /heartbeat add <title>".to_string();
+    }
+    let added = shared::heartbeat_store::update(path, |ledger| {
+        shared::heartbeat_store::add_task(ledger, title).map(|task| task.id.clone())
+    });
+    match added {
+        Ok(id) => format!("✓ Queued heartbeat task '{id}'."),
+        Err(e) => format!("Failed to add task: {e}"),
+    }
+}
+
 fn format_heartbeat(config: Option<&shared::config::HeartbeatConfig>) -> String {
     let Some(hb) = config else {
         return "Heartbeat: not configured.".to_string();
@@ -343,10 +560,49 @@ fn format_heartbeat(config: Option<&shared::config::HeartbeatConfig>) -> String
     };
     let prompt = hb.prompt.as_deref().unwrap_or("(none)");
 
-    format!(
+    let mut text = format!(
         "Heartbeat:\n• enabled: {}\n• interval: {}s\n• model: {}\n• prompt: {}",
         hb.enabled, hb.interval_secs, model, prompt
-    )
+    );
+    if let Some(active_hours) = &hb.active_hours {
+        text.push_str(&format!("\n• active hours: {}", active_hours));
+    }
+    if hb.skip_when_idle {
+        text.push_str("\n• skip when idle: true");
+    }
+    if let Some(max) = hb.max_backoff_secs {
+        text.push_str(&format!("\n• max back-off: {}s", max));
+    }
+    text
+}
+
+/// Search this chat's messages, or every session's if `session` is `None`.
+fn handle_search(query: &str, session: Option<&str>) -> String {
+    if query.is_empty() {
+        return "Usage: /search <query>".to_string();
+    }
+    match shared::history_search::search(query, session, shared::history_search::DEFAULT_LIMIT) {
+        Ok(hits) => shared::history_search::format_hits(query, &hits),
+        Err(e) => format!("Search failed: {e}"),
+    }
+}
+
+/// This chat's transcript as a file name and contents, or `None` if it has no messages.
+fn export_transcript(
+    storage: &dyn Storage,
+    chat_id: i64,
+    args: &str,
+) -> Result<Option<(String, String)>> {
+    let format = match args.split_whitespace().next() {
+        Some(name) => name.parse()?,
+        None => ExportFormat::Markdown,
+    };
+    let messages = transcript::load(storage, Some(&session_label(chat_id)))?;
+    if messages.is_empty() {
+        return Ok(None);
+    }
+    let file_name = format!("transcript-{chat_id}.{}", format.extension());
+    Ok(Some((file_name, transcript::export(&messages, format)?)))
 }
 
 fn format_mcp_servers(config: &shared::config::McpConfig) -> String {
@@ -630,6 +886,7 @@ mod tests {
                 model: None,
                 prompt: "Summarize the day.".to_string(),
                 enabled: true,
+                ..Default::default()
             },
             shared::config::ScheduleTaskConfig {
                 name: "check".to_string(),
@@ -637,6 +894,7 @@ mod tests {
                 model: Some(shared::config::ModelRef::Named("small".to_string())),
                 prompt: "Check status.".to_string(),
                 enabled: false,
+                ..Default::default()
             },
         ];
         let result = format_schedules(&schedules);
@@ -655,12 +913,66 @@ mod tests {
             model: None,
             prompt: "A".repeat(100),
             enabled: true,
+            ..Default::default()
         }];
         let result = format_schedules(&schedules);
         assert!(result.contains("…"));
         assert!(!result.contains(&"A".repeat(100)));
     }
 
+    #[test]
+    fn test_format_schedules_one_shot_and_timezone() {
+        let schedules = vec![
+            shared::config::ScheduleTaskConfig {
+                name: "call-alice".to_string(),
+                prompt: "Remind the user to call Alice.".to_string(),
+                enabled: true,
+                at: Some("2026-03-05T09:00:00+00:00".to_string()),
+                ..Default::default()
+            },
+            shared::config::ScheduleTaskConfig {
+                name: "standup".to_string(),
+                cron: "0 9 * * 1-5".to_string(),
+                prompt: "Standup.".to_string(),
+                enabled: true,
+                timezone: Some("Europe/Berlin".to_string()),
+                ..Default::default()
+            },
+        ];
+        let result = format_schedules(&schedules);
+        assert!(result.contains("call-alice | at 2026-03-05T09:00:00+00:00"));
+        assert!(result.contains("standup | 0 9 * * 1-5 (Europe/Berlin)"));
+    }
+
+    #[test]
+    fn test_heartbeat_add_and_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+
+        assert_eq!(format_heartbeat_tasks(&path), "No heartbeat tasks.");
+        assert_eq!(
+            handle_heartbeat_add(&path, ""),
+            "Usage: /heartbeat add <title>"
+        );
+        assert_eq!(
+            handle_heartbeat_add(&path, "Check disk usage"),
+            "✓ Queued heartbeat task 'check-disk-usage'."
+        );
+        assert_eq!(
+            format_heartbeat_tasks(&path),
+            "Heartbeat tasks:\n• [pending] check-disk-usage: Check disk usage"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_heartbeat_command_usage() {
+        let config = make_model_config(None);
+        let result = handle_heartbeat_command("bogus", &config).await;
+        assert!(result.contains("/heartbeat add <title>"));
+        let result = handle_heartbeat_command("", &config).await;
+        assert_eq!(result, "Heartbeat: not configured.");
+    }
+
     #[test]
     fn test_format_heartbeat_none() {
         let result = format_heartbeat(None);
@@ -674,12 +986,23 @@ mod tests {
             interval_secs: 1200,
             model: Some(shared::config::ModelRef::Named("small".to_string())),
             prompt: Some("heartbeat check".to_string()),
+            lease_secs: 3600,
+            active_hours: Some(shared::config::ActiveHours {
+                start: "08:00".to_string(),
+                end: "22:00".to_string(),
+                timezone: None,
+            }),
+            skip_when_idle: true,
+            max_backoff_secs: None,
         };
         let result = format_heartbeat(Some(&hb));
         assert!(result.contains("enabled: true"));
         assert!(result.contains("interval: 1200s"));
         assert!(result.contains("model: small"));
         assert!(result.contains("prompt: heartbeat check"));
+        assert!(result.contains("active hours: 08:00-22:00 UTC"));
+        assert!(result.contains("skip when idle: true"));
+        assert!(!result.contains("back-off"));
     }
 
     #[test]
@@ -689,6 +1012,10 @@ mod tests {
             interval_secs: 300,
             model: None,
             prompt: None,
+            lease_secs: 3600,
+            active_hours: None,
+            skip_when_idle: false,
+            max_backoff_secs: None,
         };
         let result = format_heartbeat(Some(&hb));
         assert!(result.contains("enabled: false"));
@@ -696,6 +1023,41 @@ mod tests {
         assert!(result.contains("prompt: (none)"));
     }
 
+    #[test]
+    fn test_handle_search_requires_query() {
+        assert_eq!(handle_search("", None), "Usage: /search <query>");
+    }
+
+    #[test]
+    fn test_export_transcript() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage =
+            std::sync::Arc::new(shared::storage::FileStorage::new(dir.path().to_path_buf()));
+        assert!(
+            export_transcript(storage.as_ref(), 5, "")
+                .unwrap()
+                .is_none()
+        );
+
+        let mut history = hi_history::ChatHistory::open(storage.clone()).unwrap();
+        history.set_session("telegram:5");
+        history.push(hi_history::ChatMessage::user("Book a table"));
+        history.set_session("telegram:6");
+        history.push(hi_history::ChatMessage::user("Other chat"));
+        history.save().unwrap();
+
+        let (file_name, contents) = export_transcript(storage.as_ref(), 5, "").unwrap().unwrap();
+        assert_eq!(file_name, "transcript-5.md");
+        assert!(contents.contains("Book a table"));
+        assert!(!contents.contains("Other chat"));
+
+        let (file_name, _) = export_transcript(storage.as_ref(), 5, "html")
+            .unwrap()
+            .unwrap();
+        assert_eq!(file_name, "transcript-5.html");
+        assert!(export_transcript(storage.as_ref(), 5, "pdf").is_err());
+    }
+
     #[test]
     fn test_format_mcp_servers_empty() {
         let config = shared::config::McpConfig {
@@ -755,6 +1117,51 @@ mod tests {
         assert!(result.contains("Usage:"));
     }
 
+    #[test]
+    fn test_handle_cron_pause_resume_missing_name() {
+        let config = make_model_config(None);
+        assert_eq!(
+            handle_cron_command("pause", &config),
+            "Usage: /cron pause <name>"
+        );
+        assert_eq!(
+            handle_cron_command("resume", &config),
+            "Usage: /cron resume <name>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_cron_run_missing_name() {
+        let config = make_model_config(None);
+        assert_eq!(
+            handle_cron_run("", &config).await,
+            "Usage: /cron run <name>"
+        );
+    }
+
+    #[test]
+    fn test_format_runs() {
+        use shared::schedule_store::{RunTrigger, ScheduleRun};
+
+        assert_eq!(format_runs(&[]), "No schedule runs recorded.");
+
+        let started = "2026-03-05T09:00:00+00:00".parse().unwrap();
+        let runs = vec![
+            ScheduleRun::new("daily", RunTrigger::Cron, started, &Ok("first".to_string())),
+            ScheduleRun::new(
+                "daily",
+                RunTrigger::Manual,
+                started,
+                &Err(anyhow::anyhow!("boom")),
+            ),
+        ];
+        let result = format_runs(&runs);
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines[0], "Recent runs:");
+        assert!(lines[1].contains("daily [manual] failed: boom"));
+        assert!(lines[2].contains("daily [cron] ok: first"));
+    }
+
     #[test]
     fn test_handle_cron_remove_missing_name() {
         let config = make_model_config(None);
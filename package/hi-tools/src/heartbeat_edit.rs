@@ -1,11 +1,10 @@
-use std::io::Write;
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use serde::{Deserialize, Serialize};
-use tempfile::NamedTempFile;
+use shared::atomic_file;
 
 #[derive(Debug, thiserror::Error)]
 #[error("{0}")]
@@ -59,32 +58,26 @@ impl HeartbeatEditTool {
         if !args.content.contains("# Heartbeat") {
             anyhow::bail!("Heartbeat content must include a '# Heartbeat...' header");
         }
+        let ledger = shared::heartbeat_store::parse(&args.content);
+        if let Some(cycle) = shared::heartbeat_store::find_dependency_cycle(&ledger) {
+            anyhow::bail!(
+                "Heartbeat tasks have a dependency cycle: {}",
+                cycle.join(" -> ")
+            );
+        }
         match args.mode {
             HeartbeatEditMode::Replace => Ok(()),
         }
     }
 
+    /// Write under the ledger lock, so that the replacement does not land in the middle of
+    /// a heartbeat update and get overwritten by it.
     fn write_content(&self, content: &str) -> Result<()> {
-        if let Some(parent) = self.heartbeat_path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
-        }
-
-        let base_dir = self
-            .heartbeat_path
-            .parent()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."));
-        let mut temp = NamedTempFile::new_in(&base_dir)
-            .context("Failed to create temporary heartbeat file")?;
-        temp.write_all(content.as_bytes())
-            .context("Failed to write temporary heartbeat file")?;
-        temp.flush()
-            .context("Failed to flush temporary heartbeat file")?;
-        temp.persist(&self.heartbeat_path)
-            .map_err(|e| e.error)
-            .with_context(|| format!("Failed to persist {}", self.heartbeat_path.display()))?;
-        Ok(())
+        let path = &self.heartbeat_path;
+        atomic_file::with_lock(path, || {
+            atomic_file::write(path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))
+        })
     }
 }
 
@@ -105,7 +98,7 @@ impl Tool for HeartbeatEditTool {
                 "properties": {
                     "content": {
                         "type": "string",
-                        "description": "Full markdown content for HEARTBEAT.md. Must include '# Heartbeat Tasks' header."
+                        "description": "Full markdown content for HEARTBEAT.md. Must include '# Heartbeat Tasks' header. Task lines may end with attributes such as {priority=5 due=2026-03-05T12:00:00Z every=1d depends_on=other-task}."
                     },
                     "mode": {
                         "type": "string",
@@ -167,6 +160,23 @@ mod tests {
         assert!(written.contains("task-1"));
     }
 
+    #[test]
+    fn test_replace_waits_for_ledger_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        let lock = atomic_file::lock(&path).unwrap();
+        let tool = HeartbeatEditTool::new(path.clone());
+        let writer = std::thread::spawn(move || tool.write_content("# Heartbeat Tasks\n"));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!path.exists());
+        drop(lock);
+        writer.join().unwrap().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Heartbeat Tasks\n"
+        );
+    }
+
     #[tokio::test]
     async fn test_replace_heartbeat_missing_header() {
         let dir = tempfile::tempdir().unwrap();
@@ -194,4 +204,27 @@ mod tests {
             .unwrap_err();
         assert!(err.to_string().contains("must not be empty"));
     }
+
+    #[tokio::test]
+    async fn test_replace_heartbeat_rejects_dependency_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        let tool = HeartbeatEditTool::new(path.clone());
+        let content = "# Heartbeat Tasks\n\n\
+            - [pending] build: Build {depends_on=test}\n\
+            - [pending] test: Test {depends_on=deploy}\n\
+            - [pending] deploy: Deploy {depends_on=build}\n";
+        let err = tool
+            .call(HeartbeatEditArgs {
+                content: content.to_string(),
+                mode: HeartbeatEditMode::Replace,
+            })
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("dependency cycle: build -> test -> deploy -> build")
+        );
+        assert!(!path.exists());
+    }
 }
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+use shared::heartbeat_scratchpad;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct HeartbeatNoteError(String);
+
+#[derive(Deserialize)]
+pub struct HeartbeatNoteArgs {
+    pub task_id: String,
+    pub note: String,
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Lets the heartbeat agent leave working notes for the next tick on the same task.
+pub struct HeartbeatNoteTool {
+    scratchpad_path: PathBuf,
+}
+
+impl HeartbeatNoteTool {
+    pub fn new(scratchpad_path: PathBuf) -> Self {
+        Self { scratchpad_path }
+    }
+}
+
+impl Tool for HeartbeatNoteTool {
+    const NAME: &'static str = "heartbeat_note";
+
+    type Error = HeartbeatNoteError;
+    type Args = HeartbeatNoteArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "heartbeat_note".to_string(),
+            description: format!(
+                "Save working notes for a heartbeat task. The notes are shown to you on the next \
+                tick for that task, so use them to record progress on work that takes several \
+                ticks. Notes are appended unless 'replace' is true, and only the last {} \
+                characters are kept. Notes are dropped once the task is finished.",
+                heartbeat_scratchpad::MAX_NOTES_CHARS
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "task_id": {
+                        "type": "string",
+                        "description": "The task the notes belong to."
+                    },
+                    "note": {
+                        "type": "string",
+                        "description": "Text to save. Empty with replace=true clears the notes."
+                    },
+                    "replace": {
+                        "type": "boolean",
+                        "description": "Replace the existing notes instead of appending (default false)."
+                    }
+                },
+                "required": ["task_id", "note"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.task_id.trim().is_empty() {
+            return Err(HeartbeatNoteError("task_id must not be empty".to_string()));
+        }
+        let notes = heartbeat_scratchpad::write(
+            &self.scratchpad_path,
+            &args.task_id,
+            &args.note,
+            args.replace,
+        )
+        .map_err(|e| HeartbeatNoteError(e.to_string()))?;
+
+        if notes.is_empty() {
+            return Ok(format!("Cleared notes for task '{}'", args.task_id));
+        }
+        Ok(format!(
+            "Saved notes for task '{}' ({} characters)",
+            args.task_id,
+            notes.chars().count()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_note_appends_and_clears() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heartbeat_scratchpad.json");
+        let tool = HeartbeatNoteTool::new(path.clone());
+
+        let result = tool
+            .call(HeartbeatNoteArgs {
+                task_id: "t1".to_string(),
+                note: "checked 2 of 5 hosts".to_string(),
+                replace: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, "Saved notes for task 't1' (20 characters)");
+        assert_eq!(
+            heartbeat_scratchpad::load(&path).unwrap()["t1"],
+            "checked 2 of 5 hosts"
+        );
+
+        let result = tool
+            .call(HeartbeatNoteArgs {
+                task_id: "t1".to_string(),
+                note: String::new(),
+                replace: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, "Cleared notes for task 't1'");
+        assert!(heartbeat_scratchpad::load(&path).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_note_rejects_empty_task_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = HeartbeatNoteTool::new(dir.path().join("heartbeat_scratchpad.json"));
+        let result = tool
+            .call(HeartbeatNoteArgs {
+                task_id: " ".to_string(),
+                note: "x".to_string(),
+                replace: false,
+            })
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use chrono::Utc;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use serde::Deserialize;
@@ -21,11 +22,16 @@ pub struct HeartbeatWriteArgs {
 
 pub struct HeartbeatWriteTool {
     heartbeat_md_path: PathBuf,
+    /// Lease given to a task moved to in-progress, from `HeartbeatConfig::lease_secs`.
+    lease: chrono::Duration,
 }
 
 impl HeartbeatWriteTool {
-    pub fn new(heartbeat_md_path: PathBuf) -> Self {
-        Self { heartbeat_md_path }
+    pub fn new(heartbeat_md_path: PathBuf, lease: chrono::Duration) -> Self {
+        Self {
+            heartbeat_md_path,
+            lease,
+        }
     }
 }
 
@@ -40,7 +46,9 @@ impl Tool for HeartbeatWriteTool {
         ToolDefinition {
             name: "heartbeat_write".to_string(),
             description: "Update the status of a heartbeat task in HEARTBEAT.md. \
-                Valid transitions: pending -> in-progress, in-progress -> done, in-progress -> failed."
+                Valid transitions: pending -> in-progress, in-progress -> done, in-progress -> failed, \
+                in-progress -> pending, failed -> pending. A failed task is retried automatically \
+                until it runs out of attempts."
                 .to_string(),
             parameters: serde_json::json!({
                 "type": "object",
@@ -68,43 +76,76 @@ impl Tool for HeartbeatWriteTool {
         let new_status =
             TaskStatus::from_str(&args.new_status).map_err(|e| HeartbeatWriteError(e))?;
 
-        let mut ledger = heartbeat_store::load(&self.heartbeat_md_path)
-            .map_err(|e| HeartbeatWriteError(e.to_string()))?;
-
-        let task_idx = ledger
-            .tasks
-            .iter()
-            .position(|t| t.id == args.task_id)
-            .ok_or_else(|| {
-                HeartbeatWriteError(format!("Task '{}' not found in HEARTBEAT.md", args.task_id))
-            })?;
-
-        if !heartbeat_store::validate_transition(&ledger.tasks[task_idx].status, &new_status) {
-            return Err(HeartbeatWriteError(format!(
-                "Invalid transition: {} -> {} for task '{}'",
-                ledger.tasks[task_idx].status, new_status, args.task_id
-            )));
-        }
+        let (retrying, next_run) = heartbeat_store::update(&self.heartbeat_md_path, |ledger| {
+            let task_idx = ledger
+                .tasks
+                .iter()
+                .position(|t| t.id == args.task_id)
+                .ok_or_else(|| {
+                    HeartbeatWriteError(format!(
+                        "Task '{}' not found in HEARTBEAT.md",
+                        args.task_id
+                    ))
+                })?;
+
+            if !heartbeat_store::validate_transition(&ledger.tasks[task_idx].status, &new_status) {
+                return Err(HeartbeatWriteError(format!(
+                    "Invalid transition: {} -> {} for task '{}'",
+                    ledger.tasks[task_idx].status, new_status, args.task_id
+                ))
+                .into());
+            }
 
-        ledger.tasks[task_idx].status = new_status.clone();
+            let now = Utc::now();
+            let task = &mut ledger.tasks[task_idx];
+            match new_status {
+                TaskStatus::InProgress => task.start(now, self.lease),
+                TaskStatus::Done => task.complete(now),
+                TaskStatus::Failed => task.fail(now, args.note.as_deref()),
+                TaskStatus::Pending => {
+                    if task.outcome.is_some() {
+                        return Err(HeartbeatWriteError(format!(
+                            "Task '{}' has used all {} attempts",
+                            args.task_id,
+                            task.max_attempts()
+                        ))
+                        .into());
+                    }
+                    task.release();
+                }
+            }
+            let retrying = task.is_retryable();
+            let next_run = task
+                .not_before
+                .filter(|_| task.every.is_some() && task.status == TaskStatus::Pending);
 
-        if let Some(note) = args.note {
-            if !note.is_empty() {
-                match &mut ledger.tasks[task_idx].description {
+            if let Some(note) = args.note.as_deref().filter(|note| !note.is_empty()) {
+                match &mut task.description {
                     Some(desc) => {
                         desc.push('\n');
-                        desc.push_str(&note);
-                    }
-                    None => {
-                        ledger.tasks[task_idx].description = Some(note);
+                        desc.push_str(note);
                     }
+                    None => task.description = Some(note.to_string()),
                 }
             }
+            Ok((retrying, next_run))
+        })
+        .map_err(|e| HeartbeatWriteError(e.to_string()))?;
+
+        if let Some(next_run) = next_run {
+            return Ok(format!(
+                "Task '{}' updated to {}; it recurs next at {}",
+                args.task_id,
+                new_status,
+                next_run.to_rfc3339()
+            ));
+        }
+        if retrying {
+            return Ok(format!(
+                "Task '{}' updated to {}; it will be retried on a later heartbeat",
+                args.task_id, new_status
+            ));
         }
-
-        heartbeat_store::save(&self.heartbeat_md_path, &ledger)
-            .map_err(|e| HeartbeatWriteError(e.to_string()))?;
-
         Ok(format!("Task '{}' updated to {}", args.task_id, new_status))
     }
 }
@@ -133,10 +174,11 @@ mod tests {
                 status: TaskStatus::Pending,
                 title: "Test task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::minutes(5));
         let args = HeartbeatWriteArgs {
             task_id: "t1".to_string(),
             new_status: "in-progress".to_string(),
@@ -147,6 +189,9 @@ mod tests {
 
         let ledger = heartbeat_store::load(&path).unwrap();
         assert_eq!(ledger.tasks[0].status, TaskStatus::InProgress);
+        let lease_until = ledger.tasks[0].lease_until.unwrap();
+        assert!(lease_until <= Utc::now() + chrono::Duration::minutes(5));
+        assert!(lease_until > Utc::now() + chrono::Duration::minutes(4));
     }
 
     #[tokio::test]
@@ -160,10 +205,11 @@ mod tests {
                 status: TaskStatus::InProgress,
                 title: "Test task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
         let args = HeartbeatWriteArgs {
             task_id: "t1".to_string(),
             new_status: "done".to_string(),
@@ -187,10 +233,11 @@ mod tests {
                 status: TaskStatus::InProgress,
                 title: "Test task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
         let args = HeartbeatWriteArgs {
             task_id: "t1".to_string(),
             new_status: "failed".to_string(),
@@ -207,6 +254,58 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_failed_task_retried_until_final() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(
+            &path,
+            "- [in-progress] t1: Test task {attempts=1 max_attempts=2}\n",
+        )
+        .unwrap();
+
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
+        let fail = || HeartbeatWriteArgs {
+            task_id: "t1".to_string(),
+            new_status: "failed".to_string(),
+            note: Some("Network timeout".to_string()),
+        };
+        let result = tool.call(fail()).await.unwrap();
+        assert!(result.contains("retried"));
+
+        let retry = HeartbeatWriteArgs {
+            task_id: "t1".to_string(),
+            new_status: "pending".to_string(),
+            note: None,
+        };
+        tool.call(retry).await.unwrap();
+        let start = HeartbeatWriteArgs {
+            task_id: "t1".to_string(),
+            new_status: "in-progress".to_string(),
+            note: None,
+        };
+        tool.call(start).await.unwrap();
+        let ledger = heartbeat_store::load(&path).unwrap();
+        assert_eq!(ledger.tasks[0].attempts, 2);
+        assert!(ledger.tasks[0].lease_until.is_some());
+
+        let result = tool.call(fail()).await.unwrap();
+        assert!(!result.contains("retried"));
+        let ledger = heartbeat_store::load(&path).unwrap();
+        assert_eq!(
+            ledger.tasks[0].outcome.as_deref(),
+            Some("failed after 2 attempts: Network timeout")
+        );
+
+        let retry = HeartbeatWriteArgs {
+            task_id: "t1".to_string(),
+            new_status: "pending".to_string(),
+            note: None,
+        };
+        let err = tool.call(retry).await.unwrap_err();
+        assert!(err.to_string().contains("used all 2 attempts"));
+    }
+
     #[tokio::test]
     async fn test_invalid_transition_rejected() {
         let dir = tempfile::tempdir().unwrap();
@@ -218,10 +317,11 @@ mod tests {
                 status: TaskStatus::Pending,
                 title: "Test task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
         let args = HeartbeatWriteArgs {
             task_id: "t1".to_string(),
             new_status: "done".to_string(),
@@ -242,10 +342,11 @@ mod tests {
                 status: TaskStatus::Pending,
                 title: "Test task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
         let args = HeartbeatWriteArgs {
             task_id: "nonexistent".to_string(),
             new_status: "in-progress".to_string(),
@@ -266,10 +367,11 @@ mod tests {
                 status: TaskStatus::Pending,
                 title: "Test task".to_string(),
                 description: Some("Original description".to_string()),
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
         let args = HeartbeatWriteArgs {
             task_id: "t1".to_string(),
             new_status: "in-progress".to_string(),
@@ -294,10 +396,11 @@ mod tests {
                 status: TaskStatus::Pending,
                 title: "Test task".to_string(),
                 description: None,
+                ..Default::default()
             }],
         );
 
-        let tool = HeartbeatWriteTool::new(path.clone());
+        let tool = HeartbeatWriteTool::new(path.clone(), chrono::Duration::hours(1));
         let args = HeartbeatWriteArgs {
             task_id: "t1".to_string(),
             new_status: "bogus".to_string(),
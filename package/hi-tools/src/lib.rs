@@ -1,24 +1,37 @@
 pub mod bash;
 pub mod heartbeat_edit;
+pub mod heartbeat_note;
 pub mod heartbeat_write;
 pub mod list_files;
 pub mod memory;
+pub mod pin;
 pub mod read_file;
 pub mod read_skills;
+pub mod recall_archive;
+pub mod remind;
 pub mod schedule_add;
+pub mod schedule_control;
 pub mod schedule_remove;
 mod schedule_storage;
 pub mod schedule_view;
+pub mod search_history;
 pub mod write_file;
 
 pub use bash::BashTool;
 pub use heartbeat_edit::HeartbeatEditTool;
+pub use heartbeat_note::HeartbeatNoteTool;
 pub use heartbeat_write::HeartbeatWriteTool;
 pub use list_files::ListFilesTool;
 pub use memory::MemoryTool;
+pub use pin::{PinArgs, PinTool};
 pub use read_file::ReadFileTool;
 pub use read_skills::{ReadSkillsTool, SkillSummary};
+pub use recall_archive::RecallArchiveTool;
+pub use remind::RemindTool;
 pub use schedule_add::ScheduleAddTool;
+pub use schedule_control::{ScheduleControlTool, ScheduleRunFn};
 pub use schedule_remove::ScheduleRemoveTool;
+pub use schedule_storage::ScheduleStorage;
 pub use schedule_view::ScheduleViewTool;
+pub use search_history::SearchHistoryTool;
 pub use write_file::WriteFileTool;
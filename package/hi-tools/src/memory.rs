@@ -1,6 +1,7 @@
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use serde::Deserialize;
+use shared::atomic_file;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, thiserror::Error)]
@@ -205,6 +206,8 @@ fn read_memory(path: &Path, section: Option<&str>) -> Result<String, String> {
 }
 
 fn write_memory(path: &Path, section_path: &str, content: &str) -> Result<String, String> {
+    // Hold the lock across the read so concurrent writers to other sections are not lost.
+    let _lock = atomic_file::lock(path).map_err(|e| format!("Failed to lock memory: {e:#}"))?;
     let text = if path.exists() {
         std::fs::read_to_string(path).map_err(|e| format!("Failed to read memory: {e}"))?
     } else {
@@ -214,11 +217,7 @@ fn write_memory(path: &Path, section_path: &str, content: &str) -> Result<String
     let parts: Vec<&str> = section_path.split('/').collect();
     let new_text = rebuild_with_section(&text, &parts, content);
 
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create memory directory: {e}"))?;
-    }
-    std::fs::write(path, &new_text).map_err(|e| format!("Failed to write memory: {e}"))?;
+    atomic_file::write(path, &new_text).map_err(|e| format!("Failed to write memory: {e:#}"))?;
 
     Ok(format!("Written to section '{}'", section_path))
 }
@@ -417,6 +416,25 @@ mod tests {
         assert!(content.contains("Hello world"));
     }
 
+    #[test]
+    fn test_concurrent_writes_keep_every_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.md");
+        std::thread::scope(|scope| {
+            for n in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    write_memory(path, &format!("Section {n}"), &format!("Note {n}")).unwrap();
+                });
+            }
+        });
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        for n in 0..8 {
+            assert!(content.contains(&format!("# Section {n}\nNote {n}")));
+        }
+    }
+
     #[test]
     fn test_write_memory_nested_section() {
         let dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,74 @@
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+/// Longest note the agent can pin.
+const MAX_NOTE_CHARS: usize = 1000;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct PinError(String);
+
+#[derive(Deserialize)]
+pub struct PinArgs {
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Asks the chat session to pin the current exchange, or a note, so that compaction keeps it.
+/// The session applies the pin from the recorded call once the reply is complete.
+pub struct PinTool;
+
+impl Tool for PinTool {
+    const NAME: &'static str = "pin";
+
+    type Error = PinError;
+    type Args = PinArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "pin".to_string(),
+            description: "Pin the current exchange so that history compaction keeps it word for word instead of summarizing it. Use it for standing instructions and key decisions. With a note, pin only the note, e.g. a decision stated in one sentence."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "note": {
+                        "type": "string",
+                        "description": format!("Short statement to pin instead of the exchange (at most {MAX_NOTE_CHARS} characters)")
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args.note.as_deref().map(str::trim) {
+            Some("") => Err(PinError("The note must not be empty".to_string())),
+            Some(note) if note.chars().count() > MAX_NOTE_CHARS => Err(PinError(format!(
+                "The note must be at most {MAX_NOTE_CHARS} characters"
+            ))),
+            Some(_) => Ok("Pinned the note; compaction will keep it.".to_string()),
+            None => Ok("Pinned this exchange; compaction will keep it.".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pin_validates_note() {
+        let pin = |note: Option<&str>| {
+            PinTool.call(PinArgs {
+                note: note.map(str::to_string),
+            })
+        };
+        assert!(pin(None).await.unwrap().contains("exchange"));
+        assert!(pin(Some("Use tabs")).await.unwrap().contains("note"));
+        assert!(pin(Some("  ")).await.is_err());
+        assert!(pin(Some(&"x".repeat(MAX_NOTE_CHARS + 1))).await.is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use shared::storage::Storage;
+
+/// Most messages returned by one call.
+const MAX_MESSAGES: u64 = 50;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct RecallArchiveError(String);
+
+#[derive(Deserialize)]
+pub struct RecallArchiveArgs {
+    pub session: String,
+    pub from: u64,
+    pub to: u64,
+}
+
+pub struct RecallArchiveTool {
+    storage: Arc<dyn Storage>,
+    /// Session whose archive the tool reads, or `None` for every session's.
+    session: Option<String>,
+}
+
+impl RecallArchiveTool {
+    pub fn new(storage: Arc<dyn Storage>, session: Option<&str>) -> Self {
+        Self {
+            storage,
+            session: session.map(str::to_string),
+        }
+    }
+}
+
+impl Tool for RecallArchiveTool {
+    const NAME: &'static str = "recall_archive";
+
+    type Error = RecallArchiveError;
+    type Args = RecallArchiveArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "recall_archive".to_string(),
+            description: format!(
+                "Read the original messages that compaction replaced with a summary. A conversation summary ends with the session and message range it covers, e.g. [Archived: messages 0-19 of session \"tui\"]. Returns at most {MAX_MESSAGES} messages per call."
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "session": {
+                        "type": "string",
+                        "description": "Session named in the summary, e.g. \"tui\" or \"telegram:42\""
+                    },
+                    "from": {
+                        "type": "integer",
+                        "description": "First message number to return"
+                    },
+                    "to": {
+                        "type": "integer",
+                        "description": "Last message number to return (inclusive)"
+                    }
+                },
+                "required": ["session", "from", "to"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if let Some(session) = self.session.as_deref()
+            && args.session != session
+        {
+            return Err(RecallArchiveError(format!(
+                "Only this conversation's archive (session '{session}') can be read"
+            )));
+        }
+        if args.to < args.from {
+            return Err(RecallArchiveError(format!(
+                "'to' ({}) must not be less than 'from' ({})",
+                args.to, args.from
+            )));
+        }
+        let to = args.to.min(args.from.saturating_add(MAX_MESSAGES - 1));
+        let messages = self
+            .storage
+            .load_history_archive(&args.session, args.from..to.saturating_add(1))
+            .map_err(|e| RecallArchiveError(format!("{e:#}")))?;
+        if messages.is_empty() {
+            return Ok(format!(
+                "Session '{}' has no archived messages in {}-{}.",
+                args.session, args.from, args.to
+            ));
+        }
+
+        let last = args.from + messages.len() as u64 - 1;
+        let mut out = format!(
+            "Archived messages {}-{last} of session '{}':",
+            args.from, args.session
+        );
+        for (number, message) in (args.from..).zip(&messages) {
+            let role = message["role"].as_str().unwrap_or("unknown");
+            let content = message["content"].as_str().unwrap_or_default();
+            out.push_str(&format!("\n[{number}] {role}: {content}"));
+        }
+        if last == to && to < args.to {
+            out.push_str(&format!(
+                "\n(Stopped at {last}; call again from {} for the rest.)",
+                last + 1
+            ));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use shared::storage::FileStorage;
+
+    #[tokio::test]
+    async fn test_recall_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        let messages: Vec<_> = (0..60)
+            .map(|i| json!({ "role": "user", "content": format!("message {i}") }))
+            .collect();
+        storage.archive_history("telegram:7", &messages).unwrap();
+        let tool = RecallArchiveTool::new(storage.clone(), Some("telegram:7"));
+
+        let args = |from, to| RecallArchiveArgs {
+            session: "telegram:7".to_string(),
+            from,
+            to,
+        };
+        let out = tool.call(args(1, 2)).await.unwrap();
+        assert_eq!(
+            out,
+            "Archived messages 1-2 of session 'telegram:7':\n[1] user: message 1\n[2] user: message 2"
+        );
+
+        let out = tool.call(args(0, 59)).await.unwrap();
+        assert!(out.contains("[49] user: message 49"));
+        assert!(!out.contains("[50]"));
+        assert!(out.ends_with("call again from 50 for the rest.)"));
+
+        let out = tool.call(args(58, 70)).await.unwrap();
+        assert!(out.starts_with("Archived messages 58-59"));
+        assert!(!out.contains("Stopped"));
+
+        let out = tool.call(args(100, 120)).await.unwrap();
+        assert_eq!(
+            out,
+            "Session 'telegram:7' has no archived messages in 100-120."
+        );
+        assert!(tool.call(args(5, 4)).await.is_err());
+
+        storage.archive_history("tui", &messages).unwrap();
+        let tui = || RecallArchiveArgs {
+            session: "tui".to_string(),
+            from: 0,
+            to: 1,
+        };
+        let err = tool.call(tui()).await.unwrap_err().to_string();
+        assert!(err.contains("Only this conversation's archive"));
+        let every_session = RecallArchiveTool::new(storage, None);
+        let out = every_session.call(tui()).await.unwrap();
+        assert!(out.contains("[1] user"));
+    }
+}
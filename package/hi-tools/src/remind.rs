@@ -0,0 +1,211 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+use shared::config::{ModelRef, ScheduleTaskConfig};
+use shared::schedule_time::{parse_timezone, parse_when};
+
+use crate::schedule_add::ScheduleMutationOutput;
+use crate::schedule_storage::ScheduleStorage;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct RemindError(String);
+
+impl From<anyhow::Error> for RemindError {
+    fn from(value: anyhow::Error) -> Self {
+        Self(value.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RemindArgs {
+    pub when: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub model: Option<ModelRef>,
+}
+
+pub struct RemindTool {
+    storage: ScheduleStorage,
+}
+
+impl RemindTool {
+    pub fn new(storage: ScheduleStorage) -> Self {
+        Self { storage }
+    }
+
+    fn build_reminder(args: &RemindArgs, now: DateTime<Utc>) -> Result<ScheduleTaskConfig> {
+        if args.prompt.trim().is_empty() {
+            return Err(anyhow!("Prompt must not be empty"));
+        }
+        let tz = parse_timezone(args.timezone.as_deref())?;
+        let fire_at = parse_when(&args.when, now, tz)?;
+
+        let name = match args.name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => format!("reminder-{}", fire_at.format("%Y%m%d-%H%M%S")),
+        };
+
+        Ok(ScheduleTaskConfig {
+            name,
+            model: args.model.clone(),
+            prompt: args.prompt.trim().to_string(),
+            enabled: true,
+            at: Some(fire_at.to_rfc3339()),
+            timezone: args.timezone.clone().filter(|tz| !tz.trim().is_empty()),
+            ..Default::default()
+        })
+    }
+}
+
+impl Tool for RemindTool {
+    const NAME: &'static str = "remind";
+
+    type Error = RemindError;
+    type Args = RemindArgs;
+    type Output = ScheduleMutationOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "remind".to_string(),
+            description: "Schedule a one-shot reminder. The prompt runs once at the given time and the reminder is then archived."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "when": {
+                        "type": "string",
+                        "description": "When to fire: RFC 3339, 'YYYY-MM-DD HH:MM', 'in 2 hours', 'in 1h 30m', 'tomorrow at 9', 'friday 5pm', 'next monday'"
+                    },
+                    "prompt": {
+                        "type": "string",
+                        "description": "Prompt that will be sent when the reminder fires, e.g. 'Remind the user to call Alice'"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Optional unique name. Defaults to reminder-<timestamp>"
+                    },
+                    "timezone": {
+                        "type": "string",
+                        "description": "IANA timezone used for local times such as 'tomorrow at 9' (default UTC)"
+                    },
+                    "model": {
+                        "description": "Optional model override. Either a string name or inline {\"provider\":...,\"model\":...,\"context_window\":...} object",
+                        "oneOf": [
+                            {"type": "string"},
+                            {"type": "object"}
+                        ]
+                    }
+                },
+                "required": ["when", "prompt"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let reminder = Self::build_reminder(&args, Utc::now())?;
+
+        self.storage.update(|schedules| {
+            if schedules
+                .iter()
+                .any(|s| s.name.eq_ignore_ascii_case(&reminder.name))
+            {
+                return Err(RemindError(format!(
+                    "Schedule '{}' already exists; choose another name",
+                    reminder.name
+                )));
+            }
+            schedules.push(reminder.clone());
+            Ok(())
+        })?;
+
+        Ok(ScheduleMutationOutput {
+            status: "ok",
+            message: format!(
+                "Reminder '{}' set for {}.",
+                reminder.name,
+                reminder.at.as_deref().unwrap_or_default()
+            ),
+            schedule: Some(reminder),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule_storage::file_storage;
+
+    fn args(when: &str) -> RemindArgs {
+        RemindArgs {
+            when: when.to_string(),
+            prompt: "Remind the user to call Alice".to_string(),
+            name: None,
+            timezone: None,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn test_build_reminder_resolves_time_and_name() {
+        let now = "2026-03-04T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let reminder = RemindTool::build_reminder(&args("tomorrow at 9"), now).unwrap();
+        assert_eq!(reminder.name, "reminder-20260305-090000");
+        assert_eq!(reminder.at.as_deref(), Some("2026-03-05T09:00:00+00:00"));
+        assert!(reminder.enabled);
+        assert!(reminder.cron.is_empty());
+        assert!(reminder.is_one_shot());
+    }
+
+    #[test]
+    fn test_build_reminder_with_timezone() {
+        let now = "2026-03-04T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut a = args("tomorrow at 9");
+        a.timezone = Some("Asia/Taipei".to_string());
+        let reminder = RemindTool::build_reminder(&a, now).unwrap();
+        assert_eq!(reminder.at.as_deref(), Some("2026-03-05T01:00:00+00:00"));
+        assert_eq!(reminder.timezone.as_deref(), Some("Asia/Taipei"));
+    }
+
+    #[test]
+    fn test_build_reminder_rejects_bad_input() {
+        let now = "2026-03-04T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(RemindTool::build_reminder(&args("someday"), now).is_err());
+        let mut a = args("in 1 hour");
+        a.timezone = Some("Atlantis/Capital".to_string());
+        assert!(RemindTool::build_reminder(&a, now).is_err());
+        let mut a = args("in 1 hour");
+        a.prompt = "  ".to_string();
+        assert!(RemindTool::build_reminder(&a, now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remind_persists_and_rejects_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        let tool = RemindTool::new(file_storage(&path));
+
+        let mut a = args("in 2 hours");
+        a.name = Some("call-alice".to_string());
+        let result = tool.call(a).await.unwrap();
+        assert_eq!(result.status, "ok");
+
+        let stored: Vec<ScheduleTaskConfig> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].name, "call-alice");
+        assert!(stored[0].is_one_shot());
+
+        let mut a = args("in 3 hours");
+        a.name = Some("Call-Alice".to_string());
+        let err = tool.call(a).await.unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+    }
+}
@@ -1,4 +1,3 @@
-use std::path::PathBuf;
 use std::str::FromStr;
 
 use anyhow::{Result, anyhow};
@@ -6,7 +5,7 @@ use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use serde::{Deserialize, Serialize};
 
-use shared::config::{ModelRef, ScheduleTaskConfig};
+use shared::config::{ModelRef, OutputContract, ScheduleTaskConfig};
 
 use crate::schedule_storage::ScheduleStorage;
 
@@ -27,6 +26,10 @@ pub struct ScheduleAddArgs {
     pub prompt: String,
     #[serde(default)]
     pub model: Option<ModelRef>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub output: Option<OutputContract>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,10 +44,8 @@ pub struct ScheduleAddTool {
 }
 
 impl ScheduleAddTool {
-    pub fn new(path: PathBuf) -> Self {
-        Self {
-            storage: ScheduleStorage::new(path),
-        }
+    pub fn new(storage: ScheduleStorage) -> Self {
+        Self { storage }
     }
 
     fn validate_args(args: &ScheduleAddArgs) -> Result<()> {
@@ -55,6 +56,10 @@ impl ScheduleAddTool {
             return Err(anyhow!("Prompt must not be empty"));
         }
         validate_cron_expression(&args.cron)?;
+        shared::schedule_time::parse_timezone(args.timezone.as_deref())?;
+        if let Some(output) = &args.output {
+            shared::schedule_output::validate_contract(output)?;
+        }
         Ok(())
     }
 }
@@ -93,6 +98,27 @@ impl Tool for ScheduleAddTool {
                             {"type": "string"},
                             {"type": "object"}
                         ]
+                    },
+                    "timezone": {
+                        "type": "string",
+                        "description": "Optional IANA timezone the cron expression is evaluated in (default UTC)"
+                    },
+                    "output": {
+                        "type": "object",
+                        "description": "Optional output contract deciding when results are delivered. {\"sentinel\": \"NO_UPDATE\"} stays silent when the response is exactly the sentinel; \"schema\" (JSON Schema) requires structured JSON output; \"deliver_if\" {\"pointer\": \"/alert\", \"equals\": true} delivers only matching JSON results",
+                        "properties": {
+                            "sentinel": {"type": "string"},
+                            "schema": {"type": "object"},
+                            "deliver_if": {
+                                "type": "object",
+                                "properties": {
+                                    "pointer": {"type": "string"},
+                                    "equals": {},
+                                    "not_equals": {}
+                                },
+                                "required": ["pointer"]
+                            }
+                        }
                     }
                 },
                 "required": ["name", "cron", "prompt"]
@@ -103,38 +129,42 @@ impl Tool for ScheduleAddTool {
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         Self::validate_args(&args)?;
 
-        let mut schedules = self.storage.load()?;
-        if schedules
-            .iter()
-            .any(|s| s.name.eq_ignore_ascii_case(&args.name))
-        {
-            return Err(ScheduleAddError(format!(
-                "Schedule '{}' already exists; remove it first to replace",
-                args.name
-            )));
-        }
+        let new_schedule = self.storage.update(|schedules| {
+            if schedules
+                .iter()
+                .any(|s| s.name.eq_ignore_ascii_case(&args.name))
+            {
+                return Err(ScheduleAddError(format!(
+                    "Schedule '{}' already exists; remove it first to replace",
+                    args.name
+                )));
+            }
 
-        let auto_enable = !schedules.iter().any(|s| s.enabled);
+            let auto_enable = !schedules.iter().any(|s| s.enabled);
 
-        let new_schedule = ScheduleTaskConfig {
-            name: args.name.trim().to_string(),
-            cron: args.cron.trim().to_string(),
-            model: args.model.clone(),
-            prompt: args.prompt.trim().to_string(),
-            enabled: auto_enable,
-        };
-
-        schedules.push(new_schedule.clone());
-        self.storage.save(&schedules)?;
+            let new_schedule = ScheduleTaskConfig {
+                name: args.name.trim().to_string(),
+                cron: args.cron.trim().to_string(),
+                model: args.model.clone(),
+                prompt: args.prompt.trim().to_string(),
+                enabled: auto_enable,
+                timezone: args.timezone.clone().filter(|tz| !tz.trim().is_empty()),
+                output: args.output.clone(),
+                ..Default::default()
+            };
+            schedules.push(new_schedule.clone());
+            Ok(new_schedule)
+        })?;
+        let auto_enable = new_schedule.enabled;
 
         let message = if auto_enable {
             format!(
-                "Added schedule '{}' (cron: {}). Schedule auto-enabled. Restart to activate.",
+                "Added schedule '{}' (cron: {}). Schedule auto-enabled; the running scheduler picks it up within a minute.",
                 new_schedule.name, new_schedule.cron
             )
         } else {
             format!(
-                "Added schedule '{}' (cron: {}). Restart required for changes to take effect.",
+                "Added schedule '{}' (cron: {}). The running scheduler picks up changes within a minute.",
                 new_schedule.name, new_schedule.cron
             )
         };
@@ -156,10 +186,12 @@ fn validate_cron_expression(expr: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::schedule_storage::file_storage;
+    use std::path::PathBuf;
 
     fn make_tool(temp_dir: &tempfile::TempDir) -> ScheduleAddTool {
         let path = temp_dir.path().join("schedules.json");
-        ScheduleAddTool::new(path)
+        ScheduleAddTool::new(file_storage(&path))
     }
 
     fn read_schedules(path: &PathBuf) -> Vec<ScheduleTaskConfig> {
@@ -176,6 +208,8 @@ mod tests {
             cron: "0 0 * * *".to_string(),
             prompt: "Generate daily summary".to_string(),
             model: None,
+            timezone: None,
+            output: None,
         };
 
         let result = tool.call(args).await.unwrap();
@@ -194,6 +228,8 @@ mod tests {
             cron: "invalid cron".to_string(),
             prompt: "noop".to_string(),
             model: None,
+            timezone: None,
+            output: None,
         };
 
         let err = tool.call(args).await.unwrap_err();
@@ -212,15 +248,80 @@ mod tests {
         )
         .unwrap();
 
-        let tool = ScheduleAddTool::new(path);
+        let tool = ScheduleAddTool::new(file_storage(&path));
         let args = ScheduleAddArgs {
             name: "daily".to_string(),
             cron: "0 12 * * *".to_string(),
             prompt: "ping".to_string(),
             model: None,
+            timezone: None,
+            output: None,
         };
 
         let err = tool.call(args).await.unwrap_err();
         assert!(err.to_string().contains("already exists"));
     }
+
+    #[tokio::test]
+    async fn test_add_schedule_with_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = make_tool(&dir);
+        let args = ScheduleAddArgs {
+            name: "standup".to_string(),
+            cron: "0 9 * * 1-5".to_string(),
+            prompt: "Standup reminder".to_string(),
+            model: None,
+            timezone: Some("Europe/Berlin".to_string()),
+            output: None,
+        };
+
+        tool.call(args).await.unwrap();
+        let schedules = read_schedules(&dir.path().join("schedules.json"));
+        assert_eq!(schedules[0].timezone.as_deref(), Some("Europe/Berlin"));
+    }
+
+    #[tokio::test]
+    async fn test_add_schedule_invalid_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = make_tool(&dir);
+        let args = ScheduleAddArgs {
+            name: "standup".to_string(),
+            cron: "0 9 * * 1-5".to_string(),
+            prompt: "Standup reminder".to_string(),
+            model: None,
+            timezone: Some("Europe/Gotham".to_string()),
+            output: None,
+        };
+
+        let err = tool.call(args).await.unwrap_err();
+        assert!(err.to_string().contains("Unknown timezone"));
+    }
+
+    #[tokio::test]
+    async fn test_add_schedule_with_output_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = make_tool(&dir);
+        let args: ScheduleAddArgs = serde_json::from_value(serde_json::json!({
+            "name": "disk-watch",
+            "cron": "*/15 * * * *",
+            "prompt": "Check disk usage",
+            "output": {"sentinel": "NO_UPDATE"}
+        }))
+        .unwrap();
+
+        tool.call(args).await.unwrap();
+        let schedules = read_schedules(&dir.path().join("schedules.json"));
+        let output = schedules[0].output.as_ref().unwrap();
+        assert_eq!(output.sentinel.as_deref(), Some("NO_UPDATE"));
+
+        let args: ScheduleAddArgs = serde_json::from_value(serde_json::json!({
+            "name": "bad-contract",
+            "cron": "*/15 * * * *",
+            "prompt": "Check disk usage",
+            "output": {"schema": "object"}
+        }))
+        .unwrap();
+        let err = tool.call(args).await.unwrap_err();
+        assert!(err.to_string().contains("Output schema"));
+    }
 }
@@ -0,0 +1,246 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+
+use crate::schedule_add::ScheduleMutationOutput;
+use crate::schedule_storage::ScheduleStorage;
+
+/// Runs a schedule by name and returns its response. Provided by the scheduler owner.
+pub type ScheduleRunFn = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = anyhow::Result<String>> + Send>> + Send + Sync,
+>;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ScheduleControlError(String);
+
+impl From<anyhow::Error> for ScheduleControlError {
+    fn from(value: anyhow::Error) -> Self {
+        Self(value.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleAction {
+    Run,
+    Pause,
+    Resume,
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleControlArgs {
+    pub action: ScheduleAction,
+    pub name: String,
+}
+
+pub struct ScheduleControlTool {
+    storage: ScheduleStorage,
+    runner: Option<ScheduleRunFn>,
+}
+
+impl ScheduleControlTool {
+    pub fn new(storage: ScheduleStorage, runner: Option<ScheduleRunFn>) -> Self {
+        Self { storage, runner }
+    }
+
+    fn set_enabled(
+        &self,
+        name: &str,
+        enabled: bool,
+    ) -> Result<ScheduleMutationOutput, ScheduleControlError> {
+        let schedule = self
+            .storage
+            .set_enabled(name, enabled)?
+            .ok_or_else(|| ScheduleControlError(format!("Schedule '{}' not found", name)))?;
+        let verb = if enabled { "Resumed" } else { "Paused" };
+        Ok(ScheduleMutationOutput {
+            status: "ok",
+            message: format!("{} schedule '{}'.", verb, name),
+            schedule: Some(schedule),
+        })
+    }
+}
+
+impl Tool for ScheduleControlTool {
+    const NAME: &'static str = "cron_control";
+
+    type Error = ScheduleControlError;
+    type Args = ScheduleControlArgs;
+    type Output = ScheduleMutationOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: "cron_control".to_string(),
+            description:
+                "Run a schedule immediately (to test it), or pause/resume it by toggling enabled"
+                    .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["run", "pause", "resume"],
+                        "description": "run: execute now and return the response; pause/resume: disable or enable the schedule"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Schedule name"
+                    }
+                },
+                "required": ["action", "name"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let name = args.name.trim();
+        if name.is_empty() {
+            return Err(ScheduleControlError(
+                "Schedule name must not be empty".to_string(),
+            ));
+        }
+
+        match args.action {
+            ScheduleAction::Pause => self.set_enabled(name, false),
+            ScheduleAction::Resume => self.set_enabled(name, true),
+            ScheduleAction::Run => {
+                let runner = self.runner.as_ref().ok_or_else(|| {
+                    ScheduleControlError("Manual runs are not available here".to_string())
+                })?;
+                let schedules = self.storage.load()?;
+                let schedule = shared::schedule_store::find(&schedules, name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ScheduleControlError(format!("Schedule '{}' not found", name))
+                    })?;
+                let response = runner(schedule.name.clone()).await?;
+                Ok(ScheduleMutationOutput {
+                    status: "ok",
+                    message: response,
+                    schedule: Some(schedule),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule_storage::file_storage;
+    use shared::config::ScheduleTaskConfig;
+    use std::path::PathBuf;
+
+    fn write_schedules(path: &PathBuf) {
+        std::fs::write(
+            path,
+            r#"[
+                {"name": "daily", "cron": "0 0 * * *", "prompt": "summarize", "enabled": true}
+            ]"#,
+        )
+        .unwrap();
+    }
+
+    fn read_schedules(path: &PathBuf) -> Vec<ScheduleTaskConfig> {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        write_schedules(&path);
+        let tool = ScheduleControlTool::new(file_storage(&path), None);
+
+        let args = ScheduleControlArgs {
+            action: ScheduleAction::Pause,
+            name: "Daily".to_string(),
+        };
+        tool.call(args).await.unwrap();
+        assert!(!read_schedules(&path)[0].enabled);
+
+        let args = ScheduleControlArgs {
+            action: ScheduleAction::Resume,
+            name: "daily".to_string(),
+        };
+        let out = tool.call(args).await.unwrap();
+        assert!(out.message.contains("Resumed"));
+        assert!(read_schedules(&path)[0].enabled);
+    }
+
+    #[tokio::test]
+    async fn test_pause_config_only_schedule() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        let config: Vec<ScheduleTaskConfig> = serde_json::from_str(
+            r#"[{"name": "daily", "cron": "0 0 * * *", "prompt": "summarize"}]"#,
+        )
+        .unwrap();
+        let storage = Arc::new(shared::storage::FileStorage::new(dir.path().to_path_buf()));
+        let tool = ScheduleControlTool::new(ScheduleStorage::new(storage, Some(&config)), None);
+
+        let args = ScheduleControlArgs {
+            action: ScheduleAction::Pause,
+            name: "daily".to_string(),
+        };
+        tool.call(args).await.unwrap();
+        let saved = read_schedules(&path);
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].name, "daily");
+        assert!(!saved[0].enabled);
+    }
+
+    #[tokio::test]
+    async fn test_run_uses_runner() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        write_schedules(&path);
+        let runner: ScheduleRunFn =
+            Arc::new(|name| Box::pin(async move { Ok(format!("ran {name}")) }));
+        let tool = ScheduleControlTool::new(file_storage(&path), Some(runner));
+
+        let args = ScheduleControlArgs {
+            action: ScheduleAction::Run,
+            name: "DAILY".to_string(),
+        };
+        let out = tool.call(args).await.unwrap();
+        assert_eq!(out.message, "ran daily");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_schedule_and_missing_runner() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        write_schedules(&path);
+        let tool = ScheduleControlTool::new(file_storage(&path), None);
+
+        let args = ScheduleControlArgs {
+            action: ScheduleAction::Pause,
+            name: "nope".to_string(),
+        };
+        assert!(
+            tool.call(args)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("not found")
+        );
+
+        let args = ScheduleControlArgs {
+            action: ScheduleAction::Run,
+            name: "daily".to_string(),
+        };
+        assert!(
+            tool.call(args)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("not available")
+        );
+    }
+}
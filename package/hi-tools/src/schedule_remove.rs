@@ -1,5 +1,3 @@
-use std::path::PathBuf;
-
 use anyhow::{Result, anyhow};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
@@ -37,16 +35,16 @@ pub struct ScheduleRemoveTool {
 }
 
 impl ScheduleRemoveTool {
-    pub fn new(path: PathBuf) -> Self {
-        Self {
-            storage: ScheduleStorage::new(path),
-        }
+    pub fn new(storage: ScheduleStorage) -> Self {
+        Self { storage }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::schedule_storage::file_storage;
+    use std::path::PathBuf;
 
     fn write_schedules(path: &PathBuf, schedules: &[ScheduleTaskConfig]) {
         let json = serde_json::to_string_pretty(schedules).unwrap();
@@ -65,10 +63,11 @@ mod tests {
                 model: None,
                 prompt: "ping".into(),
                 enabled: true,
+                ..Default::default()
             }],
         );
 
-        let tool = ScheduleRemoveTool::new(path.clone());
+        let tool = ScheduleRemoveTool::new(file_storage(&path));
         let output = tool
             .call(ScheduleRemoveArgs {
                 name: "daily".into(),
@@ -93,10 +92,11 @@ mod tests {
                 model: None,
                 prompt: "ping".into(),
                 enabled: false,
+                ..Default::default()
             }],
         );
 
-        let tool = ScheduleRemoveTool::new(path);
+        let tool = ScheduleRemoveTool::new(file_storage(&path));
         let err = tool
             .call(ScheduleRemoveArgs {
                 name: "missing".into(),
@@ -109,7 +109,7 @@ mod tests {
     #[tokio::test]
     async fn test_remove_schedule_empty_name() {
         let dir = tempfile::tempdir().unwrap();
-        let tool = ScheduleRemoveTool::new(dir.path().join("schedules.json"));
+        let tool = ScheduleRemoveTool::new(file_storage(&dir.path().join("schedules.json")));
         let err = tool
             .call(ScheduleRemoveArgs { name: "   ".into() })
             .await
@@ -149,21 +149,18 @@ impl Tool for ScheduleRemoveTool {
             ));
         }
 
-        let mut schedules = self.storage.load()?;
-        let (removed_index, removed_schedule) = schedules
-            .iter()
-            .enumerate()
-            .find(|(_, s)| s.name.eq_ignore_ascii_case(args.name.trim()))
-            .map(|(idx, s)| (idx, s.clone()))
-            .ok_or_else(|| anyhow!(format!("Schedule '{}' not found", args.name)))?;
-
-        schedules.remove(removed_index);
-        self.storage.save(&schedules)?;
+        let removed_schedule = self.storage.update(|schedules| {
+            let removed_index = schedules
+                .iter()
+                .position(|s| s.name.eq_ignore_ascii_case(args.name.trim()))
+                .ok_or_else(|| anyhow!(format!("Schedule '{}' not found", args.name)))?;
+            Ok::<_, ScheduleRemoveError>(schedules.remove(removed_index))
+        })?;
 
         Ok(ScheduleRemoveOutput {
             status: "ok",
             message: format!(
-                "Removed schedule '{}'. Restart required for changes to take effect.",
+                "Removed schedule '{}'. The running scheduler picks up changes within a minute.",
                 removed_schedule.name
             ),
             removed: Some(removed_schedule),
@@ -1,65 +1,84 @@
-use std::fs;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{Result, anyhow};
 use shared::config::ScheduleTaskConfig;
-use tempfile::NamedTempFile;
+use shared::storage::Storage;
 
+/// The schedules as the agent's tools see them: the stored schedules, or the config ones
+/// until schedules are first saved, as for the scheduler and `hi cron`.
+#[derive(Clone)]
 pub struct ScheduleStorage {
-    path: PathBuf,
+    storage: Arc<dyn Storage>,
+    config_schedules: Option<Vec<ScheduleTaskConfig>>,
 }
 
 impl ScheduleStorage {
-    pub fn new(path: PathBuf) -> Self {
-        Self { path }
+    pub fn new(storage: Arc<dyn Storage>, config_schedules: Option<&[ScheduleTaskConfig]>) -> Self {
+        Self {
+            storage,
+            config_schedules: config_schedules.map(<[_]>::to_vec),
+        }
     }
 
     pub fn load(&self) -> Result<Vec<ScheduleTaskConfig>> {
-        if !self.path.exists() {
-            return Ok(Vec::new());
-        }
-
-        let content = fs::read_to_string(&self.path)
-            .with_context(|| format!("Failed to read {}", self.path.display()))?;
-        let schedules: Vec<ScheduleTaskConfig> = serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
-
+        let schedules = match self.storage.load_schedules()? {
+            Some(schedules) => schedules,
+            None => self.config_schedules.clone().unwrap_or_default(),
+        };
         Ok(filter_valid_schedules(schedules))
     }
 
-    pub fn save(&self, schedules: &[ScheduleTaskConfig]) -> Result<()> {
-        if let Some(parent) = self.path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
-        }
-
-        let json =
-            serde_json::to_string_pretty(schedules).context("Failed to serialize schedules")?;
+    /// Load the schedules, let `f` change them and save the result as one atomic update,
+    /// so concurrent writers are not lost. The first update saves the config schedules
+    /// along with the change. Nothing is written if `f` fails.
+    pub fn update<T, E>(
+        &self,
+        f: impl FnOnce(&mut Vec<ScheduleTaskConfig>) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<anyhow::Error>,
+    {
+        let mut f = Some(f);
+        let mut result = None;
+        let fallback = self.config_schedules.as_deref();
+        self.storage.update_schedules(fallback, &mut |schedules| {
+            let Some(f) = f.take() else {
+                return false;
+            };
+            let value = f(schedules);
+            let changed = value.is_ok();
+            result = Some(value);
+            changed
+        })?;
+        result.unwrap_or_else(|| Err(anyhow!("Schedule update did not run").into()))
+    }
 
-        let mut temp = NamedTempFile::new_in(
-            self.path
-                .parent()
-                .map(PathBuf::from)
-                .as_deref()
-                .unwrap_or(Path::new(".")),
+    /// Pause or resume the named schedule as [`shared::schedule_store::set_enabled`] does.
+    /// Returns the updated schedule, or `None` when no schedule matches.
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<Option<ScheduleTaskConfig>> {
+        shared::schedule_store::set_enabled(
+            self.storage.as_ref(),
+            self.config_schedules.as_deref(),
+            name,
+            enabled,
         )
-        .context("Failed to create temporary schedules file")?;
-        temp.write_all(json.as_bytes())
-            .context("Failed to write temporary schedules file")?;
-        temp.flush()
-            .context("Failed to flush temporary schedules file")?;
-        temp.persist(&self.path)
-            .map_err(|e| e.error)
-            .with_context(|| format!("Failed to persist {}", self.path.display()))?;
-
-        Ok(())
     }
 }
 
 fn filter_valid_schedules(mut schedules: Vec<ScheduleTaskConfig>) -> Vec<ScheduleTaskConfig> {
-    schedules.retain(|s| {
-        !s.name.trim().is_empty() && !s.cron.trim().is_empty() && !s.prompt.trim().is_empty()
-    });
+    schedules.retain(ScheduleTaskConfig::has_required_fields);
     schedules
 }
+
+/// File storage in the directory holding `schedules_path`, without config schedules, for
+/// tool tests.
+#[cfg(test)]
+pub(crate) fn file_storage(schedules_path: &std::path::Path) -> ScheduleStorage {
+    let dir = schedules_path
+        .parent()
+        .expect("schedules path has a parent");
+    ScheduleStorage::new(
+        Arc::new(shared::storage::FileStorage::new(dir.to_path_buf())),
+        None,
+    )
+}
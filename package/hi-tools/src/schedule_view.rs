@@ -1,7 +1,8 @@
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use serde::Deserialize;
-use std::path::PathBuf;
+
+use crate::schedule_storage::ScheduleStorage;
 
 #[derive(Debug, thiserror::Error)]
 #[error("{0}")]
@@ -14,12 +15,12 @@ pub struct ScheduleViewArgs {
 }
 
 pub struct ScheduleViewTool {
-    schedules_path: PathBuf,
+    storage: ScheduleStorage,
 }
 
 impl ScheduleViewTool {
-    pub fn new(schedules_path: PathBuf) -> Self {
-        Self { schedules_path }
+    pub fn new(storage: ScheduleStorage) -> Self {
+        Self { storage }
     }
 }
 
@@ -50,7 +51,7 @@ impl Tool for ScheduleViewTool {
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let schedules =
-            load_schedules(&self.schedules_path).map_err(|e| ScheduleViewError(e.to_string()))?;
+            load_schedules(&self.storage).map_err(|e| ScheduleViewError(e.to_string()))?;
 
         if schedules.is_empty() {
             return Ok("No schedules configured.".to_string());
@@ -94,10 +95,17 @@ impl Tool for ScheduleViewTool {
 #[derive(Deserialize)]
 struct ScheduleEntry {
     name: String,
+    #[serde(default)]
     cron: String,
     #[serde(default)]
     model: Option<serde_json::Value>,
     prompt: String,
+    #[serde(default)]
+    at: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    output: Option<serde_json::Value>,
 }
 
 fn format_schedule(s: &ScheduleEntry) -> String {
@@ -106,37 +114,42 @@ fn format_schedule(s: &ScheduleEntry) -> String {
         Some(v) => v.to_string(),
         None => "(default)".to_string(),
     };
-    format!(
-        "- {}\n  cron: {}\n  model: {}\n  prompt: {}",
-        s.name, s.cron, model_str, s.prompt
-    )
-}
-
-fn load_schedules(path: &std::path::Path) -> anyhow::Result<Vec<ScheduleEntry>> {
-    if !path.exists() {
-        return Ok(Vec::new());
+    let trigger = match (&s.at, &s.timezone) {
+        (Some(at), _) => format!("at: {} (one-shot)", at),
+        (None, Some(tz)) => format!("cron: {} ({})", s.cron, tz),
+        (None, None) => format!("cron: {}", s.cron),
+    };
+    let mut out = format!(
+        "- {}\n  {}\n  model: {}\n  prompt: {}",
+        s.name, trigger, model_str, s.prompt
+    );
+    if let Some(output) = &s.output {
+        out.push_str(&format!("\n  output: {}", output));
     }
+    out
+}
 
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
-
-    let schedules: Vec<ScheduleEntry> = serde_json::from_str(&content)
-        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+fn load_schedules(storage: &ScheduleStorage) -> anyhow::Result<Vec<ScheduleEntry>> {
+    let schedules: Vec<ScheduleEntry> =
+        serde_json::from_value(serde_json::to_value(storage.load()?)?)?;
 
     Ok(schedules
         .into_iter()
-        .filter(|s| !s.name.is_empty() && !s.cron.is_empty() && !s.prompt.is_empty())
+        .filter(|s| {
+            !s.name.is_empty() && !s.prompt.is_empty() && (!s.cron.is_empty() || s.at.is_some())
+        })
         .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::schedule_storage::file_storage;
 
     #[test]
     fn test_load_schedules_nonexistent() {
-        let path = std::path::PathBuf::from("/tmp/nonexistent_schedules_test.json");
-        let result = load_schedules(&path).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let result = load_schedules(&file_storage(&dir.path().join("schedules.json"))).unwrap();
         assert!(result.is_empty());
     }
 
@@ -153,7 +166,7 @@ mod tests {
         )
         .unwrap();
 
-        let result = load_schedules(&path).unwrap();
+        let result = load_schedules(&file_storage(&dir.path().join("schedules.json"))).unwrap();
         assert_eq!(result.len(), 2);
         assert_eq!(result[0].name, "daily");
         assert_eq!(result[1].name, "hourly");
@@ -177,7 +190,7 @@ mod tests {
         )
         .unwrap();
 
-        let result = load_schedules(&path).unwrap();
+        let result = load_schedules(&file_storage(&dir.path().join("schedules.json"))).unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].name, "valid");
     }
@@ -189,6 +202,9 @@ mod tests {
             cron: "0 0 * * *".to_string(),
             model: Some(serde_json::Value::String("small".to_string())),
             prompt: "summarize".to_string(),
+            at: None,
+            timezone: None,
+            output: None,
         };
         let out = format_schedule(&s);
         assert!(out.contains("daily"));
@@ -204,16 +220,34 @@ mod tests {
             cron: "*/5 * * * *".to_string(),
             model: None,
             prompt: "check status".to_string(),
+            at: None,
+            timezone: None,
+            output: None,
         };
         let out = format_schedule(&s);
         assert!(out.contains("(default)"));
     }
 
+    #[test]
+    fn test_format_schedule_with_output_contract() {
+        let s = ScheduleEntry {
+            name: "disk-watch".to_string(),
+            cron: "*/15 * * * *".to_string(),
+            model: None,
+            prompt: "check disk".to_string(),
+            at: None,
+            timezone: None,
+            output: Some(serde_json::json!({"sentinel": "NO_UPDATE"})),
+        };
+        let out = format_schedule(&s);
+        assert!(out.contains("output: {\"sentinel\":\"NO_UPDATE\"}"));
+    }
+
     #[tokio::test]
     async fn test_view_schedules_empty() {
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join("schedules.json");
-        let tool = ScheduleViewTool::new(path);
+        let tool = ScheduleViewTool::new(file_storage(&path));
         let args = ScheduleViewArgs { name: None };
         let result = tool.call(args).await.unwrap();
         assert_eq!(result, "No schedules configured.");
@@ -232,7 +266,7 @@ mod tests {
         )
         .unwrap();
 
-        let tool = ScheduleViewTool::new(path);
+        let tool = ScheduleViewTool::new(file_storage(&path));
         let args = ScheduleViewArgs { name: None };
         let result = tool.call(args).await.unwrap();
         assert!(result.contains("2 schedule(s) configured"));
@@ -253,7 +287,7 @@ mod tests {
         )
         .unwrap();
 
-        let tool = ScheduleViewTool::new(path);
+        let tool = ScheduleViewTool::new(file_storage(&path));
         let args = ScheduleViewArgs {
             name: Some("daily".to_string()),
         };
@@ -272,7 +306,7 @@ mod tests {
         )
         .unwrap();
 
-        let tool = ScheduleViewTool::new(path);
+        let tool = ScheduleViewTool::new(file_storage(&path));
         let args = ScheduleViewArgs {
             name: Some("weekly".to_string()),
         };
@@ -291,11 +325,30 @@ mod tests {
         )
         .unwrap();
 
-        let tool = ScheduleViewTool::new(path);
+        let tool = ScheduleViewTool::new(file_storage(&path));
         let args = ScheduleViewArgs {
             name: Some("daily-summary".to_string()),
         };
         let result = tool.call(args).await.unwrap();
         assert!(result.contains("Daily-Summary"));
     }
+
+    #[test]
+    fn test_load_and_format_one_shot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedules.json");
+        std::fs::write(
+            &path,
+            r#"[
+                {"name": "call-alice", "at": "2026-03-05T09:00:00+00:00", "prompt": "call Alice"},
+                {"name": "standup", "cron": "0 9 * * 1-5", "timezone": "Europe/Berlin", "prompt": "standup"}
+            ]"#,
+        )
+        .unwrap();
+
+        let result = load_schedules(&file_storage(&dir.path().join("schedules.json"))).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(format_schedule(&result[0]).contains("at: 2026-03-05T09:00:00+00:00 (one-shot)"));
+        assert!(format_schedule(&result[1]).contains("cron: 0 9 * * 1-5 (Europe/Berlin)"));
+    }
 }
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::Deserialize;
+use shared::history_search::{DEFAULT_LIMIT, format_hits};
+use shared::storage::Storage;
+
+/// Most hits the agent can ask for in one call.
+const MAX_LIMIT: usize = 50;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct SearchHistoryError(String);
+
+#[derive(Deserialize)]
+pub struct SearchHistoryArgs {
+    pub query: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+pub struct SearchHistoryTool {
+    storage: Arc<dyn Storage>,
+    /// Session whose messages the tool searches, or `None` for every session.
+    session: Option<String>,
+}
+
+impl SearchHistoryTool {
+    pub fn new(storage: Arc<dyn Storage>, session: Option<&str>) -> Self {
+        Self {
+            storage,
+            session: session.map(str::to_string),
+        }
+    }
+}
+
+impl Tool for SearchHistoryTool {
+    const NAME: &'static str = "search_history";
+
+    type Error = SearchHistoryError;
+    type Args = SearchHistoryArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let scope = match self.session {
+            Some(_) => "this conversation",
+            None => "past conversations from every session",
+        };
+        ToolDefinition {
+            name: "search_history".to_string(),
+            description: format!(
+                "Search {scope}, including messages removed from the current context by compaction. Returns the newest matching messages with their session, time and a snippet."
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Words to search for. Messages must contain all of them; case is ignored."
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Maximum number of results (default {DEFAULT_LIMIT}, at most {MAX_LIMIT}).")
+                    }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let query = args.query.trim();
+        if query.is_empty() {
+            return Err(SearchHistoryError("query must not be empty".to_string()));
+        }
+        let limit = args.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let hits = self
+            .storage
+            .search_history(query, self.session.as_deref(), limit)
+            .map_err(|e| SearchHistoryError(format!("{e:#}")))?;
+        Ok(format_hits(query, &hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use shared::history_search::SearchEntry;
+    use shared::storage::FileStorage;
+
+    #[tokio::test]
+    async fn test_search_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        storage
+            .index_messages(&[
+                SearchEntry::new("tui", Utc::now(), "user", "Book the venue for Friday"),
+                SearchEntry::new("tui", Utc::now(), "assistant", "The venue is booked."),
+            ])
+            .unwrap();
+        let tool = SearchHistoryTool::new(storage.clone(), None);
+
+        let args = SearchHistoryArgs {
+            query: "venue".to_string(),
+            limit: Some(1),
+        };
+        let out = tool.call(args).await.unwrap();
+        assert!(out.contains("tui assistant: The venue is booked."));
+        assert!(!out.contains("Friday"));
+
+        let args = SearchHistoryArgs {
+            query: "lunch".to_string(),
+            limit: None,
+        };
+        assert_eq!(tool.call(args).await.unwrap(), "No messages match 'lunch'.");
+
+        let args = SearchHistoryArgs {
+            query: " ".to_string(),
+            limit: None,
+        };
+        assert!(tool.call(args).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_history_is_scoped_to_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        storage
+            .index_messages(&[
+                SearchEntry::new("tui", Utc::now(), "user", "My password hint is blue"),
+                SearchEntry::new("telegram:7", Utc::now(), "user", "What is the hint?"),
+            ])
+            .unwrap();
+        let tool = SearchHistoryTool::new(storage, Some("telegram:7"));
+
+        let args = SearchHistoryArgs {
+            query: "hint".to_string(),
+            limit: None,
+        };
+        let out = tool.call(args).await.unwrap();
+        assert!(out.contains("telegram:7 user: What is the hint?"));
+        assert!(!out.contains("blue"));
+    }
+}
@@ -14,35 +14,84 @@ use ratatui::widgets::{Block, Paragraph, Wrap};
 use tokio::sync::mpsc;
 
 enum SessionCmd {
-    Send(String),
+    Turn(Turn),
+    Undo,
+    /// `/pin [note]`: pin the note, or the last exchange.
+    Pin(Option<String>),
+    Unpin,
+    Fork(String, Option<usize>),
+    SwitchBranch(String),
+    Branches,
     Reset,
     SwitchModel(String),
 }
 
+/// A command that asks the session for a reply.
+enum Turn {
+    Send(String),
+    /// `/retry`: replace the reply to the last message.
+    Retry,
+    /// `/edit`: replace the last message and its reply.
+    Edit(String),
+}
+
 enum SessionReply {
     StreamChunk(String),
-    StreamDone,
+    /// Details of the saved user message and reply, as `ChatMessage::details` formats them.
+    StreamDone {
+        user: Option<String>,
+        reply: Option<String>,
+    },
     Error(String),
+    /// A `/retry` or `/edit` failed: the messages, put back as they were, and the error.
+    ReplaceFailed(Vec<DisplayMessage>, String),
+    /// Whether `/undo` removed anything.
+    Undone(bool),
+    /// The session moved to another branch: its messages and a notice.
+    BranchChanged(Vec<DisplayMessage>, String),
+    Notice(String),
     ResetDone,
     ModelSwitched(String),
 }
 
+/// A displayed message: role, content and its details line, if it has one.
+type DisplayMessage = (String, String, Option<String>);
+
 struct App {
-    messages: Vec<(String, String)>,
+    messages: Vec<DisplayMessage>,
+    /// Show each message's time, model, token usage and latency (`/details`).
+    show_details: bool,
     input: String,
     waiting: bool,
     should_quit: bool,
     streaming_buffer: String,
 }
 
+/// The session's history as shown: every message with its details line.
+fn display_messages(session: &hi_core::session::ChatSession) -> Vec<DisplayMessage> {
+    session
+        .history()
+        .messages()
+        .iter()
+        .map(|m| (m.role.clone(), m.content.clone(), m.details()))
+        .collect()
+}
+
+/// Drop the last user message and everything shown after it, returning its text.
+fn rewind_last_turn(messages: &mut Vec<DisplayMessage>) -> Option<String> {
+    let start = messages.iter().rposition(|m| m.0 == "user")?;
+    messages.drain(start..).next().map(|m| m.1)
+}
+
 fn render(frame: &mut Frame, app: &App) {
     let chunks = Layout::vertical([Constraint::Min(1), Constraint::Length(3)]).split(frame.area());
 
     let mut lines: Vec<Line> = Vec::new();
-    for (role, content) in &app.messages {
+    for (role, content, details) in &app.messages {
         let prefix = match role.as_str() {
             "user" => "[You] ",
             "assistant" => "[AI] ",
+            "tool" => "[Tool] ",
             _ => "[System] ",
         };
         let style = match role.as_str() {
@@ -56,6 +105,12 @@ fn render(frame: &mut Frame, app: &App) {
                 Span::raw(line),
             ]));
         }
+        if let Some(details) = details.as_deref().filter(|_| app.show_details) {
+            lines.push(Line::from(Span::styled(
+                format!("  {details}"),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
         lines.push(Line::from(""));
     }
 
@@ -109,6 +164,7 @@ pub async fn run_tui(config_path: Option<PathBuf>) -> Result<()> {
         Some(ref p) => shared::config::ModelConfig::load_from_path(p)?,
         None => shared::config::ModelConfig::load()?,
     };
+    shared::storage::init(config.storage.as_ref())?;
 
     enable_raw_mode()?;
     let mut stdout = stdout();
@@ -129,7 +185,7 @@ async fn run(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     config: shared::config::ModelConfig,
 ) -> Result<()> {
-    let mut session = hi_core::session::ChatSession::new(config).await?;
+    let mut session = hi_core::session::ChatSession::new(config, "tui").await?;
 
     let skill_list: Vec<(String, String)> = session
         .skills()
@@ -137,12 +193,7 @@ async fn run(
         .map(|s| (s.name.clone(), s.description.clone()))
         .collect();
 
-    let initial_messages: Vec<(String, String)> = session
-        .history()
-        .messages()
-        .iter()
-        .map(|m| (m.role.clone(), m.content.clone()))
-        .collect();
+    let initial_messages = display_messages(&session);
 
     let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel::<SessionCmd>();
     let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<SessionReply>();
@@ -150,7 +201,7 @@ async fn run(
     tokio::spawn(async move {
         while let Some(cmd) = cmd_rx.recv().await {
             match cmd {
-                SessionCmd::Send(text) => {
+                SessionCmd::Turn(turn) => {
                     let (stream_tx, mut stream_rx) =
                         mpsc::channel::<String>(hi_core::provider::STREAM_CHANNEL_CAPACITY);
                     let forwarder_tx = reply_tx.clone();
@@ -159,15 +210,93 @@ async fn run(
                             let _ = forwarder_tx.send(SessionReply::StreamChunk(chunk));
                         }
                     });
-                    match session.send_message_streaming(&text, stream_tx).await {
+                    let replaces = !matches!(turn, Turn::Send(_));
+                    let result = match turn {
+                        Turn::Send(text) => session.send_message_streaming(&text, stream_tx).await,
+                        Turn::Retry => session.retry_streaming(stream_tx).await,
+                        Turn::Edit(text) => session.edit_last_streaming(&text, stream_tx).await,
+                    };
+                    match result {
                         Ok(_) => {
-                            let _ = reply_tx.send(SessionReply::StreamDone);
+                            let messages = session.history().messages();
+                            let details = |role: &str| {
+                                messages
+                                    .iter()
+                                    .rev()
+                                    .find(|m| m.role == role)
+                                    .and_then(|m| m.details())
+                            };
+                            let _ = reply_tx.send(SessionReply::StreamDone {
+                                user: details("user"),
+                                reply: details("assistant"),
+                            });
+                        }
+                        Err(e) if replaces => {
+                            let _ = reply_tx.send(SessionReply::ReplaceFailed(
+                                display_messages(&session),
+                                format!("{e}"),
+                            ));
                         }
                         Err(e) => {
                             let _ = reply_tx.send(SessionReply::Error(format!("{e}")));
                         }
                     }
                 }
+                SessionCmd::Undo => {
+                    let reply = match session.undo() {
+                        Ok(undone) => SessionReply::Undone(undone.is_some()),
+                        Err(e) => SessionReply::Error(format!("{e}")),
+                    };
+                    let _ = reply_tx.send(reply);
+                }
+                SessionCmd::Pin(note) => {
+                    let reply = match session.pin(note.as_deref()) {
+                        Ok(true) if note.is_some() => {
+                            SessionReply::Notice("Pinned the note.".to_string())
+                        }
+                        Ok(true) => SessionReply::Notice(
+                            "Pinned the last message and its reply.".to_string(),
+                        ),
+                        Ok(false) => SessionReply::Notice("Nothing to pin.".to_string()),
+                        Err(e) => SessionReply::Error(format!("{e}")),
+                    };
+                    let _ = reply_tx.send(reply);
+                }
+                SessionCmd::Unpin => {
+                    let reply = match session.unpin() {
+                        Ok(0) => SessionReply::Notice("Nothing is pinned.".to_string()),
+                        Ok(n) => SessionReply::Notice(format!("Unpinned messages ({n}).")),
+                        Err(e) => SessionReply::Error(format!("{e}")),
+                    };
+                    let _ = reply_tx.send(reply);
+                }
+                SessionCmd::Fork(name, turns) => {
+                    let reply = match session.fork_branch(&name, turns) {
+                        Ok(()) => SessionReply::BranchChanged(
+                            display_messages(&session),
+                            format!("Forked into branch '{name}'."),
+                        ),
+                        Err(e) => SessionReply::Error(format!("{e}")),
+                    };
+                    let _ = reply_tx.send(reply);
+                }
+                SessionCmd::SwitchBranch(name) => {
+                    let reply = match session.switch_branch(&name) {
+                        Ok(()) => SessionReply::BranchChanged(
+                            display_messages(&session),
+                            format!("Switched to branch '{name}'."),
+                        ),
+                        Err(e) => SessionReply::Error(format!("{e}")),
+                    };
+                    let _ = reply_tx.send(reply);
+                }
+                SessionCmd::Branches => {
+                    let reply = match session.describe_branches() {
+                        Ok(text) => SessionReply::Notice(text),
+                        Err(e) => SessionReply::Error(format!("{e}")),
+                    };
+                    let _ = reply_tx.send(reply);
+                }
                 SessionCmd::Reset => {
                     let _ = session.reset();
                     let _ = reply_tx.send(SessionReply::ResetDone);
@@ -195,6 +324,7 @@ async fn run(
 
     let mut app = App {
         messages: initial_messages,
+        show_details: false,
         input: String::new(),
         waiting: false,
         should_quit: false,
@@ -209,17 +339,48 @@ async fn run(
                 SessionReply::StreamChunk(chunk) => {
                     app.streaming_buffer.push_str(&chunk);
                 }
-                SessionReply::StreamDone => {
+                SessionReply::StreamDone { user, reply } => {
+                    if let Some(sent) = app.messages.iter_mut().rev().find(|m| m.0 == "user") {
+                        sent.2 = user;
+                    }
                     let finished = std::mem::take(&mut app.streaming_buffer);
                     if !finished.is_empty() {
-                        app.messages.push(("assistant".to_string(), finished));
+                        app.messages
+                            .push(("assistant".to_string(), finished, reply));
                     }
                     app.waiting = false;
                 }
                 SessionReply::Error(e) => {
                     app.streaming_buffer.clear();
                     app.messages
-                        .push(("system".to_string(), format!("Error: {e}")));
+                        .push(("system".to_string(), format!("Error: {e}"), None));
+                    app.waiting = false;
+                }
+                SessionReply::ReplaceFailed(messages, e) => {
+                    app.streaming_buffer.clear();
+                    app.messages = messages;
+                    app.messages
+                        .push(("system".to_string(), format!("Error: {e}"), None));
+                    app.waiting = false;
+                }
+                SessionReply::Undone(undone) => {
+                    let msg = if undone {
+                        rewind_last_turn(&mut app.messages);
+                        "Removed the last message and its reply."
+                    } else {
+                        "Nothing to undo."
+                    };
+                    app.messages
+                        .push(("system".to_string(), msg.to_string(), None));
+                    app.waiting = false;
+                }
+                SessionReply::BranchChanged(messages, notice) => {
+                    app.messages = messages;
+                    app.messages.push(("system".to_string(), notice, None));
+                    app.waiting = false;
+                }
+                SessionReply::Notice(text) => {
+                    app.messages.push(("system".to_string(), text, None));
                     app.waiting = false;
                 }
                 SessionReply::ResetDone => {
@@ -228,8 +389,11 @@ async fn run(
                     app.waiting = false;
                 }
                 SessionReply::ModelSwitched(name) => {
-                    app.messages
-                        .push(("system".to_string(), format!("Switched to model: {name}")));
+                    app.messages.push((
+                        "system".to_string(),
+                        format!("Switched to model: {name}"),
+                        None,
+                    ));
                     app.waiting = false;
                 }
             }
@@ -272,6 +436,99 @@ async fn run(
                             continue;
                         }
 
+                        if trimmed == "/undo" {
+                            app.waiting = true;
+                            let _ = cmd_tx.send(SessionCmd::Undo);
+                            continue;
+                        }
+
+                        if trimmed == "/retry" {
+                            match rewind_last_turn(&mut app.messages) {
+                                Some(last) => {
+                                    app.messages.push(("user".to_string(), last, None));
+                                    app.waiting = true;
+                                    let _ = cmd_tx.send(SessionCmd::Turn(Turn::Retry));
+                                }
+                                None => app.messages.push((
+                                    "system".to_string(),
+                                    "Nothing to retry.".to_string(),
+                                    None,
+                                )),
+                            }
+                            continue;
+                        }
+
+                        if trimmed == "/edit" || trimmed.starts_with("/edit ") {
+                            let edited = trimmed.strip_prefix("/edit").unwrap_or("").trim();
+                            let msg = if edited.is_empty() {
+                                "Usage: /edit <new message>"
+                            } else if rewind_last_turn(&mut app.messages).is_some() {
+                                app.messages
+                                    .push(("user".to_string(), edited.to_string(), None));
+                                app.waiting = true;
+                                let _ =
+                                    cmd_tx.send(SessionCmd::Turn(Turn::Edit(edited.to_string())));
+                                continue;
+                            } else {
+                                "Nothing to edit."
+                            };
+                            app.messages
+                                .push(("system".to_string(), msg.to_string(), None));
+                            continue;
+                        }
+
+                        if trimmed == "/pin" || trimmed.starts_with("/pin ") {
+                            let note = trimmed.strip_prefix("/pin").unwrap_or("").trim();
+                            app.waiting = true;
+                            let _ = cmd_tx.send(SessionCmd::Pin(
+                                (!note.is_empty()).then(|| note.to_string()),
+                            ));
+                            continue;
+                        }
+
+                        if trimmed == "/unpin" {
+                            app.waiting = true;
+                            let _ = cmd_tx.send(SessionCmd::Unpin);
+                            continue;
+                        }
+
+                        if trimmed == "/branches" {
+                            app.waiting = true;
+                            let _ = cmd_tx.send(SessionCmd::Branches);
+                            continue;
+                        }
+
+                        if trimmed == "/fork" || trimmed.starts_with("/fork ") {
+                            let args = trimmed.strip_prefix("/fork").unwrap_or("");
+                            match hi_history::branch::parse_fork_args(args) {
+                                Some((name, turns)) => {
+                                    app.waiting = true;
+                                    let _ = cmd_tx.send(SessionCmd::Fork(name.to_string(), turns));
+                                }
+                                None => app.messages.push((
+                                    "system".to_string(),
+                                    "Usage: /fork <name> [n]".to_string(),
+                                    None,
+                                )),
+                            }
+                            continue;
+                        }
+
+                        if trimmed == "/switch" || trimmed.starts_with("/switch ") {
+                            let name = trimmed.strip_prefix("/switch").unwrap_or("").trim();
+                            if name.is_empty() {
+                                app.messages.push((
+                                    "system".to_string(),
+                                    "Usage: /switch <branch>".to_string(),
+                                    None,
+                                ));
+                            } else {
+                                app.waiting = true;
+                                let _ = cmd_tx.send(SessionCmd::SwitchBranch(name.to_string()));
+                            }
+                            continue;
+                        }
+
                         if trimmed == "/model" || trimmed.starts_with("/model ") {
                             let target = trimmed
                                 .strip_prefix("/model")
@@ -283,6 +540,29 @@ async fn run(
                             continue;
                         }
 
+                        if trimmed == "/search" || trimmed.starts_with("/search ") {
+                            let query = trimmed.strip_prefix("/search").unwrap_or("").trim();
+                            let msg = if query.is_empty() {
+                                "Usage: /search <query>".to_string()
+                            } else {
+                                match shared::history_search::search(
+                                    query,
+                                    None,
+                                    shared::history_search::DEFAULT_LIMIT,
+                                ) {
+                                    Ok(hits) => shared::history_search::format_hits(query, &hits),
+                                    Err(e) => format!("Search failed: {e}"),
+                                }
+                            };
+                            app.messages.push(("system".to_string(), msg, None));
+                            continue;
+                        }
+
+                        if trimmed == "/details" {
+                            app.show_details = !app.show_details;
+                            continue;
+                        }
+
                         if trimmed == "/skills" {
                             let msg = if skill_list.is_empty() {
                                 "No skills loaded.".to_string()
@@ -293,13 +573,13 @@ async fn run(
                                 }
                                 lines.join("\n")
                             };
-                            app.messages.push(("system".to_string(), msg));
+                            app.messages.push(("system".to_string(), msg, None));
                             continue;
                         }
 
-                        app.messages.push(("user".to_string(), text.clone()));
+                        app.messages.push(("user".to_string(), text.clone(), None));
                         app.waiting = true;
-                        let _ = cmd_tx.send(SessionCmd::Send(text));
+                        let _ = cmd_tx.send(SessionCmd::Turn(Turn::Send(text)));
                     }
                     KeyCode::Backspace => {
                         app.input.pop();
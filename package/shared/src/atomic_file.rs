@@ -0,0 +1,185 @@
+//! Atomic writes and cross-process locks for the files hi keeps on disk.
+//!
+//! [`write`] replaces a file by writing a temporary file next to it and renaming it over
+//! the original, so readers never see a half-written file. Read-modify-write sequences
+//! hold [`lock`] for their whole duration. The lock is an advisory lock on a
+//! `<file>.lock` sidecar, because the rename gives the data file a new inode on every
+//! write. Locks are not reentrant: taking the lock for a file that this thread already
+//! holds blocks forever.
+
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tempfile::NamedTempFile;
+
+/// Replace `path` with `contents`, creating parent directories as needed.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let dir = parent_dir(path);
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+    let mut temp = NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create temporary file in {}", dir.display()))?;
+    temp.write_all(contents.as_ref())
+        .with_context(|| format!("Failed to write temporary file for {}", path.display()))?;
+    temp.as_file()
+        .sync_all()
+        .with_context(|| format!("Failed to sync temporary file for {}", path.display()))?;
+    temp.persist(path)
+        .map_err(|e| e.error)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Exclusive lock for a file, shared with every other thread and process. Released on drop.
+pub struct FileLock {
+    _file: File,
+}
+
+/// Block until this process holds the lock for `path`.
+pub fn lock(path: &Path) -> Result<FileLock> {
+    let dir = parent_dir(path);
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+    let lock_path = lock_path(path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    Ok(FileLock { _file: file })
+}
+
+/// Run `f` while holding the lock for `path`.
+pub fn with_lock<T>(path: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let _lock = lock(path)?;
+    f()
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_else(|| OsString::from("data"));
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Set in child processes spawned by the cross-process test.
+    const CHILD_COUNTER_ENV: &str = "HI_ATOMIC_FILE_TEST_COUNTER";
+    const INCREMENTS: usize = 25;
+
+    fn increment(path: &Path) {
+        with_lock(path, || {
+            let current: usize = std::fs::read_to_string(path)
+                .map(|text| text.trim().parse().unwrap())
+                .unwrap_or(0);
+            write(path, format!("{}\n", current + 1))
+        })
+        .unwrap();
+    }
+
+    fn read_counter(path: &Path) -> usize {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_write_replaces_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("state.json");
+        write(&path, "first").unwrap();
+        write(&path, "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+
+        let leftovers: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, vec![OsString::from("state.json")]);
+    }
+
+    #[test]
+    fn test_lock_path_is_sidecar() {
+        assert_eq!(
+            lock_path(Path::new("/data/memory.md")),
+            PathBuf::from("/data/memory.md.lock")
+        );
+    }
+
+    #[test]
+    fn test_competing_threads_do_not_lose_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counter");
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..INCREMENTS {
+                        increment(&path);
+                    }
+                });
+            }
+        });
+        assert_eq!(read_counter(&path), 8 * INCREMENTS);
+    }
+
+    /// Body of the child processes in `test_competing_processes_do_not_lose_updates`;
+    /// does nothing when run as a normal test.
+    #[test]
+    fn child_increments_counter() {
+        let Ok(path) = std::env::var(CHILD_COUNTER_ENV) else {
+            return;
+        };
+        for _ in 0..INCREMENTS {
+            increment(Path::new(&path));
+        }
+    }
+
+    #[test]
+    fn test_competing_processes_do_not_lose_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counter");
+        let children: Vec<_> = (0..4)
+            .map(|_| {
+                std::process::Command::new(std::env::current_exe().unwrap())
+                    .args([
+                        "--exact",
+                        "atomic_file::tests::child_increments_counter",
+                        "--test-threads=1",
+                    ])
+                    .env(CHILD_COUNTER_ENV, &path)
+                    .stdout(std::process::Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        for _ in 0..INCREMENTS {
+            increment(&path);
+        }
+        for mut child in children {
+            assert!(child.wait().unwrap().success());
+        }
+        assert_eq!(read_counter(&path), 5 * INCREMENTS);
+    }
+}
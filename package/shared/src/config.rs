@@ -1,6 +1,8 @@
 use std::io::{BufRead, Write};
 
 use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -66,21 +68,219 @@ pub struct HeartbeatConfig {
     pub model: Option<ModelRef>,
     #[serde(default)]
     pub prompt: Option<String>,
+    /// How long a picked-up ledger task may stay in progress before it is retried.
+    #[serde(default = "default_lease_secs")]
+    pub lease_secs: u64,
+    /// Daily window in which ticks run. Ticks outside it are skipped.
+    #[serde(default)]
+    pub active_hours: Option<ActiveHours>,
+    /// Skip the model call when the ledger has no ready task, instead of sending `prompt`.
+    #[serde(default)]
+    pub skip_when_idle: bool,
+    /// When set, each idle tick doubles the wait before the next one, up to this many
+    /// seconds. The wait returns to `interval_secs` once a task runs.
+    #[serde(default)]
+    pub max_backoff_secs: Option<u64>,
+}
+
+/// Daily heartbeat window, e.g. 08:00 to 22:00. An `end` before `start` wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveHours {
+    /// Local start time, `HH:MM`.
+    pub start: String,
+    /// Local end time, `HH:MM` (exclusive).
+    pub end: String,
+    /// IANA timezone name; UTC when unset.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl HeartbeatConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.interval_secs == 0 {
+            bail!("heartbeat.interval_secs must be at least 1");
+        }
+        if self.lease().is_none() {
+            bail!(
+                "heartbeat.lease_secs must be between 1 and {}",
+                i64::MAX / 1000
+            );
+        }
+        if let Some(active_hours) = &self.active_hours {
+            active_hours.validate()?;
+        }
+        Ok(())
+    }
+
+    /// How long a picked-up task may stay in progress; `None` when `lease_secs` is invalid.
+    pub fn lease(&self) -> Option<chrono::Duration> {
+        crate::heartbeat_store::lease_from_secs(self.lease_secs)
+    }
+}
+
+impl ActiveHours {
+    pub fn validate(&self) -> Result<()> {
+        self.parse().map(|_| ())
+    }
+
+    /// Whether `now` falls inside the window.
+    pub fn contains(&self, now: DateTime<Utc>) -> Result<bool> {
+        let (start, end, tz) = self.parse()?;
+        let local = now.with_timezone(&tz).time();
+        Ok(if start < end {
+            start <= local && local < end
+        } else {
+            local >= start || local < end
+        })
+    }
+
+    fn parse(&self) -> Result<(NaiveTime, NaiveTime, Tz)> {
+        let parse_time = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .with_context(|| format!("Invalid active_hours time '{}': expected HH:MM", value))
+        };
+        let start = parse_time(&self.start)?;
+        let end = parse_time(&self.end)?;
+        if start == end {
+            bail!("active_hours start and end must differ");
+        }
+        let tz = crate::schedule_time::parse_timezone(self.timezone.as_deref())?;
+        Ok((start, end, tz))
+    }
+}
+
+impl std::fmt::Display for ActiveHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{} {}",
+            self.start,
+            self.end,
+            self.timezone.as_deref().unwrap_or("UTC")
+        )
+    }
 }
 
 fn default_interval_secs() -> u64 {
     1200
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_lease_secs() -> u64 {
+    crate::heartbeat_store::DEFAULT_LEASE_SECS
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ScheduleTaskConfig {
     pub name: String,
+    #[serde(default)]
     pub cron: String,
     #[serde(default)]
     pub model: Option<ModelRef>,
     pub prompt: String,
     #[serde(default)]
     pub enabled: bool,
+    /// RFC 3339 fire time for one-shot tasks. One-shot tasks run once and are archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+    /// IANA timezone used to evaluate `cron` (defaults to UTC).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// What to do on startup about cron firings missed while hi was not running.
+    #[serde(default, skip_serializing_if = "CatchUpPolicy::is_skip")]
+    pub catch_up: CatchUpPolicy,
+    /// Maximum number of missed runs replayed by `run_all` (default 10).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catch_up_limit: Option<usize>,
+    /// Maximum number of simultaneous runs of this schedule (default 1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+    /// What to do when the schedule fires while `max_concurrency` runs are in flight.
+    #[serde(default, skip_serializing_if = "OverlapPolicy::is_skip")]
+    pub overlap: OverlapPolicy,
+    /// Checks the response and decides whether it is delivered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputContract>,
+}
+
+/// How a schedule's response is checked and when it is delivered.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OutputContract {
+    /// Response meaning "nothing to report", e.g. `NO_UPDATE`. It is never delivered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentinel: Option<String>,
+    /// JSON Schema the response must match. A mismatch fails the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+    /// Deliver a JSON response only when this condition holds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deliver_if: Option<DeliveryCondition>,
+}
+
+/// Condition on a JSON response. With neither `equals` nor `not_equals`, the value at
+/// `pointer` must be present and truthy.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DeliveryCondition {
+    /// JSON Pointer into the response, e.g. `/status`.
+    pub pointer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_equals: Option<serde_json::Value>,
+}
+
+pub const DEFAULT_CATCH_UP_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Drop missed runs.
+    #[default]
+    Skip,
+    /// Run once if at least one firing was missed.
+    RunOnce,
+    /// Replay every missed firing, up to `catch_up_limit`.
+    RunAll,
+}
+
+impl CatchUpPolicy {
+    fn is_skip(&self) -> bool {
+        *self == Self::Skip
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Drop the new firing.
+    #[default]
+    Skip,
+    /// Wait for a running instance to finish, then run.
+    Queue,
+    /// Abort the oldest running instance and start the new one.
+    CancelPrevious,
+}
+
+impl OverlapPolicy {
+    fn is_skip(&self) -> bool {
+        *self == Self::Skip
+    }
+}
+
+impl ScheduleTaskConfig {
+    pub fn effective_max_concurrency(&self) -> usize {
+        self.max_concurrency.unwrap_or(1).max(1)
+    }
+
+    pub fn is_one_shot(&self) -> bool {
+        self.at.as_deref().is_some_and(|at| !at.trim().is_empty())
+    }
+
+    /// A task needs a name, a prompt, and either a cron expression or a one-shot time.
+    pub fn has_required_fields(&self) -> bool {
+        !self.name.trim().is_empty()
+            && !self.prompt.trim().is_empty()
+            && (!self.cron.trim().is_empty() || self.is_one_shot())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -129,6 +329,17 @@ fn default_trigger_ratio() -> f64 {
     0.8
 }
 
+/// Tool results compaction keeps when `compact` is not configured.
+pub const DEFAULT_KEEP_TOOL_RESULTS: usize = 3;
+
+fn default_keep_tool_results() -> usize {
+    DEFAULT_KEEP_TOOL_RESULTS
+}
+
+fn default_max_summaries() -> usize {
+    4
+}
+
 fn default_poll_timeout_secs() -> Option<u32> {
     Some(30)
 }
@@ -137,6 +348,18 @@ fn default_large_release_threshold_bytes() -> usize {
     1_048_576 // 1 MB
 }
 
+fn default_large_session_bytes() -> usize {
+    262_144 // 256 KB
+}
+
+fn default_memory_check_interval_secs() -> u64 {
+    30
+}
+
+fn default_metrics_listen() -> String {
+    "127.0.0.1:9464".to_string()
+}
+
 fn default_session_ttl_secs() -> u64 {
     3600
 }
@@ -169,6 +392,16 @@ pub struct SessionConfig {
 pub struct MemoryConfig {
     #[serde(default = "default_large_release_threshold_bytes")]
     pub large_release_threshold_bytes: usize,
+    /// Resident memory budget for `hi remote`. Above it, large sessions are compacted and
+    /// least-recently-used sessions evicted.
+    #[serde(default)]
+    pub rss_budget_bytes: Option<u64>,
+    /// History size above which a session is compacted when the budget is exceeded.
+    #[serde(default = "default_large_session_bytes")]
+    pub large_session_bytes: usize,
+    /// How often resident memory is checked against the budget.
+    #[serde(default = "default_memory_check_interval_secs")]
+    pub check_interval_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -179,6 +412,46 @@ pub struct RemoteConfig {
     pub session: Option<SessionConfig>,
 }
 
+/// Which sessions' past messages a session can read through `search_history`,
+/// `recall_archive` and Telegram's `/search`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecallConfig {
+    /// Let every session read every other session's messages, e.g. a Telegram chat the
+    /// TUI's. Off by default, so each session reads only its own.
+    #[serde(default)]
+    pub cross_session: bool,
+}
+
+/// Prometheus `/metrics` endpoint served by `hi remote` and `hi daemon`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address to listen on. Defaults to loopback only.
+    #[serde(default = "default_metrics_listen")]
+    pub listen: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageBackend {
+    /// One file per store in the data directory.
+    #[default]
+    File,
+    /// A single SQLite database. Requires the `sqlite` feature.
+    Sqlite,
+}
+
+/// Where chat history, schedules, the runtime index and heartbeat ticks are stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Database file for the `sqlite` backend. Defaults to `data_dir()/hi.db`.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpServerConfig {
     #[serde(default)]
@@ -209,6 +482,16 @@ pub struct CompactConfig {
     pub model: Option<ModelRef>,
     #[serde(default)]
     pub prompt: Option<String>,
+    /// Most recent tool results that compaction keeps verbatim.
+    #[serde(default = "default_keep_tool_results")]
+    pub keep_tool_results: usize,
+    /// Estimated tokens of the newest messages to keep. Without it, compaction keeps the
+    /// newer half of the messages.
+    #[serde(default)]
+    pub target_tokens: Option<usize>,
+    /// Summaries kept before the oldest are merged into the long-term gist.
+    #[serde(default = "default_max_summaries")]
+    pub max_summaries: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -235,6 +518,12 @@ pub struct ModelConfig {
     pub memory: Option<MemoryConfig>,
     #[serde(default)]
     pub thinking: Option<ThinkingConfig>,
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
+    #[serde(default)]
+    pub storage: Option<StorageConfig>,
+    #[serde(default)]
+    pub recall: Option<RecallConfig>,
 }
 
 const CONFIG_TEMPLATE: &str = r#"{
@@ -435,6 +724,13 @@ impl ModelConfig {
         Self::load_from_path(&path)
     }
 
+    /// The session whose messages `session` may recall: `None` for every session when
+    /// `recall.cross_session` is set, otherwise `session` itself.
+    pub fn recall_scope<'a>(&self, session: &'a str) -> Option<&'a str> {
+        let cross_session = self.recall.as_ref().is_some_and(|r| r.cross_session);
+        (!cross_session).then_some(session)
+    }
+
     pub fn load_from_path(path: &std::path::Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Config file not found at: {}", path.display()))?;
@@ -462,6 +758,9 @@ impl ModelConfig {
                 );
             }
         }
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.validate()?;
+        }
         if let Some(ref remote) = self.remote {
             if let Some(ref telegram) = remote.telegram {
                 if telegram.enabled && telegram.bot_token.is_empty() {
@@ -469,6 +768,35 @@ impl ModelConfig {
                 }
             }
         }
+        if let Some(memory) = &self.memory
+            && memory.rss_budget_bytes.is_some()
+            && memory.check_interval_secs == 0
+        {
+            bail!("memory.check_interval_secs must be greater than 0");
+        }
+        if let Some(compact) = &self.compact
+            && let Some(target) = compact.target_tokens
+            && target as f64 >= self.context_window as f64 * compact.trigger_ratio
+        {
+            bail!("compact.target_tokens must be below trigger_ratio × context_window");
+        }
+        if self.compact.as_ref().is_some_and(|c| c.max_summaries == 0) {
+            bail!("compact.max_summaries must be greater than 0");
+        }
+        if let Some(metrics) = self.metrics.as_ref().filter(|m| m.enabled) {
+            metrics
+                .listen
+                .parse::<std::net::SocketAddr>()
+                .with_context(|| format!("Invalid metrics.listen address: {}", metrics.listen))?;
+        }
+        if let Some(storage) = &self.storage
+            && storage.backend == StorageBackend::Sqlite
+            && !cfg!(feature = "sqlite")
+        {
+            bail!(
+                "storage.backend is \"sqlite\", but this build of hi was compiled without the `sqlite` feature"
+            );
+        }
         Ok(())
     }
 
@@ -967,6 +1295,37 @@ mod tests {
         assert!(compact.enabled);
         assert_eq!(compact.strategy, CompactStrategy::Truncate);
         assert!((compact.trigger_ratio - 0.8).abs() < f64::EPSILON);
+        assert_eq!(compact.keep_tool_results, DEFAULT_KEEP_TOOL_RESULTS);
+        assert!(compact.target_tokens.is_none());
+        assert_eq!(compact.max_summaries, 4);
+    }
+
+    #[test]
+    fn test_compact_retention_settings_are_validated() {
+        let json = r#"{
+            "provider": "openai",
+            "model": "gpt-4o",
+            "api_key": "sk-test",
+            "context_window": 10000,
+            "compact": {
+                "enabled": true,
+                "keep_tool_results": 5,
+                "target_tokens": 4000
+            }
+        }"#;
+        let mut config: ModelConfig = serde_json::from_str(json).unwrap();
+        let compact = config.compact.as_ref().unwrap();
+        assert_eq!(compact.keep_tool_results, 5);
+        assert_eq!(compact.target_tokens, Some(4000));
+        assert!(config.validate().is_ok());
+
+        config.compact.as_mut().unwrap().target_tokens = Some(8000);
+        assert!(config.validate().is_err());
+
+        let compact = config.compact.as_mut().unwrap();
+        compact.target_tokens = None;
+        compact.max_summaries = 0;
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -1181,6 +1540,102 @@ mod tests {
         let config: ModelConfig = serde_json::from_str(json).unwrap();
         let hb = config.heartbeat.as_ref().unwrap();
         assert_eq!(hb.interval_secs, 1200);
+        assert_eq!(hb.lease_secs, 3600);
+        assert!(hb.active_hours.is_none());
+        assert!(!hb.skip_when_idle);
+        assert!(hb.max_backoff_secs.is_none());
+    }
+
+    #[test]
+    fn test_active_hours_window() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let day = ActiveHours {
+            start: "08:00".to_string(),
+            end: "22:00".to_string(),
+            timezone: Some("Europe/Berlin".to_string()),
+        };
+        assert!(day.contains(at("2026-03-05T07:00:00Z")).unwrap());
+        assert!(!day.contains(at("2026-03-05T06:59:00Z")).unwrap());
+        assert!(!day.contains(at("2026-03-05T21:00:00Z")).unwrap());
+        assert_eq!(day.to_string(), "08:00-22:00 Europe/Berlin");
+
+        let night = ActiveHours {
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+            timezone: None,
+        };
+        assert!(night.contains(at("2026-03-05T23:30:00Z")).unwrap());
+        assert!(night.contains(at("2026-03-05T05:59:00Z")).unwrap());
+        assert!(!night.contains(at("2026-03-05T12:00:00Z")).unwrap());
+    }
+
+    #[test]
+    fn test_active_hours_validation() {
+        let json = r#"{
+            "provider": "ollama",
+            "model": "qwen2.5:3b",
+            "context_window": 4096,
+            "heartbeat": {
+                "enabled": true,
+                "active_hours": {"start": "8am", "end": "22:00"}
+            }
+        }"#;
+        let config: ModelConfig = serde_json::from_str(json).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("expected HH:MM"));
+
+        let same = ActiveHours {
+            start: "09:00".to_string(),
+            end: "09:00".to_string(),
+            timezone: None,
+        };
+        assert!(same.validate().is_err());
+        let bad_tz = ActiveHours {
+            end: "17:00".to_string(),
+            timezone: Some("Mars/Olympus".to_string()),
+            ..same
+        };
+        assert!(bad_tz.validate().is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_lease_validation() {
+        let mut config: ModelConfig = serde_json::from_str(
+            r#"{"provider": "ollama", "model": "m", "context_window": 4096, "heartbeat": {}}"#,
+        )
+        .unwrap();
+        let heartbeat = config.heartbeat.as_mut().unwrap();
+        assert_eq!(heartbeat.lease(), Some(chrono::Duration::hours(1)));
+        heartbeat.lease_secs = 0;
+        assert!(config.validate().is_err());
+        config.heartbeat.as_mut().unwrap().lease_secs = u64::MAX;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_interval_validation() {
+        let json = r#"{
+            "provider": "ollama",
+            "model": "qwen2.5:3b",
+            "context_window": 4096,
+            "heartbeat": {"enabled": true, "interval_secs": 0}
+        }"#;
+        let mut config: ModelConfig = serde_json::from_str(json).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("interval_secs"));
+        config.heartbeat.as_mut().unwrap().interval_secs = 1;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_recall_scope() {
+        let json = r#"{"provider": "ollama", "model": "qwen2.5:3b", "context_window": 4096}"#;
+        let mut config: ModelConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.recall_scope("tui"), Some("tui"));
+        config.recall = Some(RecallConfig {
+            cross_session: true,
+        });
+        assert_eq!(config.recall_scope("tui"), None);
     }
 
     #[test]
@@ -1239,6 +1694,63 @@ mod tests {
         assert!(telegram.allowed_user_ids.is_none());
     }
 
+    #[test]
+    fn test_memory_budget_config() {
+        let json = r#"{
+            "provider": "ollama",
+            "model": "qwen2.5:14b",
+            "context_window": 32000,
+            "memory": { "rss_budget_bytes": 402653184 }
+        }"#;
+        let mut config: ModelConfig = serde_json::from_str(json).unwrap();
+        let memory = config.memory.as_ref().unwrap();
+        assert_eq!(memory.rss_budget_bytes, Some(402_653_184));
+        assert_eq!(memory.large_release_threshold_bytes, 1_048_576);
+        assert_eq!(memory.large_session_bytes, 262_144);
+        assert_eq!(memory.check_interval_secs, 30);
+        assert!(config.validate().is_ok());
+
+        config.memory.as_mut().unwrap().check_interval_secs = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_metrics_config_defaults_and_validation() {
+        let json = r#"{
+            "provider": "ollama",
+            "model": "qwen2.5:14b",
+            "context_window": 32000,
+            "metrics": { "enabled": true }
+        }"#;
+        let mut config: ModelConfig = serde_json::from_str(json).unwrap();
+        let metrics = config.metrics.as_ref().unwrap();
+        assert_eq!(metrics.listen, "127.0.0.1:9464");
+        assert!(config.validate().is_ok());
+
+        config.metrics.as_mut().unwrap().listen = "localhost".to_string();
+        assert!(config.validate().is_err());
+        config.metrics.as_mut().unwrap().enabled = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_storage_config_defaults_to_file() {
+        let json = r#"{
+            "provider": "ollama",
+            "model": "qwen2.5:14b",
+            "context_window": 32000,
+            "storage": { "backend": "sqlite", "path": "/tmp/hi.db" }
+        }"#;
+        let config: ModelConfig = serde_json::from_str(json).unwrap();
+        let storage = config.storage.as_ref().unwrap();
+        assert_eq!(storage.backend, StorageBackend::Sqlite);
+        assert_eq!(storage.path.as_deref(), Some("/tmp/hi.db"));
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "sqlite"));
+
+        let storage: StorageConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(storage.backend, StorageBackend::File);
+    }
+
     #[test]
     fn test_prompt_with_default_uses_input() {
         let input = b"anthropic\n";
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::atomic_file;
+use crate::heartbeat_store::HeartbeatLedger;
+
+const SCRATCHPAD_FILE: &str = "heartbeat_scratchpad.json";
+
+/// Notes kept per task; older text is dropped from the front when a note grows past this.
+pub const MAX_NOTES_CHARS: usize = 2000;
+
+/// Per-task working notes the heartbeat agent carries between ticks.
+pub type Scratchpad = BTreeMap<String, String>;
+
+pub fn default_path() -> Result<PathBuf> {
+    Ok(crate::paths::data_dir()?.join(SCRATCHPAD_FILE))
+}
+
+pub fn load(path: &Path) -> Result<Scratchpad> {
+    if !path.exists() {
+        return Ok(Scratchpad::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn save(path: &Path, scratchpad: &Scratchpad) -> Result<()> {
+    let content =
+        serde_json::to_string_pretty(scratchpad).context("Failed to serialize scratchpad")?;
+    atomic_file::write(path, content)
+}
+
+/// Add `note` to a task's notes, or replace them. Returns the notes as stored.
+///
+/// Notes roll: once they exceed [`MAX_NOTES_CHARS`], the oldest lines are dropped. An
+/// empty note with `replace` clears the task's notes.
+pub fn write(path: &Path, task_id: &str, note: &str, replace: bool) -> Result<String> {
+    let _lock = atomic_file::lock(path)?;
+    let mut scratchpad = load(path)?;
+    let note = note.trim();
+    let notes = match scratchpad.remove(task_id) {
+        Some(existing) if !replace && !note.is_empty() => format!("{existing}\n{note}"),
+        Some(existing) if !replace => existing,
+        _ => note.to_string(),
+    };
+    let notes = keep_tail(&notes, MAX_NOTES_CHARS);
+    if !notes.is_empty() {
+        scratchpad.insert(task_id.to_string(), notes.clone());
+    }
+    save(path, &scratchpad)?;
+    Ok(notes)
+}
+
+/// Drop notes for tasks that are no longer in the ledger or have finished for good.
+pub fn retain_active(path: &Path, ledger: &HeartbeatLedger) -> Result<()> {
+    let _lock = atomic_file::lock(path)?;
+    let mut scratchpad = load(path)?;
+    let before = scratchpad.len();
+    scratchpad.retain(|id, _| {
+        ledger
+            .tasks
+            .iter()
+            .any(|task| &task.id == id && !task.is_final())
+    });
+    if scratchpad.len() == before {
+        return Ok(());
+    }
+    save(path, &scratchpad)
+}
+
+/// The last `max_chars` characters of `text`, starting at a line boundary when possible.
+fn keep_tail(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    let (start, _) = text
+        .char_indices()
+        .nth(total - max_chars)
+        .unwrap_or((0, ' '));
+    let tail = &text[start..];
+    match tail.find('\n') {
+        Some(newline) if newline + 1 < tail.len() => tail[newline + 1..].to_string(),
+        _ => tail.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heartbeat_store::{HeartbeatTask, TaskStatus};
+
+    #[test]
+    fn test_write_appends_and_replaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SCRATCHPAD_FILE);
+        assert!(load(&path).unwrap().is_empty());
+
+        assert_eq!(
+            write(&path, "a", "read 3 of 10 files", false).unwrap(),
+            "read 3 of 10 files"
+        );
+        assert_eq!(
+            write(&path, "a", "read 6 of 10 files", false).unwrap(),
+            "read 3 of 10 files\nread 6 of 10 files"
+        );
+        assert_eq!(
+            write(&path, "a", "  ", false).unwrap(),
+            "read 3 of 10 files\nread 6 of 10 files"
+        );
+        assert_eq!(write(&path, "a", "start over", true).unwrap(), "start over");
+        write(&path, "b", "other task", false).unwrap();
+
+        let scratchpad = load(&path).unwrap();
+        assert_eq!(scratchpad["a"], "start over");
+        assert_eq!(scratchpad["b"], "other task");
+
+        assert_eq!(write(&path, "a", "", true).unwrap(), "");
+        assert!(!load(&path).unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn test_write_keeps_recent_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SCRATCHPAD_FILE);
+        for i in 0..500 {
+            write(&path, "a", &format!("step {i} finished"), false).unwrap();
+        }
+        let notes = &load(&path).unwrap()["a"];
+        assert!(notes.chars().count() <= MAX_NOTES_CHARS);
+        assert!(notes.starts_with("step "));
+        assert!(notes.ends_with("step 499 finished"));
+    }
+
+    #[test]
+    fn test_retain_active() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SCRATCHPAD_FILE);
+        for id in ["active", "done", "gone"] {
+            write(&path, id, "note", false).unwrap();
+        }
+        let ledger = HeartbeatLedger {
+            header: String::new(),
+            tasks: vec![
+                HeartbeatTask {
+                    id: "active".into(),
+                    status: TaskStatus::InProgress,
+                    title: "Active".into(),
+                    ..Default::default()
+                },
+                HeartbeatTask {
+                    id: "done".into(),
+                    status: TaskStatus::Done,
+                    title: "Done".into(),
+                    ..Default::default()
+                },
+            ],
+        };
+        retain_active(&path, &ledger).unwrap();
+        let scratchpad = load(&path).unwrap();
+        assert_eq!(scratchpad.keys().collect::<Vec<_>>(), vec!["active"]);
+    }
+}
@@ -1,14 +1,32 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::atomic_file;
+use crate::schedule_time::{format_interval, parse_interval};
+
+/// Attempts a task gets before a failure is final, unless the task sets `max_attempts`.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// How long a task may stay in progress before it is considered abandoned.
+pub const DEFAULT_LEASE_SECS: u64 = 3600;
+
+/// A lease of `secs` seconds, or `None` when it is zero or too long for a `Duration`.
+pub fn lease_from_secs(secs: u64) -> Option<chrono::Duration> {
+    let secs = i64::try_from(secs).ok().filter(|secs| *secs > 0)?;
+    chrono::Duration::try_seconds(secs)
+}
+
 /// Status of a heartbeat task in the ledger.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum TaskStatus {
+    #[default]
     Pending,
     InProgress,
     Done,
@@ -41,12 +59,228 @@ impl FromStr for TaskStatus {
 }
 
 /// A single heartbeat task entry.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct HeartbeatTask {
     pub id: String,
     pub status: TaskStatus,
     pub title: String,
     pub description: Option<String>,
+    /// Higher runs first; defaults to 0.
+    pub priority: i32,
+    /// The task is not picked up before this time.
+    pub not_before: Option<DateTime<Utc>>,
+    /// Among equal priorities, tasks due sooner run first.
+    pub due: Option<DateTime<Utc>>,
+    /// Recurrence interval. A finished recurring task goes back to `Pending`.
+    pub every: Option<chrono::Duration>,
+    /// Tasks that must be `Done` before this one is picked up. A recurring dependency
+    /// counts as done from its last completion until its next occurrence is due.
+    pub depends_on: Vec<String>,
+    /// End of the current in-progress lease. A task still in progress after this was
+    /// abandoned (the agent errored or never reported back).
+    pub lease_until: Option<DateTime<Utc>>,
+    /// How many times the task has been picked up.
+    pub attempts: u32,
+    /// Per-task retry limit; `DEFAULT_MAX_ATTEMPTS` when unset.
+    pub max_attempts: Option<u32>,
+    /// When the task reached its final outcome. For a recurring task, when its last
+    /// occurrence was done.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Final outcome, e.g. `failed after 3 attempts: lease expired`.
+    pub outcome: Option<String>,
+}
+
+impl HeartbeatTask {
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS)
+    }
+
+    pub fn has_attempts_left(&self) -> bool {
+        self.attempts < self.max_attempts()
+    }
+
+    /// Whether the task is in progress without a live lease.
+    pub fn lease_expired(&self, now: DateTime<Utc>) -> bool {
+        self.status == TaskStatus::InProgress && self.lease_until.is_none_or(|until| until <= now)
+    }
+
+    /// Mark the task in progress under a lease of `lease` from `now`.
+    pub fn start(&mut self, now: DateTime<Utc>, lease: chrono::Duration) {
+        self.status = TaskStatus::InProgress;
+        self.attempts += 1;
+        self.lease_until = Some(
+            now.checked_add_signed(lease)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        );
+        self.finished_at = None;
+        self.outcome = None;
+    }
+
+    /// Return the task to `Pending` without recording an outcome.
+    pub fn release(&mut self) {
+        self.status = TaskStatus::Pending;
+        self.lease_until = None;
+    }
+
+    pub fn complete(&mut self, now: DateTime<Utc>) {
+        let outcome = self.describe_outcome("done", None);
+        self.finish(TaskStatus::Done, now, outcome);
+    }
+
+    /// Mark the task failed. The failure is final once no attempts are left; until then
+    /// `recover` puts the task back to `Pending` for another try.
+    pub fn fail(&mut self, now: DateTime<Utc>, reason: Option<&str>) {
+        if self.has_attempts_left() {
+            self.status = TaskStatus::Failed;
+            self.lease_until = None;
+            return;
+        }
+        self.fail_permanently(now, reason);
+    }
+
+    /// Mark the task failed without further retries.
+    pub fn fail_permanently(&mut self, now: DateTime<Utc>, reason: Option<&str>) {
+        let outcome = self.describe_outcome("failed", reason);
+        self.finish(TaskStatus::Failed, now, outcome);
+    }
+
+    /// Whether the task has reached a state that no longer changes on its own.
+    pub fn is_final(&self) -> bool {
+        match self.status {
+            TaskStatus::Done => true,
+            TaskStatus::Failed => !self.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// One-line summary for task listings.
+    pub fn summary(&self) -> String {
+        let mut line = format!("[{}] {}: {}", self.status, self.id, self.title);
+        let mut details = Vec::new();
+        if self.priority != 0 {
+            details.push(format!("priority {}", self.priority));
+        }
+        if let Some(due) = &self.due {
+            details.push(format!("due {}", format_timestamp(due)));
+        }
+        if let Some(not_before) = &self.not_before {
+            details.push(format!("not before {}", format_timestamp(not_before)));
+        }
+        if let Some(every) = self.every {
+            details.push(format!("every {}", format_interval(every)));
+        }
+        if !self.depends_on.is_empty() {
+            details.push(format!("after {}", self.depends_on.join(", ")));
+        }
+        if self.attempts > 0 {
+            details.push(format!("attempt {}/{}", self.attempts, self.max_attempts()));
+        }
+        if let Some(outcome) = &self.outcome {
+            details.push(outcome.clone());
+        }
+        if !details.is_empty() {
+            line.push_str(&format!(" ({})", details.join(", ")));
+        }
+        line
+    }
+
+    fn describe_outcome(&self, verb: &str, reason: Option<&str>) -> String {
+        let mut outcome = if self.attempts == 0 {
+            verb.to_string()
+        } else {
+            format!("{} after {}", verb, plural_attempts(self.attempts))
+        };
+        if let Some(reason) = reason.filter(|r| !r.is_empty()) {
+            outcome.push_str(": ");
+            outcome.push_str(reason);
+        }
+        outcome
+    }
+
+    /// Whether the task failed but may be retried.
+    pub fn is_retryable(&self) -> bool {
+        self.status == TaskStatus::Failed
+            && self.outcome.is_none()
+            && self.attempts > 0
+            && self.has_attempts_left()
+    }
+
+    fn finish(&mut self, status: TaskStatus, now: DateTime<Utc>, outcome: String) {
+        self.status = status;
+        self.lease_until = None;
+        self.finished_at = Some(now);
+        self.outcome = Some(outcome);
+        if let Some(every) = self.every {
+            self.reschedule(every, now);
+        }
+    }
+
+    /// Queue the next occurrence of a recurring task, keeping the previous `not_before`
+    /// as the anchor so occurrences do not drift. A task whose next occurrence is out of
+    /// range stays finished.
+    fn reschedule(&mut self, every: chrono::Duration, now: DateTime<Utc>) {
+        let Some(next) = next_occurrence(self.not_before.unwrap_or(now), every, now) else {
+            return;
+        };
+        if self.status != TaskStatus::Done {
+            self.finished_at = None;
+        }
+        self.status = TaskStatus::Pending;
+        self.not_before = Some(next);
+        self.attempts = 0;
+        self.outcome = None;
+    }
+
+    /// Whether tasks that depend on this one may run.
+    fn satisfies_dependents(&self, now: DateTime<Utc>) -> bool {
+        match self.status {
+            TaskStatus::Done => true,
+            TaskStatus::Pending => {
+                self.every.is_some()
+                    && self.finished_at.is_some()
+                    && self.not_before.is_some_and(|t| now < t)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the task may be picked up: pending, past `not_before`, and with every
+    /// dependency done.
+    pub fn is_ready(&self, now: DateTime<Utc>, ledger: &HeartbeatLedger) -> bool {
+        self.status == TaskStatus::Pending
+            && self.not_before.is_none_or(|t| t <= now)
+            && self.depends_on.iter().all(|dep| {
+                ledger
+                    .tasks
+                    .iter()
+                    .any(|t| t.id == *dep && t.satisfies_dependents(now))
+            })
+    }
+}
+
+/// The first `anchor + k * every` with `k >= 1` that is after `now`, or `None` when it
+/// is out of range.
+fn next_occurrence(
+    anchor: DateTime<Utc>,
+    every: chrono::Duration,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let every_ms = every.num_milliseconds();
+    if every_ms <= 0 {
+        return None;
+    }
+    let elapsed_ms = (now - anchor).num_milliseconds().max(0);
+    let periods = elapsed_ms / every_ms + 1;
+    let offset = chrono::Duration::try_milliseconds(every_ms.checked_mul(periods)?)?;
+    anchor.checked_add_signed(offset)
+}
+
+fn plural_attempts(n: u32) -> String {
+    if n == 1 {
+        "1 attempt".to_string()
+    } else {
+        format!("{n} attempts")
+    }
 }
 
 /// The full heartbeat ledger parsed from HEARTBEAT.md.
@@ -59,21 +293,202 @@ pub struct HeartbeatLedger {
 
 const DEFAULT_TEMPLATE: &str = "# Heartbeat Tasks\n\n";
 
+const MAX_ID_LEN: usize = 32;
+
+/// Default ledger location: `data_dir()/HEARTBEAT.md`.
+pub fn default_path() -> Result<PathBuf> {
+    Ok(crate::paths::data_dir()?.join("HEARTBEAT.md"))
+}
+
+/// Append a pending task with an id derived from `title`, unique within the ledger.
+pub fn add_task<'a>(ledger: &'a mut HeartbeatLedger, title: &str) -> Result<&'a mut HeartbeatTask> {
+    let title = title.trim();
+    if title.is_empty() {
+        bail!("Task title must not be empty");
+    }
+    if title.contains('\n') {
+        bail!("Task title must be a single line");
+    }
+    let id = unique_task_id(ledger, title);
+    ledger.tasks.push(HeartbeatTask {
+        id,
+        title: title.to_string(),
+        ..Default::default()
+    });
+    Ok(ledger.tasks.last_mut().expect("task was just pushed"))
+}
+
+/// Lowercase, dash-separated slug of `title`, suffixed with `-2`, `-3`, ... if taken.
+fn unique_task_id(ledger: &HeartbeatLedger, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= MAX_ID_LEN {
+            break;
+        }
+    }
+    let base = match slug.trim_end_matches('-') {
+        "" => "task",
+        slug => slug,
+    };
+    let taken = |id: &str| ledger.tasks.iter().any(|t| t.id == id);
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|id| !taken(id))
+        .expect("some suffix is free")
+}
+
+/// Mark a task `Done` or `Failed` by hand, from any state that is not final. A manual
+/// failure is not retried.
+pub fn resolve_task(
+    ledger: &mut HeartbeatLedger,
+    id: &str,
+    status: TaskStatus,
+    now: DateTime<Utc>,
+    reason: Option<&str>,
+) -> Result<()> {
+    let Some(task) = ledger.tasks.iter_mut().find(|t| t.id == id) else {
+        bail!("Task '{}' not found in HEARTBEAT.md", id);
+    };
+    if task.is_final() {
+        bail!("Task '{}' is already {}", id, task.status);
+    }
+    match status {
+        TaskStatus::Done => task.complete(now),
+        TaskStatus::Failed => task.fail_permanently(now, reason),
+        other => bail!(
+            "Tasks can only be resolved as done or failed, not {}",
+            other
+        ),
+    }
+    Ok(())
+}
+
 /// Validate whether a status transition is allowed.
 ///
 /// Valid transitions:
 /// - `Pending` → `InProgress`
 /// - `InProgress` → `Done`
 /// - `InProgress` → `Failed`
+/// - `InProgress` → `Pending` (lease released)
+/// - `Failed` → `Pending` (retry; callers check the attempt limit)
 pub fn validate_transition(from: &TaskStatus, to: &TaskStatus) -> bool {
     matches!(
         (from, to),
         (TaskStatus::Pending, TaskStatus::InProgress)
             | (TaskStatus::InProgress, TaskStatus::Done)
             | (TaskStatus::InProgress, TaskStatus::Failed)
+            | (TaskStatus::InProgress, TaskStatus::Pending)
+            | (TaskStatus::Failed, TaskStatus::Pending)
     )
 }
 
+/// Index of the task to run next: the ready task with the highest priority, then the
+/// earliest due time, then the first in the ledger.
+pub fn next_ready(ledger: &HeartbeatLedger, now: DateTime<Utc>) -> Option<usize> {
+    ledger
+        .tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| task.is_ready(now, ledger))
+        .min_by_key(|(i, task)| {
+            (
+                std::cmp::Reverse(task.priority),
+                task.due.is_none(),
+                task.due,
+                *i,
+            )
+        })
+        .map(|(i, _)| i)
+}
+
+/// Find a dependency cycle, returned as the ids along it (first id repeated at the end).
+///
+/// Dependencies on ids that are not in the ledger are ignored.
+pub fn find_dependency_cycle(ledger: &HeartbeatLedger) -> Option<Vec<String>> {
+    let deps: HashMap<&str, &[String]> = ledger
+        .tasks
+        .iter()
+        .map(|t| (t.id.as_str(), t.depends_on.as_slice()))
+        .collect();
+    let mut visited = HashSet::new();
+    for task in &ledger.tasks {
+        let mut path = Vec::new();
+        if let Some(cycle) = visit_dependencies(&task.id, &deps, &mut path, &mut visited) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+fn visit_dependencies<'a>(
+    id: &'a str,
+    deps: &HashMap<&'a str, &'a [String]>,
+    path: &mut Vec<&'a str>,
+    visited: &mut HashSet<&'a str>,
+) -> Option<Vec<String>> {
+    if let Some(start) = path.iter().position(|p| *p == id) {
+        let mut cycle: Vec<String> = path[start..].iter().map(|p| p.to_string()).collect();
+        cycle.push(id.to_string());
+        return Some(cycle);
+    }
+    if !visited.insert(id) {
+        return None;
+    }
+    path.push(id);
+    for dep in deps.get(id).copied().unwrap_or_default() {
+        if let Some(cycle) = visit_dependencies(dep, deps, path, visited) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    None
+}
+
+/// Tasks changed by `recover`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Recovery {
+    /// In-progress tasks whose lease ran out and went back to `Pending`.
+    pub released: Vec<String>,
+    /// Failed tasks put back to `Pending` for another attempt.
+    pub retried: Vec<String>,
+    /// Tasks that failed for good because they ran out of attempts.
+    pub exhausted: Vec<String>,
+}
+
+impl Recovery {
+    pub fn is_empty(&self) -> bool {
+        self.released.is_empty() && self.retried.is_empty() && self.exhausted.is_empty()
+    }
+}
+
+/// Release expired leases and requeue failed tasks that have attempts left.
+pub fn recover(ledger: &mut HeartbeatLedger, now: DateTime<Utc>) -> Recovery {
+    let mut recovery = Recovery::default();
+    for task in &mut ledger.tasks {
+        if task.lease_expired(now) {
+            if task.has_attempts_left() {
+                task.release();
+                recovery.released.push(task.id.clone());
+            } else {
+                task.fail(now, Some("lease expired"));
+                recovery.exhausted.push(task.id.clone());
+            }
+        } else if task.is_retryable() {
+            task.release();
+            recovery.retried.push(task.id.clone());
+        }
+    }
+    recovery
+}
+
 /// Parse a HEARTBEAT.md text into a ledger.
 ///
 /// Non-destructive: malformed lines are preserved in the header section.
@@ -105,10 +520,8 @@ pub fn parse(text: &str) -> HeartbeatLedger {
             };
 
             tasks.push(HeartbeatTask {
-                id: task.0,
-                status: task.1,
-                title: task.2,
                 description,
+                ..task
             });
         } else if !in_tasks {
             if !header.is_empty() {
@@ -121,8 +534,11 @@ pub fn parse(text: &str) -> HeartbeatLedger {
     HeartbeatLedger { header, tasks }
 }
 
-/// Try to parse a single task line: `- [status] task-id: Title text`
-fn parse_task_line(line: &str) -> Option<(String, TaskStatus, String)> {
+/// Try to parse a single task line: `- [status] task-id: Title text {key=value ...}`
+///
+/// The trailing `{...}` attribute block is optional. A block that does not parse is
+/// kept as part of the title.
+fn parse_task_line(line: &str) -> Option<HeartbeatTask> {
     let trimmed = line.trim_start();
     let rest = trimmed.strip_prefix("- [")?;
     let bracket_end = rest.find(']')?;
@@ -134,13 +550,170 @@ fn parse_task_line(line: &str) -> Option<(String, TaskStatus, String)> {
 
     let colon_pos = after_bracket.find(':')?;
     let id = after_bracket[..colon_pos].trim().to_string();
-    let title = after_bracket[colon_pos + 1..].trim().to_string();
+    let title = after_bracket[colon_pos + 1..].trim();
 
-    if id.is_empty() || title.is_empty() {
+    let mut task = HeartbeatTask {
+        id,
+        status,
+        ..Default::default()
+    };
+    let title = split_attributes(title, &mut task);
+    if task.id.is_empty() || title.is_empty() {
         return None;
     }
+    task.title = title.to_string();
+    Some(task)
+}
 
-    Some((id, status, title))
+/// Strip a trailing attribute block from `title`, applying it to `task`.
+fn split_attributes<'a>(title: &'a str, task: &mut HeartbeatTask) -> &'a str {
+    if !title.ends_with('}') {
+        return title;
+    }
+    for (start, _) in title.match_indices(" {") {
+        let block = &title[start + 2..title.len() - 1];
+        let mut parsed = task.clone();
+        let applied = parse_attributes(block).is_some_and(|attrs| {
+            attrs
+                .iter()
+                .all(|(key, value)| apply_attribute(&mut parsed, key, value).is_some())
+        });
+        if applied {
+            *task = parsed;
+            return title[..start].trim_end();
+        }
+    }
+    title
+}
+
+/// Split `key=value key="quoted value"` pairs.
+fn parse_attributes(block: &str) -> Option<Vec<(String, String)>> {
+    let mut attrs = Vec::new();
+    let mut chars = block.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            key.push(c);
+        }
+        if key.is_empty() || chars.next() != Some('=') {
+            return None;
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                value.push(c);
+            }
+        }
+        attrs.push((key, value));
+    }
+    Some(attrs)
+}
+
+fn apply_attribute(task: &mut HeartbeatTask, key: &str, value: &str) -> Option<()> {
+    match key {
+        "priority" => task.priority = value.parse().ok()?,
+        "not_before" => task.not_before = Some(parse_timestamp(value)?),
+        "due" => task.due = Some(parse_timestamp(value)?),
+        "every" => task.every = Some(parse_interval(value)?),
+        "depends_on" => {
+            let ids: Vec<String> = value.split(',').map(|id| id.trim().to_string()).collect();
+            if ids.iter().any(|id| id.is_empty()) {
+                return None;
+            }
+            task.depends_on = ids;
+        }
+        "lease" => task.lease_until = Some(parse_timestamp(value)?),
+        "attempts" => task.attempts = value.parse().ok()?,
+        "max_attempts" => task.max_attempts = Some(value.parse().ok()?),
+        "finished" => task.finished_at = Some(parse_timestamp(value)?),
+        "outcome" => task.outcome = Some(value.to_string()),
+        _ => return None,
+    }
+    Some(())
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn format_timestamp(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The attribute block for `task`, or an empty string when it has none.
+fn format_attributes(task: &HeartbeatTask) -> String {
+    let mut attrs: Vec<(&str, String)> = Vec::new();
+    if task.priority != 0 {
+        attrs.push(("priority", task.priority.to_string()));
+    }
+    if let Some(not_before) = &task.not_before {
+        attrs.push(("not_before", format_timestamp(not_before)));
+    }
+    if let Some(due) = &task.due {
+        attrs.push(("due", format_timestamp(due)));
+    }
+    if let Some(every) = task.every {
+        attrs.push(("every", format_interval(every)));
+    }
+    if !task.depends_on.is_empty() {
+        attrs.push(("depends_on", task.depends_on.join(",")));
+    }
+    if let Some(lease) = &task.lease_until {
+        attrs.push(("lease", format_timestamp(lease)));
+    }
+    if task.attempts > 0 {
+        attrs.push(("attempts", task.attempts.to_string()));
+    }
+    if let Some(max) = task.max_attempts {
+        attrs.push(("max_attempts", max.to_string()));
+    }
+    if let Some(finished) = &task.finished_at {
+        attrs.push(("finished", format_timestamp(finished)));
+    }
+    if let Some(outcome) = &task.outcome {
+        attrs.push(("outcome", outcome.clone()));
+    }
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = attrs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, quote_value(&value)))
+        .collect();
+    format!(" {{{}}}", pairs.join(" "))
+}
+
+fn quote_value(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\\' | '{' | '}'));
+    if plain {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
 }
 
 /// Serialize a ledger back to markdown.
@@ -159,8 +732,11 @@ pub fn serialize(ledger: &HeartbeatLedger) -> String {
 
     for task in &ledger.tasks {
         out.push_str(&format!(
-            "- [{}] {}: {}\n",
-            task.status, task.id, task.title
+            "- [{}] {}: {}{}\n",
+            task.status,
+            task.id,
+            task.title,
+            format_attributes(task)
         ));
         if let Some(ref desc) = task.description {
             for desc_line in desc.lines() {
@@ -178,11 +754,37 @@ pub fn serialize(ledger: &HeartbeatLedger) -> String {
 /// If the file exists but is malformed, returns what can be parsed.
 pub fn load(path: &Path) -> Result<HeartbeatLedger> {
     if !path.exists() {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        return atomic_file::with_lock(path, || read_or_create(path));
+    }
+    read_or_create(path)
+}
+
+/// Save a heartbeat ledger to disk.
+///
+/// Prefer [`update`] when the ledger was loaded to be modified, so that concurrent
+/// writers cannot overwrite each other's changes.
+pub fn save(path: &Path, ledger: &HeartbeatLedger) -> Result<()> {
+    atomic_file::with_lock(path, || write(path, ledger))
+}
+
+/// Load the ledger, apply `f`, and save the result, holding the ledger lock throughout.
+///
+/// Nothing is written if `f` fails or leaves the ledger unchanged.
+pub fn update<T>(path: &Path, f: impl FnOnce(&mut HeartbeatLedger) -> Result<T>) -> Result<T> {
+    atomic_file::with_lock(path, || {
+        let mut ledger = read_or_create(path)?;
+        let original = ledger.clone();
+        let result = f(&mut ledger)?;
+        if ledger != original {
+            write(path, &ledger)?;
         }
-        std::fs::write(path, DEFAULT_TEMPLATE)
+        Ok(result)
+    })
+}
+
+fn read_or_create(path: &Path) -> Result<HeartbeatLedger> {
+    if !path.exists() {
+        atomic_file::write(path, DEFAULT_TEMPLATE)
             .with_context(|| format!("Failed to create HEARTBEAT.md at: {}", path.display()))?;
         return Ok(parse(DEFAULT_TEMPLATE));
     }
@@ -192,16 +794,9 @@ pub fn load(path: &Path) -> Result<HeartbeatLedger> {
     Ok(parse(&text))
 }
 
-/// Save a heartbeat ledger to disk.
-pub fn save(path: &Path, ledger: &HeartbeatLedger) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
-    }
-    let text = serialize(ledger);
-    std::fs::write(path, &text)
-        .with_context(|| format!("Failed to write HEARTBEAT.md at: {}", path.display()))?;
-    Ok(())
+fn write(path: &Path, ledger: &HeartbeatLedger) -> Result<()> {
+    atomic_file::write(path, serialize(ledger))
+        .with_context(|| format!("Failed to write HEARTBEAT.md at: {}", path.display()))
 }
 
 #[cfg(test)]
@@ -249,6 +844,14 @@ mod tests {
             &TaskStatus::InProgress,
             &TaskStatus::Failed
         ));
+        assert!(validate_transition(
+            &TaskStatus::InProgress,
+            &TaskStatus::Pending
+        ));
+        assert!(validate_transition(
+            &TaskStatus::Failed,
+            &TaskStatus::Pending
+        ));
     }
 
     #[test]
@@ -265,10 +868,6 @@ mod tests {
             &TaskStatus::Pending,
             &TaskStatus::Pending
         ));
-        assert!(!validate_transition(
-            &TaskStatus::InProgress,
-            &TaskStatus::Pending
-        ));
         assert!(!validate_transition(
             &TaskStatus::InProgress,
             &TaskStatus::InProgress
@@ -283,10 +882,6 @@ mod tests {
         ));
         assert!(!validate_transition(&TaskStatus::Done, &TaskStatus::Done));
         assert!(!validate_transition(&TaskStatus::Done, &TaskStatus::Failed));
-        assert!(!validate_transition(
-            &TaskStatus::Failed,
-            &TaskStatus::Pending
-        ));
         assert!(!validate_transition(
             &TaskStatus::Failed,
             &TaskStatus::InProgress
@@ -384,12 +979,14 @@ mod tests {
                     status: TaskStatus::Pending,
                     title: "First task".to_string(),
                     description: None,
+                    ..Default::default()
                 },
                 HeartbeatTask {
                     id: "task-2".to_string(),
                     status: TaskStatus::InProgress,
                     title: "Second task".to_string(),
                     description: Some("With a description".to_string()),
+                    ..Default::default()
                 },
             ],
         };
@@ -417,12 +1014,14 @@ mod tests {
                     status: TaskStatus::Pending,
                     title: "Z task".to_string(),
                     description: None,
+                    ..Default::default()
                 },
                 HeartbeatTask {
                     id: "a-task".to_string(),
                     status: TaskStatus::Done,
                     title: "A task".to_string(),
                     description: None,
+                    ..Default::default()
                 },
             ],
         };
@@ -433,6 +1032,331 @@ mod tests {
         assert!(z_pos < a_pos);
     }
 
+    fn at(s: &str) -> DateTime<Utc> {
+        parse_timestamp(s).unwrap()
+    }
+
+    #[test]
+    fn test_attributes_roundtrip() {
+        let text = "# Heartbeat Tasks\n\n\
+            - [in-progress] backup: Run backup {lease=2026-03-05T10:00:00Z attempts=2 max_attempts=5}\n\
+            \x20 Nightly database dump\n\
+            - [failed] deploy: Deploy to staging {attempts=3 finished=2026-03-05T09:00:00Z outcome=\"failed after 3 attempts: \\\"ssh\\\" timed out\"}\n";
+        let ledger = parse(text);
+        let backup = &ledger.tasks[0];
+        assert_eq!(backup.title, "Run backup");
+        assert_eq!(backup.lease_until, Some(at("2026-03-05T10:00:00Z")));
+        assert_eq!(backup.attempts, 2);
+        assert_eq!(backup.max_attempts(), 5);
+        assert_eq!(backup.description.as_deref(), Some("Nightly database dump"));
+        let deploy = &ledger.tasks[1];
+        assert_eq!(
+            deploy.outcome.as_deref(),
+            Some("failed after 3 attempts: \"ssh\" timed out")
+        );
+        assert_eq!(deploy.finished_at, Some(at("2026-03-05T09:00:00Z")));
+
+        let serialized = serialize(&ledger);
+        assert_eq!(parse(&serialized), ledger);
+        assert!(serialized.contains(
+            "- [in-progress] backup: Run backup {lease=2026-03-05T10:00:00Z attempts=2 max_attempts=5}\n"
+        ));
+    }
+
+    #[test]
+    fn test_unparsable_attribute_block_stays_in_title() {
+        for title in [
+            "Render {template}",
+            "Check {lease=soon}",
+            "Unknown {color=red}",
+            "Unterminated {outcome=\"oops}",
+        ] {
+            let ledger = parse(&format!("- [pending] t1: {}\n", title));
+            assert_eq!(ledger.tasks[0].title, title);
+            assert_eq!(ledger.tasks[0].attempts, 0);
+            assert_eq!(parse(&serialize(&ledger)), ledger);
+        }
+    }
+
+    #[test]
+    fn test_scheduling_attributes_roundtrip() {
+        let text = "- [pending] report: Weekly report {priority=-2 not_before=2026-03-06T09:00:00Z due=2026-03-06T17:00:00Z every=\"1 week\" depends_on=fetch,summarize}\n";
+        let ledger = parse(text);
+        let task = &ledger.tasks[0];
+        assert_eq!(task.title, "Weekly report");
+        assert_eq!(task.priority, -2);
+        assert_eq!(task.not_before, Some(at("2026-03-06T09:00:00Z")));
+        assert_eq!(task.due, Some(at("2026-03-06T17:00:00Z")));
+        assert_eq!(task.every, Some(chrono::Duration::weeks(1)));
+        assert_eq!(task.depends_on, vec!["fetch", "summarize"]);
+
+        let serialized = serialize(&ledger);
+        assert!(serialized.contains("every=1w depends_on=fetch,summarize}"));
+        assert_eq!(parse(&serialized), ledger);
+        assert_eq!(
+            parse("- [pending] t: T {every=never}\n").tasks[0].title,
+            "T {every=never}"
+        );
+        assert_eq!(
+            parse("- [pending] t: T {depends_on=a,,b}\n").tasks[0].title,
+            "T {depends_on=a,,b}"
+        );
+    }
+
+    #[test]
+    fn test_next_ready_orders_by_priority_then_due() {
+        let now = at("2026-03-05T10:00:00Z");
+        let ledger = parse(
+            "- [done] fetch: Fetch\n\
+             - [pending] plain: Plain\n\
+             - [pending] due-late: Due late {priority=1 due=2026-03-07T00:00:00Z}\n\
+             - [pending] due-soon: Due soon {priority=1 due=2026-03-06T00:00:00Z}\n\
+             - [pending] future: Future {priority=9 not_before=2026-03-05T11:00:00Z}\n\
+             - [pending] blocked: Blocked {priority=9 depends_on=plain}\n\
+             - [pending] unblocked: Unblocked {priority=1 depends_on=fetch}\n",
+        );
+        assert_eq!(
+            ledger.tasks[next_ready(&ledger, now).unwrap()].id,
+            "due-soon"
+        );
+        assert_eq!(
+            ledger.tasks[next_ready(&ledger, at("2026-03-05T11:00:00Z")).unwrap()].id,
+            "future"
+        );
+
+        let mut ledger = ledger;
+        ledger
+            .tasks
+            .retain(|t| t.priority == 0 || t.id == "unblocked");
+        assert_eq!(
+            ledger.tasks[next_ready(&ledger, now).unwrap()].id,
+            "unblocked"
+        );
+        ledger.tasks.retain(|t| t.id == "blocked");
+        assert!(next_ready(&ledger, now).is_none());
+    }
+
+    #[test]
+    fn test_find_dependency_cycle() {
+        let ledger = parse(
+            "- [pending] a: A {depends_on=b}\n\
+             - [pending] b: B {depends_on=c,missing}\n\
+             - [pending] c: C\n",
+        );
+        assert!(find_dependency_cycle(&ledger).is_none());
+
+        let ledger = parse(
+            "- [pending] a: A {depends_on=b}\n\
+             - [pending] b: B {depends_on=c}\n\
+             - [pending] c: C {depends_on=b}\n",
+        );
+        assert_eq!(
+            find_dependency_cycle(&ledger),
+            Some(vec!["b".to_string(), "c".to_string(), "b".to_string()])
+        );
+        let ledger = parse("- [pending] a: A {depends_on=a}\n");
+        assert_eq!(
+            find_dependency_cycle(&ledger),
+            Some(vec!["a".to_string(), "a".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_recurring_task_is_rescheduled() {
+        let now = at("2026-03-05T10:05:00Z");
+        let mut ledger =
+            parse("- [pending] digest: Daily digest {not_before=2026-03-03T09:00:00Z every=1d}\n");
+        ledger.tasks[0].start(now, chrono::Duration::hours(1));
+        ledger.tasks[0].complete(now);
+        let task = &ledger.tasks[0];
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.not_before, Some(at("2026-03-06T09:00:00Z")));
+        assert_eq!(task.attempts, 0);
+        assert_eq!(task.outcome, None);
+        assert_eq!(task.finished_at, Some(now));
+        assert!(!task.is_ready(now, &ledger));
+
+        // Years behind schedule: the next slot is computed, not stepped to.
+        let late = at("2031-03-05T10:05:00Z");
+        ledger.tasks[0].start(late, chrono::Duration::hours(1));
+        ledger.tasks[0].fail_permanently(late, Some("boom"));
+        let task = &ledger.tasks[0];
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.not_before, Some(at("2031-03-06T09:00:00Z")));
+        assert_eq!(task.outcome, None);
+        assert_eq!(task.finished_at, None);
+    }
+
+    #[test]
+    fn test_recurring_task_out_of_range_stays_finished() {
+        let now = at("2026-03-05T10:05:00Z");
+        let mut ledger = parse("- [pending] t: T {every=9000000000000000s}\n");
+        assert!(ledger.tasks[0].every.is_some());
+        ledger.tasks[0].complete(now);
+        assert_eq!(ledger.tasks[0].status, TaskStatus::Done);
+        assert_eq!(ledger.tasks[0].not_before, None);
+
+        let ledger = parse("- [pending] t: T {every=99999999999999w}\n");
+        assert_eq!(ledger.tasks[0].every, None);
+        assert_eq!(ledger.tasks[0].title, "T {every=99999999999999w}");
+    }
+
+    #[test]
+    fn test_recurring_dependency_is_done_until_next_occurrence() {
+        let now = at("2026-03-05T10:05:00Z");
+        let mut ledger = parse(
+            "- [pending] fetch: Fetch {not_before=2026-03-05T09:00:00Z every=1d}\n\
+             - [pending] report: Report {depends_on=fetch}\n",
+        );
+        assert!(!ledger.tasks[1].is_ready(now, &ledger));
+        ledger.tasks[0].start(now, chrono::Duration::hours(1));
+        ledger.tasks[0].complete(now);
+        assert_eq!(ledger.tasks[0].status, TaskStatus::Pending);
+        assert!(ledger.tasks[1].is_ready(now, &ledger));
+        assert!(!ledger.tasks[1].is_ready(at("2026-03-06T09:00:00Z"), &ledger));
+    }
+
+    #[test]
+    fn test_recover_releases_expired_leases() {
+        let now = at("2026-03-05T10:00:00Z");
+        let mut ledger = parse(
+            "- [in-progress] live: Live lease {lease=2026-03-05T10:30:00Z attempts=1}\n\
+             - [in-progress] expired: Expired lease {lease=2026-03-05T09:30:00Z attempts=1}\n\
+             - [in-progress] legacy: Stuck before leases existed\n\
+             - [in-progress] last: Out of attempts {lease=2026-03-05T09:30:00Z attempts=3}\n",
+        );
+
+        let recovery = recover(&mut ledger, now);
+        assert_eq!(recovery.released, vec!["expired", "legacy"]);
+        assert_eq!(recovery.exhausted, vec!["last"]);
+        assert!(recovery.retried.is_empty());
+
+        assert_eq!(ledger.tasks[0].status, TaskStatus::InProgress);
+        assert_eq!(ledger.tasks[1].status, TaskStatus::Pending);
+        assert!(ledger.tasks[1].lease_until.is_none());
+        assert_eq!(ledger.tasks[1].attempts, 1);
+        assert_eq!(ledger.tasks[2].status, TaskStatus::Pending);
+        let last = &ledger.tasks[3];
+        assert_eq!(last.status, TaskStatus::Failed);
+        assert_eq!(
+            last.outcome.as_deref(),
+            Some("failed after 3 attempts: lease expired")
+        );
+        assert_eq!(last.finished_at, Some(now));
+    }
+
+    #[test]
+    fn test_fail_retries_until_limit() {
+        let now = at("2026-03-05T10:00:00Z");
+        let lease = chrono::Duration::hours(1);
+        let mut ledger = parse("- [pending] flaky: Flaky task {max_attempts=2}\n");
+
+        ledger.tasks[0].start(now, lease);
+        assert_eq!(ledger.tasks[0].lease_until, Some(now + lease));
+        ledger.tasks[0].fail(now, Some("timeout"));
+        assert!(ledger.tasks[0].is_retryable());
+        assert!(ledger.tasks[0].outcome.is_none());
+
+        let recovery = recover(&mut ledger, now);
+        assert_eq!(recovery.retried, vec!["flaky"]);
+        assert_eq!(ledger.tasks[0].status, TaskStatus::Pending);
+
+        ledger.tasks[0].start(now, lease);
+        ledger.tasks[0].fail(now, Some("timeout"));
+        assert!(!ledger.tasks[0].is_retryable());
+        assert_eq!(
+            ledger.tasks[0].outcome.as_deref(),
+            Some("failed after 2 attempts: timeout")
+        );
+        assert!(recover(&mut ledger, now).is_empty());
+        assert_eq!(ledger.tasks[0].status, TaskStatus::Failed);
+    }
+
+    #[test]
+    fn test_recover_leaves_legacy_failures_alone() {
+        let mut ledger = parse("- [failed] old: Failed before retries existed\n");
+        assert!(recover(&mut ledger, Utc::now()).is_empty());
+        assert_eq!(ledger.tasks[0].status, TaskStatus::Failed);
+    }
+
+    #[test]
+    fn test_complete_records_outcome() {
+        let now = at("2026-03-05T10:00:00Z");
+        let mut task = HeartbeatTask {
+            id: "t1".to_string(),
+            title: "Task".to_string(),
+            ..Default::default()
+        };
+        task.start(now, chrono::Duration::minutes(5));
+        task.complete(now);
+        assert_eq!(task.status, TaskStatus::Done);
+        assert!(task.lease_until.is_none());
+        assert_eq!(task.outcome.as_deref(), Some("done after 1 attempt"));
+        assert_eq!(task.finished_at, Some(now));
+    }
+
+    #[test]
+    fn test_add_task_generates_unique_ids() {
+        let mut ledger = parse("# Heartbeat Tasks\n\n- [done] check-logs: Check logs\n");
+        let id = add_task(&mut ledger, "  Check logs!  ").unwrap().id.clone();
+        assert_eq!(id, "check-logs-2");
+        let task = add_task(
+            &mut ledger,
+            "Renew the TLS certificate for example.com before it expires",
+        )
+        .unwrap();
+        assert_eq!(task.id, "renew-the-tls-certificate-for-ex");
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(add_task(&mut ledger, "???").unwrap().id, "task");
+        assert!(add_task(&mut ledger, " ").is_err());
+        assert!(add_task(&mut ledger, "two\nlines").is_err());
+        assert_eq!(ledger.tasks.len(), 4);
+    }
+
+    #[test]
+    fn test_resolve_task() {
+        let now = at("2026-03-05T10:00:00Z");
+        let mut ledger = parse(
+            "- [pending] a: A\n\
+             - [in-progress] b: B {attempts=1}\n\
+             - [done] c: C\n",
+        );
+        resolve_task(&mut ledger, "a", TaskStatus::Done, now, None).unwrap();
+        assert_eq!(ledger.tasks[0].outcome.as_deref(), Some("done"));
+        resolve_task(
+            &mut ledger,
+            "b",
+            TaskStatus::Failed,
+            now,
+            Some("not needed"),
+        )
+        .unwrap();
+        assert_eq!(ledger.tasks[1].status, TaskStatus::Failed);
+        assert_eq!(
+            ledger.tasks[1].outcome.as_deref(),
+            Some("failed after 1 attempt: not needed")
+        );
+        assert!(recover(&mut ledger, now).is_empty());
+
+        let err = resolve_task(&mut ledger, "c", TaskStatus::Failed, now, None).unwrap_err();
+        assert!(err.to_string().contains("already done"));
+        assert!(resolve_task(&mut ledger, "zzz", TaskStatus::Done, now, None).is_err());
+        assert!(resolve_task(&mut ledger, "a", TaskStatus::Pending, now, None).is_err());
+    }
+
+    #[test]
+    fn test_task_summary() {
+        let ledger = parse(
+            "- [pending] report: Weekly report {priority=2 due=2026-03-06T17:00:00Z every=1w depends_on=fetch attempts=1}\n\
+             - [pending] plain: Plain task\n",
+        );
+        assert_eq!(
+            ledger.tasks[0].summary(),
+            "[pending] report: Weekly report (priority 2, due 2026-03-06T17:00:00Z, every 1w, after fetch, attempt 1/3)"
+        );
+        assert_eq!(ledger.tasks[1].summary(), "[pending] plain: Plain task");
+    }
+
     #[test]
     fn test_load_creates_file_when_missing() {
         let dir = tempfile::tempdir().unwrap();
@@ -469,12 +1393,14 @@ mod tests {
                     status: TaskStatus::Pending,
                     title: "Task A".to_string(),
                     description: Some("Description A".to_string()),
+                    ..Default::default()
                 },
                 HeartbeatTask {
                     id: "task-b".to_string(),
                     status: TaskStatus::Done,
                     title: "Task B".to_string(),
                     description: None,
+                    ..Default::default()
                 },
             ],
         };
@@ -498,4 +1424,38 @@ mod tests {
         let ledger = parse(text);
         assert!(ledger.tasks.is_empty());
     }
+
+    #[test]
+    fn test_concurrent_updates_keep_every_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let path = &path;
+                scope.spawn(move || {
+                    for n in 0..10 {
+                        update(path, |ledger| {
+                            add_task(ledger, &format!("Writer {writer} task {n}"))?;
+                            Ok(())
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(load(&path).unwrap().tasks.len(), 40);
+    }
+
+    #[test]
+    fn test_update_does_not_write_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        std::fs::write(&path, "# Tasks\n\n- [pending] t1: Do something\n").unwrap();
+        let result: Result<()> = update(&path, |ledger| {
+            ledger.tasks.clear();
+            bail!("nope")
+        });
+        assert!(result.is_err());
+        assert_eq!(load(&path).unwrap().tasks.len(), 1);
+    }
 }
@@ -0,0 +1,255 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+const TRANSCRIPTS_FILE: &str = "heartbeat_transcripts.jsonl";
+
+/// Transcripts kept in heartbeat_transcripts.jsonl; older entries are dropped.
+pub(crate) const MAX_TRANSCRIPTS: usize = 200;
+/// Maximum characters kept for a tool call's arguments or output.
+const TOOL_TEXT_CHARS: usize = 1000;
+/// Maximum characters kept for the agent's final response.
+const RESPONSE_CHARS: usize = 4000;
+
+/// One tool call made by the heartbeat agent during a tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub name: String,
+    pub args: String,
+    pub success: bool,
+    /// Truncated tool output on success, error message on failure.
+    pub output: String,
+}
+
+impl ToolCallRecord {
+    pub fn new(name: &str, args: &str, result: &std::result::Result<String, String>) -> Self {
+        let (success, output) = match result {
+            Ok(output) => (true, output),
+            Err(e) => (false, e),
+        };
+        Self {
+            name: name.to_string(),
+            args: truncate(args, TOOL_TEXT_CHARS),
+            success,
+            output: truncate(output, TOOL_TEXT_CHARS),
+        }
+    }
+}
+
+/// Everything that happened in one heartbeat tick that called the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TickTranscript {
+    pub started_at: String,
+    pub finished_at: String,
+    /// Ledger task the tick worked on; `None` for the fallback prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TickTranscript {
+    pub fn new(
+        started_at: DateTime<Utc>,
+        task_id: Option<&str>,
+        prompt: &str,
+        tool_calls: Vec<ToolCallRecord>,
+        reply: &std::result::Result<String, String>,
+    ) -> Self {
+        let (response, error) = match reply {
+            Ok(response) => (Some(truncate(response, RESPONSE_CHARS)), None),
+            Err(e) => (None, Some(e.clone())),
+        };
+        Self {
+            started_at: started_at.to_rfc3339(),
+            finished_at: Utc::now().to_rfc3339(),
+            task_id: task_id.map(str::to_string),
+            prompt: prompt.to_string(),
+            tool_calls,
+            response,
+            error,
+        }
+    }
+}
+
+impl std::fmt::Display for TickTranscript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let subject = self.task_id.as_deref().unwrap_or("(fallback)");
+        let (status, text) = match (&self.response, &self.error) {
+            (_, Some(e)) => ("failed", e.as_str()),
+            (Some(response), None) => ("ok", response.as_str()),
+            (None, None) => ("ok", ""),
+        };
+        let first_line = text.lines().next().unwrap_or_default();
+        let preview: String = first_line.chars().take(80).collect();
+        let ellipsis = if preview.len() < text.trim_end().len() {
+            "…"
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{} {} [{}, {} tool call{}]: {}{}",
+            self.started_at,
+            subject,
+            status,
+            self.tool_calls.len(),
+            if self.tool_calls.len() == 1 { "" } else { "s" },
+            preview,
+            ellipsis
+        )
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Append a transcript to the heartbeat transcript log.
+pub fn record(transcript: &TickTranscript) -> Result<()> {
+    crate::storage::storage()?.record_tick(transcript)
+}
+
+/// Most recent transcripts (oldest first), optionally filtered by task id.
+pub fn load(task_id: Option<&str>, limit: usize) -> Vec<TickTranscript> {
+    crate::storage::storage()
+        .and_then(|s| s.load_ticks(task_id, limit))
+        .unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to load heartbeat transcripts");
+            Vec::new()
+        })
+}
+
+pub(crate) fn record_in(dir: &Path, transcript: &TickTranscript, max_records: usize) -> Result<()> {
+    let path = dir.join(TRANSCRIPTS_FILE);
+    let _lock = crate::atomic_file::lock(&path)?;
+    let mut transcripts = read_log(&path);
+    transcripts.push(transcript.clone());
+    let start = transcripts.len().saturating_sub(max_records);
+
+    let mut content = String::new();
+    for transcript in &transcripts[start..] {
+        content.push_str(
+            &serde_json::to_string(transcript).context("Failed to serialize tick transcript")?,
+        );
+        content.push('\n');
+    }
+    crate::atomic_file::write(&path, content)
+}
+
+pub(crate) fn load_in(dir: &Path, task_id: Option<&str>, limit: usize) -> Vec<TickTranscript> {
+    let mut transcripts: Vec<TickTranscript> = read_log(&dir.join(TRANSCRIPTS_FILE))
+        .into_iter()
+        .filter(|t| task_id.is_none_or(|id| t.task_id.as_deref() == Some(id)))
+        .collect();
+    let start = transcripts.len().saturating_sub(limit);
+    transcripts.drain(..start);
+    transcripts
+}
+
+fn read_log(path: &Path) -> Vec<TickTranscript> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(transcript) => Some(transcript),
+            Err(e) => {
+                warn!(error = %e, "Skipping malformed heartbeat transcript");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(
+        task_id: Option<&str>,
+        reply: std::result::Result<String, String>,
+    ) -> TickTranscript {
+        let calls = vec![ToolCallRecord::new(
+            "heartbeat_write",
+            r#"{"task_id":"a","new_status":"done"}"#,
+            &Ok("Task 'a' updated".to_string()),
+        )];
+        TickTranscript::new(Utc::now(), task_id, "Execute task", calls, &reply)
+    }
+
+    #[test]
+    fn test_record_and_load_transcripts() {
+        let dir = tempfile::tempdir().unwrap();
+        record_in(dir.path(), &transcript(Some("a"), Ok("done".into())), 10).unwrap();
+        record_in(dir.path(), &transcript(None, Ok("all quiet".into())), 10).unwrap();
+        record_in(
+            dir.path(),
+            &transcript(Some("b"), Err("timeout".into())),
+            10,
+        )
+        .unwrap();
+
+        let all = load_in(dir.path(), None, 10);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].tool_calls[0].name, "heartbeat_write");
+        assert_eq!(all[2].error.as_deref(), Some("timeout"));
+        assert!(all[2].response.is_none());
+
+        let only_a = load_in(dir.path(), Some("a"), 10);
+        assert_eq!(only_a.len(), 1);
+        assert_eq!(only_a[0].response.as_deref(), Some("done"));
+
+        let latest = load_in(dir.path(), None, 1);
+        assert_eq!(latest[0].task_id.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_record_drops_oldest_transcripts() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..5 {
+            record_in(dir.path(), &transcript(None, Ok(i.to_string())), 3).unwrap();
+        }
+        let transcripts = load_in(dir.path(), None, usize::MAX);
+        assert_eq!(transcripts.len(), 3);
+        assert_eq!(transcripts[0].response.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_tool_call_record_truncates() {
+        let record = ToolCallRecord::new("read_file", "{}", &Ok("x".repeat(5000)));
+        assert_eq!(record.output.chars().count(), TOOL_TEXT_CHARS + 1);
+        assert!(record.output.ends_with('…'));
+        let failed = ToolCallRecord::new("read_file", "{}", &Err("not found".into()));
+        assert!(!failed.success);
+        assert_eq!(failed.output, "not found");
+    }
+
+    #[test]
+    fn test_transcript_display() {
+        let mut t = transcript(Some("a"), Ok("Checked disk usage\nAll fine".into()));
+        t.started_at = "2026-01-01T00:00:00+00:00".into();
+        assert_eq!(
+            t.to_string(),
+            "2026-01-01T00:00:00+00:00 a [ok, 1 tool call]: Checked disk usage…"
+        );
+        let mut t = transcript(None, Err("boom".into()));
+        t.started_at = "2026-01-01T00:00:00+00:00".into();
+        assert_eq!(
+            t.to_string(),
+            "2026-01-01T00:00:00+00:00 (fallback) [failed, 1 tool call]: boom"
+        );
+    }
+}
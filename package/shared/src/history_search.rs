@@ -0,0 +1,518 @@
+//! Full-text search over the chat history of every session.
+//!
+//! Messages are indexed once, when a session first saves them, so the index keeps messages
+//! that compaction later drops from the history. Messages a session undoes are removed
+//! again, and opening a history indexes the messages it has that are missing, such as those
+//! saved before the index existed. Terms are lowercased runs of letters and
+//! digits; CJK characters are one term each, since those scripts do not separate words with
+//! spaces. A query matches the messages that contain all of its terms.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+const DOCS_FILE: &str = "history_search.jsonl";
+
+/// Hits returned when the caller does not ask for a number.
+pub const DEFAULT_LIMIT: usize = 10;
+/// Characters of context kept before the first matching term in a snippet.
+const SNIPPET_LEAD: usize = 40;
+const SNIPPET_CHARS: usize = 160;
+
+/// A message as stored in the search index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SearchEntry {
+    /// Label of the session that wrote the message, e.g. `tui` or `telegram:42`.
+    pub session: String,
+    pub timestamp: String,
+    pub role: String,
+    pub content: String,
+}
+
+impl SearchEntry {
+    pub fn new(session: &str, timestamp: DateTime<Utc>, role: &str, content: &str) -> Self {
+        Self {
+            session: session.to_string(),
+            timestamp: timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+}
+
+/// A line of the docs file: an indexed message, or the removal of an earlier copy of one.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Record {
+    Added(SearchEntry),
+    Removed { removed: SearchEntry },
+}
+
+/// A matching message, with a snippet around the first matching term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub session: String,
+    pub timestamp: String,
+    pub role: String,
+    pub snippet: String,
+}
+
+impl SearchHit {
+    pub(crate) fn new(entry: SearchEntry, terms: &[String]) -> Self {
+        Self {
+            snippet: snippet(&entry.content, terms),
+            session: entry.session,
+            timestamp: entry.timestamp,
+            role: entry.role,
+        }
+    }
+}
+
+impl std::fmt::Display for SearchHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}: {}",
+            self.timestamp, self.session, self.role, self.snippet
+        )
+    }
+}
+
+/// Add messages to the search index.
+pub fn record(entries: &[SearchEntry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    crate::storage::storage()?.index_messages(entries)
+}
+
+/// Messages containing every term of `query`, newest first, only those of `session` if
+/// given.
+pub fn search(query: &str, session: Option<&str>, limit: usize) -> Result<Vec<SearchHit>> {
+    crate::storage::storage()?.search_history(query, session, limit)
+}
+
+/// One hit per line, newest first, or a note that nothing matched.
+pub fn format_hits(query: &str, hits: &[SearchHit]) -> String {
+    if hits.is_empty() {
+        return format!("No messages match '{query}'.");
+    }
+    let mut lines = vec![format!("Messages matching '{query}':")];
+    lines.extend(hits.iter().map(|hit| format!("• {hit}")));
+    lines.join("\n")
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ac00}'..='\u{d7af}')
+}
+
+/// Terms of `text` with their byte ranges, in order.
+pub(crate) fn terms(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut terms = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if is_cjk(c) {
+            if let Some(s) = start.take() {
+                terms.push((s..i, text[s..i].to_lowercase()));
+            }
+            let end = i + c.len_utf8();
+            terms.push((i..end, text[i..end].to_string()));
+        } else if c.is_alphanumeric() {
+            start.get_or_insert(i);
+        } else if let Some(s) = start.take() {
+            terms.push((s..i, text[s..i].to_lowercase()));
+        }
+    }
+    if let Some(s) = start {
+        terms.push((s..text.len(), text[s..].to_lowercase()));
+    }
+    terms
+}
+
+/// The distinct terms of a query, in order.
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    terms(query)
+        .into_iter()
+        .map(|(_, term)| term)
+        .filter(|term| seen.insert(term.clone()))
+        .collect()
+}
+
+/// The `entries` the index is missing, given how many copies of an entry it holds. An entry
+/// listed twice is missing once if the index holds one copy.
+pub(crate) fn missing(
+    entries: &[SearchEntry],
+    mut indexed: impl FnMut(&SearchEntry) -> usize,
+) -> Vec<SearchEntry> {
+    let mut seen: HashMap<&SearchEntry, usize> = HashMap::new();
+    entries
+        .iter()
+        .filter(|&entry| {
+            let count = seen.entry(entry).or_default();
+            *count += 1;
+            *count > indexed(entry)
+        })
+        .cloned()
+        .collect()
+}
+
+fn key(entry: &SearchEntry) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    entry.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Up to [`SNIPPET_CHARS`] characters of `content` around the first of `terms`, on one line.
+pub(crate) fn snippet(content: &str, terms: &[String]) -> String {
+    let first = self::terms(content)
+        .into_iter()
+        .find(|(_, term)| terms.contains(term))
+        .map_or(0, |(range, _)| range.start);
+    let lead = content[..first]
+        .chars()
+        .count()
+        .saturating_sub(SNIPPET_LEAD);
+    let chars: Vec<char> = content.chars().collect();
+    let end = (lead + SNIPPET_CHARS).min(chars.len());
+
+    let mut snippet = String::new();
+    if lead > 0 {
+        snippet.push('…');
+    }
+    let mut last_space = false;
+    for &c in &chars[lead..end] {
+        let space = c.is_whitespace();
+        if !(space && last_space) {
+            snippet.push(if space { ' ' } else { c });
+        }
+        last_space = space;
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet.trim().to_string()
+}
+
+/// Postings over the docs file, kept in memory and caught up with lines appended since.
+#[derive(Default)]
+pub(crate) struct InvertedIndex {
+    /// Bytes of the docs file already indexed.
+    read_to: u64,
+    /// Byte offset of each indexed entry; entry ids index into this.
+    offsets: Vec<u64>,
+    postings: HashMap<String, Vec<u32>>,
+    /// Entry ids by a hash of the entry, to find the copies of a removed or indexed entry.
+    by_key: HashMap<u64, Vec<u32>>,
+    /// Ids of removed entries, left out of matches.
+    removed: HashSet<u32>,
+}
+
+impl InvertedIndex {
+    fn catch_up(&mut self, path: &Path) -> Result<()> {
+        let Ok(file) = File::open(path) else {
+            *self = Self::default();
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.read_to {
+            *self = Self::default();
+        }
+        if len == self.read_to {
+            return Ok(());
+        }
+
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(self.read_to))?;
+        let mut offset = self.read_to;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            // A line without its newline is still being written; index it next time.
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            match serde_json::from_str::<Record>(&line) {
+                Ok(Record::Added(entry)) => self.add(offset, &entry),
+                Ok(Record::Removed { removed }) => self.remove(&removed),
+                Err(e) => warn!(error = %e, offset, "Skipping malformed search entry"),
+            }
+            offset += read as u64;
+        }
+        self.read_to = offset;
+        Ok(())
+    }
+
+    fn add(&mut self, offset: u64, entry: &SearchEntry) {
+        let id = self.offsets.len() as u32;
+        self.offsets.push(offset);
+        self.by_key.entry(key(entry)).or_default().push(id);
+        let mut seen = HashSet::new();
+        for (_, term) in terms(&entry.content) {
+            if seen.insert(term.clone()) {
+                self.postings.entry(term).or_default().push(id);
+            }
+        }
+    }
+
+    /// Mark the newest copy of `entry` that is still indexed as removed.
+    fn remove(&mut self, entry: &SearchEntry) {
+        let copies = self.by_key.get(&key(entry)).map_or(&[][..], Vec::as_slice);
+        if let Some(&id) = copies.iter().rev().find(|id| !self.removed.contains(id)) {
+            self.removed.insert(id);
+        }
+    }
+
+    /// How many copies of `entry` are indexed and not removed.
+    fn count(&self, entry: &SearchEntry) -> usize {
+        self.by_key.get(&key(entry)).map_or(0, |copies| {
+            copies
+                .iter()
+                .filter(|id| !self.removed.contains(id))
+                .count()
+        })
+    }
+
+    /// Offsets of the newest `limit` entries containing every term.
+    fn matches(&self, terms: &[String], limit: usize) -> Vec<u64> {
+        let mut lists = Vec::with_capacity(terms.len());
+        for term in terms {
+            match self.postings.get(term) {
+                Some(list) => lists.push(list),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|list| list.len());
+        let Some((shortest, rest)) = lists.split_first() else {
+            return Vec::new();
+        };
+        shortest
+            .iter()
+            .rev()
+            .filter(|id| !self.removed.contains(id))
+            .filter(|id| rest.iter().all(|list| list.binary_search(id).is_ok()))
+            .take(limit)
+            .map(|&id| self.offsets[id as usize])
+            .collect()
+    }
+}
+
+pub(crate) fn record_in(dir: &Path, entries: &[SearchEntry]) -> Result<()> {
+    let path = dir.join(DOCS_FILE);
+    let _lock = crate::atomic_file::lock(&path)?;
+    append(&path, entries.iter().cloned().map(Record::Added))
+}
+
+pub(crate) fn remove_in(dir: &Path, entries: &[SearchEntry]) -> Result<()> {
+    let path = dir.join(DOCS_FILE);
+    let _lock = crate::atomic_file::lock(&path)?;
+    let removals = entries
+        .iter()
+        .cloned()
+        .map(|removed| Record::Removed { removed });
+    append(&path, removals)
+}
+
+/// Index the `entries` that `index` is missing, under the docs file's lock so that two
+/// processes do not both add them.
+pub(crate) fn record_missing_in(
+    dir: &Path,
+    index: &Mutex<InvertedIndex>,
+    entries: &[SearchEntry],
+) -> Result<()> {
+    let path = dir.join(DOCS_FILE);
+    let _lock = crate::atomic_file::lock(&path)?;
+    let missing = {
+        let mut index = index.lock().unwrap_or_else(|e| e.into_inner());
+        index.catch_up(&path)?;
+        missing(entries, |entry| index.count(entry))
+    };
+    append(&path, missing.into_iter().map(Record::Added))
+}
+
+fn append(path: &Path, records: impl Iterator<Item = Record>) -> Result<()> {
+    let mut content = String::new();
+    for record in records {
+        content.push_str(&serde_json::to_string(&record).context("Failed to serialize message")?);
+        content.push('\n');
+    }
+    if content.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to append to {}", path.display()))
+}
+
+pub(crate) fn search_in(
+    dir: &Path,
+    index: &Mutex<InvertedIndex>,
+    query: &str,
+    session: Option<&str>,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let path = dir.join(DOCS_FILE);
+    let offsets = {
+        let mut index = index.lock().unwrap_or_else(|e| e.into_inner());
+        index.catch_up(&path)?;
+        // The index does not know sessions: read matches until enough are the session's.
+        index.matches(&terms, if session.is_some() { usize::MAX } else { limit })
+    };
+    if offsets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut reader = BufReader::new(
+        File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?,
+    );
+    let mut hits = Vec::with_capacity(offsets.len().min(limit));
+    let mut line = String::new();
+    for offset in offsets {
+        if hits.len() == limit {
+            break;
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        line.clear();
+        reader.read_line(&mut line)?;
+        let entry: SearchEntry =
+            serde_json::from_str(&line).context("Failed to parse search entry")?;
+        if session.is_none_or(|session| entry.session == session) {
+            hits.push(SearchHit::new(entry, &terms));
+        }
+    }
+    Ok(hits)
+}
+
+/// Every indexed message that was not removed, oldest first.
+#[cfg(feature = "sqlite")]
+pub(crate) fn load_entries_in(dir: &Path) -> Vec<SearchEntry> {
+    let Ok(text) = std::fs::read_to_string(dir.join(DOCS_FILE)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<SearchEntry> = Vec::new();
+    let records = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok());
+    for record in records {
+        match record {
+            Record::Added(entry) => entries.push(entry),
+            Record::Removed { removed } => {
+                if let Some(i) = entries.iter().rposition(|entry| *entry == removed) {
+                    entries.remove(i);
+                }
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_split_words_and_cjk() {
+        let found: Vec<String> = terms("Deploy the API-server 到生产 today!")
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect();
+        assert_eq!(
+            found,
+            vec!["deploy", "the", "api", "server", "到", "生", "产", "today"]
+        );
+        assert_eq!(query_terms("api API Api"), vec!["api"]);
+    }
+
+    #[test]
+    fn test_snippet_centers_on_first_match() {
+        let content = format!("{} needle {}", "hay ".repeat(50), "straw ".repeat(50));
+        let found = snippet(&content, &["needle".to_string()]);
+        assert!(found.starts_with('…'));
+        assert!(found.ends_with('…'));
+        assert!(found.contains("needle"));
+        assert!(found.chars().count() <= SNIPPET_CHARS + 2);
+
+        assert_eq!(snippet("short\n\nline", &[]), "short line");
+    }
+
+    #[test]
+    fn test_index_catches_up_with_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = Mutex::new(InvertedIndex::default());
+        let entry = |content: &str| SearchEntry::new("tui", Utc::now(), "user", content);
+
+        record_in(dir.path(), &[entry("first note about rust")]).unwrap();
+        assert_eq!(
+            search_in(dir.path(), &index, "rust", None, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        record_in(dir.path(), &[entry("second note about Rust")]).unwrap();
+        let hits = search_in(dir.path(), &index, "RUST note", None, 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].snippet.starts_with("second"));
+
+        // An unterminated line is left for the next search.
+        let path = dir.path().join(DOCS_FILE);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"session\":\"tui\"").unwrap();
+        assert_eq!(
+            search_in(dir.path(), &index, "rust", None, 10)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            search_in(dir.path(), &index, "rust", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_format_hits() {
+        assert_eq!(format_hits("x", &[]), "No messages match 'x'.");
+        let hit = SearchHit {
+            session: "tui".to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            role: "user".to_string(),
+            snippet: "about x".to_string(),
+        };
+        assert_eq!(
+            format_hits("x", &[hit]),
+            "Messages matching 'x':\n• 2026-01-01T00:00:00Z tui user: about x"
+        );
+    }
+}
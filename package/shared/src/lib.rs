@@ -1,8 +1,18 @@
+pub mod atomic_file;
 pub mod config;
+pub mod heartbeat_scratchpad;
 pub mod heartbeat_store;
+pub mod heartbeat_transcript;
+pub mod history_search;
 pub mod logging;
 pub mod mcp_store;
 pub mod memory;
+#[cfg(feature = "otlp")]
+pub mod otlp;
 pub mod paths;
 pub mod runtime_index;
+pub mod schedule_output;
 pub mod schedule_store;
+pub mod schedule_time;
+pub mod storage;
+pub mod tool_audit;
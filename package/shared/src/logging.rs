@@ -1,11 +1,30 @@
 use tracing_appender::rolling;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
-pub fn init_logging() {
-    init_logging_to_dir(crate::paths::data_dir().ok());
+/// Keeps trace exporters alive. Dropping it flushes and shuts them down, so hold it until
+/// the process exits.
+#[must_use]
+pub struct LoggingGuard {
+    #[cfg(feature = "otlp")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
 }
 
-pub fn init_logging_to_dir(base_dir: Option<std::path::PathBuf>) {
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.tracer_provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("[logging] Failed to flush OTLP spans: {e}");
+        }
+    }
+}
+
+pub fn init_logging() -> LoggingGuard {
+    init_logging_to_dir(crate::paths::data_dir().ok())
+}
+
+pub fn init_logging_to_dir(base_dir: Option<std::path::PathBuf>) -> LoggingGuard {
     let log_dir = base_dir.and_then(|dir| {
         let logs = dir.join("logs");
         match std::fs::create_dir_all(&logs) {
@@ -21,28 +40,38 @@ pub fn init_logging_to_dir(base_dir: Option<std::path::PathBuf>) {
 
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
 
-    match log_dir {
-        Some(dir) => {
-            let file_appender = rolling::daily(&dir, "hi.log");
-            let file_layer = fmt::layer()
-                .with_writer(file_appender)
-                .with_ansi(false)
-                .with_target(true);
-
-            let stderr_layer = fmt::layer().with_writer(std::io::stderr).with_target(true);
-
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(file_layer)
-                .with(stderr_layer)
-                .init();
-        }
-        None => {
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(fmt::layer().with_writer(std::io::stderr))
-                .init();
+    let file_layer = log_dir.map(|dir| {
+        fmt::layer()
+            .with_writer(rolling::daily(&dir, "hi.log"))
+            .with_ansi(false)
+            .with_target(true)
+    });
+    let stderr_layer = fmt::layer().with_writer(std::io::stderr).with_target(true);
+
+    #[cfg(feature = "otlp")]
+    let tracer_provider = match crate::otlp::provider_from_env() {
+        Some(Ok(provider)) => Some(provider),
+        Some(Err(e)) => {
+            eprintln!("[logging] {e:#}. Continuing without OTLP export.");
+            None
         }
+        None => None,
+    };
+    #[cfg(feature = "otlp")]
+    let otlp_layer = tracer_provider.as_ref().map(crate::otlp::layer);
+    #[cfg(not(feature = "otlp"))]
+    let otlp_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(file_layer)
+        .with(stderr_layer)
+        .with(otlp_layer)
+        .init();
+
+    LoggingGuard {
+        #[cfg(feature = "otlp")]
+        tracer_provider,
     }
 }
 
@@ -5,6 +5,9 @@ impl Default for MemoryConfig {
     fn default() -> Self {
         Self {
             large_release_threshold_bytes: 1_048_576,
+            rss_budget_bytes: None,
+            large_session_bytes: 262_144,
+            check_interval_secs: 30,
         }
     }
 }
@@ -15,10 +18,12 @@ pub fn should_reclaim(config: &MemoryConfig, released_bytes: usize) -> bool {
 
 pub fn evaluate_reclamation(config: &MemoryConfig, released_bytes: usize) {
     if should_reclaim(config, released_bytes) {
+        let trimmed = trim_heap();
         info!(
             released_bytes,
             threshold = config.large_release_threshold_bytes,
             action = "reclaim",
+            trimmed,
             "Memory reclamation"
         );
     } else {
@@ -31,6 +36,39 @@ pub fn evaluate_reclamation(config: &MemoryConfig, released_bytes: usize) {
     }
 }
 
+/// Return freed heap pages to the OS with `malloc_trim(0)`.
+///
+/// Only glibc has `malloc_trim`; on other targets (including musl) this is a no-op.
+/// Returns `true` if any memory was released.
+pub fn trim_heap() -> bool {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        // SAFETY: malloc_trim only walks the allocator's own free lists.
+        unsafe { libc::malloc_trim(0) == 1 }
+    }
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    {
+        false
+    }
+}
+
+/// Resident set size of this process in bytes, or `None` where it cannot be read.
+pub fn resident_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        // statm reports sizes in pages: total, resident, shared, ...
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        // SAFETY: sysconf has no preconditions.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Some(pages * u64::try_from(page_size).ok()?)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -39,6 +77,7 @@ mod tests {
     fn test_should_reclaim_above_threshold() {
         let config = MemoryConfig {
             large_release_threshold_bytes: 1000,
+            ..MemoryConfig::default()
         };
         assert!(should_reclaim(&config, 1000));
         assert!(should_reclaim(&config, 2000));
@@ -48,6 +87,7 @@ mod tests {
     fn test_should_reclaim_below_threshold() {
         let config = MemoryConfig {
             large_release_threshold_bytes: 1000,
+            ..MemoryConfig::default()
         };
         assert!(!should_reclaim(&config, 999));
         assert!(!should_reclaim(&config, 0));
@@ -57,12 +97,27 @@ mod tests {
     fn test_default_config() {
         let config = MemoryConfig::default();
         assert_eq!(config.large_release_threshold_bytes, 1_048_576);
+        assert!(config.rss_budget_bytes.is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_resident_bytes_reads_statm() {
+        let before = resident_bytes().unwrap();
+        assert!(before > 0);
+        // Touch 8 MB so the resident size grows.
+        let block = vec![1u8; 8 * 1024 * 1024];
+        let after = resident_bytes().unwrap();
+        assert!(after >= before + 4 * 1024 * 1024, "{before} -> {after}");
+        drop(block);
+        trim_heap();
     }
 
     #[test]
     fn test_evaluate_reclamation_skip() {
         let config = MemoryConfig {
             large_release_threshold_bytes: 1_000_000,
+            ..MemoryConfig::default()
         };
         evaluate_reclamation(&config, 100);
     }
@@ -71,6 +126,7 @@ mod tests {
     fn test_evaluate_reclamation_reclaim() {
         let config = MemoryConfig {
             large_release_threshold_bytes: 100,
+            ..MemoryConfig::default()
         };
         evaluate_reclamation(&config, 200);
     }
@@ -0,0 +1,138 @@
+//! OTLP trace export, enabled by the `otlp` cargo feature.
+//!
+//! Spans are sent over OTLP/HTTP (protobuf) when `OTEL_EXPORTER_OTLP_ENDPOINT` or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set. The other standard `OTEL_*` variables
+//! (headers, timeout, service name) are honored by the exporter.
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::Subscriber;
+use tracing_subscriber::Layer;
+use tracing_subscriber::registry::LookupSpan;
+
+const ENDPOINT_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+];
+const SERVICE_NAME: &str = "hi";
+
+/// Build a tracer provider from the `OTEL_*` environment, or `None` if no endpoint is set.
+pub fn provider_from_env() -> Option<Result<SdkTracerProvider>> {
+    let configured = ENDPOINT_VARS
+        .iter()
+        .any(|var| std::env::var(var).is_ok_and(|v| !v.trim().is_empty()));
+    configured.then(|| build_provider(None))
+}
+
+/// Build a tracer provider that batches spans to `endpoint`, a full OTLP/HTTP traces URL.
+/// With `None`, the endpoint comes from the environment.
+pub fn build_provider(endpoint: Option<&str>) -> Result<SdkTracerProvider> {
+    let mut builder = SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        builder = builder.with_endpoint(endpoint);
+    }
+    let exporter = builder.build().context("Failed to build OTLP exporter")?;
+
+    let mut resource = Resource::builder();
+    if std::env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.with_service_name(SERVICE_NAME);
+    }
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build())
+}
+
+/// A `tracing` layer that turns spans into OpenTelemetry spans on `provider`.
+pub fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S> + use<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Accept one HTTP request, answer 200, and hand back the raw request bytes.
+    fn collector_stand_in() -> (String, mpsc::Receiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(500)))
+                .unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 8192];
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+                if request_complete(&request) {
+                    break;
+                }
+            }
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\ncontent-type: application/x-protobuf\r\ncontent-length: 0\r\n\r\n",
+            );
+            let _ = tx.send(request);
+        });
+        (endpoint, rx)
+    }
+
+    fn request_complete(request: &[u8]) -> bool {
+        let text = String::from_utf8_lossy(request);
+        let Some(header_end) = text.find("\r\n\r\n") else {
+            return false;
+        };
+        let content_length = text[..header_end]
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        request.len() >= header_end + 4 + content_length
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_spans_are_exported_to_collector() {
+        let (endpoint, rx) = collector_stand_in();
+        let provider = build_provider(Some(&endpoint)).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let turn = tracing::info_span!("agent_turn", session = "tui");
+            turn.in_scope(|| {
+                tracing::info_span!("model_request", provider = "ollama", input_tokens = 12)
+                    .in_scope(|| {});
+            });
+        });
+        provider.force_flush().unwrap();
+
+        let request = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(contains(&request, b"POST /v1/traces"));
+        assert!(contains(&request, b"agent_turn"));
+        assert!(contains(&request, b"model_request"));
+        assert!(contains(&request, b"ollama"));
+        assert!(contains(&request, SERVICE_NAME.as_bytes()));
+        let _ = provider.shutdown();
+    }
+}
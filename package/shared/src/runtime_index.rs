@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -11,6 +11,16 @@ pub struct RuntimeIndex {
     pub schedule_names: Vec<String>,
     #[serde(default)]
     pub last_heartbeat_epoch: Option<u64>,
+    /// Set when the most recent heartbeat tick was skipped; cleared when a tick runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_heartbeat_skip: Option<HeartbeatSkip>,
+}
+
+/// A heartbeat tick that did not call the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeartbeatSkip {
+    pub epoch: u64,
+    pub reason: String,
 }
 
 impl RuntimeIndex {
@@ -39,38 +49,53 @@ impl RuntimeIndex {
     }
 }
 
-fn index_path() -> Result<PathBuf> {
-    let data_dir = crate::paths::data_dir()?;
-    Ok(data_dir.join("runtime_index.json"))
-}
+const INDEX_FILE: &str = "runtime_index.json";
 
 pub fn load() -> RuntimeIndex {
-    let path = match index_path() {
-        Ok(p) => p,
-        Err(_) => return RuntimeIndex::default(),
-    };
+    crate::storage::storage()
+        .and_then(|s| s.load_runtime_index())
+        .unwrap_or_default()
+}
+
+/// Load the index, apply `f`, and save it as one atomic update.
+pub fn update(f: impl FnOnce(&mut RuntimeIndex)) -> Result<()> {
+    let mut f = Some(f);
+    crate::storage::storage()?.update_runtime_index(&mut |index| {
+        if let Some(f) = f.take() {
+            f(index);
+        }
+    })
+}
 
+pub(crate) fn load_in(dir: &Path) -> RuntimeIndex {
+    load_from(&dir.join(INDEX_FILE))
+}
+
+pub(crate) fn update_in(dir: &Path, f: &mut dyn FnMut(&mut RuntimeIndex)) -> Result<()> {
+    let path = dir.join(INDEX_FILE);
+    let _lock = crate::atomic_file::lock(&path)?;
+    let mut index = load_from(&path);
+    f(&mut index);
+    write(&path, &index)
+}
+
+fn load_from(path: &Path) -> RuntimeIndex {
     if !path.exists() {
         return RuntimeIndex::default();
     }
 
-    match std::fs::read_to_string(&path) {
+    match std::fs::read_to_string(path) {
         Ok(text) => serde_json::from_str(&text).unwrap_or_default(),
         Err(_) => RuntimeIndex::default(),
     }
 }
 
-pub fn save(index: &RuntimeIndex) -> Result<()> {
-    let path = index_path()?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
+fn write(path: &Path, index: &RuntimeIndex) -> Result<()> {
     let json = serde_json::to_string_pretty(index)?;
-    std::fs::write(&path, json)?;
-    Ok(())
+    crate::atomic_file::write(path, json)
 }
 
-pub fn refresh_memory_sections(memory_path: &std::path::Path) -> Vec<String> {
+pub fn refresh_memory_sections(memory_path: &Path) -> Vec<String> {
     if !memory_path.exists() {
         return Vec::new();
     }
@@ -166,6 +191,10 @@ mod tests {
             memory_sections: vec!["Notes".to_string()],
             schedule_names: vec!["daily".to_string()],
             last_heartbeat_epoch: Some(123456),
+            last_heartbeat_skip: Some(HeartbeatSkip {
+                epoch: 123789,
+                reason: "no actionable tasks".to_string(),
+            }),
         };
         let json = serde_json::to_string_pretty(&index).unwrap();
         std::fs::write(&path, &json).unwrap();
@@ -175,6 +204,10 @@ mod tests {
         assert_eq!(loaded.memory_sections, vec!["Notes"]);
         assert_eq!(loaded.schedule_names, vec!["daily"]);
         assert_eq!(loaded.last_heartbeat_epoch, Some(123456));
+        assert_eq!(
+            loaded.last_heartbeat_skip.unwrap().reason,
+            "no actionable tasks"
+        );
     }
 
     #[test]
@@ -213,6 +246,7 @@ mod tests {
                 model: None,
                 prompt: "test".to_string(),
                 enabled: true,
+                ..Default::default()
             },
             crate::config::ScheduleTaskConfig {
                 name: "hourly".to_string(),
@@ -220,6 +254,7 @@ mod tests {
                 model: None,
                 prompt: "test".to_string(),
                 enabled: false,
+                ..Default::default()
             },
         ];
         let names = refresh_schedule_names(&schedules);
@@ -0,0 +1,372 @@
+//! Output contracts for scheduled tasks.
+//!
+//! A contract decides whether a schedule response is worth delivering: a sentinel such as
+//! `NO_UPDATE` means "nothing to report", a JSON Schema describes structured output, and a
+//! `deliver_if` condition picks which structured results are sent.
+
+use anyhow::{Result, anyhow, bail};
+use serde_json::Value;
+
+use crate::config::{DeliveryCondition, OutputContract};
+
+/// Decide whether `response` should be delivered.
+///
+/// Returns `Ok(false)` for the sentinel or an unmatched condition, and an error when the
+/// response breaks the contract (not JSON, or not matching the schema).
+pub fn evaluate(contract: &OutputContract, response: &str) -> Result<bool> {
+    if let Some(sentinel) = &contract.sentinel
+        && is_sentinel(response, sentinel)
+    {
+        return Ok(false);
+    }
+    if contract.schema.is_none() && contract.deliver_if.is_none() {
+        return Ok(true);
+    }
+
+    let value = parse_json_response(response)?;
+    if let Some(schema) = &contract.schema {
+        validate(&value, schema, "")
+            .map_err(|e| anyhow!("Response does not match the output schema: {}", e))?;
+    }
+    Ok(contract
+        .deliver_if
+        .as_ref()
+        .is_none_or(|condition| matches_condition(&value, condition)))
+}
+
+/// Extra prompt text telling the model how to answer under `contract`.
+pub fn instructions(contract: &OutputContract) -> Option<String> {
+    let mut lines = Vec::new();
+    if let Some(sentinel) = &contract.sentinel {
+        lines.push(format!(
+            "If there is nothing worth reporting, reply with exactly {} and nothing else.",
+            sentinel
+        ));
+    }
+    if let Some(schema) = &contract.schema {
+        lines.push(format!(
+            "Otherwise reply with only a JSON value, without code fences, that matches this JSON Schema:\n{}",
+            schema
+        ));
+    } else if contract.deliver_if.is_some() {
+        lines.push("Otherwise reply with only a JSON value, without code fences.".to_string());
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Check a contract for mistakes before it is saved.
+pub fn validate_contract(contract: &OutputContract) -> Result<()> {
+    if let Some(sentinel) = &contract.sentinel
+        && sentinel.trim().is_empty()
+    {
+        bail!("Output sentinel must not be empty");
+    }
+    if let Some(schema) = &contract.schema
+        && !schema.is_object()
+    {
+        bail!("Output schema must be a JSON object");
+    }
+    if let Some(condition) = &contract.deliver_if
+        && !condition.pointer.is_empty()
+        && !condition.pointer.starts_with('/')
+    {
+        bail!(
+            "deliver_if.pointer must be a JSON Pointer such as /status, got '{}'",
+            condition.pointer
+        );
+    }
+    Ok(())
+}
+
+/// Sentinel comparison tolerates surrounding whitespace, quotes, backticks, and a final period.
+fn is_sentinel(response: &str, sentinel: &str) -> bool {
+    let trimmed = response
+        .trim()
+        .trim_matches(|c| matches!(c, '`' | '"' | '\'' | '.'))
+        .trim();
+    trimmed == sentinel.trim()
+}
+
+/// Parse a response as JSON, accepting a fenced ```json block.
+fn parse_json_response(response: &str) -> Result<Value> {
+    let trimmed = response.trim();
+    let body = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| inner.trim_start_matches("json").trim())
+        .unwrap_or(trimmed);
+    serde_json::from_str(body).map_err(|e| anyhow!("Response is not valid JSON: {}", e))
+}
+
+fn matches_condition(value: &Value, condition: &DeliveryCondition) -> bool {
+    let found = value.pointer(&condition.pointer);
+    if let Some(expected) = &condition.equals {
+        return found == Some(expected);
+    }
+    if let Some(unexpected) = &condition.not_equals {
+        return found != Some(unexpected);
+    }
+    found.is_some_and(is_truthy)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+/// Validate `value` against the commonly used subset of JSON Schema: `type`, `enum`,
+/// `const`, `properties`, `required`, `additionalProperties: false`, `items`, and numeric,
+/// string, and array bounds. Other keywords are ignored.
+fn validate(value: &Value, schema: &Value, path: &str) -> Result<()> {
+    let Some(schema) = schema.as_object() else {
+        return Ok(());
+    };
+    let at = if path.is_empty() { "/" } else { path };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+            bail!("{} should be {}", at, allowed.join(" or "));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum")
+        && !options.contains(value)
+    {
+        bail!("{} should be one of {}", at, Value::Array(options.clone()));
+    }
+    if let Some(expected) = schema.get("const")
+        && value != expected
+    {
+        bail!("{} should be {}", at, expected);
+    }
+
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+            && n < min
+        {
+            bail!("{} should be at least {}", at, min);
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+            && n > max
+        {
+            bail!("{} should be at most {}", at, max);
+        }
+    }
+    if let Some(s) = value.as_str() {
+        let len = s.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+            && len < min
+        {
+            bail!("{} should have at least {} characters", at, min);
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+            && len > max
+        {
+            bail!("{} should have at most {} characters", at, max);
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        let len = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+            && len < min
+        {
+            bail!("{} should have at least {} items", at, min);
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+            && len > max
+        {
+            bail!("{} should have at most {} items", at, max);
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                validate(item, item_schema, &format!("{}/{}", path, i))?;
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    bail!("{} is missing required property '{}'", at, key);
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(properties) = properties {
+            for (key, property_schema) in properties {
+                if let Some(property) = object.get(key) {
+                    validate(property, property_schema, &format!("{}/{}", path, key))?;
+                }
+            }
+        }
+        if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+            for key in object.keys() {
+                if !properties.is_some_and(|p| p.contains_key(key)) {
+                    bail!("{} has unexpected property '{}'", at, key);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        // Models often write whole numbers as `3.0`, which JSON Schema counts as integers.
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn alert_contract() -> OutputContract {
+        OutputContract {
+            sentinel: Some("NO_UPDATE".to_string()),
+            schema: Some(json!({
+                "type": "object",
+                "properties": {
+                    "severity": {"type": "string", "enum": ["info", "warning", "critical"]},
+                    "message": {"type": "string", "minLength": 1}
+                },
+                "required": ["severity", "message"]
+            })),
+            deliver_if: Some(DeliveryCondition {
+                pointer: "/severity".to_string(),
+                equals: None,
+                not_equals: Some(json!("info")),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_sentinel_suppresses_delivery() {
+        let contract = OutputContract {
+            sentinel: Some("NO_UPDATE".to_string()),
+            ..Default::default()
+        };
+        assert!(!evaluate(&contract, "NO_UPDATE").unwrap());
+        assert!(!evaluate(&contract, "  `NO_UPDATE`.\n").unwrap());
+        assert!(evaluate(&contract, "Disk usage is at 91%").unwrap());
+        assert!(evaluate(&contract, "NO_UPDATE, but disk is at 91%").unwrap());
+    }
+
+    #[test]
+    fn test_schema_and_condition() {
+        let contract = alert_contract();
+        assert!(!evaluate(&contract, "NO_UPDATE").unwrap());
+        assert!(
+            evaluate(
+                &contract,
+                r#"{"severity": "critical", "message": "disk full"}"#
+            )
+            .unwrap()
+        );
+        assert!(
+            !evaluate(
+                &contract,
+                "```json\n{\"severity\": \"info\", \"message\": \"all good\"}\n```"
+            )
+            .unwrap()
+        );
+
+        let err = evaluate(&contract, r#"{"severity": "panic", "message": "x"}"#).unwrap_err();
+        assert!(err.to_string().contains("/severity should be one of"));
+        let err = evaluate(&contract, r#"{"severity": "warning"}"#).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("missing required property 'message'")
+        );
+        let err = evaluate(&contract, "Everything is fine").unwrap_err();
+        assert!(err.to_string().contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_condition_without_schema() {
+        let contract = OutputContract {
+            deliver_if: Some(DeliveryCondition {
+                pointer: "/changed".to_string(),
+                equals: None,
+                not_equals: None,
+            }),
+            ..Default::default()
+        };
+        assert!(evaluate(&contract, r#"{"changed": true}"#).unwrap());
+        assert!(!evaluate(&contract, r#"{"changed": false}"#).unwrap());
+        assert!(!evaluate(&contract, r#"{"other": 1}"#).unwrap());
+    }
+
+    #[test]
+    fn test_validate_nested_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "items": {
+                    "type": "array",
+                    "maxItems": 2,
+                    "items": {"type": "integer", "minimum": 0}
+                }
+            },
+            "additionalProperties": false
+        });
+        assert!(validate(&json!({"items": [1, 2]}), &schema, "").is_ok());
+        assert!(validate(&json!({"items": [1.0, 2]}), &schema, "").is_ok());
+        let err = validate(&json!({"items": [1.5]}), &schema, "").unwrap_err();
+        assert!(err.to_string().contains("integer"));
+        let err = validate(&json!({"items": [1, -2]}), &schema, "").unwrap_err();
+        assert_eq!(err.to_string(), "/items/1 should be at least 0");
+        let err = validate(&json!({"items": [1, 2, 3]}), &schema, "").unwrap_err();
+        assert!(err.to_string().contains("at most 2 items"));
+        let err = validate(&json!({"extra": true}), &schema, "").unwrap_err();
+        assert!(err.to_string().contains("unexpected property 'extra'"));
+    }
+
+    #[test]
+    fn test_instructions_and_contract_validation() {
+        let text = instructions(&alert_contract()).unwrap();
+        assert!(text.contains("exactly NO_UPDATE"));
+        assert!(text.contains("JSON Schema"));
+        assert!(instructions(&OutputContract::default()).is_none());
+
+        assert!(validate_contract(&alert_contract()).is_ok());
+        let bad = OutputContract {
+            schema: Some(json!("object")),
+            ..Default::default()
+        };
+        assert!(validate_contract(&bad).is_err());
+        let bad = OutputContract {
+            deliver_if: Some(DeliveryCondition {
+                pointer: "status".to_string(),
+                equals: None,
+                not_equals: None,
+            }),
+            ..Default::default()
+        };
+        assert!(validate_contract(&bad).is_err());
+    }
+}
@@ -1,61 +1,424 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::Path;
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use crate::atomic_file;
 use crate::config::ScheduleTaskConfig;
+use crate::storage::Storage;
+
+const SCHEDULES_FILE: &str = "schedules.json";
+const ARCHIVE_FILE: &str = "schedules_archive.json";
+const RUNS_FILE: &str = "schedule_runs.jsonl";
+const STATE_FILE: &str = "schedule_state.json";
+
+/// Run records kept in schedule_runs.jsonl; older entries are dropped.
+pub(crate) const MAX_RUN_RECORDS: usize = 1000;
+/// Maximum characters of output or error kept per run record.
+const RUN_SUMMARY_CHARS: usize = 500;
+
+/// A one-shot task that has already fired.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSchedule {
+    #[serde(flatten)]
+    pub task: ScheduleTaskConfig,
+    pub fired_at: String,
+}
 
-fn schedules_path() -> Result<PathBuf> {
-    Ok(crate::paths::data_dir()?.join("schedules.json"))
+/// What caused a schedule to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunTrigger {
+    Cron,
+    OneShot,
+    Manual,
+    CatchUp,
 }
 
-pub fn load(config_schedules: Option<&[ScheduleTaskConfig]>) -> Vec<ScheduleTaskConfig> {
-    match load_from_file() {
-        Ok(schedules) => schedules,
-        Err(_) => config_schedules.unwrap_or_default().to_vec(),
+impl RunTrigger {
+    /// Whether this run corresponds to a scheduled firing (as opposed to a manual one).
+    pub fn is_scheduled(self) -> bool {
+        matches!(self, Self::Cron | Self::CatchUp)
     }
 }
 
-fn load_from_file() -> Result<Vec<ScheduleTaskConfig>> {
-    let path = schedules_path()?;
-    if !path.exists() {
-        anyhow::bail!("schedules.json not found");
+impl std::fmt::Display for RunTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cron => write!(f, "cron"),
+            Self::OneShot => write!(f, "one-shot"),
+            Self::Manual => write!(f, "manual"),
+            Self::CatchUp => write!(f, "catch-up"),
+        }
     }
+}
 
-    let content = std::fs::read_to_string(&path)
-        .with_context(|| format!("Failed to read {}", path.display()))?;
+/// One completed schedule run, appended to schedule_runs.jsonl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    pub name: String,
+    pub trigger: RunTrigger,
+    pub started_at: String,
+    pub finished_at: String,
+    pub success: bool,
+    /// Truncated response on success, error message on failure.
+    pub summary: String,
+    /// Successful run whose output contract withheld delivery.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub suppressed: bool,
+}
 
-    let schedules: Vec<ScheduleTaskConfig> = serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse {}", path.display()))?;
+impl ScheduleRun {
+    pub fn new(
+        name: &str,
+        trigger: RunTrigger,
+        started_at: DateTime<Utc>,
+        result: &Result<String>,
+    ) -> Self {
+        let (success, text) = match result {
+            Ok(output) => (true, output.clone()),
+            Err(e) => (false, e.to_string()),
+        };
+        Self {
+            name: name.to_string(),
+            trigger,
+            started_at: started_at.to_rfc3339(),
+            finished_at: Utc::now().to_rfc3339(),
+            success,
+            summary: text.chars().take(RUN_SUMMARY_CHARS).collect(),
+            suppressed: false,
+        }
+    }
+}
+
+impl std::fmt::Display for ScheduleRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match (self.success, self.suppressed) {
+            (true, false) => "ok",
+            (true, true) => "ok (not delivered)",
+            (false, _) => "failed",
+        };
+        let first_line = self.summary.lines().next().unwrap_or_default();
+        let preview: String = first_line.chars().take(80).collect();
+        let ellipsis = if preview.len() < self.summary.trim_end().len() {
+            "…"
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{} {} [{}] {}: {}{}",
+            self.started_at, self.name, self.trigger, status, preview, ellipsis
+        )
+    }
+}
+
+/// Changes whenever the stored schedules change; see [`Storage::schedules_revision`].
+///
+/// [`Storage::schedules_revision`]: crate::storage::Storage::schedules_revision
+pub fn revision() -> Option<u64> {
+    crate::storage::storage()
+        .and_then(|s| s.schedules_revision())
+        .ok()
+}
 
-    let valid: Vec<ScheduleTaskConfig> = schedules
+/// Stored schedules, or `config_schedules` when none have been saved.
+pub fn load(config_schedules: Option<&[ScheduleTaskConfig]>) -> Vec<ScheduleTaskConfig> {
+    let stored = crate::storage::storage().and_then(|s| s.load_schedules());
+    resolve(stored, config_schedules)
+}
+
+/// Pick stored schedules over the config ones, dropping entries that cannot run.
+pub(crate) fn resolve(
+    stored: Result<Option<Vec<ScheduleTaskConfig>>>,
+    config_schedules: Option<&[ScheduleTaskConfig]>,
+) -> Vec<ScheduleTaskConfig> {
+    let schedules = match stored {
+        Ok(Some(schedules)) => schedules,
+        Ok(None) => return config_schedules.unwrap_or_default().to_vec(),
+        Err(e) => {
+            warn!(error = %e, "Failed to load stored schedules; using config schedules");
+            return config_schedules.unwrap_or_default().to_vec();
+        }
+    };
+    schedules
         .into_iter()
         .filter(|s| {
-            if s.name.is_empty() || s.cron.is_empty() || s.prompt.is_empty() {
+            if !s.has_required_fields() {
                 warn!(
                     name = s.name,
-                    "Skipping schedule with missing required fields (name, cron, or prompt)"
+                    "Skipping schedule with missing required fields (name, cron or at, prompt)"
                 );
                 return false;
             }
             true
         })
-        .collect();
-
-    info!(count = valid.len(), path = %path.display(), "Loaded schedules from file");
-    Ok(valid)
+        .collect()
 }
 
 pub fn save(schedules: &[ScheduleTaskConfig]) -> Result<()> {
-    let path = schedules_path()?;
+    update(None, |stored| {
+        *stored = schedules.to_vec();
+        Some(())
+    })
+    .map(drop)
+}
+
+/// Load schedules as [`load`] does, apply `f`, and save the result if `f` returns `Some`,
+/// as one atomic update. `None` leaves the stored schedules untouched.
+pub fn update<T>(
+    config_schedules: Option<&[ScheduleTaskConfig]>,
+    f: impl FnOnce(&mut Vec<ScheduleTaskConfig>) -> Option<T>,
+) -> Result<Option<T>> {
+    let mut f = Some(f);
+    let mut result = None;
+    crate::storage::storage()?.update_schedules(config_schedules, &mut |schedules| {
+        result = f.take().and_then(|f| f(schedules));
+        result.is_some()
+    })?;
+    Ok(result)
+}
+
+pub(crate) fn load_schedules_in(dir: &Path) -> Result<Option<Vec<ScheduleTaskConfig>>> {
+    let path = dir.join(SCHEDULES_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let schedules: Vec<ScheduleTaskConfig> = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    info!(count = schedules.len(), path = %path.display(), "Loaded schedules from file");
+    Ok(Some(schedules))
+}
+
+/// The modification time of schedules.json in nanoseconds, or `0` if it is missing.
+pub(crate) fn revision_in(dir: &Path) -> u64 {
+    std::fs::metadata(dir.join(SCHEDULES_FILE))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+pub(crate) fn update_schedules_in(
+    dir: &Path,
+    config_schedules: Option<&[ScheduleTaskConfig]>,
+    f: &mut dyn FnMut(&mut Vec<ScheduleTaskConfig>) -> bool,
+) -> Result<()> {
+    let path = dir.join(SCHEDULES_FILE);
+    let _lock = atomic_file::lock(&path)?;
+    let mut schedules = resolve(load_schedules_in(dir), config_schedules);
+    if f(&mut schedules) {
+        write_schedules(&path, &schedules)?;
+    }
+    Ok(())
+}
+
+fn write_schedules(path: &Path, schedules: &[ScheduleTaskConfig]) -> Result<()> {
     let content =
         serde_json::to_string_pretty(schedules).context("Failed to serialize schedules")?;
-    std::fs::write(&path, content)
-        .with_context(|| format!("Failed to write {}", path.display()))?;
+    atomic_file::write(path, content)?;
     info!(count = schedules.len(), path = %path.display(), "Saved schedules to file");
     Ok(())
 }
 
+/// Find a schedule by name (case-insensitive).
+pub fn find<'a>(schedules: &'a [ScheduleTaskConfig], name: &str) -> Option<&'a ScheduleTaskConfig> {
+    schedules.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+/// Set `enabled` on the named schedule in `storage` and save it, falling back to
+/// `config_schedules` as [`update`] does. Returns the updated schedule, or `None` when no
+/// schedule matches.
+///
+/// Resuming a paused schedule records now as its last run, so catch-up does not replay the
+/// firings that fell inside the pause.
+pub fn set_enabled(
+    storage: &dyn Storage,
+    config_schedules: Option<&[ScheduleTaskConfig]>,
+    name: &str,
+    enabled: bool,
+) -> Result<Option<ScheduleTaskConfig>> {
+    let mut updated = None;
+    let mut resumed = false;
+    storage.update_schedules(config_schedules, &mut |schedules| {
+        let Some(schedule) = schedules
+            .iter_mut()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+        else {
+            return false;
+        };
+        resumed = enabled && !schedule.enabled;
+        schedule.enabled = enabled;
+        updated = Some(schedule.clone());
+        true
+    })?;
+    if resumed && let Some(schedule) = &updated {
+        storage.record_last_run(&schedule.name, Utc::now())?;
+    }
+    Ok(updated)
+}
+
+/// Append a run record to the run history.
+pub fn record_run(run: &ScheduleRun) -> Result<()> {
+    crate::storage::storage()?.record_run(run)
+}
+
+/// Most recent runs (oldest first), optionally filtered by schedule name.
+pub fn load_runs(name: Option<&str>, limit: usize) -> Vec<ScheduleRun> {
+    crate::storage::storage()
+        .and_then(|s| s.load_runs(name, limit))
+        .unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to load schedule runs");
+            Vec::new()
+        })
+}
+
+pub(crate) fn record_run_in(dir: &Path, run: &ScheduleRun, max_records: usize) -> Result<()> {
+    let path = dir.join(RUNS_FILE);
+    let _lock = atomic_file::lock(&path)?;
+    let mut runs = read_run_log(&path);
+    runs.push(run.clone());
+    let start = runs.len().saturating_sub(max_records);
+
+    let mut content = String::new();
+    for run in &runs[start..] {
+        content.push_str(&serde_json::to_string(run).context("Failed to serialize run record")?);
+        content.push('\n');
+    }
+    atomic_file::write(&path, content)
+}
+
+pub(crate) fn load_runs_in(dir: &Path, name: Option<&str>, limit: usize) -> Vec<ScheduleRun> {
+    let mut runs: Vec<ScheduleRun> = read_run_log(&dir.join(RUNS_FILE))
+        .into_iter()
+        .filter(|r| name.is_none_or(|n| r.name.eq_ignore_ascii_case(n)))
+        .collect();
+    let start = runs.len().saturating_sub(limit);
+    runs.drain(..start);
+    runs
+}
+
+fn read_run_log(path: &Path) -> Vec<ScheduleRun> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(run) => Some(run),
+            Err(e) => {
+                warn!(error = %e, "Skipping malformed schedule run record");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Last scheduled run time per schedule name, used to work out missed firings.
+pub fn load_last_runs() -> HashMap<String, DateTime<Utc>> {
+    crate::storage::storage()
+        .and_then(|s| s.load_last_runs())
+        .unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to load last schedule runs");
+            HashMap::new()
+        })
+}
+
+/// Persist the time of a scheduled run.
+pub fn record_last_run(name: &str, at: DateTime<Utc>) -> Result<()> {
+    crate::storage::storage()?.record_last_run(name, at)
+}
+
+pub(crate) fn load_last_runs_in(dir: &Path) -> HashMap<String, DateTime<Utc>> {
+    std::fs::read_to_string(dir.join(STATE_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn record_last_run_in(dir: &Path, name: &str, at: DateTime<Utc>) -> Result<()> {
+    let path = dir.join(STATE_FILE);
+    let _lock = atomic_file::lock(&path)?;
+    let mut last_runs = load_last_runs_in(dir);
+    let entry = last_runs.entry(name.to_string()).or_insert(at);
+    if at > *entry {
+        *entry = at;
+    }
+    let content =
+        serde_json::to_string_pretty(&last_runs).context("Failed to serialize schedule state")?;
+    atomic_file::write(&path, content)
+}
+
+/// Remove a fired one-shot task from schedules.json and record it in the archive.
+pub fn archive_one_shot(task: &ScheduleTaskConfig, fired_at: DateTime<Utc>) -> Result<()> {
+    crate::storage::storage()?.archive_one_shot(task, fired_at)
+}
+
+/// Load every archived one-shot task. Missing or unreadable archives are treated as empty.
+pub fn load_archive() -> Vec<ArchivedSchedule> {
+    crate::storage::storage()
+        .and_then(|s| s.load_archive())
+        .unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to load schedule archive");
+            Vec::new()
+        })
+}
+
+/// Whether this one-shot task has already fired (same name and fire time).
+pub fn is_archived(archive: &[ArchivedSchedule], task: &ScheduleTaskConfig) -> bool {
+    archive
+        .iter()
+        .any(|a| a.task.name == task.name && a.task.at == task.at)
+}
+
+pub(crate) fn archive_one_shot_in(
+    dir: &Path,
+    task: &ScheduleTaskConfig,
+    fired_at: DateTime<Utc>,
+) -> Result<()> {
+    let schedules_path = dir.join(SCHEDULES_FILE);
+    let _lock = atomic_file::lock(&schedules_path)?;
+    if schedules_path.exists() {
+        let content = std::fs::read_to_string(&schedules_path)
+            .with_context(|| format!("Failed to read {}", schedules_path.display()))?;
+        let mut schedules: Vec<ScheduleTaskConfig> = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", schedules_path.display()))?;
+        let before = schedules.len();
+        schedules.retain(|s| !(s.name == task.name && s.at == task.at));
+        if schedules.len() != before {
+            let content = serde_json::to_string_pretty(&schedules)
+                .context("Failed to serialize schedules")?;
+            atomic_file::write(&schedules_path, content)?;
+        }
+    }
+
+    let archive_path = dir.join(ARCHIVE_FILE);
+    let _archive_lock = atomic_file::lock(&archive_path)?;
+    let mut archive = load_archive_in(dir);
+    archive.push(ArchivedSchedule {
+        task: task.clone(),
+        fired_at: fired_at.to_rfc3339(),
+    });
+    let content =
+        serde_json::to_string_pretty(&archive).context("Failed to serialize schedule archive")?;
+    atomic_file::write(&archive_path, content)?;
+    info!(name = task.name, path = %archive_path.display(), "Archived one-shot schedule");
+    Ok(())
+}
+
+pub(crate) fn load_archive_in(dir: &Path) -> Vec<ArchivedSchedule> {
+    std::fs::read_to_string(dir.join(ARCHIVE_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -68,6 +431,7 @@ mod tests {
             model: None,
             prompt: prompt.to_string(),
             enabled: false,
+            ..Default::default()
         }
     }
 
@@ -121,7 +485,7 @@ mod tests {
             serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
         let valid: Vec<_> = loaded
             .into_iter()
-            .filter(|s| !s.name.is_empty() && !s.cron.is_empty() && !s.prompt.is_empty())
+            .filter(|s| s.has_required_fields())
             .collect();
         assert_eq!(valid.len(), 1);
         assert_eq!(valid[0].name, "valid");
@@ -155,6 +519,7 @@ mod tests {
                 model: Some(crate::config::ModelRef::Named("small".to_string())),
                 prompt: "check status".to_string(),
                 enabled: false,
+                ..Default::default()
             },
             make_schedule("no-model", "0 0 * * *", "daily task"),
         ];
@@ -187,6 +552,7 @@ mod tests {
                 model: None,
                 prompt: "daily task".to_string(),
                 enabled: true,
+                ..Default::default()
             },
             ScheduleTaskConfig {
                 name: "disabled-schedule".to_string(),
@@ -194,6 +560,7 @@ mod tests {
                 model: None,
                 prompt: "noon task".to_string(),
                 enabled: false,
+                ..Default::default()
             },
         ];
 
@@ -230,4 +597,250 @@ mod tests {
         assert_eq!(loaded[0].name, "test");
         assert!(!loaded[0].enabled, "enabled should default to false");
     }
+
+    #[test]
+    fn test_one_shot_without_cron_is_valid() {
+        let json = r#"[
+            {"name": "reminder", "at": "2026-03-05T09:00:00+00:00", "prompt": "call Alice"},
+            {"name": "neither", "prompt": "no trigger"}
+        ]"#;
+        let loaded: Vec<ScheduleTaskConfig> = serde_json::from_str(json).unwrap();
+        assert!(loaded[0].is_one_shot());
+        assert!(loaded[0].has_required_fields());
+        assert!(!loaded[1].has_required_fields());
+    }
+
+    #[test]
+    fn test_archive_one_shot_moves_task_out_of_schedules() {
+        let dir = tempfile::tempdir().unwrap();
+        let reminder = ScheduleTaskConfig {
+            name: "reminder".to_string(),
+            prompt: "call Alice".to_string(),
+            enabled: true,
+            at: Some("2026-03-05T09:00:00+00:00".to_string()),
+            ..Default::default()
+        };
+        let schedules = vec![
+            make_schedule("daily", "0 0 * * *", "summarize"),
+            reminder.clone(),
+        ];
+        std::fs::write(
+            dir.path().join(SCHEDULES_FILE),
+            serde_json::to_string_pretty(&schedules).unwrap(),
+        )
+        .unwrap();
+
+        let fired_at = "2026-03-05T09:00:01Z".parse::<DateTime<Utc>>().unwrap();
+        archive_one_shot_in(dir.path(), &reminder, fired_at).unwrap();
+
+        let remaining: Vec<ScheduleTaskConfig> = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join(SCHEDULES_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "daily");
+
+        let archive = load_archive_in(dir.path());
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive[0].task.name, "reminder");
+        assert!(is_archived(&archive, &reminder));
+
+        let rescheduled = ScheduleTaskConfig {
+            at: Some("2026-03-06T09:00:00+00:00".to_string()),
+            ..reminder
+        };
+        assert!(!is_archived(&archive, &rescheduled));
+    }
+
+    #[test]
+    fn test_archive_without_schedules_file_still_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let reminder = ScheduleTaskConfig {
+            name: "from-config".to_string(),
+            prompt: "ping".to_string(),
+            at: Some("2026-03-05T09:00:00+00:00".to_string()),
+            ..Default::default()
+        };
+        let fired_at = "2026-03-05T09:00:01Z".parse::<DateTime<Utc>>().unwrap();
+        archive_one_shot_in(dir.path(), &reminder, fired_at).unwrap();
+
+        assert!(!dir.path().join(SCHEDULES_FILE).exists());
+        assert_eq!(load_archive_in(dir.path()).len(), 1);
+    }
+
+    #[test]
+    fn test_set_enabled_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = crate::storage::FileStorage::new(dir.path().to_path_buf());
+        let config = vec![
+            make_schedule("daily", "0 0 * * *", "summarize"),
+            make_schedule("hourly", "0 * * * *", "check"),
+        ];
+        let updated = set_enabled(&storage, Some(&config), "Daily", true).unwrap();
+        assert_eq!(updated.unwrap().name, "daily");
+        let schedules = load_schedules_in(dir.path()).unwrap().unwrap();
+        assert!(schedules[0].enabled);
+        assert!(!schedules[1].enabled);
+        assert!(
+            set_enabled(&storage, None, "missing", true)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(find(&schedules, "HOURLY").unwrap().name, "hourly");
+        assert!(find(&schedules, "missing").is_none());
+    }
+
+    #[test]
+    fn test_resume_resets_last_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = crate::storage::FileStorage::new(dir.path().to_path_buf());
+        let mut daily = make_schedule("daily", "0 0 * * *", "summarize");
+        daily.enabled = true;
+        let paused_at = Utc::now() - chrono::Duration::days(4);
+        storage.record_last_run("daily", paused_at).unwrap();
+
+        set_enabled(&storage, Some(&[daily]), "daily", false).unwrap();
+        assert_eq!(load_last_runs_in(dir.path())["daily"], paused_at);
+        set_enabled(&storage, None, "daily", true).unwrap();
+        let resumed_at = load_last_runs_in(dir.path())["daily"];
+        assert!(resumed_at > paused_at);
+
+        // Resuming a schedule that was not paused keeps its last run.
+        set_enabled(&storage, None, "daily", true).unwrap();
+        assert_eq!(load_last_runs_in(dir.path())["daily"], resumed_at);
+    }
+
+    #[test]
+    fn test_record_and_load_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let started = Utc::now();
+        record_run_in(
+            dir.path(),
+            &ScheduleRun::new(
+                "daily",
+                RunTrigger::Cron,
+                started,
+                &Ok("all good".to_string()),
+            ),
+            MAX_RUN_RECORDS,
+        )
+        .unwrap();
+        record_run_in(
+            dir.path(),
+            &ScheduleRun::new(
+                "hourly",
+                RunTrigger::Manual,
+                started,
+                &Err(anyhow::anyhow!("provider down")),
+            ),
+            MAX_RUN_RECORDS,
+        )
+        .unwrap();
+        record_run_in(
+            dir.path(),
+            &ScheduleRun::new("daily", RunTrigger::Manual, started, &Ok("x".repeat(2000))),
+            MAX_RUN_RECORDS,
+        )
+        .unwrap();
+
+        let all = load_runs_in(dir.path(), None, 10);
+        assert_eq!(all.len(), 3);
+        assert!(!all[1].success);
+        assert_eq!(all[1].summary, "provider down");
+
+        let daily = load_runs_in(dir.path(), Some("DAILY"), 10);
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[1].trigger, RunTrigger::Manual);
+        assert_eq!(daily[1].summary.len(), RUN_SUMMARY_CHARS);
+
+        let latest = load_runs_in(dir.path(), None, 1);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].name, "daily");
+    }
+
+    #[test]
+    fn test_run_log_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let started = Utc::now();
+        for i in 0..8 {
+            record_run_in(
+                dir.path(),
+                &ScheduleRun::new("daily", RunTrigger::Cron, started, &Ok(i.to_string())),
+                3,
+            )
+            .unwrap();
+        }
+        let runs = load_runs_in(dir.path(), None, usize::MAX);
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].summary, "5");
+    }
+
+    #[test]
+    fn test_concurrent_run_records_are_all_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let started = Utc::now();
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let dir = dir.path();
+                scope.spawn(move || {
+                    for n in 0..10 {
+                        let run = ScheduleRun::new(
+                            &format!("job-{writer}"),
+                            RunTrigger::Cron,
+                            started,
+                            &Ok(n.to_string()),
+                        );
+                        record_run_in(dir, &run, MAX_RUN_RECORDS).unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(load_runs_in(dir.path(), None, usize::MAX).len(), 40);
+        assert_eq!(
+            load_runs_in(dir.path(), Some("job-2"), usize::MAX).len(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_record_last_run_keeps_latest() {
+        let dir = tempfile::tempdir().unwrap();
+        let early = "2026-03-05T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let late = "2026-03-06T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        record_last_run_in(dir.path(), "daily", late).unwrap();
+        record_last_run_in(dir.path(), "daily", early).unwrap();
+        record_last_run_in(dir.path(), "hourly", early).unwrap();
+
+        let last_runs = load_last_runs_in(dir.path());
+        assert_eq!(last_runs["daily"], late);
+        assert_eq!(last_runs["hourly"], early);
+    }
+
+    #[test]
+    fn test_catch_up_and_overlap_defaults() {
+        use crate::config::{CatchUpPolicy, OverlapPolicy};
+
+        let json = r#"[
+            {"name": "a", "cron": "0 * * * *", "prompt": "p"},
+            {"name": "b", "cron": "0 * * * *", "prompt": "p",
+             "catch_up": "run_all", "catch_up_limit": 3,
+             "max_concurrency": 2, "overlap": "cancel_previous"}
+        ]"#;
+        let loaded: Vec<ScheduleTaskConfig> = serde_json::from_str(json).unwrap();
+        assert_eq!(loaded[0].catch_up, CatchUpPolicy::Skip);
+        assert_eq!(loaded[0].overlap, OverlapPolicy::Skip);
+        assert_eq!(loaded[0].effective_max_concurrency(), 1);
+        assert_eq!(loaded[1].catch_up, CatchUpPolicy::RunAll);
+        assert_eq!(loaded[1].catch_up_limit, Some(3));
+        assert_eq!(loaded[1].overlap, OverlapPolicy::CancelPrevious);
+        assert_eq!(loaded[1].effective_max_concurrency(), 2);
+
+        // Defaults are left out when saving; other policies are written.
+        let saved = serde_json::to_value(&loaded).unwrap();
+        assert!(saved[0].get("catch_up").is_none());
+        assert!(saved[0].get("overlap").is_none());
+        assert_eq!(saved[1]["catch_up"], "run_all");
+        assert_eq!(saved[1]["overlap"], "cancel_previous");
+    }
 }
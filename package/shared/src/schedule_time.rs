@@ -0,0 +1,480 @@
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+/// Hour used when a day is given without a time ("tomorrow", "friday").
+const DEFAULT_HOUR: u32 = 9;
+
+const NAIVE_DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Parse an IANA timezone name, defaulting to UTC when absent or blank.
+pub fn parse_timezone(name: Option<&str>) -> Result<Tz> {
+    match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => name.parse::<Tz>().map_err(|_| {
+            anyhow!(
+                "Unknown timezone '{}' (expected an IANA name such as Europe/Berlin)",
+                name
+            )
+        }),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// Parse a stored one-shot fire time (RFC 3339).
+pub fn parse_at(at: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(at.trim())
+        .map(|dt| dt.with_timezone(&Utc))
+        .with_context(|| format!("Invalid one-shot time '{}': expected RFC 3339", at))
+}
+
+/// Resolve a human time expression to an absolute instant after `now`.
+///
+/// Supported forms:
+/// - RFC 3339 (`2026-03-01T09:00:00+01:00`) or local `YYYY-MM-DD HH:MM`
+/// - relative offsets: `in 2 hours`, `in 1h 30m`, `in an hour`
+/// - day words with optional time: `tomorrow at 9`, `today 18:30`, `friday 9am`, `next monday`
+/// - a bare time (`at 9pm`), which means the next occurrence of that time
+///
+/// Local times are interpreted in `tz`.
+pub fn parse_when(input: &str, now: DateTime<Utc>, tz: Tz) -> Result<DateTime<Utc>> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        bail!("Time expression must not be empty");
+    }
+
+    let resolved = resolve(trimmed, now, tz)?;
+    if resolved <= now {
+        bail!(
+            "Time '{}' resolves to {}, which is in the past",
+            trimmed,
+            resolved.to_rfc3339()
+        );
+    }
+    Ok(resolved)
+}
+
+fn resolve(input: &str, now: DateTime<Utc>, tz: Tz) -> Result<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    for format in NAIVE_DATETIME_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, format) {
+            return localize(naive, tz);
+        }
+    }
+
+    let lower = input.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    if tokens.first() == Some(&"in") {
+        let offset = parse_relative(&tokens[1..])
+            .ok_or_else(|| anyhow!("Could not understand relative time '{}'", input))?;
+        return now
+            .checked_add_signed(offset)
+            .ok_or_else(|| anyhow!("Relative time '{}' is too far in the future", input));
+    }
+
+    resolve_day_and_time(&tokens, now, tz)
+        .ok_or_else(|| anyhow!("Could not understand time '{}'", input))?
+}
+
+/// Parse a positive interval such as `30m`, `2h`, or `1 day`.
+pub fn parse_interval(input: &str) -> Option<Duration> {
+    let lower = input.trim().to_lowercase();
+    let tokens: Vec<&str> = lower.split_whitespace().collect();
+    parse_relative(&tokens)
+}
+
+/// Format an interval in the largest unit that divides it, e.g. `90m` or `2d`.
+pub fn format_interval(interval: Duration) -> String {
+    let secs = interval.num_seconds();
+    for (unit, size) in [("w", 604_800), ("d", 86_400), ("h", 3_600), ("m", 60)] {
+        if secs != 0 && secs % size == 0 {
+            return format!("{}{}", secs / size, unit);
+        }
+    }
+    format!("{}s", secs)
+}
+
+fn localize(naive: NaiveDateTime, tz: Tz) -> Result<DateTime<Utc>> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("Local time {} does not exist in {}", naive, tz))
+}
+
+fn parse_relative(tokens: &[&str]) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut pending: Option<i64> = None;
+
+    for token in tokens.iter().filter(|t| **t != "and") {
+        if let Some(amount) = pending.take() {
+            total = total.checked_add(&unit_duration(token, amount)?)?;
+            continue;
+        }
+        if matches!(*token, "a" | "an") {
+            pending = Some(1);
+            continue;
+        }
+        let split = token
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(token.len());
+        let amount: i64 = token[..split].parse().ok()?;
+        if split == token.len() {
+            pending = Some(amount);
+        } else {
+            total = total.checked_add(&unit_duration(&token[split..], amount)?)?;
+        }
+    }
+
+    if pending.is_some() || total <= Duration::zero() {
+        return None;
+    }
+    Some(total)
+}
+
+/// `None` for an unknown unit, or an amount too large for a `Duration`.
+fn unit_duration(unit: &str, amount: i64) -> Option<Duration> {
+    match unit {
+        "s" | "sec" | "secs" | "second" | "seconds" => Duration::try_seconds(amount),
+        "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(amount),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Duration::try_hours(amount),
+        "d" | "day" | "days" => Duration::try_days(amount),
+        "w" | "week" | "weeks" => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+enum DaySpec {
+    Today,
+    Tomorrow,
+    Date(NaiveDate),
+    Weekday { day: Weekday, skip_today: bool },
+}
+
+fn resolve_day_and_time(
+    tokens: &[&str],
+    now: DateTime<Utc>,
+    tz: Tz,
+) -> Option<Result<DateTime<Utc>>> {
+    let mut day: Option<DaySpec> = None;
+    let mut time: Option<NaiveTime> = None;
+    let mut next = false;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = tokens[i];
+        i += 1;
+        match token {
+            "at" | "on" => continue,
+            "next" => {
+                next = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        if day.is_none()
+            && let Some(spec) = parse_day(token, next)
+        {
+            day = Some(spec);
+            next = false;
+            continue;
+        }
+
+        if time.is_none() {
+            // Allow a detached meridiem: "9 am".
+            let meridiem = tokens.get(i).filter(|t| matches!(**t, "am" | "pm"));
+            let candidate = match meridiem {
+                Some(m) => format!("{token}{m}"),
+                None => token.to_string(),
+            };
+            if let Some(parsed) = parse_time_of_day(&candidate) {
+                time = Some(parsed);
+                if meridiem.is_some() {
+                    i += 1;
+                }
+                continue;
+            }
+        }
+
+        return None;
+    }
+
+    if next || (day.is_none() && time.is_none()) {
+        return None;
+    }
+
+    let today = now.with_timezone(&tz).date_naive();
+    let at_time = time.unwrap_or_else(|| NaiveTime::from_hms_opt(DEFAULT_HOUR, 0, 0).unwrap());
+
+    let date = match day {
+        Some(DaySpec::Today) => today,
+        Some(DaySpec::Tomorrow) => today.succ_opt()?,
+        Some(DaySpec::Date(date)) => date,
+        Some(DaySpec::Weekday { day, skip_today }) => {
+            let mut date = today;
+            if skip_today {
+                date = date.succ_opt()?;
+            }
+            loop {
+                let candidate_is_future = localize(date.and_time(at_time), tz)
+                    .map(|dt| dt > now)
+                    .unwrap_or(false);
+                if date.weekday() == day && (candidate_is_future || date != today) {
+                    break date;
+                }
+                date = date.succ_opt()?;
+            }
+        }
+        None => {
+            let candidate = localize(today.and_time(at_time), tz);
+            match candidate {
+                Ok(dt) if dt > now => today,
+                _ => today.succ_opt()?,
+            }
+        }
+    };
+
+    Some(localize(date.and_time(at_time), tz))
+}
+
+fn parse_day(token: &str, next: bool) -> Option<DaySpec> {
+    match token {
+        "today" | "tonight" if !next => return Some(DaySpec::Today),
+        "tomorrow" if !next => return Some(DaySpec::Tomorrow),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(token, "%Y-%m-%d") {
+        return (!next).then_some(DaySpec::Date(date));
+    }
+    let day = match token {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(DaySpec::Weekday {
+        day,
+        skip_today: next,
+    })
+}
+
+fn parse_time_of_day(token: &str) -> Option<NaiveTime> {
+    match token {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (clock, meridiem) = if let Some(rest) = token.strip_suffix("am") {
+        (rest, Some(false))
+    } else if let Some(rest) = token.strip_suffix("pm") {
+        (rest, Some(true))
+    } else {
+        (token, None)
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) if m.len() == 2 => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None if !clock.is_empty() && clock.len() <= 2 => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match meridiem {
+        Some(pm) => {
+            if !(1..=12).contains(&hour) {
+                return None;
+            }
+            match (hour, pm) {
+                (12, false) => 0,
+                (12, true) => 12,
+                (h, true) => h + 12,
+                (h, false) => h,
+            }
+        }
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        // Wednesday 2026-03-04 10:00 UTC
+        Utc.with_ymd_and_hms(2026, 3, 4, 10, 0, 0).unwrap()
+    }
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_timezone_defaults_to_utc() {
+        assert_eq!(parse_timezone(None).unwrap(), Tz::UTC);
+        assert_eq!(parse_timezone(Some("  ")).unwrap(), Tz::UTC);
+        assert_eq!(
+            parse_timezone(Some("Asia/Taipei")).unwrap(),
+            Tz::Asia__Taipei
+        );
+        assert!(parse_timezone(Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_interval_round_trip() {
+        assert_eq!(parse_interval("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_interval("1 day"), Some(Duration::days(1)));
+        assert_eq!(parse_interval("1h 30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_interval("0m"), None);
+        assert_eq!(parse_interval("soon"), None);
+        for text in ["90m", "2h", "1d", "2w", "45s"] {
+            assert_eq!(format_interval(parse_interval(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn test_relative_offsets() {
+        assert_eq!(
+            parse_when("in 2 hours", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 4, 12, 0)
+        );
+        assert_eq!(
+            parse_when("in 1h 30m", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 4, 11, 30)
+        );
+        assert_eq!(
+            parse_when("in an hour and 15 minutes", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 4, 11, 15)
+        );
+        assert_eq!(
+            parse_when("In 3 days", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 7, 10, 0)
+        );
+        assert!(parse_when("in 5", now(), Tz::UTC).is_err());
+        assert!(parse_when("in 5 fortnights", now(), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_huge_offsets_are_rejected() {
+        assert!(parse_when("in 99999999999999 weeks", now(), Tz::UTC).is_err());
+        assert!(parse_when("in 9223372036854775807 seconds", now(), Tz::UTC).is_err());
+        assert!(parse_when("in 100000000 weeks", now(), Tz::UTC).is_err());
+        assert_eq!(parse_interval("99999999999999w"), None);
+        assert_eq!(parse_interval("5000000000000000s 5000000000000000s"), None);
+    }
+
+    #[test]
+    fn test_tomorrow_at_nine() {
+        assert_eq!(
+            parse_when("tomorrow at 9", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 5, 9, 0)
+        );
+        assert_eq!(
+            parse_when("tomorrow 9:30pm", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 5, 21, 30)
+        );
+        assert_eq!(
+            parse_when("9 am tomorrow", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 5, 9, 0)
+        );
+        assert_eq!(
+            parse_when("tomorrow", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 5, 9, 0)
+        );
+    }
+
+    #[test]
+    fn test_local_time_uses_timezone() {
+        // 09:00 in Taipei (UTC+8) is 01:00 UTC.
+        let tz = parse_timezone(Some("Asia/Taipei")).unwrap();
+        assert_eq!(
+            parse_when("tomorrow at 9", now(), tz).unwrap(),
+            utc(2026, 3, 5, 1, 0)
+        );
+        assert_eq!(
+            parse_when("2026-03-10 08:15", now(), tz).unwrap(),
+            utc(2026, 3, 10, 0, 15)
+        );
+    }
+
+    #[test]
+    fn test_bare_time_rolls_to_next_day_when_past() {
+        assert_eq!(
+            parse_when("at 18:00", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 4, 18, 0)
+        );
+        assert_eq!(
+            parse_when("8am", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 5, 8, 0)
+        );
+        assert_eq!(
+            parse_when("noon", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 4, 12, 0)
+        );
+    }
+
+    #[test]
+    fn test_weekdays() {
+        assert_eq!(
+            parse_when("friday 9am", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 6, 9, 0)
+        );
+        // Same weekday later today stays today; "next" skips today.
+        assert_eq!(
+            parse_when("wednesday at 11", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 4, 11, 0)
+        );
+        assert_eq!(
+            parse_when("next wednesday at 11", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 11, 11, 0)
+        );
+        assert_eq!(
+            parse_when("wed 9", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 11, 9, 0)
+        );
+    }
+
+    #[test]
+    fn test_absolute_and_past() {
+        assert_eq!(
+            parse_when("2026-03-05T09:00:00+02:00", now(), Tz::UTC).unwrap(),
+            utc(2026, 3, 5, 7, 0)
+        );
+        let err = parse_when("today at 8", now(), Tz::UTC).unwrap_err();
+        assert!(err.to_string().contains("in the past"));
+        assert!(parse_when("2020-01-01 00:00", now(), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(parse_when("", now(), Tz::UTC).is_err());
+        assert!(parse_when("whenever you like", now(), Tz::UTC).is_err());
+        assert!(parse_when("tomorrow at 25", now(), Tz::UTC).is_err());
+        assert!(parse_when("next", now(), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_parse_at_roundtrip() {
+        let dt = utc(2026, 3, 5, 9, 0);
+        assert_eq!(parse_at(&dt.to_rfc3339()).unwrap(), dt);
+        assert!(parse_at("tomorrow").is_err());
+    }
+}
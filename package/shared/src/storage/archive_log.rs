@@ -0,0 +1,197 @@
+//! Archive of compacted chat history for the file storage backend.
+//!
+//! Each session has one file, `data_dir/history_archive/<session>.log`, using the record
+//! format of the history segments: a little-endian `u32` length followed by an LZ4 frame
+//! with a content checksum, holding the messages of one compaction. Messages are numbered
+//! from zero in the order they were archived. A record damaged by a crash is cut off by the
+//! next append.
+
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tracing::warn;
+
+use super::segment_log::{encode_frame, read_records};
+use crate::atomic_file;
+
+const ARCHIVE_DIR: &str = "history_archive";
+const ARCHIVE_SUFFIX: &str = ".log";
+
+/// Append `messages` to the archive of `session` and return their numbers.
+pub(super) fn append(data_dir: &Path, session: &str, messages: &[Value]) -> Result<Range<u64>> {
+    let path = archive_path(data_dir, session);
+    let _lock = atomic_file::lock(&path)?;
+    let data = read_file(&path)?;
+    let (records, len) = read_records(&data);
+    let start: u64 = records.iter().map(|r| r.len() as u64).sum();
+    if messages.is_empty() {
+        return Ok(start..start);
+    }
+
+    let frame = encode_frame(messages, true)?;
+    let mut record = Vec::with_capacity(frame.len() + 4);
+    record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    record.extend_from_slice(&frame);
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    if len < data.len() as u64 {
+        warn!(path = %path.display(), offset = len, "Dropping damaged history archive records");
+        file.set_len(len)
+            .with_context(|| format!("Failed to truncate {}", path.display()))?;
+    }
+    file.seek(SeekFrom::Start(len))?;
+    file.write_all(&record)
+        .with_context(|| format!("Failed to append to {}", path.display()))?;
+    file.sync_data()
+        .with_context(|| format!("Failed to sync {}", path.display()))?;
+    Ok(start..start + messages.len() as u64)
+}
+
+/// The archived messages of `session` numbered within `range`.
+pub(super) fn read(data_dir: &Path, session: &str, range: Range<u64>) -> Result<Vec<Value>> {
+    let data = read_file(&archive_path(data_dir, session))?;
+    let (records, _) = read_records(&data);
+    Ok(records
+        .into_iter()
+        .flatten()
+        .skip(range.start as usize)
+        .take(range.end.saturating_sub(range.start) as usize)
+        .collect())
+}
+
+/// Every archived message, by session.
+#[cfg(feature = "sqlite")]
+pub(super) fn load_all(data_dir: &Path) -> Result<Vec<(String, Vec<Value>)>> {
+    let dir = data_dir.join(ARCHIVE_DIR);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to list {}", dir.display())),
+    };
+    let mut archives = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        let Some(session) = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(ARCHIVE_SUFFIX))
+            .and_then(decode_session)
+        else {
+            continue;
+        };
+        let data = read_file(&dir.join(&name))?;
+        let (records, _) = read_records(&data);
+        archives.push((session, records.into_iter().flatten().collect()));
+    }
+    archives.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(archives)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn archive_path(data_dir: &Path, session: &str) -> PathBuf {
+    data_dir
+        .join(ARCHIVE_DIR)
+        .join(format!("{}{ARCHIVE_SUFFIX}", encode_session(session)))
+}
+
+/// Session labels such as `telegram:42` as file names: bytes other than ASCII letters,
+/// digits, `-` and `_` are written as `%XX`.
+pub(super) fn encode_session(session: &str) -> String {
+    let mut name = String::with_capacity(session.len());
+    for byte in session.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    name
+}
+
+#[cfg(any(feature = "sqlite", test))]
+fn decode_session(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(range: Range<usize>) -> Vec<Value> {
+        range
+            .map(|i| json!({ "role": "user", "content": format!("message {i}") }))
+            .collect()
+    }
+
+    #[test]
+    fn test_appends_number_messages_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(append(dir.path(), "tui", &messages(0..3)).unwrap(), 0..3);
+        assert_eq!(append(dir.path(), "tui", &messages(3..5)).unwrap(), 3..5);
+        assert_eq!(
+            append(dir.path(), "telegram:7", &messages(0..2)).unwrap(),
+            0..2
+        );
+
+        assert_eq!(read(dir.path(), "tui", 2..4).unwrap(), messages(2..4));
+        assert_eq!(read(dir.path(), "tui", 4..100).unwrap(), messages(4..5));
+        assert!(read(dir.path(), "missing", 0..10).unwrap().is_empty());
+        assert!(
+            dir.path()
+                .join(ARCHIVE_DIR)
+                .join("telegram%3A7.log")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_damaged_tail_is_replaced_by_next_append() {
+        let dir = tempfile::tempdir().unwrap();
+        append(dir.path(), "tui", &messages(0..2)).unwrap();
+        let path = archive_path(dir.path(), "tui");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[9, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+
+        assert_eq!(append(dir.path(), "tui", &messages(2..3)).unwrap(), 2..3);
+        assert_eq!(read(dir.path(), "tui", 0..10).unwrap(), messages(0..3));
+    }
+
+    #[test]
+    fn test_session_names_roundtrip() {
+        for session in ["tui", "telegram:-100", "a%b/c", "会话"] {
+            assert_eq!(
+                decode_session(&encode_session(session)).as_deref(),
+                Some(session)
+            );
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! History branches for the file storage backend.
+//!
+//! `data_dir/history_branches.json` lists the branches. Each branch keeps the messages
+//! after its fork point in `data_dir/history_branches/<branch>/`, a segmented log like the
+//! main history, with the branch name encoded as the history archive encodes sessions.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use super::HistoryBranch;
+use super::archive_log::encode_session;
+use super::segment_log::SegmentLog;
+use crate::atomic_file;
+
+const BRANCHES_FILE: &str = "history_branches.json";
+const BRANCHES_DIR: &str = "history_branches";
+
+/// Open the log of the messages `branch` stores itself.
+pub(super) fn open(data_dir: &Path, branch: &str) -> Result<(SegmentLog, Vec<Value>)> {
+    SegmentLog::open_dir(data_dir.join(BRANCHES_DIR).join(encode_session(branch)))
+}
+
+pub(super) fn load(data_dir: &Path) -> Result<Vec<HistoryBranch>> {
+    let path = data_dir.join(BRANCHES_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+pub(super) fn update(
+    data_dir: &Path,
+    f: &mut dyn FnMut(&mut Vec<HistoryBranch>) -> bool,
+) -> Result<()> {
+    let path = data_dir.join(BRANCHES_FILE);
+    let _lock = atomic_file::lock(&path)?;
+    let mut branches = load(data_dir)?;
+    if f(&mut branches) {
+        let content = serde_json::to_string_pretty(&branches)
+            .context("Failed to serialize history branches")?;
+        atomic_file::write(&path, content)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::HistoryLog;
+    use chrono::Utc;
+    use serde_json::json;
+
+    #[test]
+    fn test_branches_are_kept_apart_from_the_main_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, _) = open(dir.path(), "telegram:7/draft").unwrap();
+        log.rewrite(&[json!("branch message")]).unwrap();
+        update(dir.path(), &mut |branches| {
+            branches.push(HistoryBranch {
+                name: "telegram:7/draft".to_string(),
+                parent: None,
+                fork_at: 0,
+                created_at: Utc::now(),
+            });
+            true
+        })
+        .unwrap();
+
+        assert!(
+            dir.path()
+                .join(BRANCHES_DIR)
+                .join("telegram%3A7%2Fdraft")
+                .is_dir()
+        );
+        assert_eq!(load(dir.path()).unwrap()[0].name, "telegram:7/draft");
+        assert_eq!(
+            open(dir.path(), "telegram:7/draft").unwrap().1,
+            vec![json!("branch message")]
+        );
+        assert!(SegmentLog::open(dir.path()).unwrap().1.is_empty());
+    }
+}
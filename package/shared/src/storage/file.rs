@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::segment_log::SegmentLog;
+use super::{HistoryBranch, HistoryLog, Storage, archive_log, branch_log};
+use crate::config::ScheduleTaskConfig;
+use crate::heartbeat_transcript::{self, TickTranscript};
+use crate::history_search::{self, InvertedIndex, SearchEntry, SearchHit};
+use crate::runtime_index::{self, RuntimeIndex};
+use crate::schedule_store::{self, ArchivedSchedule, ScheduleRun};
+
+/// One file per store, in a data directory.
+pub struct FileStorage {
+    dir: PathBuf,
+    search_index: Mutex<InvertedIndex>,
+}
+
+impl FileStorage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            search_index: Mutex::default(),
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+}
+
+impl Storage for FileStorage {
+    fn open_history(&self) -> Result<(Box<dyn HistoryLog>, Vec<Value>)> {
+        let (log, messages) = SegmentLog::open(&self.dir)?;
+        Ok((Box::new(log), messages))
+    }
+
+    fn open_branch_history(&self, branch: &str) -> Result<(Box<dyn HistoryLog>, Vec<Value>)> {
+        let (log, messages) = branch_log::open(&self.dir, branch)?;
+        Ok((Box::new(log), messages))
+    }
+
+    fn load_branches(&self) -> Result<Vec<HistoryBranch>> {
+        branch_log::load(&self.dir)
+    }
+
+    fn update_branches(&self, f: &mut dyn FnMut(&mut Vec<HistoryBranch>) -> bool) -> Result<()> {
+        branch_log::update(&self.dir, f)
+    }
+
+    fn index_messages(&self, entries: &[SearchEntry]) -> Result<()> {
+        history_search::record_in(&self.dir, entries)
+    }
+
+    fn unindex_messages(&self, entries: &[SearchEntry]) -> Result<()> {
+        history_search::remove_in(&self.dir, entries)
+    }
+
+    fn index_missing_messages(&self, entries: &[SearchEntry]) -> Result<()> {
+        history_search::record_missing_in(&self.dir, &self.search_index, entries)
+    }
+
+    fn search_history(
+        &self,
+        query: &str,
+        session: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>> {
+        history_search::search_in(&self.dir, &self.search_index, query, session, limit)
+    }
+
+    fn archive_history(&self, session: &str, messages: &[Value]) -> Result<Range<u64>> {
+        archive_log::append(&self.dir, session, messages)
+    }
+
+    fn load_history_archive(&self, session: &str, range: Range<u64>) -> Result<Vec<Value>> {
+        archive_log::read(&self.dir, session, range)
+    }
+
+    fn load_schedules(&self) -> Result<Option<Vec<ScheduleTaskConfig>>> {
+        schedule_store::load_schedules_in(&self.dir)
+    }
+
+    fn update_schedules(
+        &self,
+        fallback: Option<&[ScheduleTaskConfig]>,
+        f: &mut dyn FnMut(&mut Vec<ScheduleTaskConfig>) -> bool,
+    ) -> Result<()> {
+        schedule_store::update_schedules_in(&self.dir, fallback, f)
+    }
+
+    fn archive_one_shot(&self, task: &ScheduleTaskConfig, fired_at: DateTime<Utc>) -> Result<()> {
+        schedule_store::archive_one_shot_in(&self.dir, task, fired_at)
+    }
+
+    fn schedules_revision(&self) -> Result<u64> {
+        Ok(schedule_store::revision_in(&self.dir))
+    }
+
+    fn load_archive(&self) -> Result<Vec<ArchivedSchedule>> {
+        Ok(schedule_store::load_archive_in(&self.dir))
+    }
+
+    fn record_run(&self, run: &ScheduleRun) -> Result<()> {
+        schedule_store::record_run_in(&self.dir, run, schedule_store::MAX_RUN_RECORDS)
+    }
+
+    fn load_runs(&self, name: Option<&str>, limit: usize) -> Result<Vec<ScheduleRun>> {
+        Ok(schedule_store::load_runs_in(&self.dir, name, limit))
+    }
+
+    fn load_last_runs(&self) -> Result<HashMap<String, DateTime<Utc>>> {
+        Ok(schedule_store::load_last_runs_in(&self.dir))
+    }
+
+    fn record_last_run(&self, name: &str, at: DateTime<Utc>) -> Result<()> {
+        schedule_store::record_last_run_in(&self.dir, name, at)
+    }
+
+    fn load_runtime_index(&self) -> Result<RuntimeIndex> {
+        Ok(runtime_index::load_in(&self.dir))
+    }
+
+    fn update_runtime_index(&self, f: &mut dyn FnMut(&mut RuntimeIndex)) -> Result<()> {
+        runtime_index::update_in(&self.dir, f)
+    }
+
+    fn record_tick(&self, transcript: &TickTranscript) -> Result<()> {
+        heartbeat_transcript::record_in(
+            &self.dir,
+            transcript,
+            heartbeat_transcript::MAX_TRANSCRIPTS,
+        )
+    }
+
+    fn load_ticks(&self, task_id: Option<&str>, limit: usize) -> Result<Vec<TickTranscript>> {
+        Ok(heartbeat_transcript::load_in(&self.dir, task_id, limit))
+    }
+}
@@ -0,0 +1,451 @@
+//! Pluggable storage for chat history, schedules, the runtime index and heartbeat ticks.
+//!
+//! [`FileStorage`] keeps one file per store in the data directory and is the default.
+//! With the `sqlite` feature, `SqliteStorage` keeps everything in one database, which
+//! gives transactions and queryable data to every process sharing it. The backend is
+//! chosen once per process by [`init`] from `config.storage`. `schedule_store`,
+//! `runtime_index`, `heartbeat_transcript` and `ChatHistory` all go through [`storage`].
+//!
+//! Messages that compaction removes from a history are kept in a per-session archive, so
+//! the agent can still read them. Branches forked from the history store only the messages
+//! after their fork point.
+//!
+//! The heartbeat ledger, memory file, scratchpad and audit log stay plain files: they are
+//! meant to be read and edited by hand.
+
+mod archive_log;
+mod branch_log;
+mod file;
+mod segment_log;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::config::{ScheduleTaskConfig, StorageBackend, StorageConfig};
+use crate::heartbeat_transcript::TickTranscript;
+use crate::history_search::{SearchEntry, SearchHit};
+use crate::runtime_index::RuntimeIndex;
+use crate::schedule_store::{ArchivedSchedule, ScheduleRun};
+
+pub use file::FileStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+/// An open chat history. Each handle remembers the state it last read or wrote, so that
+/// it notices changes made through another handle.
+pub trait HistoryLog: Send {
+    /// Append messages after the ones stored now. Returns `None` when those are what this
+    /// handle last read or wrote, and otherwise the stored messages as they were before the
+    /// append, including what other handles wrote, so that the caller can catch up.
+    fn append(&mut self, messages: &[Value]) -> Result<Option<Vec<Value>>>;
+
+    /// Replace the stored history with `messages`.
+    fn rewrite(&mut self, messages: &[Value]) -> Result<()>;
+}
+
+/// A named branch of the chat history. Its stored messages follow the first `fork_at`
+/// messages of `parent`, which are not stored again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryBranch {
+    pub name: String,
+    /// Branch this one was forked from, or `None` (with `fork_at` 0) once it stores all of
+    /// its messages. The main history is not listed and has no parent.
+    pub parent: Option<String>,
+    pub fork_at: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+pub trait Storage: Send + Sync {
+    /// Open the chat history and return its messages.
+    fn open_history(&self) -> Result<(Box<dyn HistoryLog>, Vec<Value>)>;
+    /// Open the messages a branch stores itself, after its fork point.
+    fn open_branch_history(&self, branch: &str) -> Result<(Box<dyn HistoryLog>, Vec<Value>)>;
+    /// Branches of the chat history, oldest first.
+    fn load_branches(&self) -> Result<Vec<HistoryBranch>>;
+    /// Load the branches, apply `f`, and save the result if `f` returns true, as one
+    /// atomic update.
+    fn update_branches(&self, f: &mut dyn FnMut(&mut Vec<HistoryBranch>) -> bool) -> Result<()>;
+    /// Add messages to the history search index.
+    fn index_messages(&self, entries: &[SearchEntry]) -> Result<()>;
+    /// Remove the newest indexed copy of each of `entries` from the search index.
+    fn unindex_messages(&self, entries: &[SearchEntry]) -> Result<()>;
+    /// Add the `entries` the search index is missing; an entry listed twice needs two copies.
+    fn index_missing_messages(&self, entries: &[SearchEntry]) -> Result<()>;
+    /// Indexed messages containing every term of `query`, newest first, only those of
+    /// `session` if given.
+    fn search_history(
+        &self,
+        query: &str,
+        session: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>>;
+    /// Append messages removed from the history of `session` to its archive. Returns their
+    /// numbers, which count up from zero per session.
+    fn archive_history(&self, session: &str, messages: &[Value]) -> Result<Range<u64>>;
+    /// The archived messages of `session` numbered within `range`.
+    fn load_history_archive(&self, session: &str, range: Range<u64>) -> Result<Vec<Value>>;
+
+    /// Stored schedules, or `None` when schedules have never been saved.
+    fn load_schedules(&self) -> Result<Option<Vec<ScheduleTaskConfig>>>;
+    /// Load the stored schedules (or `fallback` if there are none), apply `f`, and save the
+    /// result if `f` returns true, as one atomic update.
+    fn update_schedules(
+        &self,
+        fallback: Option<&[ScheduleTaskConfig]>,
+        f: &mut dyn FnMut(&mut Vec<ScheduleTaskConfig>) -> bool,
+    ) -> Result<()>;
+    /// Remove a fired one-shot task from the schedules and add it to the archive.
+    fn archive_one_shot(&self, task: &ScheduleTaskConfig, fired_at: DateTime<Utc>) -> Result<()>;
+    /// A value that changes whenever the stored schedules change. Only compare it for
+    /// equality; `0` means no schedules have been saved.
+    fn schedules_revision(&self) -> Result<u64>;
+    fn load_archive(&self) -> Result<Vec<ArchivedSchedule>>;
+
+    /// Append a run record, dropping the oldest beyond the retention limit.
+    fn record_run(&self, run: &ScheduleRun) -> Result<()>;
+    /// Most recent runs (oldest first), optionally filtered by schedule name.
+    fn load_runs(&self, name: Option<&str>, limit: usize) -> Result<Vec<ScheduleRun>>;
+    fn load_last_runs(&self) -> Result<HashMap<String, DateTime<Utc>>>;
+    /// Record a scheduled run time, keeping the latest per schedule.
+    fn record_last_run(&self, name: &str, at: DateTime<Utc>) -> Result<()>;
+
+    fn load_runtime_index(&self) -> Result<RuntimeIndex>;
+    fn update_runtime_index(&self, f: &mut dyn FnMut(&mut RuntimeIndex)) -> Result<()>;
+
+    /// Append a heartbeat tick, dropping the oldest beyond the retention limit.
+    fn record_tick(&self, transcript: &TickTranscript) -> Result<()>;
+    /// Most recent ticks (oldest first), optionally filtered by task id.
+    fn load_ticks(&self, task_id: Option<&str>, limit: usize) -> Result<Vec<TickTranscript>>;
+}
+
+static STORAGE: OnceLock<Arc<dyn Storage>> = OnceLock::new();
+
+/// Open the configured backend and make it the process-wide storage.
+///
+/// Call once at startup, before anything is loaded. Later calls keep the first backend.
+pub fn init(config: Option<&StorageConfig>) -> Result<()> {
+    let storage = open(config)?;
+    if STORAGE.set(Arc::from(storage)).is_err() {
+        warn!("Storage backend already initialized; keeping the first one");
+    }
+    Ok(())
+}
+
+/// The process-wide storage; the file backend in the data directory unless [`init`] chose
+/// another.
+pub fn storage() -> Result<&'static dyn Storage> {
+    Ok(global()?.as_ref())
+}
+
+/// The process-wide storage as a handle that can be kept, for example by a tool.
+pub fn handle() -> Result<Arc<dyn Storage>> {
+    global().map(Arc::clone)
+}
+
+fn global() -> Result<&'static Arc<dyn Storage>> {
+    if let Some(storage) = STORAGE.get() {
+        return Ok(storage);
+    }
+    let default: Arc<dyn Storage> = Arc::new(FileStorage::new(crate::paths::data_dir()?));
+    Ok(STORAGE.get_or_init(|| default))
+}
+
+/// Open the backend described by `config`.
+pub fn open(config: Option<&StorageConfig>) -> Result<Box<dyn Storage>> {
+    let data_dir = crate::paths::data_dir()?;
+    match config.map(|c| c.backend).unwrap_or_default() {
+        StorageBackend::File => Ok(Box::new(FileStorage::new(data_dir))),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => {
+            let path = config
+                .and_then(|c| c.path.as_deref())
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|| data_dir.join(sqlite::DEFAULT_FILE));
+            Ok(Box::new(SqliteStorage::open_or_import(
+                &path,
+                &FileStorage::new(data_dir),
+            )?))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => {
+            anyhow::bail!(
+                "The sqlite storage backend requires building hi with `--features sqlite`"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::schedule_store::RunTrigger;
+    use serde_json::json;
+
+    fn schedule(name: &str) -> ScheduleTaskConfig {
+        ScheduleTaskConfig {
+            name: name.to_string(),
+            cron: "0 0 * * *".to_string(),
+            prompt: "summarize".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn one_shot(name: &str) -> ScheduleTaskConfig {
+        ScheduleTaskConfig {
+            name: name.to_string(),
+            at: Some("2026-01-01T09:00:00Z".to_string()),
+            prompt: "remind".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn tick(task_id: &str) -> TickTranscript {
+        TickTranscript::new(
+            Utc::now(),
+            Some(task_id),
+            "prompt",
+            Vec::new(),
+            &Ok("done".to_string()),
+        )
+    }
+
+    /// Behaviour every backend must share.
+    pub(crate) fn exercise(storage: &dyn Storage) {
+        let (mut first, messages) = storage.open_history().unwrap();
+        assert!(messages.is_empty());
+        let hello = json!({ "role": "user", "content": "hello" });
+        let reply = json!({ "role": "assistant", "content": "hi" });
+        first.rewrite(std::slice::from_ref(&hello)).unwrap();
+        assert_eq!(first.append(std::slice::from_ref(&reply)).unwrap(), None);
+        let (mut second, messages) = storage.open_history().unwrap();
+        assert_eq!(messages, vec![hello.clone(), reply.clone()]);
+        assert_eq!(first.append(std::slice::from_ref(&hello)).unwrap(), None);
+        assert_eq!(
+            second.append(std::slice::from_ref(&reply)).unwrap(),
+            Some(vec![hello.clone(), reply.clone(), hello.clone()])
+        );
+        assert_eq!(
+            first.append(std::slice::from_ref(&reply)).unwrap(),
+            Some(vec![
+                hello.clone(),
+                reply.clone(),
+                hello.clone(),
+                reply.clone()
+            ])
+        );
+        assert_eq!(second.append(&[]).unwrap(), None);
+        second.rewrite(std::slice::from_ref(&reply)).unwrap();
+        assert_eq!(storage.open_history().unwrap().1, vec![reply.clone()]);
+
+        assert!(storage.load_branches().unwrap().is_empty());
+        let branch = HistoryBranch {
+            name: "draft".to_string(),
+            parent: Some("main".to_string()),
+            fork_at: 1,
+            created_at: Utc::now(),
+        };
+        storage
+            .update_branches(&mut |branches| {
+                branches.push(branch.clone());
+                true
+            })
+            .unwrap();
+        assert_eq!(storage.load_branches().unwrap(), vec![branch]);
+        let (mut log, messages) = storage.open_branch_history("draft").unwrap();
+        assert!(messages.is_empty());
+        log.rewrite(std::slice::from_ref(&hello)).unwrap();
+        assert_eq!(log.append(std::slice::from_ref(&reply)).unwrap(), None);
+        assert_eq!(
+            storage.open_branch_history("draft").unwrap().1,
+            vec![hello.clone(), reply.clone()]
+        );
+        assert!(storage.open_branch_history("other").unwrap().1.is_empty());
+        assert_eq!(storage.open_history().unwrap().1, vec![reply]);
+
+        let at = Utc::now();
+        storage
+            .index_messages(&[
+                SearchEntry::new("tui", at, "user", "How do I deploy the API server?"),
+                SearchEntry::new("telegram:7", at, "assistant", "Run the deploy script."),
+                SearchEntry::new("tui", at, "user", "部署服务器"),
+            ])
+            .unwrap();
+        let hits = storage.search_history("DEPLOY", None, 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].session, "telegram:7");
+        assert_eq!(hits[0].snippet, "Run the deploy script.");
+        assert_eq!(
+            storage
+                .search_history("deploy api", None, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(storage.search_history("deploy", None, 1).unwrap().len(), 1);
+        assert_eq!(
+            storage.search_history("服务", None, 10).unwrap()[0].role,
+            "user"
+        );
+        assert!(
+            storage
+                .search_history("kubernetes", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(storage.search_history("  ", None, 10).unwrap().is_empty());
+        let hits = storage.search_history("deploy", Some("tui"), 1).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session, "tui");
+        assert!(
+            storage
+                .search_history("deploy", Some("cli"), 10)
+                .unwrap()
+                .is_empty()
+        );
+
+        let note = SearchEntry::new("tui", at, "user", "a note about caching");
+        storage.index_messages(std::slice::from_ref(&note)).unwrap();
+        storage
+            .index_missing_messages(&[note.clone(), note.clone()])
+            .unwrap();
+        assert_eq!(
+            storage.search_history("caching", None, 10).unwrap().len(),
+            2
+        );
+        storage
+            .unindex_messages(&[note.clone(), note.clone()])
+            .unwrap();
+        assert!(
+            storage
+                .search_history("caching", None, 10)
+                .unwrap()
+                .is_empty()
+        );
+        storage.index_messages(std::slice::from_ref(&note)).unwrap();
+        assert_eq!(
+            storage.search_history("caching", None, 10).unwrap().len(),
+            1
+        );
+        storage
+            .unindex_messages(std::slice::from_ref(&note))
+            .unwrap();
+
+        let old = [
+            hello.clone(),
+            json!({ "role": "assistant", "content": "older" }),
+        ];
+        assert_eq!(storage.archive_history("tui", &old).unwrap(), 0..2);
+        assert_eq!(
+            storage
+                .archive_history("tui", std::slice::from_ref(&hello))
+                .unwrap(),
+            2..3
+        );
+        assert_eq!(storage.archive_history("telegram:7", &old).unwrap(), 0..2);
+        assert_eq!(
+            storage.load_history_archive("tui", 1..3).unwrap(),
+            vec![old[1].clone(), hello.clone()]
+        );
+        assert!(
+            storage
+                .load_history_archive("tui", 3..9)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            storage
+                .load_history_archive("cli", 0..9)
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(storage.load_schedules().unwrap().is_none());
+        assert_eq!(storage.schedules_revision().unwrap(), 0);
+        let fallback = [schedule("daily")];
+        storage
+            .update_schedules(Some(&fallback), &mut |schedules| {
+                assert_eq!(schedules.len(), 1);
+                false
+            })
+            .unwrap();
+        assert!(storage.load_schedules().unwrap().is_none());
+        storage
+            .update_schedules(Some(&fallback), &mut |schedules| {
+                schedules.push(one_shot("once"));
+                true
+            })
+            .unwrap();
+        let stored = storage.load_schedules().unwrap().unwrap();
+        assert_eq!(stored.len(), 2);
+        let revision = storage.schedules_revision().unwrap();
+        assert_ne!(revision, 0);
+
+        storage.archive_one_shot(&stored[1], Utc::now()).unwrap();
+        assert_eq!(storage.load_schedules().unwrap().unwrap().len(), 1);
+        assert_ne!(storage.schedules_revision().unwrap(), revision);
+        let archive = storage.load_archive().unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive[0].task.name, "once");
+
+        for (name, n) in [("daily", 0), ("hourly", 1), ("daily", 2)] {
+            let run = ScheduleRun::new(name, RunTrigger::Cron, Utc::now(), &Ok(n.to_string()));
+            storage.record_run(&run).unwrap();
+        }
+        let runs = storage.load_runs(None, 10).unwrap();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[2].summary, "2");
+        let runs = storage.load_runs(Some("DAILY"), 1).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].summary, "2");
+        assert_eq!(runs[0].trigger, RunTrigger::Cron);
+
+        let earlier = Utc::now() - chrono::Duration::hours(1);
+        let later = Utc::now();
+        storage.record_last_run("daily", later).unwrap();
+        storage.record_last_run("daily", earlier).unwrap();
+        let last_runs = storage.load_last_runs().unwrap();
+        assert_eq!(last_runs["daily"].timestamp(), later.timestamp());
+
+        assert!(
+            storage
+                .load_runtime_index()
+                .unwrap()
+                .schedule_names
+                .is_empty()
+        );
+        storage
+            .update_runtime_index(&mut |index| index.schedule_names = vec!["daily".to_string()])
+            .unwrap();
+        storage
+            .update_runtime_index(&mut |index| index.last_heartbeat_epoch = Some(42))
+            .unwrap();
+        let index = storage.load_runtime_index().unwrap();
+        assert_eq!(index.schedule_names, vec!["daily"]);
+        assert_eq!(index.last_heartbeat_epoch, Some(42));
+
+        for id in ["a", "b", "a"] {
+            storage.record_tick(&tick(id)).unwrap();
+        }
+        assert_eq!(storage.load_ticks(None, 10).unwrap().len(), 3);
+        assert_eq!(storage.load_ticks(Some("a"), 10).unwrap().len(), 2);
+        assert_eq!(
+            storage.load_ticks(None, 1).unwrap()[0].task_id.as_deref(),
+            Some("a")
+        );
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        exercise(&FileStorage::new(dir.path().to_path_buf()));
+    }
+}
@@ -0,0 +1,505 @@
+//! Append-only chat history for the file storage backend.
+//!
+//! History lives in `data_dir/history/` as a checkpoint plus a log of segments:
+//!
+//! - `checkpoint.lz4` is an LZ4 frame holding every message up to the checkpoint and the
+//!   id of the first segment written after it. It is replaced atomically.
+//! - `segment-<id>.log` files hold records appended since. Each record is a little-endian
+//!   `u32` length followed by an LZ4 frame with a content checksum, holding the messages of
+//!   one save. A segment is closed once it reaches `segment_bytes`.
+//!
+//! Loading replays the checkpoint and then every record up to the first one that is
+//! truncated or fails its checksum, which is what a crash during an append leaves behind.
+//! An append adds a record when the log ends cleanly and has not grown past
+//! `checkpoint_bytes`. If another writer changed the log since this handle last saw it,
+//! the append replays the log first and returns what it holds. A damaged tail or a log due
+//! for compaction is replaced by a new checkpoint holding the replayed messages and the
+//! new ones, which also deletes the old segments.
+
+use std::borrow::Cow;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use super::HistoryLog;
+use crate::atomic_file;
+
+const HISTORY_DIR: &str = "history";
+const CHECKPOINT_FILE: &str = "checkpoint.lz4";
+/// Single-file history written before the segmented log; migrated on load.
+const LEGACY_FILE: &str = "history.json.lz4";
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".log";
+/// Segment size at which appends move to a new segment.
+const SEGMENT_BYTES: u64 = 1024 * 1024;
+/// Log size after which the next save writes a checkpoint and drops the segments.
+const CHECKPOINT_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct Checkpoint<'a> {
+    /// First segment whose records come after this checkpoint.
+    next_segment: u64,
+    messages: Cow<'a, [Value]>,
+}
+
+/// The end of the log: the last segment and its length in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tail {
+    segment: u64,
+    len: u64,
+}
+
+pub(crate) struct SegmentLog {
+    dir: PathBuf,
+    /// Where this process last saw the log end, if it has read or written it cleanly.
+    tail: Option<Tail>,
+    segment_bytes: u64,
+    checkpoint_bytes: u64,
+}
+
+impl SegmentLog {
+    /// Open the log under `data_dir` and return the messages it holds.
+    pub(crate) fn open(data_dir: &Path) -> Result<(Self, Vec<Value>)> {
+        let mut log = Self::new(data_dir.join(HISTORY_DIR));
+        let _lock = atomic_file::lock(&log.dir)?;
+        log.migrate_legacy(&data_dir.join(LEGACY_FILE))?;
+        let messages = log.replay()?;
+        Ok((log, messages))
+    }
+
+    /// Open a log kept directly in `dir`, such as a history branch's.
+    pub(crate) fn open_dir(dir: PathBuf) -> Result<(Self, Vec<Value>)> {
+        let mut log = Self::new(dir);
+        let _lock = atomic_file::lock(&log.dir)?;
+        let messages = log.replay()?;
+        Ok((log, messages))
+    }
+
+    fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            tail: None,
+            segment_bytes: SEGMENT_BYTES,
+            checkpoint_bytes: CHECKPOINT_BYTES,
+        }
+    }
+
+    fn migrate_legacy(&mut self, legacy_path: &Path) -> Result<()> {
+        if !legacy_path.exists() {
+            return Ok(());
+        }
+        if !self.dir.join(CHECKPOINT_FILE).exists() {
+            let compressed =
+                std::fs::read(legacy_path).with_context(|| "Failed to read history file")?;
+            let messages: Vec<Value> = decode_frame(&compressed)
+                .with_context(|| format!("Failed to decode {}", legacy_path.display()))?;
+            let segments = self.segments()?;
+            self.checkpoint(&messages, &segments)?;
+            info!(
+                messages = messages.len(),
+                path = %legacy_path.display(),
+                "Migrated history to the segmented log"
+            );
+        }
+        std::fs::remove_file(legacy_path)
+            .with_context(|| format!("Failed to remove {}", legacy_path.display()))
+    }
+
+    fn replay(&mut self) -> Result<Vec<Value>> {
+        let checkpoint_path = self.dir.join(CHECKPOINT_FILE);
+        let (mut messages, next_segment) = if checkpoint_path.exists() {
+            let compressed = std::fs::read(&checkpoint_path)
+                .with_context(|| format!("Failed to read {}", checkpoint_path.display()))?;
+            let checkpoint: Checkpoint = decode_frame(&compressed)
+                .with_context(|| format!("Failed to decode {}", checkpoint_path.display()))?;
+            (checkpoint.messages.into_owned(), checkpoint.next_segment)
+        } else {
+            (Vec::new(), 0)
+        };
+
+        self.tail = None;
+        for segment in self.segments()? {
+            if segment < next_segment {
+                continue;
+            }
+            let path = self.segment_path(segment);
+            let data = std::fs::read(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let (records, len) = read_records(&data);
+            for record in records {
+                messages.extend(record);
+            }
+            self.tail = Some(Tail { segment, len });
+            if len < data.len() as u64 {
+                warn!(
+                    path = %path.display(),
+                    offset = len,
+                    "Ignoring damaged history records; the next save rewrites the history"
+                );
+                break;
+            }
+        }
+        Ok(messages)
+    }
+
+    fn append_record(&mut self, mut tail: Tail, messages: &[Value]) -> Result<()> {
+        if tail.len >= self.segment_bytes {
+            tail = Tail {
+                segment: tail.segment + 1,
+                len: 0,
+            };
+        }
+
+        let frame = encode_frame(messages, true)?;
+        let mut record = Vec::with_capacity(frame.len() + 4);
+        record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        record.extend_from_slice(&frame);
+
+        let path = self.segment_path(tail.segment);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.write_all(&record)
+            .with_context(|| format!("Failed to append to {}", path.display()))?;
+        file.sync_data()
+            .with_context(|| format!("Failed to sync {}", path.display()))?;
+        tail.len += record.len() as u64;
+        self.tail = Some(tail);
+        Ok(())
+    }
+
+    /// Write `messages` as the new checkpoint, start a fresh segment after it and delete
+    /// the segments it covers.
+    fn checkpoint(&mut self, messages: &[Value], segments: &[u64]) -> Result<()> {
+        let next_segment = segments.last().map_or(1, |last| last + 1);
+        // Create the next segment before the checkpoint points at it, so the log always
+        // ends in a segment and ids keep increasing even if we crash in between.
+        let next_path = self.segment_path(next_segment);
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        std::fs::File::create(&next_path)
+            .with_context(|| format!("Failed to create {}", next_path.display()))?;
+
+        let checkpoint = Checkpoint {
+            next_segment,
+            messages: Cow::Borrowed(messages),
+        };
+        atomic_file::write(
+            &self.dir.join(CHECKPOINT_FILE),
+            encode_frame(&checkpoint, false)?,
+        )
+        .with_context(|| "Failed to write history checkpoint")?;
+
+        for &segment in segments {
+            let path = self.segment_path(segment);
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!(path = %path.display(), error = %e, "Failed to remove history segment");
+            }
+        }
+        self.tail = Some(Tail {
+            segment: next_segment,
+            len: 0,
+        });
+        Ok(())
+    }
+
+    /// Segment ids on disk, in order.
+    fn segments(&self) -> Result<Vec<u64>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to list {}", self.dir.display()));
+            }
+        };
+        let mut segments: Vec<u64> = entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                name.to_str()?
+                    .strip_prefix(SEGMENT_PREFIX)?
+                    .strip_suffix(SEGMENT_SUFFIX)?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        segments.sort_unstable();
+        Ok(segments)
+    }
+
+    fn current_tail(&self, segments: &[u64]) -> Result<Option<Tail>> {
+        let Some(&segment) = segments.last() else {
+            return Ok(None);
+        };
+        let len = self.segment_len(segment)?;
+        Ok(Some(Tail { segment, len }))
+    }
+
+    fn log_bytes(&self, segments: &[u64]) -> Result<u64> {
+        segments.iter().map(|&s| self.segment_len(s)).sum()
+    }
+
+    fn segment_len(&self, segment: u64) -> Result<u64> {
+        let path = self.segment_path(segment);
+        Ok(std::fs::metadata(&path)
+            .with_context(|| format!("Failed to stat {}", path.display()))?
+            .len())
+    }
+
+    fn segment_path(&self, segment: u64) -> PathBuf {
+        self.dir
+            .join(format!("{SEGMENT_PREFIX}{segment:08}{SEGMENT_SUFFIX}"))
+    }
+}
+
+impl HistoryLog for SegmentLog {
+    fn append(&mut self, messages: &[Value]) -> Result<Option<Vec<Value>>> {
+        if messages.is_empty() {
+            return Ok(None);
+        }
+        let _lock = atomic_file::lock(&self.dir)?;
+        let segments = self.segments()?;
+        let current = self.current_tail(&segments)?;
+        let appendable = self.log_bytes(&segments)? < self.checkpoint_bytes;
+        if let Some(tail) = self.tail
+            && current == Some(tail)
+            && appendable
+        {
+            self.append_record(tail, messages)?;
+            return Ok(None);
+        }
+
+        let changed = current != self.tail;
+        let mut stored = self.replay()?;
+        match self.tail {
+            Some(tail) if current == Some(tail) && appendable => {
+                self.append_record(tail, messages)?
+            }
+            _ => {
+                let len = stored.len();
+                stored.extend_from_slice(messages);
+                self.checkpoint(&stored, &segments)?;
+                stored.truncate(len);
+            }
+        }
+        Ok(changed.then_some(stored))
+    }
+
+    fn rewrite(&mut self, messages: &[Value]) -> Result<()> {
+        let _lock = atomic_file::lock(&self.dir)?;
+        let segments = self.segments()?;
+        self.checkpoint(messages, &segments)
+    }
+}
+
+/// Parse records from a segment, stopping at the first damaged one. Returns the records
+/// and the length of the intact prefix.
+pub(super) fn read_records(data: &[u8]) -> (Vec<Vec<Value>>, u64) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + 4) {
+        let len = u32::from_le_bytes(header.try_into().unwrap()) as usize;
+        let Some(frame) = data.get(offset + 4..offset + 4 + len) else {
+            break;
+        };
+        let Ok(messages) = decode_frame(frame) else {
+            break;
+        };
+        records.push(messages);
+        offset += 4 + len;
+    }
+    (records, offset as u64)
+}
+
+pub(super) fn encode_frame<T: Serialize + ?Sized>(value: &T, checksum: bool) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(value).with_context(|| "Failed to serialize history")?;
+    let info = lz4_flex::frame::FrameInfo::new().content_checksum(checksum);
+    let mut encoder = lz4_flex::frame::FrameEncoder::with_frame_info(info, Vec::new());
+    encoder
+        .write_all(&json)
+        .with_context(|| "Failed to compress history")?;
+    encoder
+        .finish()
+        .with_context(|| "Failed to finish compression")
+}
+
+fn decode_frame<T: serde::de::DeserializeOwned>(compressed: &[u8]) -> Result<T> {
+    let mut decoder = lz4_flex::frame::FrameDecoder::new(compressed);
+    let mut json = Vec::new();
+    decoder
+        .read_to_end(&mut json)
+        .with_context(|| "Failed to decompress history")?;
+    serde_json::from_slice(&json).with_context(|| "Failed to parse history JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(text: &str) -> Value {
+        json!({ "role": "user", "content": text })
+    }
+
+    fn messages(range: std::ops::Range<usize>) -> Vec<Value> {
+        range.map(|i| message(&format!("message {i}"))).collect()
+    }
+
+    fn log_size(dir: &Path) -> u64 {
+        std::fs::read_dir(dir.join(HISTORY_DIR))
+            .unwrap()
+            .map(|entry| entry.unwrap().metadata().unwrap().len())
+            .sum()
+    }
+
+    #[test]
+    fn test_appends_replay_after_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert!(loaded.is_empty());
+
+        let all = messages(0..6);
+        log.rewrite(&all[..2]).unwrap();
+        assert_eq!(log.append(&all[2..4]).unwrap(), None);
+        assert_eq!(log.append(&all[4..]).unwrap(), None);
+
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, all);
+    }
+
+    #[test]
+    fn test_first_save_writes_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, _) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(log.append(&[]).unwrap(), None);
+        assert_eq!(log.append(&messages(0..1)).unwrap(), None);
+        assert!(dir.path().join(HISTORY_DIR).join(CHECKPOINT_FILE).exists());
+
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, messages(0..1));
+    }
+
+    #[test]
+    fn test_append_cost_does_not_grow_with_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, _) = SegmentLog::open(dir.path()).unwrap();
+        log.rewrite(&messages(0..10)).unwrap();
+
+        let append_reply = |log: &mut SegmentLog| {
+            let before = log_size(dir.path());
+            assert_eq!(log.append(&[message("reply")]).unwrap(), None);
+            log_size(dir.path()) - before
+        };
+        let short = append_reply(&mut log);
+        assert_eq!(log.append(&messages(10..5000)).unwrap(), None);
+        let long = append_reply(&mut log);
+        assert_eq!(short, long);
+    }
+
+    #[test]
+    fn test_rewrite_replaces_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, _) = SegmentLog::open(dir.path()).unwrap();
+        log.rewrite(&messages(0..5)).unwrap();
+        assert_eq!(log.append(&messages(5..10)).unwrap(), None);
+        log.rewrite(&messages(5..10)).unwrap();
+
+        let (log, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, messages(5..10));
+        assert_eq!(log.segments().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_segments_rotate_until_checkpoint_is_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, _) = SegmentLog::open(dir.path()).unwrap();
+        log.segment_bytes = 1;
+        log.checkpoint_bytes = u64::MAX;
+        log.rewrite(&messages(0..1)).unwrap();
+        for i in 1..5 {
+            assert_eq!(log.append(&messages(i..i + 1)).unwrap(), None);
+        }
+        assert_eq!(log.segments().unwrap().len(), 4);
+
+        log.checkpoint_bytes = 1;
+        assert_eq!(log.append(&messages(5..6)).unwrap(), None);
+        assert_eq!(log.segments().unwrap().len(), 1);
+
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, messages(0..6));
+    }
+
+    #[test]
+    fn test_torn_record_is_dropped_by_next_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut log, _) = SegmentLog::open(dir.path()).unwrap();
+        log.rewrite(&messages(0..2)).unwrap();
+        assert_eq!(log.append(&messages(2..4)).unwrap(), None);
+
+        let segment = log.segment_path(log.tail.unwrap().segment);
+        let len = std::fs::metadata(&segment).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&segment).unwrap();
+        file.set_len(len - 3).unwrap();
+
+        let (mut log, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, messages(0..2));
+        assert_eq!(
+            log.append(&[message("after crash")]).unwrap(),
+            Some(messages(0..2))
+        );
+
+        let mut all = loaded;
+        all.push(message("after crash"));
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, all);
+    }
+
+    #[test]
+    fn test_stale_writer_appends_after_other_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut first, _) = SegmentLog::open(dir.path()).unwrap();
+        first.rewrite(&messages(0..2)).unwrap();
+        let (mut second, _) = SegmentLog::open(dir.path()).unwrap();
+
+        assert_eq!(first.append(&messages(2..3)).unwrap(), None);
+        let other = message("from the other session");
+        assert_eq!(
+            second.append(std::slice::from_ref(&other)).unwrap(),
+            Some(messages(0..3))
+        );
+        assert_eq!(second.append(&messages(3..4)).unwrap(), None);
+        let mut expected = messages(0..3);
+        expected.push(other);
+        expected.extend(messages(3..4));
+        assert_eq!(
+            first.append(&messages(4..5)).unwrap(),
+            Some(expected.clone())
+        );
+        assert_eq!(first.append(&messages(5..6)).unwrap(), None);
+
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        expected.extend(messages(4..6));
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn test_migrates_legacy_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join(LEGACY_FILE);
+        std::fs::write(&legacy, encode_frame(&messages(0..3), false).unwrap()).unwrap();
+
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded, messages(0..3));
+        assert!(!legacy.exists());
+        assert!(dir.path().join(HISTORY_DIR).join(CHECKPOINT_FILE).exists());
+
+        let (_, loaded) = SegmentLog::open(dir.path()).unwrap();
+        assert_eq!(loaded.len(), 3);
+    }
+}
@@ -0,0 +1,879 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::info;
+
+use super::{FileStorage, HistoryBranch, HistoryLog, Storage, archive_log};
+use crate::config::ScheduleTaskConfig;
+use crate::heartbeat_transcript::{MAX_TRANSCRIPTS, TickTranscript};
+use crate::history_search::{self, SearchEntry, SearchHit};
+use crate::runtime_index::RuntimeIndex;
+use crate::schedule_store::{self, ArchivedSchedule, MAX_RUN_RECORDS, ScheduleRun};
+
+/// Database file name in the data directory when `storage.path` is not set.
+pub(crate) const DEFAULT_FILE: &str = "hi.db";
+
+const SCHEMA: &str = "
+CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE history (id INTEGER PRIMARY KEY AUTOINCREMENT, message TEXT NOT NULL);
+CREATE TABLE schedules (position INTEGER PRIMARY KEY, config TEXT NOT NULL);
+CREATE TABLE schedule_archive (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    fired_at TEXT NOT NULL,
+    config TEXT NOT NULL
+);
+CREATE TABLE schedule_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    record TEXT NOT NULL
+);
+CREATE INDEX schedule_runs_name ON schedule_runs (name COLLATE NOCASE);
+CREATE TABLE schedule_state (name TEXT PRIMARY KEY, last_run INTEGER NOT NULL);
+CREATE TABLE heartbeat_ticks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id TEXT,
+    record TEXT NOT NULL
+);
+CREATE INDEX heartbeat_ticks_task ON heartbeat_ticks (task_id);
+";
+
+/// `terms` holds the terms of `content` as `history_search::terms` splits them, separated by
+/// spaces, so both backends match the same messages.
+const SEARCH_SCHEMA: &str = "
+CREATE VIRTUAL TABLE history_search USING fts5(
+    terms,
+    session UNINDEXED,
+    timestamp UNINDEXED,
+    role UNINDEXED,
+    content UNINDEXED,
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+";
+
+/// `message` is the JSON of one archived message, LZ4-compressed with its length prepended.
+const ARCHIVE_SCHEMA: &str = "
+CREATE TABLE history_archive (
+    session TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    message BLOB NOT NULL,
+    PRIMARY KEY (session, seq)
+) WITHOUT ROWID;
+";
+
+/// Branches of the history, in creation order, and the messages each stores after its
+/// fork point.
+const BRANCH_SCHEMA: &str = "
+CREATE TABLE history_branches (position INTEGER PRIMARY KEY, record TEXT NOT NULL);
+CREATE TABLE branch_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    branch TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+CREATE INDEX branch_history_branch ON branch_history (branch, id);
+";
+
+/// Schema changes in order; `user_version` counts those applied.
+const MIGRATIONS: &[&str] = &[SCHEMA, SEARCH_SCHEMA, ARCHIVE_SCHEMA, BRANCH_SCHEMA];
+
+/// Bumped by every history rewrite, so open handles notice they are stale. A branch's
+/// generation is kept under this key followed by `:` and the branch name.
+const HISTORY_GENERATION: &str = "history_generation";
+/// Counts schedule saves. Absent until schedules are first saved; until then the config
+/// schedules apply.
+const SCHEDULES_REVISION: &str = "schedules_revision";
+const RUNTIME_INDEX: &str = "runtime_index";
+
+/// Every store in one SQLite database, shared safely between processes.
+pub struct SqliteStorage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    /// Open the database at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, |_| Ok(()))
+    }
+
+    /// Open the database at `path`. A new database is filled with the data already kept
+    /// by `files`, so switching backends keeps history, schedules and run logs.
+    pub fn open_or_import(path: &Path, files: &FileStorage) -> Result<Self> {
+        Self::open_with(path, |tx| import(tx, files))
+    }
+
+    fn open_with(path: &Path, init: impl FnOnce(&Transaction) -> Result<()>) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut conn =
+            Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > MIGRATIONS.len() {
+            anyhow::bail!(
+                "{} was written by a newer version of hi (schema {version})",
+                path.display()
+            );
+        }
+        for migration in &MIGRATIONS[version..] {
+            tx.execute_batch(migration)?;
+        }
+        if version == 0 {
+            init(&tx)?;
+        }
+        if version < MIGRATIONS.len() {
+            tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+        }
+        tx.commit()?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        lock(&self.conn)
+    }
+
+    /// Open the main history (`None`) or a branch's stored messages.
+    fn open_log(&self, branch: Option<&str>) -> Result<(Box<dyn HistoryLog>, Vec<Value>)> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let messages = load_history(&tx, branch)?;
+        let state = history_state(&tx, branch)?;
+        tx.commit()?;
+        let log = SqliteHistory {
+            conn: Arc::clone(&self.conn),
+            branch: branch.map(str::to_string),
+            state,
+        };
+        Ok((Box::new(log), messages))
+    }
+}
+
+fn lock(conn: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    conn.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn import(tx: &Transaction, files: &FileStorage) -> Result<()> {
+    let (_, messages) = files.open_history()?;
+    insert_history(tx, None, &messages)?;
+    let branches = files.load_branches()?;
+    for branch in &branches {
+        insert_history(
+            tx,
+            Some(&branch.name),
+            &files.open_branch_history(&branch.name)?.1,
+        )?;
+    }
+    write_branches(tx, &branches)?;
+    if let Some(schedules) = files.load_schedules()? {
+        write_schedules(tx, &schedules)?;
+    }
+    for archived in files.load_archive()? {
+        insert_archived(tx, &archived)?;
+    }
+    for run in files.load_runs(None, usize::MAX)? {
+        insert_run(tx, &run)?;
+    }
+    for (name, at) in files.load_last_runs()? {
+        upsert_last_run(tx, &name, at)?;
+    }
+    set_meta(tx, RUNTIME_INDEX, &to_json(&files.load_runtime_index()?)?)?;
+    for tick in files.load_ticks(None, usize::MAX)? {
+        insert_tick(tx, &tick)?;
+    }
+    insert_search_entries(tx, &history_search::load_entries_in(files.dir()))?;
+    for (session, archived) in archive_log::load_all(files.dir())? {
+        append_archive(tx, &session, &archived)?;
+    }
+    info!(
+        messages = messages.len(),
+        "Imported file storage into SQLite"
+    );
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).context("Failed to serialize record")
+}
+
+fn from_json<T: DeserializeOwned>(text: &str) -> Result<T> {
+    serde_json::from_str(text).context("Failed to parse stored record")
+}
+
+fn get_meta(tx: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(tx
+        .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?)
+}
+
+fn set_meta(tx: &Connection, key: &str, value: &str) -> Result<()> {
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// Rows selected by `sql` (one JSON column), parsed.
+fn query_json<T: DeserializeOwned>(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<T>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, |row| row.get::<_, String>(0))?;
+    rows.map(|row| from_json(&row?)).collect()
+}
+
+fn sql_limit(limit: usize) -> i64 {
+    i64::try_from(limit).unwrap_or(i64::MAX)
+}
+
+/// Drop all but the newest `keep` rows of `table`.
+fn trim(tx: &Connection, table: &str, keep: usize) -> Result<()> {
+    tx.execute(
+        &format!(
+            "DELETE FROM {table} WHERE id <= \
+             (SELECT id FROM {table} ORDER BY id DESC LIMIT 1 OFFSET ?1)"
+        ),
+        [sql_limit(keep)],
+    )?;
+    Ok(())
+}
+
+fn generation_key(branch: Option<&str>) -> String {
+    match branch {
+        Some(branch) => format!("{HISTORY_GENERATION}:{branch}"),
+        None => HISTORY_GENERATION.to_string(),
+    }
+}
+
+/// The generation and last message id of the main history (`None`) or a branch, which
+/// change with every write.
+fn history_state(conn: &Connection, branch: Option<&str>) -> Result<(i64, i64)> {
+    let generation = get_meta(conn, &generation_key(branch))?
+        .and_then(|g| g.parse().ok())
+        .unwrap_or(0);
+    let last_id = match branch {
+        Some(branch) => conn.query_row(
+            "SELECT COALESCE(MAX(id), 0) FROM branch_history WHERE branch = ?1",
+            [branch],
+            |row| row.get(0),
+        )?,
+        None => conn.query_row("SELECT COALESCE(MAX(id), 0) FROM history", [], |row| {
+            row.get(0)
+        })?,
+    };
+    Ok((generation, last_id))
+}
+
+fn load_history(conn: &Connection, branch: Option<&str>) -> Result<Vec<Value>> {
+    match branch {
+        Some(branch) => query_json(
+            conn,
+            "SELECT message FROM branch_history WHERE branch = ?1 ORDER BY id",
+            [branch],
+        ),
+        None => query_json(conn, "SELECT message FROM history ORDER BY id", []),
+    }
+}
+
+fn insert_history(tx: &Connection, branch: Option<&str>, messages: &[Value]) -> Result<()> {
+    match branch {
+        Some(branch) => {
+            let mut stmt =
+                tx.prepare_cached("INSERT INTO branch_history (branch, message) VALUES (?1, ?2)")?;
+            for message in messages {
+                stmt.execute(params![branch, to_json(message)?])?;
+            }
+        }
+        None => {
+            let mut stmt = tx.prepare_cached("INSERT INTO history (message) VALUES (?1)")?;
+            for message in messages {
+                stmt.execute([to_json(message)?])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn delete_history(tx: &Connection, branch: Option<&str>) -> Result<()> {
+    match branch {
+        Some(branch) => tx.execute("DELETE FROM branch_history WHERE branch = ?1", [branch])?,
+        None => tx.execute("DELETE FROM history", [])?,
+    };
+    Ok(())
+}
+
+fn load_branches(conn: &Connection) -> Result<Vec<HistoryBranch>> {
+    query_json(
+        conn,
+        "SELECT record FROM history_branches ORDER BY position",
+        [],
+    )
+}
+
+fn write_branches(tx: &Connection, branches: &[HistoryBranch]) -> Result<()> {
+    tx.execute("DELETE FROM history_branches", [])?;
+    let mut stmt =
+        tx.prepare_cached("INSERT INTO history_branches (position, record) VALUES (?1, ?2)")?;
+    for (position, branch) in branches.iter().enumerate() {
+        stmt.execute(params![position as i64, to_json(branch)?])?;
+    }
+    Ok(())
+}
+
+fn load_schedules(conn: &Connection) -> Result<Option<Vec<ScheduleTaskConfig>>> {
+    if get_meta(conn, SCHEDULES_REVISION)?.is_none() {
+        return Ok(None);
+    }
+    query_json(conn, "SELECT config FROM schedules ORDER BY position", []).map(Some)
+}
+
+fn schedules_revision(conn: &Connection) -> Result<u64> {
+    Ok(get_meta(conn, SCHEDULES_REVISION)?
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(0))
+}
+
+fn write_schedules(tx: &Connection, schedules: &[ScheduleTaskConfig]) -> Result<()> {
+    tx.execute("DELETE FROM schedules", [])?;
+    let mut stmt = tx.prepare_cached("INSERT INTO schedules (position, config) VALUES (?1, ?2)")?;
+    for (position, schedule) in schedules.iter().enumerate() {
+        stmt.execute(params![sql_limit(position), to_json(schedule)?])?;
+    }
+    let revision = schedules_revision(tx)? + 1;
+    set_meta(tx, SCHEDULES_REVISION, &revision.to_string())
+}
+
+fn insert_archived(tx: &Connection, archived: &ArchivedSchedule) -> Result<()> {
+    tx.execute(
+        "INSERT INTO schedule_archive (name, fired_at, config) VALUES (?1, ?2, ?3)",
+        params![
+            archived.task.name,
+            archived.fired_at,
+            to_json(&archived.task)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_run(tx: &Connection, run: &ScheduleRun) -> Result<()> {
+    tx.execute(
+        "INSERT INTO schedule_runs (name, record) VALUES (?1, ?2)",
+        params![run.name, to_json(run)?],
+    )?;
+    Ok(())
+}
+
+fn upsert_last_run(tx: &Connection, name: &str, at: DateTime<Utc>) -> Result<()> {
+    tx.execute(
+        "INSERT INTO schedule_state (name, last_run) VALUES (?1, ?2)
+         ON CONFLICT (name) DO UPDATE SET last_run = MAX(last_run, excluded.last_run)",
+        params![name, at.timestamp_millis()],
+    )?;
+    Ok(())
+}
+
+fn insert_search_entries(tx: &Connection, entries: &[SearchEntry]) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO history_search (terms, session, timestamp, role, content)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for entry in entries {
+        let terms: Vec<String> = history_search::terms(&entry.content)
+            .into_iter()
+            .map(|(_, term)| term)
+            .collect();
+        stmt.execute(params![
+            terms.join(" "),
+            entry.session,
+            entry.timestamp,
+            entry.role,
+            entry.content
+        ])?;
+    }
+    Ok(())
+}
+
+fn append_archive(tx: &Connection, session: &str, messages: &[Value]) -> Result<Range<u64>> {
+    let start: u64 = tx.query_row(
+        "SELECT COALESCE(MAX(seq) + 1, 0) FROM history_archive WHERE session = ?1",
+        [session],
+        |row| row.get(0),
+    )?;
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO history_archive (session, seq, message) VALUES (?1, ?2, ?3)",
+    )?;
+    for (seq, message) in (start..).zip(messages) {
+        let compressed = lz4_flex::compress_prepend_size(to_json(message)?.as_bytes());
+        stmt.execute(params![session, seq, compressed])?;
+    }
+    Ok(start..start + messages.len() as u64)
+}
+
+fn insert_tick(tx: &Connection, tick: &TickTranscript) -> Result<()> {
+    tx.execute(
+        "INSERT INTO heartbeat_ticks (task_id, record) VALUES (?1, ?2)",
+        params![tick.task_id, to_json(tick)?],
+    )?;
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn open_history(&self) -> Result<(Box<dyn HistoryLog>, Vec<Value>)> {
+        self.open_log(None)
+    }
+
+    fn open_branch_history(&self, branch: &str) -> Result<(Box<dyn HistoryLog>, Vec<Value>)> {
+        self.open_log(Some(branch))
+    }
+
+    fn load_branches(&self) -> Result<Vec<HistoryBranch>> {
+        load_branches(&self.conn())
+    }
+
+    fn update_branches(&self, f: &mut dyn FnMut(&mut Vec<HistoryBranch>) -> bool) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut branches = load_branches(&tx)?;
+        if f(&mut branches) {
+            write_branches(&tx, &branches)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn index_messages(&self, entries: &[SearchEntry]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        insert_search_entries(&tx, entries)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn unindex_messages(&self, entries: &[SearchEntry]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "DELETE FROM history_search WHERE rowid = (
+                     SELECT MAX(rowid) FROM history_search
+                     WHERE session = ?1 AND timestamp = ?2 AND role = ?3 AND content = ?4)",
+            )?;
+            for entry in entries {
+                stmt.execute(params![
+                    entry.session,
+                    entry.timestamp,
+                    entry.role,
+                    entry.content
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn index_missing_messages(&self, entries: &[SearchEntry]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut indexed: HashMap<SearchEntry, usize> = HashMap::new();
+        {
+            let mut stmt =
+                tx.prepare("SELECT session, timestamp, role, content FROM history_search")?;
+            let rows = stmt.query_map([], |row| {
+                Ok(SearchEntry {
+                    session: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    role: row.get(2)?,
+                    content: row.get(3)?,
+                })
+            })?;
+            for row in rows {
+                *indexed.entry(row?).or_default() += 1;
+            }
+        }
+        let missing = history_search::missing(entries, |entry| {
+            indexed.get(entry).copied().unwrap_or_default()
+        });
+        insert_search_entries(&tx, &missing)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn search_history(
+        &self,
+        query: &str,
+        session: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>> {
+        let terms = history_search::query_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Quoted terms are matched literally and all of them must be present.
+        let fts_query = terms
+            .iter()
+            .map(|term| format!("\"{term}\""))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT session, timestamp, role, content FROM history_search
+             WHERE history_search MATCH ?1 AND (?2 IS NULL OR session = ?2)
+             ORDER BY rowid DESC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![fts_query, session, sql_limit(limit)], |row| {
+            Ok(SearchEntry {
+                session: row.get(0)?,
+                timestamp: row.get(1)?,
+                role: row.get(2)?,
+                content: row.get(3)?,
+            })
+        })?;
+        rows.map(|row| Ok(SearchHit::new(row?, &terms))).collect()
+    }
+
+    fn archive_history(&self, session: &str, messages: &[Value]) -> Result<Range<u64>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let range = append_archive(&tx, session, messages)?;
+        tx.commit()?;
+        Ok(range)
+    }
+
+    fn load_history_archive(&self, session: &str, range: Range<u64>) -> Result<Vec<Value>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT message FROM history_archive
+             WHERE session = ?1 AND seq >= ?2 AND seq < ?3 ORDER BY seq",
+        )?;
+        let rows = stmt.query_map(params![session, range.start, range.end], |row| {
+            row.get::<_, Vec<u8>>(0)
+        })?;
+        rows.map(|row| {
+            let json = lz4_flex::decompress_size_prepended(&row?)
+                .context("Failed to decompress archived message")?;
+            serde_json::from_slice(&json).context("Failed to parse archived message")
+        })
+        .collect()
+    }
+
+    fn load_schedules(&self) -> Result<Option<Vec<ScheduleTaskConfig>>> {
+        load_schedules(&self.conn())
+    }
+
+    fn update_schedules(
+        &self,
+        fallback: Option<&[ScheduleTaskConfig]>,
+        f: &mut dyn FnMut(&mut Vec<ScheduleTaskConfig>) -> bool,
+    ) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut schedules = schedule_store::resolve(load_schedules(&tx), fallback);
+        if f(&mut schedules) {
+            write_schedules(&tx, &schedules)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    fn archive_one_shot(&self, task: &ScheduleTaskConfig, fired_at: DateTime<Utc>) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if let Some(mut schedules) = load_schedules(&tx)? {
+            let before = schedules.len();
+            schedules.retain(|s| !(s.name == task.name && s.at == task.at));
+            if schedules.len() != before {
+                write_schedules(&tx, &schedules)?;
+            }
+        }
+        insert_archived(
+            &tx,
+            &ArchivedSchedule {
+                task: task.clone(),
+                fired_at: fired_at.to_rfc3339(),
+            },
+        )?;
+        tx.commit()?;
+        info!(name = task.name, "Archived one-shot schedule");
+        Ok(())
+    }
+
+    fn schedules_revision(&self) -> Result<u64> {
+        schedules_revision(&self.conn())
+    }
+
+    fn load_archive(&self) -> Result<Vec<ArchivedSchedule>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT fired_at, config FROM schedule_archive ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.map(|row| {
+            let (fired_at, config) = row?;
+            Ok(ArchivedSchedule {
+                task: from_json(&config)?,
+                fired_at,
+            })
+        })
+        .collect()
+    }
+
+    fn record_run(&self, run: &ScheduleRun) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        insert_run(&tx, run)?;
+        trim(&tx, "schedule_runs", MAX_RUN_RECORDS)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_runs(&self, name: Option<&str>, limit: usize) -> Result<Vec<ScheduleRun>> {
+        query_json(
+            &self.conn(),
+            "SELECT record FROM (
+                 SELECT id, record FROM schedule_runs
+                 WHERE ?1 IS NULL OR name = ?1 COLLATE NOCASE
+                 ORDER BY id DESC LIMIT ?2
+             ) ORDER BY id",
+            params![name, sql_limit(limit)],
+        )
+    }
+
+    fn load_last_runs(&self) -> Result<HashMap<String, DateTime<Utc>>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT name, last_run FROM schedule_state")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        let mut last_runs = HashMap::new();
+        for row in rows {
+            let (name, millis) = row?;
+            if let Some(at) = DateTime::from_timestamp_millis(millis) {
+                last_runs.insert(name, at);
+            }
+        }
+        Ok(last_runs)
+    }
+
+    fn record_last_run(&self, name: &str, at: DateTime<Utc>) -> Result<()> {
+        upsert_last_run(&self.conn(), name, at)
+    }
+
+    fn load_runtime_index(&self) -> Result<RuntimeIndex> {
+        Ok(get_meta(&self.conn(), RUNTIME_INDEX)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    fn update_runtime_index(&self, f: &mut dyn FnMut(&mut RuntimeIndex)) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut index: RuntimeIndex = get_meta(&tx, RUNTIME_INDEX)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        f(&mut index);
+        set_meta(&tx, RUNTIME_INDEX, &to_json(&index)?)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn record_tick(&self, transcript: &TickTranscript) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        insert_tick(&tx, transcript)?;
+        trim(&tx, "heartbeat_ticks", MAX_TRANSCRIPTS)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_ticks(&self, task_id: Option<&str>, limit: usize) -> Result<Vec<TickTranscript>> {
+        query_json(
+            &self.conn(),
+            "SELECT record FROM (
+                 SELECT id, record FROM heartbeat_ticks
+                 WHERE ?1 IS NULL OR task_id = ?1
+                 ORDER BY id DESC LIMIT ?2
+             ) ORDER BY id",
+            params![task_id, sql_limit(limit)],
+        )
+    }
+}
+
+/// A handle on the main history or a branch; remembers the generation and last message
+/// id it saw.
+struct SqliteHistory {
+    conn: Arc<Mutex<Connection>>,
+    branch: Option<String>,
+    state: (i64, i64),
+}
+
+impl HistoryLog for SqliteHistory {
+    fn append(&mut self, messages: &[Value]) -> Result<Option<Vec<Value>>> {
+        if messages.is_empty() {
+            return Ok(None);
+        }
+        let mut conn = lock(&self.conn);
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let branch = self.branch.as_deref();
+        let stored = if history_state(&tx, branch)? != self.state {
+            Some(load_history(&tx, branch)?)
+        } else {
+            None
+        };
+        insert_history(&tx, branch, messages)?;
+        let state = history_state(&tx, branch)?;
+        tx.commit()?;
+        self.state = state;
+        Ok(stored)
+    }
+
+    fn rewrite(&mut self, messages: &[Value]) -> Result<()> {
+        let mut conn = lock(&self.conn);
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let branch = self.branch.as_deref();
+        let (generation, _) = history_state(&tx, branch)?;
+        delete_history(&tx, branch)?;
+        set_meta(&tx, &generation_key(branch), &(generation + 1).to_string())?;
+        insert_history(&tx, branch, messages)?;
+        let state = history_state(&tx, branch)?;
+        tx.commit()?;
+        self.state = state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sqlite_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::open(&dir.path().join(DEFAULT_FILE)).unwrap();
+        super::super::tests::exercise(&storage);
+    }
+
+    #[test]
+    fn test_handles_on_separate_connections_catch_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_FILE);
+        let first = SqliteStorage::open(&path).unwrap();
+        let second = SqliteStorage::open(&path).unwrap();
+
+        let (mut a, _) = first.open_history().unwrap();
+        let (mut b, _) = second.open_history().unwrap();
+        assert_eq!(a.append(&[json!("one")]).unwrap(), None);
+        assert_eq!(b.append(&[json!("two")]).unwrap(), Some(vec![json!("one")]));
+        b.rewrite(&[json!("two")]).unwrap();
+        assert_eq!(
+            a.append(&[json!("three")]).unwrap(),
+            Some(vec![json!("two")])
+        );
+        assert_eq!(
+            first.open_history().unwrap().1,
+            vec![json!("two"), json!("three")]
+        );
+    }
+
+    #[test]
+    fn test_upgrades_older_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_FILE);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        drop(conn);
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        let entry = SearchEntry::new("tui", Utc::now(), "user", "upgrade me");
+        storage.index_messages(&[entry]).unwrap();
+        assert_eq!(
+            storage.search_history("upgrade", None, 10).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_new_database_imports_file_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = FileStorage::new(dir.path().to_path_buf());
+        let (mut log, _) = files.open_history().unwrap();
+        log.rewrite(&[json!({ "role": "user", "content": "hello" })])
+            .unwrap();
+        let schedule = ScheduleTaskConfig {
+            name: "daily".to_string(),
+            cron: "0 0 * * *".to_string(),
+            prompt: "summarize".to_string(),
+            ..Default::default()
+        };
+        files
+            .update_schedules(None, &mut |schedules| {
+                schedules.push(schedule.clone());
+                true
+            })
+            .unwrap();
+        files.record_last_run("daily", Utc::now()).unwrap();
+        files
+            .index_messages(&[SearchEntry::new("tui", Utc::now(), "user", "hello")])
+            .unwrap();
+        files
+            .archive_history("tui", &[json!({ "role": "user", "content": "older" })])
+            .unwrap();
+        files
+            .update_branches(&mut |branches| {
+                branches.push(HistoryBranch {
+                    name: "draft".to_string(),
+                    parent: Some("main".to_string()),
+                    fork_at: 1,
+                    created_at: Utc::now(),
+                });
+                true
+            })
+            .unwrap();
+        let (mut log, _) = files.open_branch_history("draft").unwrap();
+        log.rewrite(&[json!({ "role": "user", "content": "on the branch" })])
+            .unwrap();
+
+        let path = dir.path().join(DEFAULT_FILE);
+        let storage = SqliteStorage::open_or_import(&path, &files).unwrap();
+        assert_eq!(storage.open_history().unwrap().1.len(), 1);
+        assert_eq!(storage.load_schedules().unwrap().unwrap()[0].name, "daily");
+        assert!(storage.load_last_runs().unwrap().contains_key("daily"));
+        assert_eq!(storage.search_history("hello", None, 10).unwrap().len(), 1);
+        assert_eq!(storage.load_history_archive("tui", 0..10).unwrap().len(), 1);
+        assert_eq!(storage.load_branches().unwrap()[0].fork_at, 1);
+        assert_eq!(storage.open_branch_history("draft").unwrap().1.len(), 1);
+
+        // Only a new database imports; later opens keep what the database holds.
+        storage
+            .update_schedules(None, &mut |s| {
+                s.clear();
+                true
+            })
+            .unwrap();
+        drop(storage);
+        let storage = SqliteStorage::open_or_import(&path, &files).unwrap();
+        assert!(storage.load_schedules().unwrap().unwrap().is_empty());
+    }
+}
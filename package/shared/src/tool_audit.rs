@@ -0,0 +1,182 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const AUDIT_FILE: &str = "tool_audit.jsonl";
+
+/// Size at which the audit log is rotated.
+const MAX_AUDIT_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated audit logs kept next to the live one (tool_audit.1.jsonl is the newest).
+const MAX_ROTATED_FILES: usize = 5;
+/// Maximum characters of tool arguments or error text kept per entry.
+const AUDIT_TEXT_CHARS: usize = 2000;
+
+/// One tool execution, appended to tool_audit.jsonl.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolAuditEntry {
+    pub at: String,
+    /// Session or background agent that made the call, e.g. `tui`, `telegram:42`, `heartbeat`.
+    pub caller: String,
+    pub tool: String,
+    pub args: String,
+    pub duration_ms: u64,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Length of the tool output in characters.
+    #[serde(default)]
+    pub output_chars: usize,
+}
+
+impl ToolAuditEntry {
+    pub fn new(
+        caller: &str,
+        tool: &str,
+        args: &str,
+        duration: std::time::Duration,
+        result: std::result::Result<&str, &str>,
+    ) -> Self {
+        let (success, error, output_chars) = match result {
+            Ok(output) => (true, None, output.chars().count()),
+            Err(e) => (false, Some(truncate(e, AUDIT_TEXT_CHARS)), 0),
+        };
+        Self {
+            at: chrono::Utc::now().to_rfc3339(),
+            caller: caller.to_string(),
+            tool: tool.to_string(),
+            args: truncate(args, AUDIT_TEXT_CHARS),
+            duration_ms: duration.as_millis() as u64,
+            success,
+            error,
+            output_chars,
+        }
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Directory holding the audit log, shared with the application logs.
+pub fn audit_dir() -> Result<PathBuf> {
+    Ok(crate::paths::data_dir()?.join("logs"))
+}
+
+/// Append an entry to the audit log in `dir`, rotating it first if it has grown too large.
+pub fn record(dir: &Path, entry: &ToolAuditEntry) -> Result<()> {
+    record_in(dir, entry, MAX_AUDIT_BYTES)
+}
+
+fn record_in(dir: &Path, entry: &ToolAuditEntry, max_bytes: u64) -> Result<()> {
+    let path = dir.join(AUDIT_FILE);
+    // Serializes appends and rotation across concurrent tool calls and processes.
+    let _lock = crate::atomic_file::lock(&path)?;
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() >= max_bytes) {
+        rotate(dir)?;
+    }
+
+    let mut line = serde_json::to_string(entry).context("Failed to serialize audit entry")?;
+    line.push('\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Shift tool_audit.N.jsonl up by one, dropping the oldest, and move the live log to .1.
+fn rotate(dir: &Path) -> Result<()> {
+    let rotated = |n: usize| dir.join(format!("tool_audit.{n}.jsonl"));
+    let _ = std::fs::remove_file(rotated(MAX_ROTATED_FILES));
+    for n in (1..MAX_ROTATED_FILES).rev() {
+        let from = rotated(n);
+        if from.exists() {
+            std::fs::rename(&from, rotated(n + 1))
+                .with_context(|| format!("Failed to rotate {}", from.display()))?;
+        }
+    }
+    let live = dir.join(AUDIT_FILE);
+    std::fs::rename(&live, rotated(1))
+        .with_context(|| format!("Failed to rotate {}", live.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn read_entries(path: &Path) -> Vec<ToolAuditEntry> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_record_appends_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let ok = ToolAuditEntry::new(
+            "tui",
+            "bash",
+            r#"{"command":"ls"}"#,
+            Duration::from_millis(12),
+            Ok("a\nb"),
+        );
+        let failed = ToolAuditEntry::new(
+            "heartbeat",
+            "read_file",
+            "{}",
+            Duration::from_millis(3),
+            Err("not found"),
+        );
+        record_in(dir.path(), &ok, MAX_AUDIT_BYTES).unwrap();
+        record_in(dir.path(), &failed, MAX_AUDIT_BYTES).unwrap();
+
+        let entries = read_entries(&dir.path().join(AUDIT_FILE));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].caller, "tui");
+        assert_eq!(entries[0].duration_ms, 12);
+        assert_eq!(entries[0].output_chars, 3);
+        assert!(entries[0].error.is_none());
+        assert_eq!(entries[1].tool, "read_file");
+        assert!(!entries[1].success);
+        assert_eq!(entries[1].error.as_deref(), Some("not found"));
+    }
+
+    #[test]
+    fn test_record_rotates_large_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = ToolAuditEntry::new("tui", "bash", "{}", Duration::ZERO, Ok(""));
+        let line_len = serde_json::to_string(&entry).unwrap().len() as u64 + 1;
+
+        for _ in 0..(MAX_ROTATED_FILES + 3) * 2 {
+            record_in(dir.path(), &entry, line_len * 2).unwrap();
+        }
+
+        assert_eq!(read_entries(&dir.path().join(AUDIT_FILE)).len(), 2);
+        for n in 1..=MAX_ROTATED_FILES {
+            let rotated = dir.path().join(format!("tool_audit.{n}.jsonl"));
+            assert_eq!(read_entries(&rotated).len(), 2);
+        }
+        assert!(
+            !dir.path()
+                .join(format!("tool_audit.{}.jsonl", MAX_ROTATED_FILES + 1))
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_entry_truncates_args() {
+        let args = "x".repeat(AUDIT_TEXT_CHARS + 10);
+        let entry = ToolAuditEntry::new("tui", "write_file", &args, Duration::ZERO, Ok(""));
+        assert_eq!(entry.args.chars().count(), AUDIT_TEXT_CHARS + 1);
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use hi_core::control::ControlRequest;
+use shared::config::ModelConfig;
+
+pub(crate) const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// Schedule management commands
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "cron")]
+pub struct CronCommand {
+    #[argh(subcommand)]
+    pub subcommand: CronSubcommands,
+}
+
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand)]
+pub enum CronSubcommands {
+    List(CronListCommand),
+    Run(CronRunCommand),
+    Pause(CronPauseCommand),
+    Resume(CronResumeCommand),
+    History(CronHistoryCommand),
+}
+
+/// List schedules
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "list")]
+pub struct CronListCommand {}
+
+/// Run a schedule now and print its response
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "run")]
+pub struct CronRunCommand {
+    /// schedule name
+    #[argh(positional)]
+    pub name: String,
+}
+
+/// Pause a schedule (sets enabled to false)
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "pause")]
+pub struct CronPauseCommand {
+    /// schedule name
+    #[argh(positional)]
+    pub name: String,
+}
+
+/// Resume a schedule (sets enabled to true)
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "resume")]
+pub struct CronResumeCommand {
+    /// schedule name
+    #[argh(positional)]
+    pub name: String,
+}
+
+/// Show recent schedule runs
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "history")]
+pub struct CronHistoryCommand {
+    /// only show runs of this schedule
+    #[argh(positional)]
+    pub name: Option<String>,
+
+    /// maximum number of runs to show
+    #[argh(option, short = 'n', default = "DEFAULT_HISTORY_LIMIT")]
+    pub limit: usize,
+}
+
+pub async fn run(config: &ModelConfig, command: CronCommand) -> Result<()> {
+    match command.subcommand {
+        CronSubcommands::List(_) => {
+            let schedules = shared::schedule_store::load(config.schedules.as_deref());
+            if schedules.is_empty() {
+                println!("No schedules configured.");
+            }
+            for s in &schedules {
+                let trigger = match (&s.at, &s.timezone) {
+                    (Some(at), _) => format!("at {at}"),
+                    (None, Some(tz)) => format!("{} ({tz})", s.cron),
+                    (None, None) => s.cron.clone(),
+                };
+                let state = if s.enabled { "enabled" } else { "paused" };
+                println!("{}\t{}\t{}", s.name, trigger, state);
+            }
+        }
+        CronSubcommands::Run(cmd) => {
+            let response = hi_core::scheduler::Scheduler::run_now(config, &cmd.name).await?;
+            println!("{response}");
+        }
+        CronSubcommands::Pause(cmd) => set_enabled(config, &cmd.name, false).await?,
+        CronSubcommands::Resume(cmd) => set_enabled(config, &cmd.name, true).await?,
+        CronSubcommands::History(cmd) => {
+            let runs = shared::schedule_store::load_runs(cmd.name.as_deref(), cmd.limit);
+            if runs.is_empty() {
+                println!("No schedule runs recorded.");
+            }
+            for run in runs.iter().rev() {
+                println!("{run}");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn set_enabled(config: &ModelConfig, name: &str, enabled: bool) -> Result<()> {
+    let storage = shared::storage::storage()?;
+    let updated =
+        shared::schedule_store::set_enabled(storage, config.schedules.as_deref(), name, enabled)?;
+    if updated.is_none() {
+        bail!("Schedule '{name}' not found");
+    }
+    println!(
+        "{} schedule '{name}'.",
+        if enabled { "Resumed" } else { "Paused" }
+    );
+    // Apply right away if a daemon is running instead of waiting for its reload poll.
+    if let Err(e) = hi_core::control::request(&ControlRequest::CronReload).await {
+        eprintln!("Could not notify the daemon: {e}");
+    }
+    Ok(())
+}
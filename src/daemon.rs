@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use argh::FromArgs;
+use hi_core::control::ControlRequest;
+use shared::config::ModelConfig;
+
+/// Run the heartbeat and scheduler without a chat UI
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "daemon")]
+pub struct DaemonCommand {
+    /// seconds to wait for in-progress runs on shutdown (default 60)
+    #[argh(option)]
+    pub grace: Option<u64>,
+
+    #[argh(subcommand)]
+    pub subcommand: Option<DaemonSubcommands>,
+}
+
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand)]
+pub enum DaemonSubcommands {
+    Status(DaemonStatusCommand),
+    Stop(DaemonStopCommand),
+}
+
+/// Show whether a daemon is running
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "status")]
+pub struct DaemonStatusCommand {}
+
+/// Ask the running daemon to shut down gracefully
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "stop")]
+pub struct DaemonStopCommand {}
+
+pub async fn run(config: ModelConfig, command: DaemonCommand) -> Result<()> {
+    match command.subcommand {
+        None => {
+            let grace = command
+                .grace
+                .map(Duration::from_secs)
+                .unwrap_or(hi_core::daemon::DEFAULT_SHUTDOWN_GRACE);
+            hi_core::daemon::run(config, grace).await
+        }
+        Some(DaemonSubcommands::Status(_)) => {
+            match hi_core::control::request(&ControlRequest::Status).await? {
+                Some(response) => match response.into_result()?.status {
+                    Some(status) => println!("Daemon running: {status}"),
+                    None => println!("Daemon running."),
+                },
+                None => println!("No daemon running."),
+            }
+            Ok(())
+        }
+        Some(DaemonSubcommands::Stop(_)) => {
+            match hi_core::control::request(&ControlRequest::Shutdown).await? {
+                Some(response) => println!("{}", response.into_result()?.message),
+                None => bail!("No daemon running"),
+            }
+            Ok(())
+        }
+    }
+}
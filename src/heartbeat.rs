@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use chrono::Utc;
+use hi_core::control::ControlRequest;
+use shared::config::ModelConfig;
+use shared::heartbeat_store::{self, TaskStatus};
+use shared::heartbeat_transcript::{self, TickTranscript};
+use shared::schedule_time;
+
+use crate::cron::DEFAULT_HISTORY_LIMIT;
+
+/// Heartbeat task ledger commands
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "heartbeat")]
+pub struct HeartbeatCommand {
+    #[argh(subcommand)]
+    pub subcommand: HeartbeatSubcommands,
+}
+
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand)]
+pub enum HeartbeatSubcommands {
+    List(HeartbeatListCommand),
+    Add(HeartbeatAddCommand),
+    Done(HeartbeatDoneCommand),
+    Fail(HeartbeatFailCommand),
+    Tick(HeartbeatTickCommand),
+    History(HeartbeatHistoryCommand),
+}
+
+/// List heartbeat tasks
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "list")]
+pub struct HeartbeatListCommand {}
+
+/// Queue a task for the heartbeat agent
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "add")]
+pub struct HeartbeatAddCommand {
+    /// task title
+    #[argh(positional)]
+    pub title: Vec<String>,
+
+    /// longer instructions for the agent
+    #[argh(option, short = 'd')]
+    pub description: Option<String>,
+
+    /// priority; higher runs first (default 0)
+    #[argh(option, short = 'p')]
+    pub priority: Option<i32>,
+
+    /// when the task is due, e.g. "tomorrow 17:00" or an RFC 3339 time
+    #[argh(option)]
+    pub due: Option<String>,
+
+    /// do not start before this time
+    #[argh(option)]
+    pub not_before: Option<String>,
+
+    /// repeat interval, e.g. 6h or 1d
+    #[argh(option)]
+    pub every: Option<String>,
+
+    /// id of a task that must be done first (repeatable)
+    #[argh(option)]
+    pub depends_on: Vec<String>,
+
+    /// IANA timezone for --due and --not-before (default UTC)
+    #[argh(option)]
+    pub timezone: Option<String>,
+}
+
+/// Mark a task done
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "done")]
+pub struct HeartbeatDoneCommand {
+    /// task id
+    #[argh(positional)]
+    pub id: String,
+}
+
+/// Mark a task failed; it is not retried
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "fail")]
+pub struct HeartbeatFailCommand {
+    /// task id
+    #[argh(positional)]
+    pub id: String,
+
+    /// reason recorded in the task outcome
+    #[argh(option, short = 'r')]
+    pub reason: Option<String>,
+}
+
+/// Run one heartbeat tick now and print the result
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "tick")]
+pub struct HeartbeatTickCommand {}
+
+/// Show transcripts of recent heartbeat ticks
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "history")]
+pub struct HeartbeatHistoryCommand {
+    /// only show ticks that worked on this task
+    #[argh(positional)]
+    pub id: Option<String>,
+
+    /// maximum number of ticks to show
+    #[argh(option, short = 'n', default = "DEFAULT_HISTORY_LIMIT")]
+    pub limit: usize,
+
+    /// print each tick's prompt, tool calls, and full response
+    #[argh(switch, short = 'v')]
+    pub verbose: bool,
+}
+
+pub async fn run(config: &ModelConfig, command: HeartbeatCommand) -> Result<()> {
+    let path = heartbeat_store::default_path()?;
+    match command.subcommand {
+        HeartbeatSubcommands::List(_) => {
+            let ledger = heartbeat_store::load(&path)?;
+            if ledger.tasks.is_empty() {
+                println!("No heartbeat tasks.");
+            }
+            for task in &ledger.tasks {
+                println!("{}", task.summary());
+            }
+        }
+        HeartbeatSubcommands::Add(cmd) => {
+            let id = heartbeat_store::update(&path, |ledger| {
+                let id = add_task(ledger, &cmd)?;
+                if let Some(cycle) = heartbeat_store::find_dependency_cycle(ledger) {
+                    anyhow::bail!("Dependency cycle: {}", cycle.join(" -> "));
+                }
+                Ok(id)
+            })?;
+            println!("Added heartbeat task '{id}'.");
+        }
+        HeartbeatSubcommands::Done(cmd) => {
+            resolve(&path, &cmd.id, TaskStatus::Done, None)?;
+            println!("Marked task '{}' done.", cmd.id);
+        }
+        HeartbeatSubcommands::Fail(cmd) => {
+            resolve(&path, &cmd.id, TaskStatus::Failed, cmd.reason.as_deref())?;
+            println!("Marked task '{}' failed.", cmd.id);
+        }
+        HeartbeatSubcommands::Tick(_) => {
+            let report = match hi_core::control::request(&ControlRequest::HeartbeatTick).await? {
+                Some(response) => response.into_result()?.message,
+                None => hi_core::heartbeat::tick_once(config).await?,
+            };
+            println!("{report}");
+        }
+        HeartbeatSubcommands::History(cmd) => {
+            let transcripts = heartbeat_transcript::load(cmd.id.as_deref(), cmd.limit);
+            if transcripts.is_empty() {
+                println!("No heartbeat ticks recorded.");
+            }
+            for transcript in transcripts.iter().rev() {
+                if cmd.verbose {
+                    print_transcript(transcript);
+                } else {
+                    println!("{transcript}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_transcript(transcript: &TickTranscript) {
+    println!("== {transcript}");
+    println!("Prompt:\n{}", transcript.prompt);
+    for call in &transcript.tool_calls {
+        let status = if call.success { "ok" } else { "error" };
+        println!(
+            "Tool {} {} [{}]: {}",
+            call.name, call.args, status, call.output
+        );
+    }
+    if let Some(response) = &transcript.response {
+        println!("Response:\n{response}");
+    }
+    if let Some(error) = &transcript.error {
+        println!("Error: {error}");
+    }
+    println!();
+}
+
+fn add_task(
+    ledger: &mut heartbeat_store::HeartbeatLedger,
+    cmd: &HeartbeatAddCommand,
+) -> Result<String> {
+    let now = Utc::now();
+    let tz = schedule_time::parse_timezone(cmd.timezone.as_deref())?;
+    let when = |input: &Option<String>| {
+        input
+            .as_deref()
+            .map(|input| schedule_time::parse_when(input, now, tz))
+            .transpose()
+    };
+    let due = when(&cmd.due)?;
+    let not_before = when(&cmd.not_before)?;
+    let every = cmd
+        .every
+        .as_deref()
+        .map(|input| {
+            schedule_time::parse_interval(input)
+                .with_context(|| format!("Invalid interval '{input}': expected e.g. 30m, 6h, 1d"))
+        })
+        .transpose()?;
+
+    let task = heartbeat_store::add_task(ledger, &cmd.title.join(" "))?;
+    task.description = cmd.description.clone().filter(|d| !d.trim().is_empty());
+    task.priority = cmd.priority.unwrap_or(0);
+    task.due = due;
+    task.not_before = not_before;
+    task.every = every;
+    task.depends_on = cmd.depends_on.clone();
+    Ok(task.id.clone())
+}
+
+fn resolve(
+    path: &std::path::Path,
+    id: &str,
+    status: TaskStatus,
+    reason: Option<&str>,
+) -> Result<()> {
+    heartbeat_store::update(path, |ledger| {
+        heartbeat_store::resolve_task(ledger, id, status, Utc::now(), reason)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_task_sets_attributes() {
+        let mut ledger = heartbeat_store::parse("# Heartbeat Tasks\n\n- [pending] fetch: Fetch\n");
+        let cmd = HeartbeatAddCommand {
+            title: vec!["Send".to_string(), "report".to_string()],
+            description: Some("Email the weekly numbers".to_string()),
+            priority: Some(3),
+            due: Some("2099-01-01T09:00:00+01:00".to_string()),
+            not_before: None,
+            every: Some("1w".to_string()),
+            depends_on: vec!["fetch".to_string()],
+            timezone: None,
+        };
+        let id = add_task(&mut ledger, &cmd).unwrap();
+        assert_eq!(id, "send-report");
+
+        let task = &ledger.tasks[1];
+        assert_eq!(task.title, "Send report");
+        assert_eq!(task.priority, 3);
+        assert_eq!(task.due.unwrap().to_rfc3339(), "2099-01-01T08:00:00+00:00");
+        assert_eq!(task.every, Some(chrono::Duration::weeks(1)));
+        assert_eq!(task.depends_on, vec!["fetch"]);
+
+        let bad = HeartbeatAddCommand {
+            every: Some("sometimes".to_string()),
+            ..cmd
+        };
+        assert!(add_task(&mut ledger, &bad).is_err());
+        assert_eq!(ledger.tasks.len(), 2);
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use argh::FromArgs;
+use hi_history::ChatHistory;
+use hi_history::transcript::{self, ExportFormat};
+use shared::history_search;
+
+/// Chat history commands
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "history")]
+pub struct HistoryCommand {
+    #[argh(subcommand)]
+    pub subcommand: HistorySubcommands,
+}
+
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand)]
+pub enum HistorySubcommands {
+    Search(HistorySearchCommand),
+    Export(HistoryExportCommand),
+    Import(HistoryImportCommand),
+}
+
+/// Search past conversations from every session, newest first
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "search")]
+pub struct HistorySearchCommand {
+    /// words that matching messages must all contain
+    #[argh(positional)]
+    pub query: Vec<String>,
+
+    /// maximum number of messages to show
+    #[argh(option, short = 'n', default = "history_search::DEFAULT_LIMIT")]
+    pub limit: usize,
+}
+
+/// Export the conversation history
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "export")]
+pub struct HistoryExportCommand {
+    /// md, json, jsonl, html, openai or anthropic (default md)
+    #[argh(option, short = 'f', default = "ExportFormat::Markdown")]
+    pub format: ExportFormat,
+
+    /// export only this session, including its archived messages (e.g. tui, telegram:42)
+    #[argh(option, short = 's')]
+    pub session: Option<String>,
+
+    /// write to this file instead of standard output
+    #[argh(option, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+/// Add a conversation exported by hi or another chat tool to the history
+#[derive(FromArgs, Debug, PartialEq)]
+#[argh(subcommand, name = "import")]
+pub struct HistoryImportCommand {
+    /// file of OpenAI, Anthropic or Gemini messages, a ChatGPT data export, or a hi JSON or JSONL export
+    #[argh(positional)]
+    pub file: PathBuf,
+
+    /// session label for imported messages that do not have one
+    #[argh(option, short = 's', default = "String::from(\"import\")")]
+    pub session: String,
+}
+
+pub fn run(command: HistoryCommand) -> Result<()> {
+    match command.subcommand {
+        HistorySubcommands::Search(cmd) => {
+            let query = cmd.query.join(" ");
+            if query.trim().is_empty() {
+                bail!("Search query must not be empty");
+            }
+            let hits = history_search::search(&query, None, cmd.limit)?;
+            if hits.is_empty() {
+                println!("No messages match '{query}'.");
+            }
+            for hit in &hits {
+                println!("{hit}");
+            }
+        }
+        HistorySubcommands::Export(cmd) => {
+            let storage = shared::storage::storage()?;
+            let messages = transcript::load(storage, cmd.session.as_deref())?;
+            let exported = transcript::export(&messages, cmd.format)?;
+            match cmd.output {
+                Some(path) => {
+                    std::fs::write(&path, exported)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!(
+                        "Exported {} messages to {}.",
+                        messages.len(),
+                        path.display()
+                    );
+                }
+                None => print!("{exported}"),
+            }
+        }
+        HistorySubcommands::Import(cmd) => {
+            let text = std::fs::read_to_string(&cmd.file)
+                .with_context(|| format!("Failed to read {}", cmd.file.display()))?;
+            let messages = transcript::import(&text)
+                .with_context(|| format!("Failed to import {}", cmd.file.display()))?;
+            let count = messages.len();
+            let mut history = ChatHistory::open(shared::storage::handle()?)?;
+            history.set_session(&cmd.session);
+            for message in messages {
+                history.push(message);
+            }
+            history.save()?;
+            println!("Imported {count} messages.");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search() {
+        let cmd = HistoryCommand::from_args(&["history"], &["search", "-n", "5", "staging", "db"])
+            .unwrap();
+        assert_eq!(
+            cmd.subcommand,
+            HistorySubcommands::Search(HistorySearchCommand {
+                query: vec!["staging".to_string(), "db".to_string()],
+                limit: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_export_and_import() {
+        let cmd = HistoryCommand::from_args(
+            &["history"],
+            &[
+                "export",
+                "-f",
+                "html",
+                "-s",
+                "telegram:42",
+                "-o",
+                "chat.html",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            cmd.subcommand,
+            HistorySubcommands::Export(HistoryExportCommand {
+                format: ExportFormat::Html,
+                session: Some("telegram:42".to_string()),
+                output: Some(PathBuf::from("chat.html")),
+            })
+        );
+        assert!(HistoryCommand::from_args(&["history"], &["export", "-f", "pdf"]).is_err());
+
+        let cmd = HistoryCommand::from_args(&["history"], &["import", "chat.json"]).unwrap();
+        assert_eq!(
+            cmd.subcommand,
+            HistorySubcommands::Import(HistoryImportCommand {
+                file: PathBuf::from("chat.json"),
+                session: "import".to_string(),
+            })
+        );
+    }
+}
@@ -1,10 +1,21 @@
+mod cron;
+mod daemon;
+mod heartbeat;
+mod history;
+
 use std::path::PathBuf;
 
 use anyhow::Result;
 use argh::FromArgs;
+use shared::config::ModelConfig;
 use tokio::signal::unix::{SignalKind, signal};
 use tracing::{error, info};
 
+use crate::cron::CronCommand;
+use crate::daemon::DaemonCommand;
+use crate::heartbeat::HeartbeatCommand;
+use crate::history::HistoryCommand;
+
 /// Terminal LLM chat tool
 #[derive(FromArgs, Debug, PartialEq)]
 struct Cli {
@@ -23,6 +34,10 @@ enum Commands {
     Tui(TuiCommand),
     Remote(RemoteCommand),
     Config(ConfigCommand),
+    Cron(CronCommand),
+    Heartbeat(HeartbeatCommand),
+    History(HistoryCommand),
+    Daemon(DaemonCommand),
 }
 
 /// Create a starter config via guided setup or quick template
@@ -63,24 +78,36 @@ enum ConfigSubcommands {
 #[argh(subcommand, name = "validate")]
 struct ValidateCommand {}
 
+fn load_config(path: Option<&PathBuf>) -> Result<ModelConfig> {
+    let config = match path {
+        Some(p) => ModelConfig::load_from_path(p)?,
+        None => ModelConfig::load()?,
+    };
+    shared::storage::init(config.storage.as_ref())?;
+    Ok(config)
+}
+
 #[tokio::main(worker_threads = 4)]
 async fn main() -> Result<()> {
-    shared::logging::init_logging();
+    let _logging = shared::logging::init_logging();
     let cli: Cli = argh::from_env();
 
-    let mut sigterm = signal(SignalKind::terminate())?;
+    // The daemon handles signals itself so it can wait for in-progress runs.
+    if !matches!(cli.command, Commands::Daemon(_)) {
+        let mut sigterm = signal(SignalKind::terminate())?;
 
-    tokio::spawn(async move {
-        tokio::select! {
-            _ = tokio::signal::ctrl_c() => {
-                info!("Received Ctrl+C (SIGINT), shutting down...");
-            }
-            _ = sigterm.recv() => {
-                info!("Received SIGTERM, shutting down...");
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    info!("Received Ctrl+C (SIGINT), shutting down...");
+                }
+                _ = sigterm.recv() => {
+                    info!("Received SIGTERM, shutting down...");
+                }
             }
-        }
-        std::process::exit(0);
-    });
+            std::process::exit(0);
+        });
+    }
 
     match cli.command {
         Commands::Init(init_cmd) => {
@@ -142,6 +169,22 @@ async fn main() -> Result<()> {
                 }
             }
         },
+        Commands::Cron(cron_cmd) => {
+            let config = load_config(cli.config.as_ref())?;
+            cron::run(&config, cron_cmd).await
+        }
+        Commands::Heartbeat(heartbeat_cmd) => {
+            let config = load_config(cli.config.as_ref())?;
+            heartbeat::run(&config, heartbeat_cmd).await
+        }
+        Commands::History(history_cmd) => {
+            load_config(cli.config.as_ref())?;
+            history::run(history_cmd)
+        }
+        Commands::Daemon(daemon_cmd) => {
+            let config = load_config(cli.config.as_ref())?;
+            daemon::run(config, daemon_cmd).await
+        }
     }
 }
 
@@ -227,4 +270,137 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_parse_cron_subcommands() {
+        use crate::cron::*;
+
+        let cli = Cli::from_args(&["hi"], &["cron", "run", "daily"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Commands::Cron(CronCommand {
+                subcommand: CronSubcommands::Run(CronRunCommand {
+                    name: "daily".to_string()
+                }),
+            })
+        );
+
+        let cli = Cli::from_args(&["hi"], &["cron", "pause", "daily"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Cron(CronCommand {
+                subcommand: CronSubcommands::Pause(_)
+            })
+        ));
+
+        let cli = Cli::from_args(&["hi"], &["cron", "history", "-n", "5"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Commands::Cron(CronCommand {
+                subcommand: CronSubcommands::History(CronHistoryCommand {
+                    name: None,
+                    limit: 5
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_cron_run_requires_name() {
+        assert!(Cli::from_args(&["hi"], &["cron", "run"]).is_err());
+        assert!(Cli::from_args(&["hi"], &["cron"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_heartbeat_subcommands() {
+        use crate::heartbeat::*;
+
+        let cli = Cli::from_args(
+            &["hi"],
+            &[
+                "heartbeat",
+                "add",
+                "Rotate",
+                "logs",
+                "--priority",
+                "2",
+                "--depends-on",
+                "a",
+                "--depends-on",
+                "b",
+            ],
+        )
+        .unwrap();
+        let Commands::Heartbeat(HeartbeatCommand {
+            subcommand: HeartbeatSubcommands::Add(add),
+        }) = cli.command
+        else {
+            panic!("expected heartbeat add");
+        };
+        assert_eq!(add.title, vec!["Rotate", "logs"]);
+        assert_eq!(add.priority, Some(2));
+        assert_eq!(add.depends_on, vec!["a", "b"]);
+
+        let cli = Cli::from_args(&["hi"], &["heartbeat", "fail", "t1", "-r", "obsolete"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Commands::Heartbeat(HeartbeatCommand {
+                subcommand: HeartbeatSubcommands::Fail(HeartbeatFailCommand {
+                    id: "t1".to_string(),
+                    reason: Some("obsolete".to_string()),
+                }),
+            })
+        );
+
+        let cli = Cli::from_args(&["hi"], &["heartbeat", "tick"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Heartbeat(HeartbeatCommand {
+                subcommand: HeartbeatSubcommands::Tick(_)
+            })
+        ));
+        assert!(Cli::from_args(&["hi"], &["heartbeat", "done"]).is_err());
+
+        let cli =
+            Cli::from_args(&["hi"], &["heartbeat", "history", "t1", "-n", "5", "-v"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Commands::Heartbeat(HeartbeatCommand {
+                subcommand: HeartbeatSubcommands::History(HeartbeatHistoryCommand {
+                    id: Some("t1".to_string()),
+                    limit: 5,
+                    verbose: true,
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_daemon_command() {
+        use crate::daemon::*;
+
+        let cli = Cli::from_args(&["hi"], &["daemon"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Commands::Daemon(DaemonCommand {
+                grace: None,
+                subcommand: None,
+            })
+        );
+
+        let cli = Cli::from_args(&["hi"], &["daemon", "--grace", "5"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Daemon(DaemonCommand { grace: Some(5), .. })
+        ));
+
+        let cli = Cli::from_args(&["hi"], &["daemon", "stop"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Daemon(DaemonCommand {
+                subcommand: Some(DaemonSubcommands::Stop(_)),
+                ..
+            })
+        ));
+    }
 }